@@ -0,0 +1,265 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file matches the .crate archives of the registry cache to the source directories
+/// they were extracted into.
+/// registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate was extracted to
+/// registry/src/github.com-1ecc6299db9ec823/foo-0.1.0/
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// a .crate archive and, if it has been extracted, its source directory
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ArchiveSource {
+    /// path to the .crate archive
+    pub(crate) archive: PathBuf,
+    /// path to the extracted sources, None if the archive was not extracted
+    pub(crate) source: Option<PathBuf>,
+}
+
+/// the part of the path that an archive and its source have in common:
+/// (registry directory name, crate name and version)
+type MatchKey = (OsString, OsString);
+
+fn key_of(path: &Path, name: Option<&OsStr>) -> Option<MatchKey> {
+    let registry = path.parent()?.file_name()?.to_os_string();
+    Some((registry, name?.to_os_string()))
+}
+
+/// registry/cache/<registry>/<name-version>.crate
+fn archive_key(archive: &Path) -> Option<MatchKey> {
+    key_of(archive, archive.file_stem())
+}
+
+/// registry/src/<registry>/<name-version>
+fn source_key(source: &Path) -> Option<MatchKey> {
+    key_of(source, source.file_name())
+}
+
+/// pair every .crate archive with the source directory it was extracted to
+/// the order of the archives is kept
+pub(crate) fn match_archives_to_sources(
+    archives: &[PathBuf],
+    sources: &[PathBuf],
+) -> Vec<ArchiveSource> {
+    let sources_by_key: HashMap<MatchKey, &PathBuf> = sources
+        .iter()
+        .filter_map(|source| source_key(source).map(|key| (key, source)))
+        .collect();
+
+    archives
+        .iter()
+        .map(|archive| ArchiveSource {
+            archive: archive.clone(),
+            source: archive_key(archive)
+                .and_then(|key| sources_by_key.get(&key))
+                .map(|source| (*source).clone()),
+        })
+        .collect()
+}
+
+/// all source directories that do not have a .crate archive they could have been extracted from
+pub(crate) fn sources_without_archive(archives: &[PathBuf], sources: &[PathBuf]) -> Vec<PathBuf> {
+    let archive_keys = archives
+        .iter()
+        .filter_map(|archive| archive_key(archive))
+        .collect::<HashSet<MatchKey>>();
+
+    sources
+        .iter()
+        .filter(|source| match source_key(source) {
+            Some(key) => !archive_keys.contains(&key),
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// all .crate archives that were downloaded but never extracted
+pub(crate) fn archives_without_source(archives: &[PathBuf], sources: &[PathBuf]) -> Vec<PathBuf> {
+    match_archives_to_sources(archives, sources)
+        .into_iter()
+        .filter(|pair| pair.source.is_none())
+        .map(|pair| pair.archive)
+        .collect()
+}
+
+/// all source directories that have no .crate archive and whose registry has no index (registry/index/<registry>)
+/// these are left over from a registry that was removed and can never be extracted again
+pub(crate) fn sources_without_archive_or_index(
+    archives: &[PathBuf],
+    indices: &[PathBuf],
+    sources: &[PathBuf],
+) -> Vec<PathBuf> {
+    let indexed_registries = indices
+        .iter()
+        .filter_map(|index| index.file_name())
+        .collect::<HashSet<&OsStr>>();
+
+    sources_without_archive(archives, sources)
+        .into_iter()
+        .filter(|source| match source.parent().and_then(Path::file_name) {
+            Some(registry) => !indexed_registries.contains(registry),
+            None => true,
+        })
+        .collect()
+}
+
+/// all registry indices (registry/index/<registry>) whose registry has no .crate archive in the cache
+/// cargo only needs them for resolving, for example after a "cargo search" against the registry
+pub(crate) fn indices_without_archives(archives: &[PathBuf], indices: &[PathBuf]) -> Vec<PathBuf> {
+    let registries_with_archives = archives
+        .iter()
+        .filter_map(|archive| archive.parent().and_then(Path::file_name))
+        .collect::<HashSet<&OsStr>>();
+
+    indices
+        .iter()
+        .filter(|index| match index.file_name() {
+            Some(registry) => !registries_with_archives.contains(registry),
+            None => false,
+        })
+        .cloned()
+        .collect()
+}
+
+/// all archive caches (registry/cache/<registry>) without an index of the same registry
+/// and all indices (registry/index/<registry>) without an archive cache of the same registry
+pub(crate) fn unpaired_registry_dirs(pkg_caches: &[PathBuf], indices: &[PathBuf]) -> Vec<PathBuf> {
+    let names = |dirs: &[PathBuf]| -> HashSet<OsString> {
+        dirs.iter()
+            .filter_map(|dir| dir.file_name())
+            .map(OsStr::to_os_string)
+            .collect()
+    };
+    let (pkg_cache_names, index_names) = (names(pkg_caches), names(indices));
+
+    let without = |dirs: &[PathBuf], others: &HashSet<OsString>| -> Vec<PathBuf> {
+        dirs.iter()
+            .filter(|dir| dir.file_name().is_some_and(|name| !others.contains(name)))
+            .cloned()
+            .collect()
+    };
+    let mut unpaired = without(pkg_caches, &index_names);
+    unpaired.extend(without(indices, &pkg_cache_names));
+    unpaired
+}
+
+#[cfg(test)]
+mod archive_sources_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn match_archives() {
+        let archives = vec![
+            PathBuf::from("registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate"),
+            PathBuf::from("registry/cache/github.com-1ecc6299db9ec823/bar-1.0.0.crate"),
+            PathBuf::from("registry/cache/my-registry-eb2e5ba3d5b6d218/foo-0.1.0.crate"),
+        ];
+        let sources = vec![
+            PathBuf::from("registry/src/github.com-1ecc6299db9ec823/foo-0.1.0"),
+            // same crate, but different registry
+            PathBuf::from("registry/src/other-registry-0123456789abcdef/foo-0.1.0"),
+            // different version
+            PathBuf::from("registry/src/github.com-1ecc6299db9ec823/bar-1.0.1"),
+        ];
+
+        let matched = match_archives_to_sources(&archives, &sources);
+
+        assert_eq!(
+            matched,
+            vec![
+                ArchiveSource {
+                    archive: archives[0].clone(),
+                    source: Some(sources[0].clone()),
+                },
+                ArchiveSource {
+                    archive: archives[1].clone(),
+                    source: None,
+                },
+                ArchiveSource {
+                    archive: archives[2].clone(),
+                    source: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn orphaned_sources() {
+        let archives = vec![PathBuf::from(
+            "registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate",
+        )];
+        let sources = vec![
+            PathBuf::from("registry/src/github.com-1ecc6299db9ec823/foo-0.1.0"),
+            PathBuf::from("registry/src/github.com-1ecc6299db9ec823/foo-0.2.0"),
+            PathBuf::from("registry/src/other-registry-0123456789abcdef/foo-0.1.0"),
+        ];
+
+        assert_eq!(
+            sources_without_archive(&archives, &sources),
+            vec![sources[1].clone(), sources[2].clone()]
+        );
+    }
+
+    #[test]
+    fn unextracted_archives() {
+        let archives = vec![
+            PathBuf::from("registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate"),
+            PathBuf::from("registry/cache/github.com-1ecc6299db9ec823/bar-1.0.0.crate"),
+        ];
+        let sources = vec![PathBuf::from(
+            "registry/src/github.com-1ecc6299db9ec823/foo-0.1.0",
+        )];
+
+        assert_eq!(
+            archives_without_source(&archives, &sources),
+            vec![archives[1].clone()]
+        );
+    }
+
+    #[test]
+    fn fully_orphaned_sources() {
+        let archives = vec![PathBuf::from(
+            "registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate",
+        )];
+        let indices = vec![PathBuf::from("registry/index/github.com-1ecc6299db9ec823")];
+        let sources = vec![
+            PathBuf::from("registry/src/github.com-1ecc6299db9ec823/foo-0.1.0"),
+            // no archive, but the registry still has an index
+            PathBuf::from("registry/src/github.com-1ecc6299db9ec823/foo-0.2.0"),
+            // neither archive nor index
+            PathBuf::from("registry/src/other-registry-0123456789abcdef/foo-0.1.0"),
+        ];
+
+        assert_eq!(
+            sources_without_archive_or_index(&archives, &indices, &sources),
+            vec![sources[2].clone()]
+        );
+    }
+
+    #[test]
+    fn unused_indices() {
+        let archives = vec![PathBuf::from(
+            "registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate",
+        )];
+        let indices = vec![
+            PathBuf::from("registry/index/github.com-1ecc6299db9ec823"),
+            PathBuf::from("registry/index/other-registry-0123456789abcdef"),
+        ];
+
+        assert_eq!(
+            indices_without_archives(&archives, &indices),
+            vec![indices[1].clone()]
+        );
+        assert_eq!(indices_without_archives(&[], &indices), indices);
+    }
+}
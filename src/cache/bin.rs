@@ -9,13 +9,18 @@
 
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::cache::caches::Cache;
+use crate::cache::caches::{is_file_entry, Cache};
+use crate::exclude;
+use crate::settings::Settings;
+use crate::size_source;
 
 use rayon::iter::*;
 
 pub(crate) struct BinaryCache {
     path: PathBuf,
+    settings: Arc<Settings>,
     number_of_files: Option<usize>,
     total_size: Option<u64>,
     files_calculated: bool,
@@ -37,10 +42,11 @@ impl BinaryCache {
 }
 
 impl Cache for BinaryCache {
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         // init fields lazily and only compute/save values as needed
         Self {
             path,
+            settings,
             number_of_files: None,
             total_size: None,
             files_calculated: false,
@@ -67,14 +73,11 @@ impl Cache for BinaryCache {
         if let Some(total_size) = self.total_size {
             total_size
         } else if self.path().is_dir() {
+            let settings = Arc::clone(&self.settings);
             let total_size = self
                 .files()
                 .par_iter()
-                .map(|f| {
-                    fs::metadata(f)
-                        .unwrap_or_else(|_| panic!("Failed to get size of file: '{:?}'", f))
-                        .len()
-                })
+                .map(|f| size_source::file_size(&settings, f))
                 .sum();
             self.total_size = Some(total_size);
             total_size
@@ -87,13 +90,21 @@ impl Cache for BinaryCache {
     fn files(&mut self) -> &[PathBuf] {
         if self.files_calculated {
             // do nothing and return
-        } else {
+        } else if self.path_exists() {
             self.files = fs::read_dir(&self.path())
                 .unwrap_or_else(|_| panic!("Failed to read directory: '{:?}'", &self.path))
-                .map(|f| f.unwrap().path())
-                .filter(|f| f.is_file())
+                .map(|f| f.unwrap())
+                .filter(|f| {
+                    f.file_type()
+                        .is_ok_and(|file_type| is_file_entry(file_type, &f.path()))
+                })
+                .map(|f| f.path())
+                .filter(|f| !exclude::is_excluded(&self.settings, f))
                 .collect::<Vec<PathBuf>>();
             self.files_calculated = true;
+        } else {
+            // no binaries were installed yet
+            self.known_to_be_empty();
         }
         &self.files
     }
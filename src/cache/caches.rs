@@ -9,12 +9,16 @@
 
 // TODO: add remove_all() and remove_item() method?
 
+use std::fs::FileType;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::settings::Settings;
 
 // this is impl'd by the bin, git_bare_repos and git_checkouts cache
 pub(crate) trait Cache {
     /// creates a new cache object
-    fn new(path: PathBuf) -> Self;
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self;
 
     /// returns reference to the root path of the cache
     fn path(&self) -> &PathBuf;
@@ -56,7 +60,7 @@ pub(crate) trait RegistrySuperCache {
     type SubCache;
 
     /// creates a new supercache object
-    fn new(path: PathBuf) -> Self;
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self;
 
     /// invalidates all contained subcaches
     fn invalidate(&mut self);
@@ -89,7 +93,7 @@ pub(crate) trait RegistrySuperCache {
 /// a subcache, each registry is represented as a subcache
 pub(crate) trait RegistrySubCache {
     /// create a new subcache
-    fn new(path: PathBuf) -> Self;
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self;
 
     /// /returns the name of the registry
     fn name(&self) -> &str;
@@ -130,6 +134,42 @@ pub(crate) trait RegistrySubCache {
     fn number_of_items(&mut self) -> usize;
 }
 
+/// how a registry filter (such as "clean-index <REGISTRY>") is matched against the registries
+/// the index, the archives and the sources of a registry share the directory name,
+/// but several registries may have the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RegistryMatch {
+    /// the name of the registry: "github.com"
+    Name,
+    /// the directory of the registry ("github.com-1ecc6299db9ec823") or only its hash ("1ecc6299db9ec823")
+    Dir,
+}
+
+impl RegistryMatch {
+    /// whether the registry directory at `path` is the `wanted` registry
+    pub(crate) fn matches(self, path: &Path, wanted: &str) -> bool {
+        match self {
+            Self::Name => get_cache_name(path) == wanted,
+            Self::Dir => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|dir| {
+                    dir == wanted
+                        || dir
+                            .rsplit_once('-')
+                            .is_some_and(|(_name, hash)| hash == wanted)
+                }),
+        }
+    }
+}
+
+/// whether a directory entry is a file, symlinks are followed like `Path::is_file()` does
+/// the file type comes with the directory listing, so only symlinks cost an extra stat,
+/// which matters on high-latency filesystems like sshfs mounts
+pub(crate) fn is_file_entry(file_type: FileType, path: &Path) -> bool {
+    file_type.is_file() || (file_type.is_symlink() && path.is_file())
+}
+
 /// get the name of a cache directory from a path.
 /// if the full path is bla/github.com-1ecc6299db9ec823, we return github.com
 pub(crate) fn get_cache_name(path: &Path) -> String {
@@ -142,3 +182,30 @@ pub(crate) fn get_cache_name(path: &Path) -> String {
     // recombine as String
     v.join("-")
 }
+
+#[cfg(test)]
+mod caches_tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    #[cfg(unix)]
+    fn file_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("foo-0.1.0.crate");
+        fs::write(&file, b"12345").unwrap();
+        fs::create_dir(dir.path().join("foo-0.1.0")).unwrap();
+        std::os::unix::fs::symlink(&file, dir.path().join("link.crate")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("gone"), dir.path().join("broken.crate"))
+            .unwrap();
+
+        let mut files = fs::read_dir(dir.path())
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|entry| is_file_entry(entry.file_type().unwrap(), &entry.path()))
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec!["foo-0.1.0.crate", "link.crate"]);
+    }
+}
@@ -9,14 +9,20 @@
 
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::cache::caches::Cache;
+use crate::cache::caches::{is_file_entry, Cache};
+use crate::exclude;
+use crate::settings::Settings;
+use crate::size_source;
+use crate::warnings;
 
 use rayon::prelude::*;
-use walkdir::WalkDir;
+use walkdir::DirEntry;
 
 pub(crate) struct GitRepoCache {
     path: PathBuf,
+    settings: Arc<Settings>,
     total_size: Option<u64>,
     files_calculated: bool,
     files: Vec<PathBuf>,
@@ -26,10 +32,11 @@ pub(crate) struct GitRepoCache {
 }
 
 impl Cache for GitRepoCache {
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         // calculate as needed
         Self {
             path,
+            settings,
             total_size: None,
             files_calculated: false,
             files: Vec::new(),
@@ -66,12 +73,12 @@ impl Cache for GitRepoCache {
         if let Some(total_size) = self.total_size {
             total_size
         } else if self.path.is_dir() {
-            // get the size of all files in path dir
+            // get the size of all files in path dir, only files are listed
+            let settings = Arc::clone(&self.settings);
             let total_size = self
                 .files()
                 .par_iter()
-                .filter(|f| f.is_file())
-                .map(|f| fs::metadata(f).unwrap().len())
+                .map(|f| size_source::file_size(&settings, f))
                 .sum();
             self.total_size = Some(total_size);
             total_size
@@ -86,11 +93,9 @@ impl Cache for GitRepoCache {
         if self.files_calculated {
             // do nothing and return
         } else if self.path_exists() {
-            let walkdir = WalkDir::new(self.path.display().to_string());
-            let v = walkdir
-                .into_iter()
-                .map(|d| d.unwrap().into_path())
-                .filter(|d| d.is_file())
+            let v = warnings::readable_entries(&self.settings, &self.path)
+                .filter(|entry| is_file_entry(entry.file_type(), entry.path()))
+                .map(DirEntry::into_path)
                 .collect::<Vec<PathBuf>>();
             self.files = v;
         } else {
@@ -113,7 +118,7 @@ impl Cache for GitRepoCache {
             let repo_list = fs::read_dir(&self.path)
                 .unwrap_or_else(|_| panic!("Failed to read directory: '{:?}'", &self.path))
                 .map(|cratepath| cratepath.unwrap().path())
-                .filter(|p| p.is_dir())
+                .filter(|p| p.is_dir() && !exclude::is_excluded(&self.settings, p))
                 .collect::<Vec<PathBuf>>();
 
             self.items_calculated = true;
@@ -7,16 +7,24 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::cache::caches::Cache;
+use crate::exclude;
+use crate::settings::Settings;
+use crate::size_source;
+use crate::warnings;
 
 use rayon::prelude::*;
-use walkdir::WalkDir;
+use walkdir::DirEntry;
 
 pub(crate) struct GitCheckoutCache {
     path: PathBuf,
+    settings: Arc<Settings>,
     total_size: Option<u64>,
     files_calculated: bool,
     files: Vec<PathBuf>,
@@ -26,10 +34,11 @@ pub(crate) struct GitCheckoutCache {
 }
 
 impl Cache for GitCheckoutCache {
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         // lazy cache, compute only as needed and save
         Self {
             path,
+            settings,
             total_size: None,
             files_calculated: false,
             files: Vec::new(),
@@ -65,15 +74,14 @@ impl Cache for GitCheckoutCache {
         if let Some(total_size) = self.total_size {
             total_size
         } else if self.path.is_dir() {
-            // get the size of all files in path dir
+            // get the size of all files in path dir, the directories are part of files() but
+            // the du output of "--size-source" lists the size of everything below them
+            let settings = Arc::clone(&self.settings);
             let total_size = self
                 .files()
                 .par_iter()
-                .map(|f| {
-                    fs::metadata(f)
-                        .unwrap_or_else(|_| panic!("Failed to read size of file: '{:?}'", f))
-                        .len()
-                })
+                .filter(|f| !size_source::is_dir(&settings, f))
+                .map(|f| size_source::file_size(&settings, f))
                 .sum();
             self.total_size = Some(total_size);
             total_size
@@ -87,11 +95,10 @@ impl Cache for GitCheckoutCache {
         if self.files_calculated {
             // do nothing and return
         } else if self.path_exists() {
-            let walkdir = WalkDir::new(self.path.display().to_string());
-            let v = walkdir
-                .into_iter()
-                .map(|d| d.unwrap().into_path())
-                .filter(|f| f.exists())
+            // only symlinks can point to something that does not exist
+            let v = warnings::readable_entries(&self.settings, &self.path)
+                .filter(|entry| !entry.path_is_symlink() || entry.path().exists())
+                .map(DirEntry::into_path)
                 .collect::<Vec<PathBuf>>();
             self.files = v;
         } else {
@@ -130,7 +137,7 @@ impl Cache for GitCheckoutCache {
                 for i in fs::read_dir(&repo)
                     .unwrap_or_else(|_| panic!("Failed to read directory: '{:?}'", &repo))
                     .map(|cratepath| cratepath.unwrap().path())
-                    .filter(|f| f.is_dir())
+                    .filter(|f| f.is_dir() && !exclude::is_excluded(&self.settings, f))
                 {
                     both_levels_vec.push(i);
                 }
@@ -163,3 +170,21 @@ impl GitCheckoutCache {
         &self.items
     }
 }
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// git/checkouts/<repo>/<rev>, the revs grouped by repo, the most recently modified first
+pub(crate) fn checkouts_by_repo(checkouts: &[PathBuf]) -> BTreeMap<&Path, Vec<&PathBuf>> {
+    let mut by_repo: BTreeMap<&Path, Vec<&PathBuf>> = BTreeMap::new();
+    for checkout in checkouts {
+        if let Some(repo) = checkout.parent() {
+            by_repo.entry(repo).or_default().push(checkout);
+        }
+    }
+    for revs in by_repo.values_mut() {
+        revs.sort_by_key(|rev| std::cmp::Reverse(modification_time(rev)));
+    }
+    by_repo
+}
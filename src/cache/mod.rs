@@ -7,6 +7,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+pub(crate) mod archive_sources;
 pub(crate) mod bin;
 pub(crate) mod caches;
 pub(crate) mod git_bare_repos;
@@ -7,13 +7,64 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use crate::cache::caches::{get_cache_name, RegistrySubCache, RegistrySuperCache};
+use crate::cache::caches::{get_cache_name, is_file_entry, RegistrySubCache, RegistrySuperCache};
+use crate::exclude;
+use crate::relative_paths;
+use crate::settings::Settings;
+use crate::size_source;
+use crate::warnings;
 
 use rayon::iter::*;
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+
+/// indices with fewer directories in their first two levels are walked on a single thread,
+/// on shallow trees spawning the parallel walk costs more than it saves
+const PARALLEL_WALK_MIN_DIRS: usize = 64;
+
+/// check if the index at `path` is deep enough to be walked in parallel
+fn walk_in_parallel(path: &Path) -> bool {
+    rayon::current_num_threads() > 1
+        && WalkDir::new(path)
+            .min_depth(1)
+            .max_depth(2)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_dir())
+            .take(PARALLEL_WALK_MIN_DIRS)
+            .count()
+            == PARALLEL_WALK_MIN_DIRS
+}
+
+/// all entries below `path` in the order of walkdir, walked on a single thread
+fn index_files_walkdir(settings: &Settings, path: &Path) -> Vec<PathBuf> {
+    warnings::readable_entries(settings, path)
+        .map(DirEntry::into_path)
+        .collect()
+}
+
+/// all entries below `path`, the directories are read in parallel by jwalk
+fn index_files_jwalk(settings: &Settings, path: &Path) -> Vec<PathBuf> {
+    jwalk::WalkDir::new(path)
+        // the index contains ".git" and ".cache"
+        .skip_hidden(false)
+        .into_iter()
+        .filter_map(|entry| match entry {
+            // the walk is not pruned, but the contents of excluded directories are excluded as well
+            Ok(readable) => {
+                Some(readable.path()).filter(|file| !exclude::is_excluded(settings, file))
+            }
+            Err(error) => {
+                let skipped =
+                    relative_paths::shown(settings, error.path().unwrap_or(path)).display();
+                warnings::warn(settings, format!("skipped '{skipped}': {error}"));
+                None
+            }
+        })
+        .collect()
+}
 
 /// describes a single index of a crate registry index
 pub(crate) struct RegistryIndex {
@@ -21,6 +72,8 @@ pub(crate) struct RegistryIndex {
     name: String,
     /// the path of the root dir of the index, this is unique
     path: PathBuf,
+    /// the settings of the run
+    settings: Arc<Settings>,
     /// total size of the index, computed on-demand
     size: Option<u64>,
     /// number of files of the cache
@@ -33,10 +86,11 @@ pub(crate) struct RegistryIndex {
 
 impl RegistrySubCache for RegistryIndex {
     /// create a new empty `RegistryIndex`
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         Self {
             name: get_cache_name(&path),
             path,
+            settings,
             size: None,
             number_of_files: None,
             files_calculated: false,
@@ -79,20 +133,38 @@ impl RegistrySubCache for RegistryIndex {
         match self.size {
             Some(size) => size,
             None => {
-                if self.path.is_dir() {
+                if self.path.is_dir() && self.files_calculated {
                     // get the size of all files in path dir
+                    let settings = Arc::clone(&self.settings);
                     let total_size = self
                         .files()
                         .par_iter()
                         .filter(|f| f.is_file())
-                        .map(|f| {
-                            fs::metadata(f)
-                                .unwrap_or_else(|_| panic!("Failed to get size of file: '{:?}'", f))
-                                .len()
-                        })
+                        .map(|f| size_source::file_size(&settings, f))
                         .sum();
                     self.size = Some(total_size);
                     total_size
+                } else if self.path.is_dir() {
+                    // indices can contain millions of files, if we only need the size,
+                    // sum it up while walking the dir instead of keeping all the paths around
+                    let settings = &self.settings;
+                    let (total_size, number_of_files) =
+                        warnings::readable_entries(settings, &self.path)
+                            .par_bridge()
+                            .map(|direntry| {
+                                // directories are counted, but only files are sized
+                                if !is_file_entry(direntry.file_type(), direntry.path()) {
+                                    return (0, 1);
+                                }
+                                (size_source::file_size(settings, direntry.path()), 1)
+                            })
+                            .reduce(
+                                || (0, 0),
+                                |(size_a, num_a), (size_b, num_b)| (size_a + size_b, num_a + num_b),
+                            );
+                    self.size = Some(total_size);
+                    self.number_of_files = Some(number_of_files);
+                    total_size
                 } else {
                     self.known_to_be_empty();
                     0
@@ -106,11 +178,11 @@ impl RegistrySubCache for RegistryIndex {
         if self.files_calculated {
             // do nothing and return
         } else if self.path_exists() {
-            let walkdir = WalkDir::new(self.path.display().to_string());
-            let vec = walkdir
-                .into_iter()
-                .map(|direntry| direntry.unwrap().into_path())
-                .collect::<Vec<PathBuf>>();
+            let vec = if walk_in_parallel(&self.path) {
+                index_files_jwalk(&self.settings, &self.path)
+            } else {
+                index_files_walkdir(&self.settings, &self.path)
+            };
 
             self.number_of_files = Some(vec.len());
 
@@ -160,6 +232,29 @@ impl RegistrySubCache for RegistryIndex {
     }
 }
 
+impl RegistryIndex {
+    /// the sizes of the index itself and of the ".cache" dir cargo keeps for fast lookups: (git, .cache)
+    pub(crate) fn split_size(&mut self) -> (u64, u64) {
+        let cache_dir = self.path.join(".cache");
+        let settings = Arc::clone(&self.settings);
+        self.files()
+            .par_iter()
+            .filter(|f| f.is_file())
+            .map(|f| {
+                let size = size_source::file_size(&settings, f);
+                if f.starts_with(&cache_dir) {
+                    (0, size)
+                } else {
+                    (size, 0)
+                }
+            })
+            .reduce(
+                || (0, 0),
+                |(git_a, cache_a), (git_b, cache_b)| (git_a + git_b, cache_a + cache_b),
+            )
+    }
+}
+
 pub(crate) struct RegistryIndicesCache {
     /// root path of the cache
     #[allow(unused)]
@@ -181,7 +276,7 @@ impl RegistrySuperCache for RegistryIndicesCache {
     type SubCache = RegistryIndex;
 
     /// create a new empty `RegistryIndexCache`
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         if !path.exists() {
             return Self {
                 path,
@@ -201,7 +296,7 @@ impl RegistrySuperCache for RegistryIndicesCache {
             .map(|direntry| direntry.unwrap().path())
             .filter(|p| p.is_dir() && p.file_name().unwrap().to_str().unwrap().contains('-'))
             //.inspect(|p| println!("p: {:?}", p))
-            .map(RegistryIndex::new)
+            .map(|index| RegistryIndex::new(index, Arc::clone(&settings)))
             .collect::<Vec<RegistryIndex>>();
 
         Self {
@@ -288,3 +383,118 @@ impl RegistrySuperCache for RegistryIndicesCache {
         self.caches().len()
     }
 }
+
+#[cfg(test)]
+mod registry_index_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn size_without_file_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("github.com-1ecc6299db9ec823");
+        fs::create_dir_all(index.join("3").join("f")).unwrap();
+        fs::write(index.join("config.json"), b"12345").unwrap();
+        fs::write(index.join("3").join("f").join("foo"), b"123").unwrap();
+
+        let mut cache = RegistryIndex::new(index, Arc::default());
+        assert_eq!(cache.total_size(), 8);
+        // the list of files was not built to get the size
+        assert!(!cache.files_calculated);
+        // index dir, config.json, 3, 3/f, 3/f/foo
+        assert_eq!(cache.number_of_files(), 5);
+        assert_eq!(cache.files().len(), 5);
+    }
+
+    #[test]
+    fn parallel_walk_finds_the_same_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("github.com-1ecc6299db9ec823");
+        for (n, prefix) in ["3/f", "fo/ob", ".cache/fo/ob", ".git/objects/ab"]
+            .iter()
+            .enumerate()
+        {
+            fs::create_dir_all(index.join(prefix)).unwrap();
+            fs::write(index.join(prefix).join("foo"), vec![0; n]).unwrap();
+        }
+        fs::write(index.join("config.json"), b"12345").unwrap();
+
+        let settings = Settings::default();
+        let mut walkdir_files = index_files_walkdir(&settings, &index);
+        let mut jwalk_files = index_files_jwalk(&settings, &index);
+        walkdir_files.sort();
+        jwalk_files.sort();
+        assert_eq!(walkdir_files, jwalk_files);
+        assert_eq!(walkdir_files.len(), 16);
+
+        let size = |files: &[PathBuf]| -> u64 {
+            files
+                .iter()
+                .filter(|file| file.is_file())
+                .map(|file| size_source::file_size(&settings, file))
+                .sum()
+        };
+        assert_eq!(size(&walkdir_files), 11);
+        assert_eq!(size(&jwalk_files), 11);
+    }
+
+    #[test]
+    fn cache_dir_is_split_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("github.com-1ecc6299db9ec823");
+        fs::create_dir_all(index.join(".cache").join("3").join("f")).unwrap();
+        fs::create_dir_all(index.join(".git")).unwrap();
+        fs::write(index.join(".cache").join("3").join("f").join("foo"), b"123").unwrap();
+        fs::write(index.join(".git").join("packed-refs"), b"12345").unwrap();
+        fs::write(index.join("config.json"), b"12").unwrap();
+
+        let mut cache = RegistryIndex::new(index, Arc::default());
+        assert_eq!(cache.split_size(), (7, 3));
+        assert_eq!(cache.total_size(), 10);
+    }
+
+    #[test]
+    fn shallow_indices_are_walked_on_one_thread() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("3").join("f")).unwrap();
+        assert!(!walk_in_parallel(dir.path()));
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod benchmarks {
+    use super::*;
+    use crate::test::black_box;
+    use crate::test::Bencher;
+
+    /// an index with 32 * 32 directories of 4 files each
+    fn index_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for outer in 0..32 {
+            for inner in 0..32 {
+                let prefix = dir
+                    .path()
+                    .join(format!("{outer:02}"))
+                    .join(format!("{inner:02}"));
+                fs::create_dir_all(&prefix).unwrap();
+                for file in 0..4 {
+                    fs::write(prefix.join(format!("crate-{file}")), b"{}").unwrap();
+                }
+            }
+        }
+        dir
+    }
+
+    #[bench]
+    fn bench_index_files_walkdir(b: &mut Bencher) {
+        let dir = index_tree();
+        b.iter(|| black_box(index_files_walkdir(&Settings::default(), dir.path())));
+    }
+
+    #[bench]
+    fn bench_index_files_jwalk(b: &mut Bencher) {
+        let dir = index_tree();
+        b.iter(|| black_box(index_files_jwalk(&Settings::default(), dir.path())));
+    }
+}
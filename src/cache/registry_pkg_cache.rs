@@ -9,8 +9,12 @@
 
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::cache::caches::{get_cache_name, RegistrySubCache, RegistrySuperCache};
+use crate::cache::caches::{get_cache_name, is_file_entry, RegistrySubCache, RegistrySuperCache};
+use crate::exclude;
+use crate::settings::Settings;
+use crate::size_source;
 
 use rayon::prelude::*;
 
@@ -20,6 +24,8 @@ pub(crate) struct RegistryPkgCache {
     name: String,
     /// the path of the root dir of the index, this is unique
     path: PathBuf,
+    /// the settings of the run
+    settings: Arc<Settings>,
     /// total size of the index, computed on-demand
     size: Option<u64>,
     /// number of files of the cache
@@ -32,10 +38,11 @@ pub(crate) struct RegistryPkgCache {
 
 impl RegistrySubCache for RegistryPkgCache {
     /// create a new empty `RegistryPkgCache`
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         Self {
             name: get_cache_name(&path),
             path,
+            settings,
             size: None,
             number_of_files: None,
             files_calculated: false,
@@ -74,15 +81,12 @@ impl RegistrySubCache for RegistryPkgCache {
             None => {
                 if self.path.is_dir() {
                     // get the size of all files in path https://news.ycombinator.com/https://news.ycombinator.com/dir
+                    // only files are listed
+                    let settings = Arc::clone(&self.settings);
                     let total_size = self
                         .files()
                         .par_iter()
-                        .filter(|f| f.is_file())
-                        .map(|f| {
-                            fs::metadata(f)
-                                .unwrap_or_else(|_| panic!("Failed to get size of file: '{:?}'", f))
-                                .len()
-                        })
+                        .map(|f| size_source::file_size(&settings, f))
                         .sum();
                     self.size = Some(total_size);
                     total_size
@@ -101,7 +105,14 @@ impl RegistrySubCache for RegistryPkgCache {
         } else if self.path_exists() {
             let collection = fs::read_dir(&self.path)
                 .unwrap_or_else(|_| panic!("Failed to read directory (repo): '{:?}'", &self.path))
-                .map(|cratepath| cratepath.unwrap().path())
+                .map(|cratepath| cratepath.unwrap())
+                .filter(|cratepath| {
+                    cratepath
+                        .file_type()
+                        .is_ok_and(|file_type| is_file_entry(file_type, &cratepath.path()))
+                })
+                .map(|cratepath| cratepath.path())
+                .filter(|cratepath| !exclude::is_excluded(&self.settings, cratepath))
                 .collect::<Vec<_>>();
 
             self.files_calculated = true;
@@ -171,7 +182,7 @@ impl RegistrySuperCache for RegistryPkgCaches {
     type SubCache = RegistryPkgCache;
 
     /// create a new empty `RegistryPkgCaches`
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         if !path.exists() {
             return Self {
                 path,
@@ -191,7 +202,7 @@ impl RegistrySuperCache for RegistryPkgCaches {
             .map(|direntry| direntry.unwrap().path())
             .filter(|p| p.is_dir() && p.file_name().unwrap().to_str().unwrap().contains('-'))
             //.inspect(|p| println!("p: {:?}", p))
-            .map(RegistryPkgCache::new)
+            .map(|cache| RegistryPkgCache::new(cache, Arc::clone(&settings)))
             .collect::<Vec<RegistryPkgCache>>();
 
         Self {
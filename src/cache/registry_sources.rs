@@ -7,13 +7,18 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fs;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::cache::caches::{get_cache_name, RegistrySubCache, RegistrySuperCache};
+use crate::cache::caches::{get_cache_name, is_file_entry, RegistrySubCache, RegistrySuperCache};
+use crate::exclude;
+use crate::settings::Settings;
+use crate::size_source;
+use crate::warnings;
 
 use rayon::prelude::*;
-use walkdir::WalkDir;
+use walkdir::DirEntry;
 
 #[derive(Debug, Clone)]
 /// describes one registry source cache (extracted .crates)
@@ -22,6 +27,8 @@ pub(crate) struct RegistrySourceCache {
     name: String,
     /// the path of the root dir of the index, this is unique
     path: PathBuf,
+    /// the settings of the run
+    settings: Arc<Settings>,
     /// total size of the cache, computed on-demand
     size: Option<u64>,
     /// number of files of the cache
@@ -37,10 +44,11 @@ pub(crate) struct RegistrySourceCache {
 }
 
 impl RegistrySubCache for RegistrySourceCache {
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         Self {
             name: get_cache_name(&path),
             path,
+            settings,
             size: None,
             number_of_files: None,
             files_calculated: false,
@@ -84,11 +92,9 @@ impl RegistrySubCache for RegistrySourceCache {
             // do nothing as everything is already calculated
         }
         if self.path_exists() {
-            let walkdir = WalkDir::new(self.path.display().to_string());
-            let v = walkdir
-                .into_iter()
-                .map(|d| d.unwrap().into_path())
-                .filter(|d| d.is_file())
+            let v = warnings::readable_entries(&self.settings, &self.path)
+                .filter(|entry| is_file_entry(entry.file_type(), entry.path()))
+                .map(DirEntry::into_path)
                 .collect::<Vec<PathBuf>>();
             self.files = v;
         } else {
@@ -102,11 +108,12 @@ impl RegistrySubCache for RegistrySourceCache {
             return size;
         } else if self.path.is_dir() {
             // get the size of all files in path dir
+            // only files are listed
+            let settings = Arc::clone(&self.settings);
             let size = self
                 .files()
                 .par_iter()
-                .filter(|f| f.is_file())
-                .map(|f| fs::metadata(f).unwrap().len())
+                .map(|f| size_source::file_size(&settings, f))
                 .sum();
             self.size = Some(size);
         } else {
@@ -151,6 +158,7 @@ impl RegistrySubCache for RegistrySourceCache {
                 .unwrap_or_else(|_| panic!("Failed to read {:?}", self.path.display()))
                 .map(|direntry| direntry.unwrap().path())
                 .filter(|p| p.is_dir() && p.file_name().unwrap().to_str().unwrap().contains('-'))
+                .filter(|p| !exclude::is_excluded(&self.settings, p))
                 .collect::<Vec<PathBuf>>();
             self.items = folders;
             self.items_calculated = true;
@@ -170,6 +178,8 @@ impl RegistrySubCache for RegistrySourceCache {
 pub(crate) struct RegistrySourceCaches {
     /// root path of the cache
     path: PathBuf,
+    /// the settings of the run
+    settings: Arc<Settings>,
     /// list of pkg caches (from alternative registries or so)
     caches: Vec<RegistrySourceCache>,
     /// number of pkg caches found
@@ -191,10 +201,11 @@ impl RegistrySuperCache for RegistrySourceCaches {
         &mut self.caches
     }
 
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
         if !path.exists() {
             return Self {
                 path,
+                settings,
                 number_of_caches: 0,
                 caches: vec![],
                 total_number_of_files: None,
@@ -210,11 +221,12 @@ impl RegistrySuperCache for RegistrySourceCaches {
         let registry_folders = registries
             .map(|direntry| direntry.unwrap().path())
             .filter(|p| p.is_dir() && p.file_name().unwrap().to_str().unwrap().contains('-'))
-            .map(RegistrySourceCache::new)
+            .map(|cache| RegistrySourceCache::new(cache, Arc::clone(&settings)))
             .collect::<Vec<RegistrySourceCache>>();
 
         Self {
             path,
+            settings,
             number_of_caches: registry_folders.len(),
             caches: registry_folders,
             total_number_of_files: None,
@@ -299,4 +311,27 @@ impl RegistrySourceCaches {
         self.items.sort();
         self.items()
     }
+
+    /// the size of every extracted crate, summed up from the files of the caches like `total_size()`
+    /// so that the sizes of all crates add up to the total size
+    pub(crate) fn item_sizes(&mut self) -> HashMap<PathBuf, u64> {
+        let items: HashSet<PathBuf> = self.items().iter().cloned().collect();
+        let settings = Arc::clone(&self.settings);
+        let file_sizes = self
+            .files()
+            .into_par_iter()
+            .map(|file| {
+                let size = size_source::file_size(&settings, &file);
+                (file, size)
+            })
+            .collect::<Vec<_>>();
+
+        let mut sizes = HashMap::new();
+        for (file, size) in file_sizes {
+            if let Some(item) = file.ancestors().skip(1).find(|dir| items.contains(*dir)) {
+                *sizes.entry(item.to_path_buf()).or_insert(0) += size;
+            }
+        }
+        sizes
+    }
 }
@@ -12,15 +12,26 @@
 //https://github.com/rust-lang/rust-clippy/issues/7202
 #![allow(clippy::needless_collect)]
 
-use std::ffi::OsStr;
-use std::path::{Path, PathBuf};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
 
 use crate::cache::caches::*;
 use crate::cache::*;
 use crate::library::*;
 use crate::library::{CargoCachePaths, Error};
+use crate::lockfile::LockfilePins;
+use crate::output::to_json;
+use crate::relative_paths;
 use crate::remove::*;
+use crate::settings::Settings;
 use cargo_metadata::{CargoOpt, MetadataCommand};
+use serde::{Deserialize, Serialize};
+
+/// file inside the cargo home that caches the items referenced by manifests
+const METADATA_CACHE_FILE: &str = "cargo-cache-metadata.json";
 
 // the source of a crate inside the cargo cache can be represented in form of
 // an extracted .crate or a checked out git repository
@@ -40,71 +51,310 @@ impl SourceKind {
     }
 }
 
+/// the cache classes that `clean_unref()` is allowed to remove items from
+#[allow(clippy::struct_excessive_bools)] // one flag per cache class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CleanUnrefTargets {
+    /// remove the git checkouts (git/checkouts) entirely
+    pub(crate) git_checkouts: bool,
+    /// remove the registry sources (registry/src) entirely
+    pub(crate) registry_sources: bool,
+    /// remove bare git repos (git/db) that are not referenced
+    pub(crate) git_repos_bare: bool,
+    /// remove .crate archives (registry/cache) that are not referenced
+    pub(crate) registry_pkg_cache: bool,
+}
+
+impl Default for CleanUnrefTargets {
+    /// by default, clean-unref operates on all four cache classes
+    fn default() -> Self {
+        Self {
+            git_checkouts: true,
+            registry_sources: true,
+            git_repos_bare: true,
+            registry_pkg_cache: true,
+        }
+    }
+}
+
+impl CleanUnrefTargets {
+    /// only prune unreferenced archives and bare repos, keep all extracted sources and checkouts
+    pub(crate) fn keep_sources(self) -> Self {
+        Self {
+            git_checkouts: false,
+            registry_sources: false,
+            ..self
+        }
+    }
+}
+
+/// the root of the crate a manifest inside `<cargo_home>/<dir>` belongs to, `dir` is followed by two more segments
+/// the path is only split below the cargo home, so that the cargo home itself may contain
+/// segments like "registry" and Windows drive prefixes are kept as they are
+fn crate_root_below(toml_path: &Path, cargo_home: &Path, dir: &[&str]) -> Option<PathBuf> {
+    let mut segments = toml_path
+        .strip_prefix(cargo_home)
+        .ok()?
+        .components()
+        .map(Component::as_os_str);
+
+    let mut root = cargo_home.to_path_buf();
+    for expected in dir {
+        let segment = segments.next()?;
+        if segment != *expected {
+            return None;
+        }
+        root.push(segment);
+    }
+    // repo and rev or registry and crate
+    root.push(segments.next()?);
+    root.push(segments.next()?);
+    Some(root)
+}
+
 fn find_crate_name_git(toml_path: &Path, cargo_home: &Path) -> Option<SourceKind> {
     // ~/.cargo/git/checkouts/home-fb9469891e5cfbe6/3a6eccd/cargo.toml  => ~/.cargo/git/checkouts/home-fb9469891e5cfbe6/3a6eccd/
+    crate_root_below(toml_path, cargo_home, &["git", "checkouts"]).map(SourceKind::Git)
+}
 
-    // get the segments of the path
-    let v: Vec<&OsStr> = toml_path.iter().collect();
+fn find_crate_name_crate(toml_path: &Path, cargo_home: &Path) -> Option<SourceKind> {
+    //  ~/.cargo/registry/src/github.com-1ecc6299db9ec823/winapi-0.3.8/Cargo.toml => ~/.cargo/registry/src/github.com-1ecc6299db9ec823/winapi-0.3.8/
+    crate_root_below(toml_path, cargo_home, &["registry", "src"]).map(SourceKind::Crate)
+}
 
-    // if we could not find a position, return None
-    let checkouts_pos = v.iter().position(|i| i == &"checkouts")?;
+/// ~/.cargo/registry/src/github.com-1ecc6299db9ec823/semver-0.9.0
+/// =>
+/// ~/.cargo/registry/cache/github.com-1ecc6299db9ec823/semver-0.9.0.crate
+fn archive_of_source(source: &Path, registry_pkg_cache: &Path) -> Option<PathBuf> {
+    let package_name = source.file_name()?;
+    let registry = source.parent()?.file_name()?;
+    // can't use .set_extension() here because "cratename-0.1.3" will detect the ".3" as extension
+    // and change it
+    let mut archive_name = package_name.to_os_string();
+    archive_name.push(".crate");
+    Some(registry_pkg_cache.join(registry).join(archive_name))
+}
 
-    // assuming git:
-    // git checkouts repo-name ref
-    let path_segments = &v[(checkouts_pos - 1)..(checkouts_pos + 3)];
+/// ~/.cargo/git/checkouts/cargo-e7ff1db891893a9e/258c896
+/// =>
+/// ~/.cargo/git/db/cargo-e7ff1db891893a9e
+fn bare_repo_of_checkout(checkout: &Path, git_repos_bare: &Path) -> Option<PathBuf> {
+    let repo_name = checkout.parent()?.file_name()?;
+    Some(git_repos_bare.join(repo_name))
+}
 
-    let mut path = cargo_home.to_path_buf();
-    path_segments.iter().for_each(|p| path.push(p));
+/// the required archives that are not in the cache
+/// the archive paths are derived from the paths of the sources, so if a required archive is missing,
+/// the derived path may be wrong and the archive that is actually referenced would be removed
+fn missing_archives(required_crates: &[PathBuf]) -> Vec<&PathBuf> {
+    required_crates
+        .iter()
+        .filter(|archive| !archive.is_file())
+        .collect()
+}
 
-    Some(SourceKind::Git(path))
+/// the items clean-unref would remove, by cache class
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+struct PlannedRemovals {
+    archives: Vec<PathBuf>,
+    repos: Vec<PathBuf>,
+    sources: Vec<PathBuf>,
+    checkouts: Vec<PathBuf>,
 }
 
-fn find_crate_name_crate(toml_path: &Path, cargo_home: &Path) -> Option<SourceKind> {
-    //  ~/.cargo/registry/src/github.com-1ecc6299db9ec823/winapi-0.3.8/Cargo.toml => ~/.cargo/registry/src/github.com-1ecc6299db9ec823/winapi-0.3.8/
+/// the plan of "clean-unref --dry-run --format json": the referenced archives and bare repos,
+/// the items that would be removed and how many bytes that frees
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct CleanUnrefPlan {
+    required: Vec<PathBuf>,
+    to_remove: PlannedRemovals,
+    reclaimable_bytes: u64,
+}
+
+/// JSON Schema (draft 7) of the plan of clean-unref
+#[cfg(test)]
+pub(crate) const PLAN_SCHEMA: &str = r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache clean-unref --dry-run --format json",
+  "type": "object",
+  "definitions": {
+    "paths": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "required": { "$ref": "#/definitions/paths" },
+    "to_remove": {
+      "type": "object",
+      "properties": {
+        "archives": { "$ref": "#/definitions/paths" },
+        "repos": { "$ref": "#/definitions/paths" },
+        "sources": { "$ref": "#/definitions/paths" },
+        "checkouts": { "$ref": "#/definitions/paths" }
+      },
+      "required": ["archives", "repos", "sources", "checkouts"],
+      "additionalProperties": false
+    },
+    "reclaimable_bytes": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "required", "to_remove", "reclaimable_bytes"],
+  "additionalProperties": false
+}"##;
+
+impl PlannedRemovals {
+    /// the number of items that would be removed
+    fn len(&self) -> usize {
+        self.archives.len() + self.repos.len() + self.sources.len() + self.checkouts.len()
+    }
+
+    /// all the items, by cache class
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut PathBuf> {
+        self.archives
+            .iter_mut()
+            .chain(&mut self.repos)
+            .chain(&mut self.sources)
+            .chain(&mut self.checkouts)
+    }
+}
 
-    let v: Vec<&OsStr> = toml_path.iter().collect();
+impl CleanUnrefPlan {
+    /// sizes the items to remove, all lists are sorted and hold the paths as they are shown
+    fn new(
+        settings: &Settings,
+        mut required: Vec<PathBuf>,
+        mut to_remove: PlannedRemovals,
+    ) -> Self {
+        required.sort();
+        let mut reclaimable_bytes = 0;
+        for items in [
+            &mut to_remove.archives,
+            &mut to_remove.repos,
+            &mut to_remove.sources,
+            &mut to_remove.checkouts,
+        ] {
+            items.sort();
+            reclaimable_bytes += items
+                .iter()
+                .map(|item| size_of_path(settings, item))
+                .sum::<u64>();
+        }
+        for item in required.iter_mut().chain(to_remove.iter_mut()) {
+            *item = relative_paths::shown(settings, item).to_path_buf();
+        }
+        Self {
+            required,
+            to_remove,
+            reclaimable_bytes,
+        }
+    }
+}
 
-    // if we could not find a position, return None
-    let registry_pos = v.iter().position(|i| i == &"registry")?;
+/// the items a manifest references, together with a hash of the Cargo.lock they were computed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedReferences {
+    lockfile_hash: u64,
+    crates: Vec<PathBuf>,
+    git_repos: Vec<PathBuf>,
+}
 
-    let path_segments = &v[(registry_pos)..(registry_pos + 4)];
-    let mut path = cargo_home.to_path_buf();
-    path_segments.iter().for_each(|p| path.push(p));
+/// the cached references of all manifests, by manifest path
+type MetadataCache = BTreeMap<PathBuf, CachedReferences>;
 
-    Some(SourceKind::Crate(path))
+/// the Cargo.lock that belongs to a manifest, it is in the dir of the manifest or in the workspace root above it
+fn find_lockfile(manifest: &Path) -> Option<PathBuf> {
+    manifest
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lockfile| lockfile.is_file())
 }
 
-/// look at a crate manifest and remove all items from the cargo cache that are not referenced, also run --autoclean and invalidate caches
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn clean_unref(
-    cargo_cache_paths: &CargoCachePaths,
-    manifest_path: Option<&str>,
-    mut bin_cache: &mut bin::BinaryCache,
-    mut checkouts_cache: &mut git_checkouts::GitCheckoutCache,
-    mut bare_repos_cache: &mut git_bare_repos::GitRepoCache,
-    mut registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
-    mut registry_index_caches: &mut registry_index::RegistryIndicesCache,
-    mut registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
-    dry_run: bool,
-    size_changed: &mut bool,
-) -> Result<(), Error> {
-    // total cache size before removing, for the summary
-    let original_total_cache_size = bin_cache.total_size()
-        + checkouts_cache.total_size()
-        + bare_repos_cache.total_size()
-        + registry_pkg_caches.total_size()
-        + registry_index_caches.total_size()
-        + registry_sources_caches.total_size();
+/// the hash does not need to be stable across cargo-cache builds, a changed hash only invalidates the cache
+fn hash_of_file(path: &Path) -> Option<u64> {
+    let content = fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Some(hasher.finish())
+}
 
-    // first get a list of all dependencies of the project
-    let cargo_home = &cargo_cache_paths.cargo_home;
+/// a missing or broken cache file is treated like an empty cache
+fn read_metadata_cache(path: &Path) -> MetadataCache {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
 
+/// failing to write the cache is not an error, we will just run "cargo metadata" again next time
+fn write_metadata_cache(path: &Path, cache: &MetadataCache) {
+    if let Ok(json) = serde_json::to_string_pretty(cache) {
+        let _ = fs::write(path, json);
+    }
+}
+
+/// the .crate archives and bare git repos inside the cargo home that a crate manifest depends on
+/// if no manifest path is given, try to find a manifest in the current directory or upwards
+/// the results are cached in the cargo home and reused as long as the Cargo.lock does not change,
+/// without `use_metadata_cache` "cargo metadata" is always run and the cache is refreshed
+pub(crate) fn referenced_items(
+    cargo_cache_paths: &CargoCachePaths,
+    manifest_path: Option<&str>,
+    use_metadata_cache: bool,
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>), Error> {
     // if "--manifest-path" is passed to the subcommand, take this
     // if it is not passed, try to find a close manifest somewhere
     let manifest = match manifest_path {
         Some(path_str) => PathBuf::from(path_str),
         None => crate::local::get_manifest()?,
     };
+    let manifest_key = manifest.canonicalize().unwrap_or_else(|_| manifest.clone());
+    let lockfile_hash =
+        || find_lockfile(&manifest_key).and_then(|lockfile| hash_of_file(&lockfile));
+
+    let cache_path = cargo_cache_paths.cargo_home.join(METADATA_CACHE_FILE);
+    let mut cache = read_metadata_cache(&cache_path);
+
+    if use_metadata_cache {
+        if let Some(cached) = cache
+            .get(&manifest_key)
+            .filter(|cached| Some(cached.lockfile_hash) == lockfile_hash())
+        {
+            return Ok((cached.crates.clone(), cached.git_repos.clone()));
+        }
+    }
+
+    let (crates, git_repos) = referenced_items_from_metadata(cargo_cache_paths, manifest)?;
+
+    // "cargo metadata" may have updated the lockfile, so hash it again
+    // without a lockfile, there is nothing the cache could be keyed by
+    if let Some(hash) = lockfile_hash() {
+        let _ = cache.insert(
+            manifest_key.clone(),
+            CachedReferences {
+                lockfile_hash: hash,
+                crates: crates.clone(),
+                git_repos: git_repos.clone(),
+            },
+        );
+        write_metadata_cache(&cache_path, &cache);
+    }
+
+    Ok((crates, git_repos))
+}
+
+/// run "cargo metadata" on the manifest and map the dependencies to items inside the cargo home
+fn referenced_items_from_metadata(
+    cargo_cache_paths: &CargoCachePaths,
+    manifest: PathBuf,
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>), Error> {
+    let cargo_home = &cargo_cache_paths.cargo_home;
 
     let metadata = MetadataCommand::new()
         .manifest_path(&manifest)
@@ -139,74 +389,22 @@ pub(crate) fn clean_unref(
         // we need to map the git repo checkouts to bare git repos
         // and the source-checkouts to pkg cache archives!
         .map(|sourcekind| match sourcekind {
-            SourceKind::Crate(registry_src_path) => {
-                // ~/.cargo/registry/src/github.com-1ecc6299db9ec823/semver-0.9.0
-                // =>
-                // ~/.cargo/registry/cache/github.com-1ecc6299db9ec823/semver-0.9.0.crate
-                let path = registry_src_path.iter().collect::<Vec<&OsStr>>();
-                let package_name = path[path.len() - 1];
-                let registry = path[path.len() - 2];
-                let mut registry_cache_path = cargo_cache_paths.registry_pkg_cache.clone();
-                // we need to push the registry index as well
-                registry_cache_path.push(registry);
-                // this can probably be
-                // can't use .set_extension() here because "cratename-0.1.3" will detect the ".3" as extension
-                // and change it
-                registry_cache_path.push(format!(
-                    "{}{}",
-                    package_name.to_os_string().into_string().unwrap(),
-                    ".crate"
-                ));
-                SourceKind::Crate(registry_cache_path)
-            }
-            SourceKind::Git(gitpath) => {
-                // ~/.cargo/git/checkouts/cargo-e7ff1db891893a9e/258c896
-                // =>
-                // ~/.cargo/git/db/cargo-e7ff1db891893a9e
-                let mut repo_name = gitpath;
-                let _ = repo_name.pop(); // remove /258c896
-                let repo_name = repo_name.iter().last().unwrap(); // cargo-e7ff1db891893a9e
-
-                let mut db_name = cargo_cache_paths.git_repos_bare.clone();
-                db_name.push(repo_name);
-                // ~/.cargo/git/db/cargo-e7ff1db891893a9e
-                SourceKind::Git(db_name)
-            }
+            SourceKind::Crate(registry_src_path) => SourceKind::Crate(
+                archive_of_source(&registry_src_path, &cargo_cache_paths.registry_pkg_cache)
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Failed to find the archive of '{}'",
+                            registry_src_path.display()
+                        )
+                    }),
+            ),
+            SourceKind::Git(gitpath) => SourceKind::Git(
+                bare_repo_of_checkout(&gitpath, &cargo_cache_paths.git_repos_bare).unwrap_or_else(
+                    || panic!("Failed to find the bare repo of '{}'", gitpath.display()),
+                ),
+            ),
         });
 
-    // now we have a list of all cargo-home-entries a crate needs to build
-    // we can walk the cargo-cache and remove everything that is not referenced;
-    // remove: git checkouts, registry sources
-    // keep, if referenced: registry pkg cache, bare git repos
-
-    // debug
-    // println!("required packages:");
-    // required_packages.inspect(|toml| println!("{:?}", toml));
-
-    // remove the git checkout cache since it is not needed
-    remove_file(
-        &cargo_cache_paths.git_checkouts,
-        dry_run,
-        size_changed,
-        None,
-        &DryRunMessage::Default,
-        Some(checkouts_cache.total_size()),
-    );
-    // invalidate cache
-    checkouts_cache.invalidate();
-
-    // remove the registry_sources_cache as well
-    remove_file(
-        &cargo_cache_paths.registry_sources,
-        dry_run,
-        size_changed,
-        None,
-        &DryRunMessage::Default,
-        Some(registry_sources_caches.total_size()),
-    );
-    // invalidate cache
-    registry_sources_caches.invalidate();
-
     let (required_crates, required_git_repos): (Vec<SourceKind>, Vec<SourceKind>) =
         required_packages.partition(|dep| match dep {
             SourceKind::Crate(_) => true,
@@ -220,74 +418,274 @@ pub(crate) fn clean_unref(
         .into_iter()
         .map(SourceKind::inner)
         .collect();
-    // for the bare_repos_cache and registry_package_cache,
-    // remove all items but the ones that are referenced
 
-    let bare_repos = bare_repos_cache.items();
+    Ok((required_crates, required_git_repos))
+}
+
+/// look at a crate manifest and remove all items from the cargo cache that are not referenced, also run --autoclean and invalidate caches
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn clean_unref(
+    settings: &Settings,
+    cargo_cache_paths: &CargoCachePaths,
+    manifest_path: Option<&str>,
+    use_metadata_cache: bool,
+    mut bin_cache: &mut bin::BinaryCache,
+    mut checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    mut bare_repos_cache: &mut git_bare_repos::GitRepoCache,
+    mut registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    mut registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    mut registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+    targets: CleanUnrefTargets,
+    dry_run: bool,
+    print_plan: bool,
+    size_changed: &mut bool,
+) -> Result<(), Error> {
+    // total cache size before removing, for the summary
+    let original_total_cache_size = bin_cache.total_size()
+        + checkouts_cache.total_size()
+        + bare_repos_cache.total_size()
+        + registry_pkg_caches.total_size()
+        + registry_index_caches.total_size()
+        + registry_sources_caches.total_size();
 
-    // get all .crates found in the cache (we need to check all subcaches)
-    // @TODO add method to get all .crates of all caches via single method?
-    let mut crates = Vec::new();
+    // first get a list of all dependencies of the project
+    let (required_crates, required_git_repos) =
+        referenced_items(cargo_cache_paths, manifest_path, use_metadata_cache)?;
 
-    for cache in registry_pkg_caches.caches() {
-        crates.extend(cache.files());
+    for archive in missing_archives(&required_crates) {
+        eprintln!(
+            "Warning: the referenced archive '{}' is not in the cache, its path may have been derived wrongly",
+            relative_paths::shown(settings, archive).display()
+        );
     }
 
-    // filter and remove git repos
-    bare_repos
+    // now we have a list of all cargo-home-entries a crate needs to build
+    // we can walk the cargo-cache and remove everything that is not referenced;
+    // remove: git checkouts, registry sources
+    // keep, if referenced: registry pkg cache, bare git repos
+
+    // debug
+    // println!("required packages:");
+    // required_packages.inspect(|toml| println!("{:?}", toml));
+
+    // for the bare_repos_cache and registry_package_cache,
+    // remove all items but the ones that are referenced
+
+    // filter git repos
+    let unreferenced_repos: Vec<PathBuf> = bare_repos_cache
+        .items()
         .iter()
+        .filter(|_| targets.git_repos_bare)
         .filter(|repo_in_cache|
             // in the iterator, only keep crates that are not contained in
             // our dependency list and remove them
 
             !required_git_repos.contains(repo_in_cache))
-        .for_each(|repo| {
-            /* remove the repo */
-
-            remove_file(
-                repo,
-                dry_run,
-                size_changed,
-                None,
-                &DryRunMessage::Default,
-                Some(size_of_path(repo)),
-            );
-        });
+        .cloned()
+        .collect();
 
-    // filter and remove crate archives
-    crates
-        .iter()
+    // filter crate archives of all registries
+    let unreferenced_crates: Vec<PathBuf> = registry_pkg_caches
+        .files()
+        .into_iter()
+        .filter(|_| targets.registry_pkg_cache)
         .filter(|crate_in_cache|
             // in the iterator, only keep crates that are not contained in
             // our dependency list and remove them
 
             !required_crates.contains(crate_in_cache))
-        .for_each(|krate| {
-            /* remove the crate */
-            remove_file(
-                krate,
-                dry_run,
-                size_changed,
-                None,
-                &DryRunMessage::Default,
-                Some(size_of_path(krate)),
-            );
-        });
+        .collect();
+
+    if print_plan {
+        let plan = CleanUnrefPlan::new(
+            settings,
+            required_crates
+                .iter()
+                .chain(&required_git_repos)
+                .cloned()
+                .collect(),
+            PlannedRemovals {
+                archives: unreferenced_crates,
+                repos: unreferenced_repos,
+                // the checkouts and sources are removed entirely
+                sources: if targets.registry_sources {
+                    registry_sources_caches.items().to_vec()
+                } else {
+                    Vec::new()
+                },
+                checkouts: if targets.git_checkouts {
+                    checkouts_cache.items().to_vec()
+                } else {
+                    Vec::new()
+                },
+            },
+        );
+        add_planned_removals(settings, plan.to_remove.len());
+        println!("{}", to_json(settings, &plan));
+        return Ok(());
+    }
+
+    // remove the git checkout cache since it is not needed
+    if targets.git_checkouts {
+        let _ = remove_file(
+            settings,
+            &cargo_cache_paths.git_checkouts,
+            dry_run,
+            size_changed,
+            None,
+            &DryRunMessage::Default,
+            Some(checkouts_cache.total_size()),
+        );
+        // invalidate cache
+        checkouts_cache.invalidate();
+    }
+
+    // remove the registry_sources_cache as well
+    if targets.registry_sources {
+        let _ = remove_file(
+            settings,
+            &cargo_cache_paths.registry_sources,
+            dry_run,
+            size_changed,
+            None,
+            &DryRunMessage::Default,
+            Some(registry_sources_caches.total_size()),
+        );
+        // invalidate cache
+        registry_sources_caches.invalidate();
+    }
+
+    // remove the repos and crates
+    let unreferenced: Vec<&PathBuf> = unreferenced_repos
+        .iter()
+        .chain(&unreferenced_crates)
+        .collect();
+    let _ = remove_with_progress(settings, &unreferenced, dry_run, size_changed);
 
     // don't forget to invalidate caches..!
     bare_repos_cache.invalidate();
     registry_pkg_caches.invalidate();
 
-    print_size_changed_summary(
-        original_total_cache_size,
-        cargo_cache_paths,
-        &mut bin_cache,
-        &mut checkouts_cache,
-        &mut bare_repos_cache,
-        &mut registry_pkg_caches,
-        &mut registry_index_caches,
-        &mut registry_sources_caches,
+    // with json output, the size difference is only printed as json
+    if !settings.json_output {
+        print_size_changed_summary(
+            settings,
+            original_total_cache_size,
+            cargo_cache_paths,
+            &mut bin_cache,
+            &mut checkouts_cache,
+            &mut bare_repos_cache,
+            &mut registry_pkg_caches,
+            &mut registry_index_caches,
+            &mut registry_sources_caches,
+        );
+    }
+    Ok(())
+}
+
+/// split the items into the ones pinned by the lockfile and the ones to remove,
+/// nothing is removed from cache classes that are not targeted
+fn split_pinned(
+    items: Vec<PathBuf>,
+    targeted: bool,
+    is_pinned: impl Fn(&Path) -> bool,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (pinned, unpinned) = items.into_iter().partition(|item| is_pinned(item));
+    (pinned, if targeted { unpinned } else { Vec::new() })
+}
+
+/// the strict variant of `clean_unref()`: keep exactly the archives, sources, repos and checkouts
+/// that are pinned (name, version and source) by a Cargo.lock and remove everything else
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn clean_unref_lockfile(
+    settings: &Settings,
+    cargo_cache_paths: &CargoCachePaths,
+    lockfile: &Path,
+    bin_cache: &mut bin::BinaryCache,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    bare_repos_cache: &mut git_bare_repos::GitRepoCache,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+    targets: CleanUnrefTargets,
+    dry_run: bool,
+    print_plan: bool,
+    size_changed: &mut bool,
+) -> Result<(), Error> {
+    // total cache size before removing, for the summary
+    let original_total_cache_size = bin_cache.total_size()
+        + checkouts_cache.total_size()
+        + bare_repos_cache.total_size()
+        + registry_pkg_caches.total_size()
+        + registry_index_caches.total_size()
+        + registry_sources_caches.total_size();
+
+    let pins = LockfilePins::from_lockfile(lockfile)?;
+
+    let (_, unpinned_checkouts) = split_pinned(
+        checkouts_cache.items().to_vec(),
+        targets.git_checkouts,
+        |checkout| pins.pins_checkout(checkout),
+    );
+    let (_, unpinned_sources) = split_pinned(
+        registry_sources_caches.items().to_vec(),
+        targets.registry_sources,
+        |source| pins.pins_source(source),
     );
+    let (pinned_repos, unpinned_repos) = split_pinned(
+        bare_repos_cache.items().to_vec(),
+        targets.git_repos_bare,
+        |repo| pins.pins_bare_repo(repo),
+    );
+    let (pinned_archives, unpinned_archives) = split_pinned(
+        registry_pkg_caches.files(),
+        targets.registry_pkg_cache,
+        |archive| pins.pins_archive(archive),
+    );
+
+    if print_plan {
+        let plan = CleanUnrefPlan::new(
+            settings,
+            pinned_archives.into_iter().chain(pinned_repos).collect(),
+            PlannedRemovals {
+                archives: unpinned_archives,
+                repos: unpinned_repos,
+                sources: unpinned_sources,
+                checkouts: unpinned_checkouts,
+            },
+        );
+        add_planned_removals(settings, plan.to_remove.len());
+        println!("{}", to_json(settings, &plan));
+        return Ok(());
+    }
+
+    let unpinned: Vec<&PathBuf> = unpinned_checkouts
+        .iter()
+        .chain(&unpinned_sources)
+        .chain(&unpinned_repos)
+        .chain(&unpinned_archives)
+        .collect();
+    let _ = remove_with_progress(settings, &unpinned, dry_run, size_changed);
+
+    // don't forget to invalidate caches..!
+    checkouts_cache.invalidate();
+    registry_sources_caches.invalidate();
+    bare_repos_cache.invalidate();
+    registry_pkg_caches.invalidate();
+
+    if !settings.json_output {
+        print_size_changed_summary(
+            settings,
+            original_total_cache_size,
+            cargo_cache_paths,
+            bin_cache,
+            checkouts_cache,
+            bare_repos_cache,
+            registry_pkg_caches,
+            registry_index_caches,
+            registry_sources_caches,
+        );
+    }
     Ok(())
 }
 
@@ -296,6 +694,27 @@ mod clitests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn clean_unref_targets() {
+        let all = CleanUnrefTargets::default();
+        assert!(
+            all.git_checkouts
+                && all.registry_sources
+                && all.git_repos_bare
+                && all.registry_pkg_cache
+        );
+
+        assert_eq!(
+            all.keep_sources(),
+            CleanUnrefTargets {
+                git_checkouts: false,
+                registry_sources: false,
+                git_repos_bare: true,
+                registry_pkg_cache: true,
+            }
+        );
+    }
+
     #[test]
     fn sourcekind_inner() {
         let sk_crate = SourceKind::Crate(PathBuf::from("abc"));
@@ -362,4 +781,227 @@ mod clitests {
 
         assert_eq!(name, None,);
     }
+
+    #[test]
+    fn crate_name_cargo_home_with_registry_segment() {
+        // the cargo home itself contains "registry" and "checkouts"
+        let cargo_home = PathBuf::from("/home/registry/checkouts/.cargo");
+
+        assert_eq!(
+            find_crate_name_crate(
+                &cargo_home
+                    .join("registry/src/github.com-1ecc6299db9ec823/winapi-0.3.8/Cargo.toml"),
+                &cargo_home
+            ),
+            Some(SourceKind::Crate(cargo_home.join(
+                "registry/src/github.com-1ecc6299db9ec823/winapi-0.3.8"
+            ))),
+        );
+        assert_eq!(
+            find_crate_name_git(
+                &cargo_home.join("git/checkouts/home-fb9469891e5cfbe6/3a6eccd/sub/Cargo.toml"),
+                &cargo_home
+            ),
+            Some(SourceKind::Git(
+                cargo_home.join("git/checkouts/home-fb9469891e5cfbe6/3a6eccd")
+            )),
+        );
+        // not below the cargo home at all
+        assert_eq!(
+            find_crate_name_crate(
+                Path::new("/home/registry/src/reg/winapi-0.3.8/Cargo.toml"),
+                &cargo_home
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn archive_and_bare_repo_paths() {
+        let cargo_home = PathBuf::from("/home/user/.cargo");
+        assert_eq!(
+            archive_of_source(
+                &cargo_home.join("registry/src/github.com-1ecc6299db9ec823/semver-0.9.0"),
+                &cargo_home.join("registry/cache")
+            ),
+            Some(cargo_home.join("registry/cache/github.com-1ecc6299db9ec823/semver-0.9.0.crate"))
+        );
+        assert_eq!(
+            bare_repo_of_checkout(
+                &cargo_home.join("git/checkouts/cargo-e7ff1db891893a9e/258c896"),
+                &cargo_home.join("git/db")
+            ),
+            Some(cargo_home.join("git/db/cargo-e7ff1db891893a9e"))
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_paths() {
+        let cargo_home = PathBuf::from(r"C:\Users\user\.cargo");
+
+        let source = find_crate_name_crate(
+            Path::new(
+                r"C:\Users\user\.cargo\registry\src\github.com-1ecc6299db9ec823\winapi-0.3.8\Cargo.toml",
+            ),
+            &cargo_home,
+        )
+        .unwrap()
+        .inner();
+        assert_eq!(
+            source,
+            PathBuf::from(
+                r"C:\Users\user\.cargo\registry\src\github.com-1ecc6299db9ec823\winapi-0.3.8"
+            )
+        );
+        assert_eq!(
+            archive_of_source(&source, &cargo_home.join("registry").join("cache")),
+            Some(PathBuf::from(
+                r"C:\Users\user\.cargo\registry\cache\github.com-1ecc6299db9ec823\winapi-0.3.8.crate"
+            ))
+        );
+
+        // forward slashes and a lowercase drive letter are the same path on Windows
+        let checkout = find_crate_name_git(
+            Path::new(
+                "c:/Users/user/.cargo/git/checkouts/home-fb9469891e5cfbe6/3a6eccd/Cargo.toml",
+            ),
+            &cargo_home,
+        )
+        .unwrap()
+        .inner();
+        assert_eq!(
+            bare_repo_of_checkout(&checkout, &cargo_home.join("git").join("db")),
+            Some(PathBuf::from(
+                r"C:\Users\user\.cargo\git\db\home-fb9469891e5cfbe6"
+            ))
+        );
+
+        // verbatim paths as returned by canonicalize()
+        let verbatim_home = PathBuf::from(r"\\?\C:\Users\user\.cargo");
+        assert_eq!(
+            find_crate_name_crate(
+                &verbatim_home.join(r"registry\src\reg-0123456789abcdef\foo-0.1.0\Cargo.toml"),
+                &verbatim_home
+            ),
+            Some(SourceKind::Crate(
+                verbatim_home.join(r"registry\src\reg-0123456789abcdef\foo-0.1.0")
+            ))
+        );
+    }
+
+    #[test]
+    fn metadata_cache_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        let member = workspace.join("member");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(workspace.join("Cargo.lock"), "version = 3").unwrap();
+
+        // the lockfile of a workspace member is in the workspace root
+        let lockfile = find_lockfile(&member.join("Cargo.toml"));
+        assert_eq!(lockfile, Some(workspace.join("Cargo.lock")));
+        let hash = hash_of_file(&workspace.join("Cargo.lock")).unwrap();
+
+        let cache_path = dir.path().join(METADATA_CACHE_FILE);
+        // no cache yet
+        assert_eq!(read_metadata_cache(&cache_path), MetadataCache::new());
+
+        let mut cache = MetadataCache::new();
+        let _ = cache.insert(
+            member.join("Cargo.toml"),
+            CachedReferences {
+                lockfile_hash: hash,
+                crates: vec![PathBuf::from("registry/cache/reg/foo-0.1.0.crate")],
+                git_repos: Vec::new(),
+            },
+        );
+        write_metadata_cache(&cache_path, &cache);
+        assert_eq!(read_metadata_cache(&cache_path), cache);
+
+        fs::write(workspace.join("Cargo.lock"), "version = 4").unwrap();
+        assert_ne!(hash_of_file(&workspace.join("Cargo.lock")), Some(hash));
+    }
+
+    #[test]
+    fn plan_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("foo-0.1.0.crate");
+        let other_archive = dir.path().join("bar-0.1.0.crate");
+        fs::write(&archive, b"12345").unwrap();
+        fs::write(&other_archive, b"123").unwrap();
+
+        let plan = CleanUnrefPlan::new(
+            &Settings::default(),
+            vec![PathBuf::from("b.crate"), PathBuf::from("a.crate")],
+            PlannedRemovals {
+                archives: vec![archive.clone(), other_archive.clone()],
+                ..PlannedRemovals::default()
+            },
+        );
+        assert_eq!(plan.reclaimable_bytes, 8);
+        assert_eq!(plan.to_remove.archives, vec![other_archive, archive]);
+        assert_eq!(
+            plan.required,
+            vec![PathBuf::from("a.crate"), PathBuf::from("b.crate")]
+        );
+
+        let output = crate::output::to_json_at(
+            &CleanUnrefPlan {
+                required: vec![PathBuf::from("registry/cache/reg/bar-0.1.0.crate")],
+                to_remove: PlannedRemovals {
+                    archives: vec![PathBuf::from("registry/cache/reg/foo-0.1.0.crate")],
+                    ..PlannedRemovals::default()
+                },
+                reclaimable_bytes: 5,
+            },
+            crate::output::test_time(),
+        );
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "required": [
+    "registry/cache/reg/bar-0.1.0.crate"
+  ],
+  "to_remove": {
+    "archives": [
+      "registry/cache/reg/foo-0.1.0.crate"
+    ],
+    "repos": [],
+    "sources": [],
+    "checkouts": []
+  },
+  "reclaimable_bytes": 5
+}"#;
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(PLAN_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn pinned_items_are_split() {
+        let items = vec![PathBuf::from("foo"), PathBuf::from("bar")];
+        let is_pinned = |item: &Path| item == Path::new("foo");
+        assert_eq!(
+            split_pinned(items.clone(), true, is_pinned),
+            (vec![PathBuf::from("foo")], vec![PathBuf::from("bar")])
+        );
+        // nothing is removed from cache classes that are not targeted
+        assert_eq!(
+            split_pinned(items, false, is_pinned),
+            (vec![PathBuf::from("foo")], Vec::new())
+        );
+    }
+
+    #[test]
+    fn required_archives_that_are_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let cached = dir.path().join("foo-0.1.0.crate");
+        fs::write(&cached, b"").unwrap();
+        let missing = dir.path().join("bar-0.1.0.crate");
+        let required = vec![cached, missing.clone()];
+        assert_eq!(missing_archives(&required), vec![&missing]);
+    }
 }
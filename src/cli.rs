@@ -8,9 +8,20 @@
 // except according to those terms.
 
 /// This file provides the command line interface of the cargo-cache crate
-use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::io;
+use std::process;
 
+use clap::{value_t, App, AppSettings, Arg, ArgGroup, ArgMatches, Shell, SubCommand};
+
+use crate::cache::caches::RegistryMatch;
+use crate::clean_unref::CleanUnrefTargets;
+use crate::commands::export_files::ExportFormat;
+use crate::commands::size::{SizeFormat, SIZE_UNITS};
+use crate::commands::trim::{parse_percent, TrimTarget};
+use crate::config::Config;
+use crate::dirsizes::{Category, RegistryLineOrder, CATEGORIES};
 use crate::library::*;
+use crate::output::OutputFormat;
 use rustc_tools_util::*;
 
 /// cargo-cache can perform these operaitons, but only one at a time
@@ -25,14 +36,24 @@ pub(crate) enum CargoCacheCommands<'a> {
     KeepDuplicateCrates {
         dry_run: bool,
         limit: u64,
+        keep_crates: Vec<&'a str>,
     },
     ListDirs,
+    /// "--remove-dir <dirs> --estimate-redownload"
+    EstimateRedownload,
     RemoveDir {
         dry_run: bool,
     },
     AutoClean {
         dry_run: bool,
     },
+    RemoveBrokenSymlinks {
+        dry_run: bool,
+    },
+    PruneGitCheckouts {
+        dry_run: bool,
+        keep_last: usize,
+    },
     AutoCleanExpensive {
         dry_run: bool,
     },
@@ -44,35 +65,217 @@ pub(crate) enum CargoCacheCommands<'a> {
     Query {
         query_config: &'a ArgMatches<'a>,
     }, // subcommand
-    Local,      // subcommand
-    Registries, // subcommand
-    SCCache,    // subcommand
+    Local, // subcommand
+    Registries {
+        line_order: RegistryLineOrder,
+        /// "cargo cache registry --format json", None if not passed to the subcommand
+        output_format: Option<OutputFormat>,
+        /// show how many archives of each registry were extracted ("--extraction-ratio")
+        extraction_ratio: bool,
+        /// size the ".cache" dir of each index separately ("--split-index")
+        split_index: bool,
+    }, // subcommand
+    CountsOnly,
+    DiscoverHomes,
+    BiggestCrate,
+    Only {
+        categories: Vec<Category>,
+    },
+    SCCache, // subcommand
     CleanUnref {
         dry_run: bool,
         manifest_path: Option<&'a str>,
+        lockfile: Option<&'a str>,
+        use_metadata_cache: bool,
+        targets: CleanUnrefTargets,
+        /// "cargo cache clean-unref --format json", None if not passed to the subcommand
+        output_format: Option<OutputFormat>,
     }, // subcommand
     Trim {
         dry_run: bool,
         trim_limit: Option<&'a str>,
+        keep_crates: Vec<&'a str>,
+    }, // subcommand
+    TrimTo {
+        dry_run: bool,
+        target: TrimTarget<'a>,
+        keep_referenced_by: Option<&'a str>,
+        keep_crates: Vec<&'a str>,
+        /// "cargo cache trim-to --format json", None if not passed to the subcommand
+        output_format: Option<OutputFormat>,
+    }, // subcommand
+    CleanIndex {
+        dry_run: bool,
+        /// the registry and whether it is matched by name or by directory ("--registry-match-dir")
+        registry: Option<(&'a str, RegistryMatch)>,
+        /// "--unused": only the indices of registries without archives
+        unused_only: bool,
+        assume_yes: bool,
+    }, // subcommand
+    Toolchain, // subcommand
+    Compression, // subcommand
+    Doctor {
+        doctor_config: &'a ArgMatches<'a>,
+        /// "--orphans": only report the sources without a .crate archive
+        orphans: bool,
+        /// "--stale": only report the sources that are older than their .crate archive
+        stale: bool,
+        /// "--remove": remove the partial downloads that were found
+        remove: bool,
+        /// "--fix-perms": fix the permissions of the extracted sources and git checkouts
+        fix_perms: bool,
+        dry_run: bool,
+    }, // subcommand
+    Extract {
+        /// "<name>@<version>"
+        spec: &'a str,
+        target_dir: &'a str,
+        /// "cargo cache extract --format json", None if not passed to the subcommand
+        output_format: Option<OutputFormat>,
+    }, // subcommand
+    ExportFiles {
+        target: &'a str,
+        /// "--format tsv|json|jsonl", tsv by default
+        format: ExportFormat,
+    }, // subcommand
+    Size {
+        path: &'a str,
+        size_format: SizeFormat<'a>,
+        /// "cargo cache size --format json", None if not passed to the subcommand
+        output_format: Option<OutputFormat>,
+        /// "--max-depth N", also list the directories up to this depth
+        max_depth: Option<usize>,
     }, // subcommand
-    Toolchain,  // subcommand
     RemoveIfDate {
         dry_run: bool,
         arg_younger: Option<&'a str>,
         arg_older: Option<&'a str>,
         dirs: Option<&'a str>,
+        keep_crates: Vec<&'a str>,
     },
     OnlyDryRun,
     DefaultSummary,
 }
 
-pub(crate) fn clap_to_enum<'a, 'b>(config: &'b ArgMatches<'a>) -> CargoCacheCommands<'b> {
-    let dry_run = config.is_present("dry-run");
+impl CargoCacheCommands<'_> {
+    /// whether the command removes or rewrites items in the cargo home (also when dry-running)
+    pub(crate) fn removes_files(&self) -> bool {
+        matches!(
+            self,
+            Self::KeepDuplicateCrates { .. }
+                | Self::RemoveDir { .. }
+                | Self::AutoClean { .. }
+                | Self::AutoCleanExpensive { .. }
+                | Self::RemoveBrokenSymlinks { .. }
+                | Self::PruneGitCheckouts { .. }
+                | Self::CleanUnref { .. }
+                | Self::Trim { .. }
+                | Self::TrimTo { .. }
+                | Self::CleanIndex { .. }
+                | Self::RemoveIfDate { .. }
+                | Self::Doctor { remove: true, .. }
+                | Self::Doctor {
+                    fix_perms: true,
+                    ..
+                }
+                | Self::GitGCRepos { .. }
+        )
+    }
 
-    // if no args were passed, or ONLY --debug is passed, print the default summary
-    if (config.args.is_empty() && config.subcommand.is_none())
-        || (config.subcommand.is_none() && config.is_present("debug") && config.args.len() == 1)
-    {
+    /// whether the command takes a default from the config file: the output format, dry-run or a query option
+    pub(crate) fn reads_config(&self) -> bool {
+        !matches!(
+            self,
+            Self::Version
+                | Self::Toolchain
+                | Self::SCCache
+                | Self::DiscoverHomes
+                | Self::ListDirs
+                | Self::Info
+                | Self::Local
+                | Self::FSCKRepos
+                | Self::Compression
+                | Self::BiggestCrate
+                | Self::EstimateRedownload
+                | Self::ExportFiles { .. }
+        )
+    }
+
+    /// whether the command only pretends to remove items
+    pub(crate) fn dry_run(&self) -> bool {
+        match self {
+            Self::GitGCRepos { dry_run }
+            | Self::KeepDuplicateCrates { dry_run, .. }
+            | Self::RemoveDir { dry_run }
+            | Self::AutoClean { dry_run }
+            | Self::AutoCleanExpensive { dry_run }
+            | Self::RemoveBrokenSymlinks { dry_run }
+            | Self::PruneGitCheckouts { dry_run, .. }
+            | Self::CleanUnref { dry_run, .. }
+            | Self::Trim { dry_run, .. }
+            | Self::TrimTo { dry_run, .. }
+            | Self::CleanIndex { dry_run, .. }
+            | Self::Doctor { dry_run, .. }
+            | Self::RemoveIfDate { dry_run, .. } => *dry_run,
+            Self::OnlyDryRun => true,
+            _ => false,
+        }
+    }
+}
+
+/// args that only change how the output looks or how commands behave and do not select a command
+const OUTPUT_MODIFIERS: &[&str] = &[
+    "append-json",
+    "apparent",
+    "debug",
+    "dedup-hardlinks",
+    "exclude-glob",
+    "disk",
+    "fields",
+    "force-git",
+    "format",
+    "keep-runs",
+    "log-trend",
+    "max-removals",
+    "no-dry-run",
+    "no-history",
+    "precision",
+    "profile",
+    "quiet",
+    "reconcile",
+    "relative-paths",
+    "remove-jobs",
+    "size-source",
+    "sort-categories",
+    "trash",
+    "verbose",
+];
+
+/// the crates passed via "--keep-crate", these are never removed automatically
+fn keep_crates_of<'a>(matches: &'a ArgMatches<'_>) -> Vec<&'a str> {
+    matches
+        .values_of("keep-crate")
+        .map(Iterator::collect)
+        .unwrap_or_default()
+}
+
+/// validates the arguments that take a size, they are all parsed by `parse_size()`
+fn validate_size(size: &str) -> Result<(), String> {
+    parse_size(size)
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+pub(crate) fn clap_to_enum<'a, 'b>(
+    config: &'b ArgMatches<'a>,
+    config_file: &Config,
+) -> CargoCacheCommands<'b> {
+    // dry-run is decided once here and passed on to the commands
+    let dry_run = config_file.dry_run(&[config]);
+
+    // if no args were passed, or ONLY args that modify the output (see OUTPUT_MODIFIERS) are passed,
+    // print the default summary
+    if config.subcommand.is_none() && config.args.keys().all(|arg| OUTPUT_MODIFIERS.contains(arg)) {
         return CargoCacheCommands::DefaultSummary;
     }
 
@@ -80,22 +283,124 @@ pub(crate) fn clap_to_enum<'a, 'b>(config: &'b ArgMatches<'a>) -> CargoCacheComm
     // do not check for "--debug" since it is independent of all other flags
     if config.is_present("version") {
         CargoCacheCommands::Version
+    } else if config.is_present("counts-only") {
+        CargoCacheCommands::CountsOnly
+    } else if config.is_present("discover-homes") {
+        CargoCacheCommands::DiscoverHomes
+    } else if config.is_present("biggest-crate") {
+        CargoCacheCommands::BiggestCrate
+    } else if let Some(categories) = config.values_of("only") {
+        CargoCacheCommands::Only {
+            // clap only lets valid categories through
+            categories: categories
+                .filter_map(|category| category.parse().ok())
+                .collect(),
+        }
     } else if config.is_present("sccache") || config.is_present("sc") {
         CargoCacheCommands::SCCache
     } else if config.subcommand_matches("toolchain").is_some() {
         CargoCacheCommands::Toolchain
-    } else if let Some(config) = config.subcommand_matches("trim") {
-        let trim_dry_run = dry_run || config.is_present("dry-run");
+    } else if config.subcommand_matches("compression").is_some() {
+        CargoCacheCommands::Compression
+    } else if let Some(doctor_config) = config.subcommand_matches("doctor") {
+        CargoCacheCommands::Doctor {
+            doctor_config,
+            orphans: doctor_config.is_present("orphans"),
+            stale: doctor_config.is_present("stale"),
+            remove: doctor_config.is_present("remove"),
+            fix_perms: doctor_config.is_present("fix-perms"),
+            dry_run: config_file.dry_run(&[config, doctor_config]),
+        }
+    } else if let Some(extract_config) = config.subcommand_matches("extract") {
+        CargoCacheCommands::Extract {
+            // clap makes sure both are present
+            spec: extract_config.value_of("crate").unwrap_or_default(),
+            target_dir: extract_config.value_of("to").unwrap_or_default(),
+            output_format: extract_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok()),
+        }
+    } else if let Some(export_config) = config.subcommand_matches("export-files") {
+        CargoCacheCommands::ExportFiles {
+            // clap makes sure it is present
+            target: export_config.value_of("file").unwrap_or_default(),
+            // clap only accepts the known formats
+            format: export_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok())
+                .unwrap_or(ExportFormat::Tsv),
+        }
+    } else if let Some(size_config) = config.subcommand_matches("size") {
+        let size_format = if size_config.is_present("bytes") {
+            SizeFormat::Bytes
+        } else if let Some(unit) = size_config.value_of("unit") {
+            SizeFormat::Unit(unit)
+        } else {
+            SizeFormat::Human
+        };
+        CargoCacheCommands::Size {
+            // clap makes sure it is present
+            path: size_config.value_of("path").unwrap_or_default(),
+            size_format,
+            output_format: size_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok()),
+            // clap already made sure that this is a number
+            max_depth: size_config
+                .value_of("max-depth")
+                .and_then(|depth| depth.parse().ok()),
+        }
+    } else if let Some(trim_config) = config.subcommand_matches("trim") {
         CargoCacheCommands::Trim {
-            dry_run: trim_dry_run,
-            trim_limit: config.value_of("trim_limit"),
-        } // take config trim_config.value_of("trim_limit")
-    } else if let Some(config) = config.subcommand_matches("clean-unref") {
-        let arg_dry_run = dry_run || config.is_present("dry-run");
+            dry_run: config_file.dry_run(&[config, trim_config]),
+            trim_limit: trim_config.value_of("trim_limit"),
+            keep_crates: keep_crates_of(trim_config),
+        }
+    } else if let Some(trim_to_config) = config.subcommand_matches("trim-to") {
+        CargoCacheCommands::TrimTo {
+            dry_run: config_file.dry_run(&[config, trim_to_config]),
+            // clap makes sure that either a size or a valid percentage is present
+            target: match trim_to_config.value_of("trim-by").and_then(parse_percent) {
+                Some(percent) => TrimTarget::Percent(percent),
+                None => {
+                    TrimTarget::Size(trim_to_config.value_of("target_size").unwrap_or_default())
+                }
+            },
+            keep_referenced_by: trim_to_config.value_of("keep-referenced-by"),
+            keep_crates: keep_crates_of(trim_to_config),
+            output_format: trim_to_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok()),
+        }
+    } else if let Some(clean_index_config) = config.subcommand_matches("clean-index") {
+        CargoCacheCommands::CleanIndex {
+            dry_run: config_file.dry_run(&[config, clean_index_config]),
+            registry: clean_index_config.value_of("registry").map(|registry| {
+                if clean_index_config.is_present("registry-match-dir") {
+                    (registry, RegistryMatch::Dir)
+                } else {
+                    (registry, RegistryMatch::Name)
+                }
+            }),
+            unused_only: clean_index_config.is_present("unused"),
+            assume_yes: clean_index_config.is_present("yes"),
+        }
+    } else if let Some(clean_unref_config) = config.subcommand_matches("clean-unref") {
+        let targets = if clean_unref_config.is_present("keep-sources") {
+            CleanUnrefTargets::default().keep_sources()
+        } else {
+            CleanUnrefTargets::default()
+        };
         CargoCacheCommands::CleanUnref {
-            dry_run: arg_dry_run,
-            manifest_path: config.value_of("manifest-path"),
-        } // clean_unref_cfg.value_of("manifest-path"),
+            dry_run: config_file.dry_run(&[config, clean_unref_config]),
+            manifest_path: clean_unref_config.value_of("manifest-path"),
+            lockfile: clean_unref_config.value_of("only-referenced-by"),
+            use_metadata_cache: !clean_unref_config.is_present("no-metadata-cache"),
+            targets,
+            output_format: clean_unref_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok()),
+        }
     } else if config.is_present("top-cache-items") {
         let limit =
             value_t!(config.value_of("top-cache-items"), u32).unwrap_or(20 /* default*/);
@@ -111,6 +416,9 @@ pub(crate) fn clap_to_enum<'a, 'b>(config: &'b ArgMatches<'a>) -> CargoCacheComm
         CargoCacheCommands::Local
     } else if config.is_present("info") {
         CargoCacheCommands::Info
+    } else if config.is_present("estimate-redownload") {
+        // clap makes sure "--remove-dir" is present as well
+        CargoCacheCommands::EstimateRedownload
     } else if config.is_present("remove-dir") {
         // This one must come BEFORE RemoveIfDate because that one also uses --remove dir
         CargoCacheCommands::RemoveDir { dry_run } //need more info
@@ -120,6 +428,14 @@ pub(crate) fn clap_to_enum<'a, 'b>(config: &'b ArgMatches<'a>) -> CargoCacheComm
         // if we pass both --gc and --autoclean-expensive, we want autoclean-expensive to run
         // since is already includes --gc
         CargoCacheCommands::AutoCleanExpensive { dry_run }
+    } else if config.is_present("remove-broken-symlinks") {
+        CargoCacheCommands::RemoveBrokenSymlinks { dry_run }
+    } else if config.is_present("prune-git-checkouts") {
+        CargoCacheCommands::PruneGitCheckouts {
+            dry_run,
+            // clap makes sure this is a number
+            keep_last: value_t!(config.value_of("keep-last"), usize).unwrap_or(1),
+        }
     } else if config.is_present("fsck-repos") {
         CargoCacheCommands::FSCKRepos
     } else if config.is_present("gc-repos") {
@@ -136,12 +452,32 @@ pub(crate) fn clap_to_enum<'a, 'b>(config: &'b ArgMatches<'a>) -> CargoCacheComm
                 )
             })
             .unwrap_or_fatal_error();
-        CargoCacheCommands::KeepDuplicateCrates { dry_run, limit }
+        CargoCacheCommands::KeepDuplicateCrates {
+            dry_run,
+            limit,
+            keep_crates: keep_crates_of(config),
+        }
     } else if config.subcommand_matches("registry").is_some()
         || config.subcommand_matches("r").is_some()
         || config.subcommand_matches("registries").is_some()
     {
-        CargoCacheCommands::Registries
+        let registry_config = config
+            .subcommand_matches("registry")
+            .or_else(|| config.subcommand_matches("r"))
+            .or_else(|| config.subcommand_matches("registries"))
+            .unwrap();
+        let line_order = match registry_config.value_of("registry-line-sort") {
+            Some("size") => RegistryLineOrder::Size,
+            _ => RegistryLineOrder::Type,
+        };
+        CargoCacheCommands::Registries {
+            line_order,
+            output_format: registry_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok()),
+            extraction_ratio: registry_config.is_present("extraction-ratio"),
+            split_index: registry_config.is_present("split-index"),
+        }
     } else if config.is_present("list-dirs") {
         CargoCacheCommands::ListDirs
     } else if config.is_present("remove-if-younger-than")
@@ -152,6 +488,7 @@ pub(crate) fn clap_to_enum<'a, 'b>(config: &'b ArgMatches<'a>) -> CargoCacheComm
             arg_older: config.value_of("remove-if-younger-than"),
             arg_younger: config.value_of("remove-if-older-than"),
             dirs: config.value_of("remove-dir"),
+            keep_crates: keep_crates_of(config),
         }
     } else if dry_run {
         // none of the flags that do on-disk changes are present
@@ -186,6 +523,11 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .takes_value(true)
         .value_name("dir1,dir2,dir3");
 
+    let estimate_redownload = Arg::with_name("estimate-redownload")
+        .long("estimate-redownload")
+        .requires("remove-dir")
+        .help("Print how much of --remove-dir would be downloaded again, remove nothing");
+
     let gc_repos = Arg::with_name("gc-repos")
         .short("g")
         .long("gc")
@@ -211,16 +553,85 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .takes_value(true)
         .value_name("N");
 
+    let keep_crate = Arg::with_name("keep-crate")
+        .long("keep-crate")
+        .help("Exempt this crate from trim, trim-to, -o, -y and -k, may be repeated")
+        .takes_value(true)
+        .value_name("NAME")
+        .multiple(true)
+        .number_of_values(1);
+
     let dry_run = Arg::with_name("dry-run")
         .short("n")
         .long("dry-run")
         .help("Don't remove anything, just pretend");
 
+    let no_dry_run = Arg::with_name("no-dry-run")
+        .long("no-dry-run")
+        .help("Remove items even if dry-run is enabled in the config file")
+        .conflicts_with("dry-run");
+
+    let no_history = Arg::with_name("no-history")
+        .long("no-history")
+        .help("Don't log removed items to $CARGO_HOME/cargo-cache-history.log");
+
+    let max_removals = Arg::with_name("max-removals")
+        .long("max-removals")
+        .help("Remove at most N items per removal operation")
+        .takes_value(true)
+        .value_name("N")
+        .validator(|value| {
+            value
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|_| format!("expected a number of items, got '{value}'"))
+        });
+
+    let remove_jobs = Arg::with_name("remove-jobs")
+        .long("remove-jobs")
+        .help("Remove up to N items at the same time, which is faster on SSDs (default: 1)")
+        .takes_value(true)
+        .value_name("N")
+        .validator(|value| match value.parse::<usize>() {
+            Ok(jobs) if jobs > 0 => Ok(()),
+            _ => Err(format!("expected a number of jobs above 0, got '{value}'")),
+        });
+
+    let trash = Arg::with_name("trash")
+        .long("trash")
+        .help("Move removed items to the trash instead of deleting them, fails if there is none");
+
+    let fail_if_removable = Arg::with_name("fail-if-removable")
+        .long("fail-if-removable")
+        .help("Dry run, exit with an error if anything would be removed")
+        .conflicts_with("no-dry-run");
+
+    let prune_git_checkouts = Arg::with_name("prune-git-checkouts")
+        .long("prune-git-checkouts")
+        .help("Remove all but the most recently modified checkout of each git repo");
+
+    let keep_last = Arg::with_name("keep-last")
+        .long("keep-last")
+        .help("Keep the N most recently modified checkouts of each git repo (default: 1)")
+        .takes_value(true)
+        .value_name("N")
+        .requires("prune-git-checkouts")
+        .validator(|value| {
+            value
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|_| format!("expected a number of checkouts, got '{value}'"))
+        });
+
     let autoclean = Arg::with_name("autoclean")
         .short("a")
         .long("autoclean")
         .help("Removes crate source checkouts and git repo checkouts");
 
+    let remove_broken_symlinks = Arg::with_name("remove-broken-symlinks")
+        .long("remove-broken-symlinks")
+        .help("Remove symlinks in the registry and git caches whose targets do not exist");
+
     let autoclean_expensive = Arg::with_name("autoclean-expensive")
         .short("e")
         .long("autoclean-expensive")
@@ -256,6 +667,136 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .help("print some debug stats")
         .hidden(true);
 
+    let format = Arg::with_name("format")
+        .long("format")
+        .help("Format of the output")
+        .takes_value(true)
+        .value_name("FORMAT")
+        .possible_values(&["text", "json"]);
+
+    let fields = Arg::with_name("fields")
+        .long("fields")
+        .help("Only print these top-level fields of the json summary")
+        .takes_value(true)
+        .value_name("field1,field2")
+        .use_delimiter(true)
+        .requires("format");
+
+    let exclude_glob = Arg::with_name("exclude-glob")
+        .long("exclude-glob")
+        .help("Leave files matching PATTERN out of the sizes and counts, repeatable")
+        .takes_value(true)
+        .value_name("PATTERN")
+        .multiple(true)
+        .number_of_values(1);
+
+    let verbose = Arg::with_name("verbose")
+        .long("verbose")
+        .help("Print additional information in the summary");
+
+    let quiet = Arg::with_name("quiet")
+        .long("quiet")
+        .help("Only print the total size of the cache, also with --format json")
+        .conflicts_with_all(&["verbose", "fields"]);
+
+    let profile = Arg::with_name("profile")
+        .long("profile")
+        .help("Print how long each phase of the scan took below the summary");
+
+    let force_git = Arg::with_name("force-git")
+        .long("force-git")
+        .help("Size the git db even if it is on a network filesystem");
+
+    let reconcile = Arg::with_name("reconcile")
+        .long("reconcile")
+        .help("Adjust the largest sub-line of the summary so the sizes add up to their total");
+
+    let relative_paths = Arg::with_name("relative-paths")
+        .long("relative-paths")
+        .help("Print paths inside the cargo home relative to it, in text and json output");
+
+    let sort_categories = Arg::with_name("sort-categories")
+        .long("sort-categories")
+        .help("Sort the blocks of the summary by size")
+        .takes_value(true)
+        .value_name("ORDER")
+        .possible_values(&["fixed", "size"]);
+
+    let log_trend = Arg::with_name("log-trend")
+        .long("log-trend")
+        .help("Append the json summary to $CARGO_HOME/cargo-cache-trend.jsonl");
+
+    let append_json = Arg::with_name("append-json")
+        .long("append-json")
+        .help("Append the json summary to the json array in FILE")
+        .takes_value(true)
+        .value_name("FILE");
+
+    let keep_runs = Arg::with_name("keep-runs")
+        .long("keep-runs")
+        .help("Keep only the newest N runs in the file of \"--append-json\"")
+        .takes_value(true)
+        .value_name("N")
+        .requires("append-json")
+        .validator(|value| match value.parse::<usize>() {
+            Ok(runs) if runs > 0 => Ok(()),
+            _ => Err(format!("expected a number of runs above 0, got '{value}'")),
+        });
+
+    let size_source = Arg::with_name("size-source")
+        .long("size-source")
+        .help("Take file sizes from the saved output of \"du -ab $CARGO_HOME\"")
+        .takes_value(true)
+        .value_name("FILE");
+
+    let dedup_hardlinks = Arg::with_name("dedup-hardlinks")
+        .long("dedup-hardlinks")
+        .help("Count files with several hard links only once (unix only)");
+
+    let apparent = Arg::with_name("apparent")
+        .long("apparent")
+        .help("Use the apparent size of files, this is the default")
+        .conflicts_with("disk");
+
+    let disk = Arg::with_name("disk")
+        .long("disk")
+        .help("Use the disk space allocated to files like \"du\" (unix only)")
+        .conflicts_with("size-source");
+
+    let precision = Arg::with_name("precision")
+        .long("precision")
+        .help("Number of decimal places of human readable sizes, 2 by default")
+        .takes_value(true)
+        .value_name("N")
+        .validator(|value| {
+            value
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|_| format!("expected a number of decimal places, got '{value}'"))
+        });
+
+    let only = Arg::with_name("only")
+        .long("only")
+        .help("Only scan and print these parts of the cache, may be passed multiple times")
+        .takes_value(true)
+        .value_name("CATEGORY")
+        .multiple(true)
+        .number_of_values(1)
+        .possible_values(CATEGORIES);
+
+    let discover_homes = Arg::with_name("discover-homes")
+        .long("discover-homes")
+        .help("Look for cargo homes in the usual places and print the size of each one");
+
+    let biggest_crate = Arg::with_name("biggest-crate")
+        .long("biggest-crate")
+        .help("Print the crate version whose archive and sources take the most space");
+
+    let counts_only = Arg::with_name("counts-only")
+        .long("counts-only")
+        .help("Only count the files and items of the cache, without reading any sizes")
+        .conflicts_with("only");
+
     // "version" subcommand which is also hidden, prints crate version
     let version_subcmd = SubCommand::with_name("version").settings(&[AppSettings::Hidden]);
 
@@ -278,19 +819,27 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .long("human-readable")
         .help("print sizes in human readable format");
 
+    // arg of query sbcmd
+    let no_human_readable = Arg::with_name("no-hr")
+        .long("no-human-readable")
+        .help("print sizes in bytes even if human-readable is set in the config file")
+        .conflicts_with("hr");
+
     // query subcommand to allow querying
     let query = SubCommand::with_name("query")
         .about("run a query")
         .arg(Arg::with_name("QUERY"))
         .arg(&query_order)
-        .arg(&human_readable);
+        .arg(&human_readable)
+        .arg(&no_human_readable);
 
     // short q (shorter query sbcmd)
     let query_short = SubCommand::with_name("q")
         .about("run a query")
         .arg(Arg::with_name("QUERY"))
         .arg(&query_order)
-        .arg(&human_readable);
+        .arg(&human_readable)
+        .arg(&no_human_readable);
     // </query>
 
     //<local>
@@ -303,13 +852,40 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
     //</local>
 
     // <registry>
+    // arg of registry subcmd
+    let registry_line_sort = Arg::with_name("registry-line-sort")
+        .long("registry-line-sort")
+        .help("order the index, archives and sources lines of each registry by type or by size")
+        .takes_value(true)
+        .value_name("ORDER")
+        .possible_values(&["type", "size"]);
+    let extraction_ratio = Arg::with_name("extraction-ratio")
+        .long("extraction-ratio")
+        .help("show how many of the crate archives of each registry were extracted to sources");
+    let split_index = Arg::with_name("split-index")
+        .long("split-index")
+        .help("split the index of each registry into the git index and its .cache dir");
+
     // registry subcommand
-    let registry =
-        SubCommand::with_name("registry").about("query each package registry separately");
-    let registry_short = SubCommand::with_name("r").about("query each package registry separately");
+    let registry = SubCommand::with_name("registry")
+        .about("query each package registry separately")
+        .arg(&registry_line_sort)
+        .arg(&extraction_ratio)
+        .arg(&split_index)
+        .arg(&format);
+    let registry_short = SubCommand::with_name("r")
+        .about("query each package registry separately")
+        .arg(&registry_line_sort)
+        .arg(&extraction_ratio)
+        .arg(&split_index)
+        .arg(&format);
     // hidden, but have "cargo cache registries" work too
     let registries_hidden = SubCommand::with_name("registries")
         .about("query each package registry separately")
+        .arg(&registry_line_sort)
+        .arg(&extraction_ratio)
+        .arg(&split_index)
+        .arg(&format)
         .settings(&[AppSettings::Hidden]);
     //</registry>
 
@@ -334,28 +910,267 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .takes_value(true)
         .value_name("PATH");
 
+    let keep_sources = Arg::with_name("keep-sources")
+        .long("keep-sources")
+        .help("Only remove unreferenced .crate archives and git repos, keep sources and checkouts");
+
+    let only_referenced_by = Arg::with_name("only-referenced-by")
+        .long("only-referenced-by")
+        .help("Keep exactly the crates and git checkouts pinned by this Cargo.lock, remove everything else")
+        .takes_value(true)
+        .value_name("LOCKFILE")
+        .conflicts_with("manifest-path");
+
+    let no_metadata_cache = Arg::with_name("no-metadata-cache")
+        .long("no-metadata-cache")
+        .help("Always run \"cargo metadata\", even if the Cargo.lock did not change since the last run");
+
+    // the json output of clean-unref is the plan of a dry run
+    let plan_format = format
+        .clone()
+        .help("Format of the output, json prints the plan of the dry run")
+        .requires("dry-runs");
+
     let clean_unref = SubCommand::with_name("clean-unref")
         .about("remove crates that are not referenced in a Cargo.toml from the cache")
         .arg(&manifest_path)
-        .arg(&dry_run);
+        .arg(&only_referenced_by)
+        .arg(&no_metadata_cache)
+        .arg(&keep_sources)
+        .arg(&plan_format)
+        .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history)
+        .arg(&max_removals)
+        .arg(&remove_jobs)
+        .arg(&trash)
+        .arg(&fail_if_removable)
+        .group(
+            ArgGroup::with_name("dry-runs")
+                .args(&["dry-run", "fail-if-removable"])
+                .multiple(true),
+        );
     //</clean-unref>
 
     //<trim>
     let size_limit = Arg::with_name("trim_limit")
         .long("limit")
         .short("l")
-        .help("size that the cache will be reduced to, for example: '6B', '1K', '4M', '5G' or '1T'")
+        .help("size that the cache will be reduced to, for example: '6B', '1K', '4M', '500MB' or '2GiB'")
         .takes_value(true)
         .value_name("LIMIT")
+        .validator(|value| validate_size(&value))
         .required(true);
 
     let trim = SubCommand::with_name("trim")
         .about("trim old items from the cache until maximum cache size limit is reached")
         .arg(&size_limit)
-        .arg(&dry_run);
+        .arg(&keep_crate)
+        .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history)
+        .arg(&max_removals)
+        .arg(&remove_jobs)
+        .arg(&trash);
 
     // </trim>
+
+    //<trim-to>
+    let target_size = Arg::with_name("target_size")
+        .help("size the registry will be reduced to, for example: '500MB' or '5GiB'")
+        .value_name("SIZE")
+        .validator(|value| validate_size(&value))
+        .required_unless("trim-by")
+        .conflicts_with("trim-by");
+
+    let trim_by = Arg::with_name("trim-by")
+        .long("trim-by")
+        .help("Remove this percentage (1% to 99%) of the registry instead of trimming it to SIZE")
+        .takes_value(true)
+        .value_name("PERCENT")
+        .validator(|value| {
+            parse_percent(&value)
+                .map(|_| ())
+                .ok_or_else(|| format!("expected a percentage between 1% and 99%, got '{value}'"))
+        });
+
+    let keep_referenced_by = Arg::with_name("keep-referenced-by")
+        .long("keep-referenced-by")
+        .help("Never remove crates referenced by this Cargo.toml")
+        .takes_value(true)
+        .value_name("PATH");
+
+    let trim_to = SubCommand::with_name("trim-to")
+        .about("remove the least recently used crates until the registry is smaller than SIZE")
+        .arg(&target_size)
+        .arg(&trim_by)
+        .arg(&keep_referenced_by)
+        .arg(&keep_crate)
+        .arg(&format)
+        .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history)
+        .arg(&max_removals)
+        .arg(&remove_jobs)
+        .arg(&trash);
+    //</trim-to>
+
+    //<clean-index>
+    let index_registry = Arg::with_name("registry")
+        .help("only remove the index of this registry, for example 'github.com'")
+        .value_name("REGISTRY");
+
+    let registry_match_dir = Arg::with_name("registry-match-dir")
+        .long("registry-match-dir")
+        .help("match REGISTRY against the directory name or its hash instead of the registry name")
+        .requires("registry");
+
+    let unused_indices = Arg::with_name("unused")
+        .long("unused")
+        .help("only remove the indices of registries that have no .crate archives in the cache")
+        .conflicts_with("registry");
+
+    let yes = Arg::with_name("yes")
+        .short("y")
+        .long("yes")
+        .help("Don't ask for confirmation before removing the indices");
+
+    let clean_index = SubCommand::with_name("clean-index")
+        .about("remove the registry indices, cargo fetches them again when needed")
+        .arg(&index_registry)
+        .arg(&registry_match_dir)
+        .arg(&unused_indices)
+        .arg(&yes)
+        .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history)
+        .arg(&max_removals)
+        .arg(&remove_jobs)
+        .arg(&trash);
+    //</clean-index>
     let toolchain = SubCommand::with_name("toolchain").about("print stats on installed toolchains");
+
+    let compression = SubCommand::with_name("compression")
+        .about("print the ratio of extracted source size to .crate archive size of each crate");
+
+    let remove_partial = Arg::with_name("remove")
+        .long("remove")
+        .help("Remove the partially downloaded .crate archives that were found");
+
+    let orphans = Arg::with_name("orphans")
+        .long("orphans")
+        .conflicts_with("remove")
+        .help("Only report the extracted sources that have no .crate archive");
+
+    let stale = Arg::with_name("stale")
+        .long("stale")
+        .conflicts_with_all(&["orphans", "remove"])
+        .help("Only report the extracted sources that are older than their .crate archive");
+
+    let fix_perms = Arg::with_name("fix-perms")
+        .long("fix-perms")
+        .conflicts_with_all(&["orphans", "stale"])
+        .help("Make extracted sources and git checkouts writable for cargo again (unix only)");
+
+    let doctor = SubCommand::with_name("doctor")
+        .about("check the cache for orphaned, stale, corrupt and duplicate items")
+        .arg(&format)
+        .arg(&orphans)
+        .arg(&stale)
+        .arg(&remove_partial)
+        .arg(&fix_perms)
+        .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history);
+
+    //<size>
+    let size_path = Arg::with_name("path")
+        .help("directory or file to get the size of")
+        .value_name("PATH")
+        .required(true);
+
+    let size_bytes = Arg::with_name("bytes")
+        .long("bytes")
+        .short("b")
+        .help("print the size in bytes")
+        .conflicts_with("unit");
+
+    let size_unit = Arg::with_name("unit")
+        .long("unit")
+        .short("u")
+        .help("print the size in this unit")
+        .takes_value(true)
+        .value_name("UNIT")
+        .possible_values(SIZE_UNITS);
+
+    let size_max_depth = Arg::with_name("max-depth")
+        .long("max-depth")
+        .short("d")
+        .help("also list the sizes of the directories up to N levels below the path")
+        .takes_value(true)
+        .value_name("N")
+        .validator(|value| {
+            value
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|_| format!("expected a depth, got '{value}'"))
+        });
+
+    let size = SubCommand::with_name("size")
+        .about("print the size and number of files of any directory")
+        .arg(&size_path)
+        .arg(&size_bytes)
+        .arg(&size_unit)
+        .arg(&size_max_depth)
+        .arg(&format);
+    //</size>
+
+    //<extract>
+    let extract_crate = Arg::with_name("crate")
+        .help("the crate to extract, for example 'serde@1.0.130'")
+        .value_name("NAME@VERSION")
+        .required(true);
+
+    let extract_to = Arg::with_name("to")
+        .long("to")
+        .help("directory to extract the crate into")
+        .takes_value(true)
+        .value_name("DIR")
+        .required(true);
+
+    let extract = SubCommand::with_name("extract")
+        .about("extract a cached .crate archive into a directory")
+        .arg(&extract_crate)
+        .arg(&extract_to)
+        .arg(&format);
+    //</extract>
+
+    let export_files = SubCommand::with_name("export-files")
+        .about("write the path and size of every file in the cache to a tab separated file")
+        .arg(
+            Arg::with_name("file")
+                .help("the file to write to")
+                .value_name("FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("Format of the export, jsonl writes one json object per file")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["tsv", "json", "jsonl"]),
+        );
+
+    let completions = SubCommand::with_name("completions")
+        .about("print a shell completion script for cargo-cache")
+        .arg(
+            Arg::with_name("shell")
+                .help("the shell to generate the completions for")
+                .value_name("SHELL")
+                .required(true)
+                .possible_values(&Shell::variants()),
+        );
     // now thread all of these together
 
     // subcommand hack to have "cargo cache --foo" and "cargo-cache --foo" work equally
@@ -378,24 +1193,67 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .subcommand(sccache.clone())
         .subcommand(sccache_short.clone())
         .subcommand(clean_unref.clone())
+        .subcommand(clean_index.clone())
         .subcommand(toolchain.clone())
         .subcommand(trim.clone())
+        .subcommand(trim_to.clone())
+        .subcommand(compression.clone())
+        .subcommand(doctor.clone())
+        .subcommand(extract.clone())
+        .subcommand(export_files.clone())
+        .subcommand(size.clone())
+        .subcommand(completions.clone())
         .arg(&list_dirs)
         .arg(&remove_dir)
+        .arg(&estimate_redownload)
         .arg(&gc_repos)
         .arg(&fsck_repos)
         .arg(&info)
         .arg(&keep_duplicate_crates)
+        .arg(&keep_crate)
         .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history)
+        .arg(&max_removals)
+        .arg(&remove_jobs)
+        .arg(&trash)
+        .arg(&fail_if_removable)
         .arg(&autoclean)
         .arg(&autoclean_expensive)
+        .arg(&remove_broken_symlinks)
+        .arg(&prune_git_checkouts)
+        .arg(&keep_last)
         .arg(&list_top_cache_items)
         .arg(&remove_if_younger)
         .arg(&remove_if_older)
         .arg(&debug)
+        .arg(&format)
+        .arg(&fields)
+        .arg(&exclude_glob)
+        .arg(&precision)
+        .arg(&verbose)
+        .arg(&quiet)
+        .arg(&log_trend)
+        .arg(&append_json)
+        .arg(&keep_runs)
+        .arg(&profile)
+        .arg(&reconcile)
+        .arg(&force_git)
+        .arg(&relative_paths)
+        .arg(&sort_categories)
+        .arg(&size_source)
+        .arg(&dedup_hardlinks)
+        .arg(&apparent)
+        .arg(&disk)
+        .arg(&only)
+        .arg(&counts_only)
+        .arg(&discover_homes)
+        .arg(&biggest_crate)
         .setting(AppSettings::Hidden);
+    // the completions are generated for the "cargo-cache" binary
+    let mut cargo_cache_app = cache_subcmd.clone();
 
-    App::new("cargo-cache")
+    let matches = App::new("cargo-cache")
         .version(&*version_string)
         .bin_name("cargo")
         .about("Manage cargo cache")
@@ -413,22 +1271,74 @@ pub(crate) fn gen_clap<'a>() -> ArgMatches<'a> {
         .subcommand(sccache)
         .subcommand(sccache_short)
         .subcommand(clean_unref)
+        .subcommand(clean_index)
         .subcommand(toolchain.clone())
         .subcommand(trim)
+        .subcommand(trim_to)
+        .subcommand(compression)
+        .subcommand(doctor)
+        .subcommand(extract)
+        .subcommand(export_files)
+        .subcommand(size)
+        .subcommand(completions)
         .arg(&list_dirs)
         .arg(&remove_dir)
+        .arg(&estimate_redownload)
         .arg(&gc_repos)
         .arg(&fsck_repos)
         .arg(&info)
         .arg(&keep_duplicate_crates)
+        .arg(&keep_crate)
         .arg(&dry_run)
+        .arg(&no_dry_run)
+        .arg(&no_history)
+        .arg(&max_removals)
+        .arg(&remove_jobs)
+        .arg(&trash)
+        .arg(&fail_if_removable)
         .arg(&autoclean)
         .arg(&autoclean_expensive)
+        .arg(&remove_broken_symlinks)
+        .arg(&prune_git_checkouts)
+        .arg(&keep_last)
         .arg(&list_top_cache_items)
         .arg(&remove_if_younger)
         .arg(&remove_if_older)
         .arg(&debug)
-        .get_matches()
+        .arg(&format)
+        .arg(&fields)
+        .arg(&exclude_glob)
+        .arg(&precision)
+        .arg(&verbose)
+        .arg(&quiet)
+        .arg(&log_trend)
+        .arg(&append_json)
+        .arg(&keep_runs)
+        .arg(&profile)
+        .arg(&reconcile)
+        .arg(&force_git)
+        .arg(&relative_paths)
+        .arg(&sort_categories)
+        .arg(&size_source)
+        .arg(&dedup_hardlinks)
+        .arg(&apparent)
+        .arg(&disk)
+        .arg(&only)
+        .arg(&counts_only)
+        .arg(&discover_homes)
+        .arg(&biggest_crate)
+        .get_matches();
+
+    // generating the completions needs the whole app, so this can not wait until main()
+    let cache_matches = matches.subcommand_matches("cache").unwrap_or(&matches);
+    if let Some(completions_config) = cache_matches.subcommand_matches("completions") {
+        // clap only lets valid shells through
+        let shell = value_t!(completions_config, "shell", Shell).unwrap_or_else(|e| e.exit());
+        cargo_cache_app.gen_completions_to("cargo-cache", shell, &mut io::stdout());
+        process::exit(0);
+    }
+
+    matches
 }
 
 #[cfg(test)]
@@ -455,35 +1365,78 @@ Manage cargo cache\n
 USAGE:
     cargo [FLAGS] [OPTIONS] [SUBCOMMAND]\n
 FLAGS:
-    -a, --autoclean              Removes crate source checkouts and git repo checkouts
-    -e, --autoclean-expensive    As --autoclean, but also recompresses git repositories
-    -n, --dry-run                Don't remove anything, just pretend
-    -f, --fsck                   Fsck git repositories
-    -g, --gc                     Recompress git repositories (may take some time)
-    -h, --help                   Prints help information
-    -i, --info                   Print information cache directories, what they are for and what can be safely deleted
-    -l, --list-dirs              List all found directory paths
-    -V, --version                Prints version information\n
+        --apparent                  Use the apparent size of files, this is the default
+    -a, --autoclean                 Removes crate source checkouts and git repo checkouts
+    -e, --autoclean-expensive       As --autoclean, but also recompresses git repositories
+        --biggest-crate             Print the crate version whose archive and sources take the most space
+        --counts-only               Only count the files and items of the cache, without reading any sizes
+        --dedup-hardlinks           Count files with several hard links only once (unix only)
+        --discover-homes            Look for cargo homes in the usual places and print the size of each one
+        --disk                      Use the disk space allocated to files like \"du\" (unix only)
+    -n, --dry-run                   Don't remove anything, just pretend
+        --estimate-redownload       Print how much of --remove-dir would be downloaded again, remove nothing
+        --fail-if-removable         Dry run, exit with an error if anything would be removed
+        --force-git                 Size the git db even if it is on a network filesystem
+    -f, --fsck                      Fsck git repositories
+    -g, --gc                        Recompress git repositories (may take some time)
+    -h, --help                      Prints help information
+    -i, --info                      Print information cache directories, what they are for and what can be safely
+                                    deleted
+    -l, --list-dirs                 List all found directory paths
+        --log-trend                 Append the json summary to $CARGO_HOME/cargo-cache-trend.jsonl
+        --no-dry-run                Remove items even if dry-run is enabled in the config file
+        --no-history                Don't log removed items to $CARGO_HOME/cargo-cache-history.log
+        --profile                   Print how long each phase of the scan took below the summary
+        --prune-git-checkouts       Remove all but the most recently modified checkout of each git repo
+        --quiet                     Only print the total size of the cache, also with --format json
+        --reconcile                 Adjust the largest sub-line of the summary so the sizes add up to their total
+        --relative-paths            Print paths inside the cargo home relative to it, in text and json output
+        --remove-broken-symlinks    Remove symlinks in the registry and git caches whose targets do not exist
+        --trash                     Move removed items to the trash instead of deleting them, fails if there is none
+    -V, --version                   Prints version information
+        --verbose                   Print additional information in the summary\n
 OPTIONS:
+        --append-json <FILE>               Append the json summary to the json array in FILE
+        --exclude-glob <PATTERN>...        Leave files matching PATTERN out of the sizes and counts, repeatable
+        --fields <field1,field2>           Only print these top-level fields of the json summary
+        --format <FORMAT>                  Format of the output [possible values: text, json]
+        --keep-crate <NAME>...             Exempt this crate from trim, trim-to, -o, -y and -k, may be repeated
     -k, --keep-duplicate-crates <N>        Remove all but N versions of crate in the source archives directory
+        --keep-last <N>                    Keep the N most recently modified checkouts of each git repo (default: 1)
+        --keep-runs <N>                    Keep only the newest N runs in the file of \"--append-json\"
+        --max-removals <N>                 Remove at most N items per removal operation
+        --only <CATEGORY>...               Only scan and print these parts of the cache, may be passed multiple times
+                                           [possible values: git, registry, bin, sources, index, checkouts]
+        --precision <N>                    Number of decimal places of human readable sizes, 2 by default
     -r, --remove-dir <dir1,dir2,dir3>      Remove directories, accepted values: all,git-db,git-repos,
                                            registry-sources,registry-crate-cache,registry-index,registry
     -o, --remove-if-older-than <date>      Removes items older than specified date: YYYY.MM.DD or HH:MM:SS
     -y, --remove-if-younger-than <date>    Removes items younger than the specified date: YYYY.MM.DD or HH:MM:SS
+        --remove-jobs <N>                  Remove up to N items at the same time, which is faster on SSDs (default: 1)
+        --size-source <FILE>               Take file sizes from the saved output of \"du -ab $CARGO_HOME\"
+        --sort-categories <ORDER>          Sort the blocks of the summary by size [possible values: fixed, size]
     -t, --top-cache-items <N>              List the top N items taking most space in the cache\n
 SUBCOMMANDS:
-    clean-unref    remove crates that are not referenced in a Cargo.toml from the cache
-    help           Prints this message or the help of the given subcommand(s)
-    l              check local build cache (target) of a rust project
-    local          check local build cache (target) of a rust project
-    q              run a query
-    query          run a query
-    r              query each package registry separately
-    registry       query each package registry separately
-    sc             gather stats on a local sccache cache
-    sccache        gather stats on a local sccache cache
-    toolchain      print stats on installed toolchains
-    trim           trim old items from the cache until maximum cache size limit is reached\n");
+    clean-index     remove the registry indices, cargo fetches them again when needed
+    clean-unref     remove crates that are not referenced in a Cargo.toml from the cache
+    completions     print a shell completion script for cargo-cache
+    compression     print the ratio of extracted source size to .crate archive size of each crate
+    doctor          check the cache for orphaned, stale, corrupt and duplicate items
+    export-files    write the path and size of every file in the cache to a tab separated file
+    extract         extract a cached .crate archive into a directory
+    help            Prints this message or the help of the given subcommand(s)
+    l               check local build cache (target) of a rust project
+    local           check local build cache (target) of a rust project
+    q               run a query
+    query           run a query
+    r               query each package registry separately
+    registry        query each package registry separately
+    sc              gather stats on a local sccache cache
+    sccache         gather stats on a local sccache cache
+    size            print the size and number of files of any directory
+    toolchain       print stats on installed toolchains
+    trim            trim old items from the cache until maximum cache size limit is reached
+    trim-to         remove the least recently used crates until the registry is smaller than SIZE\n");
         assert_eq!(help_desired, help_real);
     }
     #[test]
@@ -503,35 +1456,78 @@ Manage cargo cache\n
 USAGE:
     cargo cache [FLAGS] [OPTIONS] [SUBCOMMAND]\n
 FLAGS:
-    -a, --autoclean              Removes crate source checkouts and git repo checkouts
-    -e, --autoclean-expensive    As --autoclean, but also recompresses git repositories
-    -n, --dry-run                Don't remove anything, just pretend
-    -f, --fsck                   Fsck git repositories
-    -g, --gc                     Recompress git repositories (may take some time)
-    -h, --help                   Prints help information
-    -i, --info                   Print information cache directories, what they are for and what can be safely deleted
-    -l, --list-dirs              List all found directory paths
-    -V, --version                Prints version information\n
+        --apparent                  Use the apparent size of files, this is the default
+    -a, --autoclean                 Removes crate source checkouts and git repo checkouts
+    -e, --autoclean-expensive       As --autoclean, but also recompresses git repositories
+        --biggest-crate             Print the crate version whose archive and sources take the most space
+        --counts-only               Only count the files and items of the cache, without reading any sizes
+        --dedup-hardlinks           Count files with several hard links only once (unix only)
+        --discover-homes            Look for cargo homes in the usual places and print the size of each one
+        --disk                      Use the disk space allocated to files like \"du\" (unix only)
+    -n, --dry-run                   Don't remove anything, just pretend
+        --estimate-redownload       Print how much of --remove-dir would be downloaded again, remove nothing
+        --fail-if-removable         Dry run, exit with an error if anything would be removed
+        --force-git                 Size the git db even if it is on a network filesystem
+    -f, --fsck                      Fsck git repositories
+    -g, --gc                        Recompress git repositories (may take some time)
+    -h, --help                      Prints help information
+    -i, --info                      Print information cache directories, what they are for and what can be safely
+                                    deleted
+    -l, --list-dirs                 List all found directory paths
+        --log-trend                 Append the json summary to $CARGO_HOME/cargo-cache-trend.jsonl
+        --no-dry-run                Remove items even if dry-run is enabled in the config file
+        --no-history                Don't log removed items to $CARGO_HOME/cargo-cache-history.log
+        --profile                   Print how long each phase of the scan took below the summary
+        --prune-git-checkouts       Remove all but the most recently modified checkout of each git repo
+        --quiet                     Only print the total size of the cache, also with --format json
+        --reconcile                 Adjust the largest sub-line of the summary so the sizes add up to their total
+        --relative-paths            Print paths inside the cargo home relative to it, in text and json output
+        --remove-broken-symlinks    Remove symlinks in the registry and git caches whose targets do not exist
+        --trash                     Move removed items to the trash instead of deleting them, fails if there is none
+    -V, --version                   Prints version information
+        --verbose                   Print additional information in the summary\n
 OPTIONS:
+        --append-json <FILE>               Append the json summary to the json array in FILE
+        --exclude-glob <PATTERN>...        Leave files matching PATTERN out of the sizes and counts, repeatable
+        --fields <field1,field2>           Only print these top-level fields of the json summary
+        --format <FORMAT>                  Format of the output [possible values: text, json]
+        --keep-crate <NAME>...             Exempt this crate from trim, trim-to, -o, -y and -k, may be repeated
     -k, --keep-duplicate-crates <N>        Remove all but N versions of crate in the source archives directory
+        --keep-last <N>                    Keep the N most recently modified checkouts of each git repo (default: 1)
+        --keep-runs <N>                    Keep only the newest N runs in the file of \"--append-json\"
+        --max-removals <N>                 Remove at most N items per removal operation
+        --only <CATEGORY>...               Only scan and print these parts of the cache, may be passed multiple times
+                                           [possible values: git, registry, bin, sources, index, checkouts]
+        --precision <N>                    Number of decimal places of human readable sizes, 2 by default
     -r, --remove-dir <dir1,dir2,dir3>      Remove directories, accepted values: all,git-db,git-repos,
                                            registry-sources,registry-crate-cache,registry-index,registry
     -o, --remove-if-older-than <date>      Removes items older than specified date: YYYY.MM.DD or HH:MM:SS
     -y, --remove-if-younger-than <date>    Removes items younger than the specified date: YYYY.MM.DD or HH:MM:SS
+        --remove-jobs <N>                  Remove up to N items at the same time, which is faster on SSDs (default: 1)
+        --size-source <FILE>               Take file sizes from the saved output of \"du -ab $CARGO_HOME\"
+        --sort-categories <ORDER>          Sort the blocks of the summary by size [possible values: fixed, size]
     -t, --top-cache-items <N>              List the top N items taking most space in the cache\n
 SUBCOMMANDS:
-    clean-unref    remove crates that are not referenced in a Cargo.toml from the cache
-    help           Prints this message or the help of the given subcommand(s)
-    l              check local build cache (target) of a rust project
-    local          check local build cache (target) of a rust project
-    q              run a query
-    query          run a query
-    r              query each package registry separately
-    registry       query each package registry separately
-    sc             gather stats on a local sccache cache
-    sccache        gather stats on a local sccache cache
-    toolchain      print stats on installed toolchains
-    trim           trim old items from the cache until maximum cache size limit is reached\n");
+    clean-index     remove the registry indices, cargo fetches them again when needed
+    clean-unref     remove crates that are not referenced in a Cargo.toml from the cache
+    completions     print a shell completion script for cargo-cache
+    compression     print the ratio of extracted source size to .crate archive size of each crate
+    doctor          check the cache for orphaned, stale, corrupt and duplicate items
+    export-files    write the path and size of every file in the cache to a tab separated file
+    extract         extract a cached .crate archive into a directory
+    help            Prints this message or the help of the given subcommand(s)
+    l               check local build cache (target) of a rust project
+    local           check local build cache (target) of a rust project
+    q               run a query
+    query           run a query
+    r               query each package registry separately
+    registry        query each package registry separately
+    sc              gather stats on a local sccache cache
+    sccache         gather stats on a local sccache cache
+    size            print the size and number of files of any directory
+    toolchain       print stats on installed toolchains
+    trim            trim old items from the cache until maximum cache size limit is reached
+    trim-to         remove the least recently used crates until the registry is smaller than SIZE\n");
 
         assert_eq!(help_desired, help_real);
     }
@@ -559,9 +1555,10 @@ USAGE:
     cargo cache query [FLAGS] [OPTIONS] [QUERY]
 
 FLAGS:
-        --help              Prints help information
-    -h, --human-readable    print sizes in human readable format
-    -V, --version           Prints version information
+        --help                 Prints help information
+    -h, --human-readable       print sizes in human readable format
+        --no-human-readable    print sizes in bytes even if human-readable is set in the config file
+    -V, --version              Prints version information
 
 OPTIONS:
     -s, --sort-by <sort>    sort files alphabetically or by file size [possible values: size, name]
@@ -625,6 +1622,43 @@ ARGS:
         // last line must be this warning:
         assert_eq!(last_line, Some("Warning: there is nothing to be dry run!"));
     }
+
+    #[test]
+    fn sizes_are_validated() {
+        for args in &[&["trim", "--limit", "5XB"][..], &["trim-to", "5XB"][..]] {
+            let cc_trim = Command::new(bin_path())
+                .arg("cache")
+                .args(*args)
+                .output()
+                .unwrap();
+            assert!(!cc_trim.status.success());
+            let stderr = String::from_utf8_lossy(&cc_trim.stderr).into_owned();
+            assert!(
+                stderr.contains("Failed to parse size: \"5XB\""),
+                "{:?}: {}",
+                args,
+                stderr
+            );
+        }
+    }
+
+    #[test]
+    fn completions() {
+        for shell in &["bash", "zsh", "fish", "powershell", "elvish"] {
+            let cc_completions = Command::new(bin_path())
+                .arg("cache")
+                .arg("completions")
+                .arg(shell)
+                .output()
+                .unwrap();
+            assert!(cc_completions.status.success());
+
+            let script = String::from_utf8_lossy(&cc_completions.stdout).into_owned();
+            assert!(script.contains("cargo-cache"), "{}: {}", shell, script);
+            // subcommands and their flags are completed
+            assert!(script.contains("trim-by"), "{}: {}", shell, script);
+        }
+    }
 }
 
 #[cfg(all(test, feature = "bench"))]
@@ -0,0 +1,237 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--biggest-crate" which finds the crate version that takes the most space
+/// in the cache, its .crate archive and its extracted sources combined, across all registries.
+use std::path::PathBuf;
+
+use crate::cache::archive_sources::match_archives_to_sources;
+use crate::cache::caches::{get_cache_name, RegistrySuperCache};
+use crate::cache::*;
+use crate::library::{cmp_versions, size_opts, Error};
+use crate::relative_paths;
+use crate::remove::parse_version;
+use crate::settings::Settings;
+use crate::size_source;
+use crate::tables::{two_row_table, TableLine};
+
+use humansize::FileSize;
+use rayon::prelude::*;
+
+/// the space a single crate version of a registry takes in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CrateFootprint {
+    name: String,
+    version: String,
+    /// the name of the registry: "github.com"
+    registry: String,
+    archive: PathBuf,
+    archive_size: u64,
+    /// the extracted sources, None if the archive was not extracted
+    source: Option<PathBuf>,
+    source_size: u64,
+}
+
+impl CrateFootprint {
+    fn total_size(&self) -> u64 {
+        self.archive_size + self.source_size
+    }
+}
+
+/// the footprint of every .crate archive together with the sources it was extracted to
+fn footprints(
+    settings: &Settings,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<Vec<CrateFootprint>, Error> {
+    let archives = registry_pkg_caches.files();
+    let sources = registry_sources_caches.items().to_vec();
+    // sized like the summary sizes the caches, with "--size-source", "--disk" and "--dedup-hardlinks"
+    let source_sizes = registry_sources_caches.item_sizes();
+
+    match_archives_to_sources(&archives, &sources)
+        .into_par_iter()
+        .map(|pair| {
+            let (name, version) = parse_version(&pair.archive)?;
+            Ok(CrateFootprint {
+                name,
+                version,
+                registry: pair
+                    .archive
+                    .parent()
+                    .map(get_cache_name)
+                    .unwrap_or_default(),
+                archive_size: size_source::file_size(settings, &pair.archive),
+                source_size: pair
+                    .source
+                    .as_ref()
+                    .and_then(|source| source_sizes.get(source))
+                    .copied()
+                    .unwrap_or(0),
+                archive: pair.archive,
+                source: pair.source,
+            })
+        })
+        .collect()
+}
+
+/// the crate version with the biggest footprint, ties go to the first one by name and version
+fn biggest(footprints: &[CrateFootprint]) -> Option<&CrateFootprint> {
+    footprints.iter().min_by(|a, b| {
+        b.total_size()
+            .cmp(&a.total_size())
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| cmp_versions(&a.version, &b.version))
+            .then_with(|| a.registry.cmp(&b.registry))
+    })
+}
+
+/// the biggest crate version and how its size is made up
+fn biggest_crate_summary(settings: &Settings, biggest: Option<&CrateFootprint>) -> String {
+    let Some(footprint) = biggest else {
+        return String::from("No .crate archives found\n");
+    };
+
+    let sources_line = match &footprint.source {
+        Some(source) => TableLine::new(
+            1,
+            &format!(
+                "sources: {}: ",
+                relative_paths::shown(settings, source).display()
+            ),
+            &footprint
+                .source_size
+                .file_size(size_opts(settings))
+                .unwrap(),
+        ),
+        None => TableLine::new(1, &"sources: ", &"not extracted"),
+    };
+    let table = vec![
+        TableLine::new(
+            0,
+            &format!(
+                "Biggest crate: {} {} ({}): ",
+                footprint.name, footprint.version, footprint.registry
+            ),
+            &footprint
+                .total_size()
+                .file_size(size_opts(settings))
+                .unwrap(),
+        ),
+        TableLine::new(
+            1,
+            &format!(
+                "archive: {}: ",
+                relative_paths::shown(settings, &footprint.archive).display()
+            ),
+            &footprint
+                .archive_size
+                .file_size(size_opts(settings))
+                .unwrap(),
+        ),
+        sources_line,
+    ];
+
+    two_row_table(2, table, true)
+}
+
+/// print the crate version whose archive and sources take the most space (cmd: "cargo cache --biggest-crate")
+pub(crate) fn biggest_crate(
+    settings: &Settings,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<(), Error> {
+    let footprints = footprints(settings, registry_pkg_caches, registry_sources_caches)?;
+    print!("{}", biggest_crate_summary(settings, biggest(&footprints)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod biggest_crate_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+    fn footprint(name: &str, archive_size: u64, source_size: Option<u64>) -> CrateFootprint {
+        let archives = "registry/cache/github.com-1ecc6299db9ec823";
+        CrateFootprint {
+            name: name.to_string(),
+            version: String::from("0.1.0"),
+            registry: String::from("github.com"),
+            archive: PathBuf::from(format!("{archives}/{name}-0.1.0.crate")),
+            archive_size,
+            source: source_size
+                .map(|_| PathBuf::from(format!("registry/src/github.com/{name}-0.1.0"))),
+            source_size: source_size.unwrap_or(0),
+        }
+    }
+
+    #[test]
+    fn archive_and_sources_are_combined() {
+        let footprints = vec![
+            // the biggest archive, but it was never extracted
+            footprint("big-archive", 3_000, None),
+            footprint("foo", 1_000, Some(4_000)),
+            footprint("bar", 1_000, Some(4_000)),
+        ];
+        let biggest = biggest(&footprints).unwrap();
+        // "bar" and "foo" take the same space, "bar" comes first by name
+        assert_eq!(biggest.name, "bar");
+
+        assert_eq!(
+            biggest_crate_summary(&Settings::default(), Some(biggest)),
+            "Biggest crate: bar 0.1.0 (github.com):                                   5 KB
+  archive: registry/cache/github.com-1ecc6299db9ec823/bar-0.1.0.crate:   1 KB
+  sources: registry/src/github.com/bar-0.1.0:                            4 KB\n"
+        );
+    }
+
+    #[test]
+    fn footprints_agree_with_the_cache_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = "github.com-1ecc6299db9ec823";
+        let archives = tmp.path().join("cache").join(registry);
+        let source = tmp.path().join("src").join(registry).join("foo-0.1.0");
+        std::fs::create_dir_all(&archives).unwrap();
+        std::fs::create_dir_all(source.join("src")).unwrap();
+        std::fs::write(archives.join("foo-0.1.0.crate"), b"123").unwrap();
+        std::fs::write(source.join("Cargo.toml"), b"1234").unwrap();
+        std::fs::write(source.join("src").join("lib.rs"), b"12345").unwrap();
+
+        let mut pkgs =
+            registry_pkg_cache::RegistryPkgCaches::new(tmp.path().join("cache"), Arc::default());
+        let mut sources =
+            registry_sources::RegistrySourceCaches::new(tmp.path().join("src"), Arc::default());
+        let footprints = footprints(&Settings::default(), &mut pkgs, &mut sources).unwrap();
+
+        assert_eq!(footprints.len(), 1);
+        assert_eq!(footprints[0].archive_size, 3);
+        // only the files count, not the directories
+        assert_eq!(footprints[0].source_size, 9);
+        assert_eq!(
+            footprints[0].total_size(),
+            pkgs.total_size() + sources.total_size()
+        );
+    }
+
+    #[test]
+    fn unextracted_and_no_crates() {
+        let footprints = vec![footprint("foo", 1_000, None)];
+        assert_eq!(
+            biggest_crate_summary(&Settings::default(), biggest(&footprints)),
+            "Biggest crate: foo 0.1.0 (github.com):                                            1 KB
+  archive: registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate:            1 KB
+  sources:                                                               not extracted\n"
+        );
+        assert_eq!(
+            biggest_crate_summary(&Settings::default(), None),
+            "No .crate archives found\n"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "cargo cache clean-index [REGISTRY]" which removes the registry indices,
+/// either all of them, only the one of a single registry or, with "--unused", only those
+/// of registries that have no .crate archives in the cache.
+/// cargo fetches an index again the next time it needs it, which can take a while.
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::cache::archive_sources::indices_without_archives;
+use crate::cache::caches::{RegistryMatch, RegistrySubCache, RegistrySuperCache};
+use crate::cache::*;
+use crate::library::{size_opts, Error};
+use crate::remove::{
+    limit_removals, remove_all, remove_file, report_removal_limit, DryRunMessage, RemovalProgress,
+};
+use crate::settings::Settings;
+
+use humansize::FileSize;
+
+/// the indices to remove with their sizes
+/// a registry is selected by its name ("github.com") or with `RegistryMatch::Dir`
+/// by its directory name ("github.com-1ecc6299db9ec823")
+/// with `only`, indices that are not listed are skipped
+fn select_indices(
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry: Option<(&str, RegistryMatch)>,
+    only: Option<&[PathBuf]>,
+) -> Result<Vec<(PathBuf, u64)>, Error> {
+    let selected = registry_index_caches
+        .caches()
+        .iter_mut()
+        .filter(|index| only.is_none_or(|listed| listed.contains(index.path())))
+        .filter(|index| {
+            registry
+                .is_none_or(|(wanted, registry_match)| registry_match.matches(index.path(), wanted))
+        })
+        .map(|index| (index.path().clone(), index.total_size()))
+        .collect::<Vec<_>>();
+
+    match registry {
+        Some((wanted, _)) if selected.is_empty() => Err(Error::NoSuchRegistryIndex(wanted.into())),
+        _ => Ok(selected),
+    }
+}
+
+/// only "y" and "yes" confirm, everything else (including no answer at all) aborts
+fn is_confirmation(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// ask the user on stdin whether the indices should really be removed
+fn confirm(settings: &Settings, number: usize, size: u64) -> bool {
+    print!(
+        "Remove {} registry {} ({})? [y/N] ",
+        number,
+        if number == 1 { "index" } else { "indices" },
+        size.file_size(size_opts(settings)).unwrap()
+    );
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(_) => is_confirmation(&answer),
+        Err(_) => false,
+    }
+}
+
+/// the indices of registries without any .crate archive in the cache
+fn unused_indices(
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+) -> Vec<PathBuf> {
+    let indices = registry_index_caches
+        .caches()
+        .iter()
+        .map(|index| index.path().clone())
+        .collect::<Vec<PathBuf>>();
+    indices_without_archives(&registry_pkg_caches.files(), &indices)
+}
+
+/// remove the indices of all registries, only the index of `registry`
+/// or with `unused_only`, only the indices of registries that have no archives
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn clean_index(
+    settings: &Settings,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry: Option<(&str, RegistryMatch)>,
+    unused_only: bool,
+    assume_yes: bool,
+    dry_run: bool,
+    size_changed: &mut bool,
+) -> Result<(), Error> {
+    let unused = if unused_only {
+        Some(unused_indices(registry_index_caches, registry_pkg_caches))
+    } else {
+        None
+    };
+    let selected = select_indices(registry_index_caches, registry, unused.as_deref())?;
+    let (indices, remaining) = limit_removals(settings, &selected);
+    let total_size: u64 = indices.iter().map(|(_path, size)| size).sum();
+
+    if unused_only && indices.is_empty() {
+        println!("No registry indices without .crate archives found");
+        return Ok(());
+    }
+
+    if !dry_run {
+        println!(
+            "Warning: the next cargo command that needs a removed index will fetch it again, this can take a while."
+        );
+        if !assume_yes && !confirm(settings, indices.len(), total_size) {
+            return Err(Error::IndexRemovalAborted);
+        }
+    }
+
+    let progress = RemovalProgress::new(settings, indices.len(), dry_run);
+    remove_all(
+        settings,
+        indices,
+        dry_run,
+        size_changed,
+        |(path, size), changed| {
+            // failed removals are reported and do not count as reclaimed
+            if remove_file(
+                settings,
+                path,
+                dry_run,
+                changed,
+                None,
+                &DryRunMessage::Default,
+                Some(*size),
+            ) {
+                progress.removed(*size);
+            }
+        },
+    );
+    progress.finish();
+    report_removal_limit(settings, remaining);
+
+    if !dry_run {
+        registry_index_caches.invalidate();
+    }
+
+    println!(
+        "{} {} registry {} totalling {}",
+        if dry_run { "Would remove" } else { "Removed" },
+        indices.len(),
+        if indices.len() == 1 {
+            "index"
+        } else {
+            "indices"
+        },
+        total_size.file_size(size_opts(settings)).unwrap()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod clean_index_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::sync::Arc;
+
+    #[test]
+    fn select_registries() {
+        let dir = tempfile::tempdir().unwrap();
+        let crates_io = dir.path().join("github.com-1ecc6299db9ec823");
+        let other = dir.path().join("my-registry-0123456789abcdef");
+        for index in &[&crates_io, &other] {
+            fs::create_dir_all(index.join(".cache")).unwrap();
+        }
+        fs::write(crates_io.join(".cache").join("serde"), b"12345").unwrap();
+        fs::write(other.join(".cache").join("foo"), b"123").unwrap();
+
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(dir.path().to_path_buf(), Arc::default());
+
+        let mut all = select_indices(&mut index_caches, None, None).unwrap();
+        all.sort();
+        assert_eq!(all, vec![(crates_io.clone(), 5), (other.clone(), 3)]);
+
+        // by name
+        assert_eq!(
+            select_indices(
+                &mut index_caches,
+                Some(("github.com", RegistryMatch::Name)),
+                None
+            )
+            .unwrap(),
+            vec![(crates_io.clone(), 5)]
+        );
+        assert_eq!(
+            select_indices(
+                &mut index_caches,
+                Some(("my-registry", RegistryMatch::Name)),
+                None
+            )
+            .unwrap(),
+            vec![(other.clone(), 3)]
+        );
+        assert!(matches!(
+            select_indices(
+                &mut index_caches,
+                Some(("my-registry-0123456789abcdef", RegistryMatch::Name)),
+                None
+            ),
+            Err(Error::NoSuchRegistryIndex(_))
+        ));
+
+        // by directory name or hash
+        assert_eq!(
+            select_indices(
+                &mut index_caches,
+                Some(("my-registry-0123456789abcdef", RegistryMatch::Dir)),
+                None
+            )
+            .unwrap(),
+            vec![(other.clone(), 3)]
+        );
+        assert_eq!(
+            select_indices(
+                &mut index_caches,
+                Some(("1ecc6299db9ec823", RegistryMatch::Dir)),
+                None
+            )
+            .unwrap(),
+            vec![(crates_io, 5)]
+        );
+        assert!(matches!(
+            select_indices(
+                &mut index_caches,
+                Some(("github.com", RegistryMatch::Dir)),
+                None
+            ),
+            Err(Error::NoSuchRegistryIndex(_))
+        ));
+
+        assert!(matches!(
+            select_indices(&mut index_caches, Some(("nope", RegistryMatch::Name)), None),
+            Err(Error::NoSuchRegistryIndex(_))
+        ));
+
+        // only the listed indices
+        assert_eq!(
+            select_indices(&mut index_caches, None, Some(std::slice::from_ref(&other))).unwrap(),
+            vec![(other, 3)]
+        );
+    }
+
+    #[test]
+    fn confirmation() {
+        assert!(is_confirmation("y\n"));
+        assert!(is_confirmation("Yes\n"));
+        assert!(!is_confirmation("\n"));
+        assert!(!is_confirmation(""));
+        assert!(!is_confirmation("no\n"));
+    }
+}
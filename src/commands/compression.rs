@@ -0,0 +1,158 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+
+use crate::cache::archive_sources::match_archives_to_sources;
+use crate::cache::caches::RegistrySuperCache;
+use crate::cache::*;
+use crate::library::{cumulative_dir_size, Error};
+use crate::remove::parse_version;
+use crate::settings::Settings;
+use crate::tables::format_table;
+
+use rayon::prelude::*;
+
+/// the size of a .crate archive compared to the size of its extracted sources
+#[derive(Debug, Clone)]
+struct CompressionRatio {
+    name: String,
+    version: String,
+    archive_size: u64,
+    source_size: u64,
+}
+
+impl CompressionRatio {
+    /// how many times bigger the extracted sources are than the archive
+    fn ratio(&self) -> f64 {
+        // precision loss does not matter, we only print 2 decimal places
+        #[allow(clippy::cast_precision_loss)]
+        if self.archive_size == 0 {
+            0.0
+        } else {
+            self.source_size as f64 / self.archive_size as f64
+        }
+    }
+}
+
+/// calculate the compression ratio of every .crate archive that has been extracted
+/// the result is sorted by ratio, highest ratio first
+fn compression_ratios(
+    settings: &Settings,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<Vec<CompressionRatio>, Error> {
+    let archives = registry_pkg_caches.files();
+    let sources = registry_sources_caches.items().to_vec();
+
+    let mut ratios = match_archives_to_sources(&archives, &sources)
+        .into_par_iter()
+        // skip all archives that were not extracted
+        .filter_map(|pair| {
+            let archive = pair.archive;
+            pair.source.map(|source| (archive, source))
+        })
+        .map(|(archive, source)| {
+            let (name, version) = parse_version(&archive)?;
+            Ok(CompressionRatio {
+                name,
+                version,
+                archive_size: fs::metadata(&archive).map_or(0, |m| m.len()),
+                source_size: cumulative_dir_size(settings, &source).dir_size,
+            })
+        })
+        .collect::<Result<Vec<CompressionRatio>, Error>>()?;
+
+    sort_by_ratio(&mut ratios);
+    Ok(ratios)
+}
+
+/// sort by ratio (descending), then by name and version
+fn sort_by_ratio(ratios: &mut [CompressionRatio]) {
+    ratios.sort_by(|a, b| {
+        b.ratio()
+            .partial_cmp(&a.ratio())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.version.cmp(&b.version))
+    });
+}
+
+/// format the ratios as a table
+fn ratio_table(ratios: &[CompressionRatio]) -> String {
+    if ratios.is_empty() {
+        return String::from("No extracted .crate archives found.");
+    }
+
+    let mut table_vec = Vec::with_capacity(ratios.len() + 1 /* header */);
+    table_vec.push(vec![
+        "Name".to_string(),
+        "Version".to_string(),
+        "Ratio".to_string(),
+    ]);
+    table_vec.extend(ratios.iter().map(|crate_ratio| {
+        vec![
+            crate_ratio.name.clone(),
+            crate_ratio.version.clone(),
+            format!("{:.2}", crate_ratio.ratio()),
+        ]
+    }));
+
+    format_table(&table_vec, 1).trim().to_string()
+}
+
+/// print the compression ratio of each extracted .crate archive
+pub(crate) fn compression_stats(
+    settings: &Settings,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<(), Error> {
+    let ratios = compression_ratios(settings, registry_pkg_caches, registry_sources_caches)?;
+    println!("{}", ratio_table(&ratios));
+    Ok(())
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn ratio(name: &str, version: &str, archive_size: u64, source_size: u64) -> CompressionRatio {
+        CompressionRatio {
+            name: name.to_string(),
+            version: version.to_string(),
+            archive_size,
+            source_size,
+        }
+    }
+
+    #[test]
+    fn sorted_table() {
+        let mut ratios = vec![
+            ratio("foo", "0.1.0", 100, 200),
+            ratio("bar", "1.0.0", 10, 100),
+            ratio("empty", "0.0.1", 0, 100),
+            ratio("baz", "0.3.0", 100, 200),
+        ];
+        sort_by_ratio(&mut ratios);
+
+        let table = ratio_table(&ratios);
+        let desired = "Name   Version  Ratio\n\
+                       bar    1.0.0    10.00\n\
+                       baz    0.3.0    2.00\n\
+                       foo    0.1.0    2.00\n\
+                       empty  0.0.1    0.00";
+        assert_eq!(table, desired);
+    }
+
+    #[test]
+    fn no_ratios() {
+        assert_eq!(ratio_table(&[]), "No extracted .crate archives found.");
+    }
+}
@@ -0,0 +1,1466 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "cargo cache doctor" which runs several checks on the cache
+/// and reports what it finds. Nothing is removed, except for partially downloaded
+/// .crate archives with "cargo cache doctor --remove".
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::cache::archive_sources::{
+    indices_without_archives, match_archives_to_sources, sources_without_archive,
+    sources_without_archive_or_index, unpaired_registry_dirs,
+};
+use crate::cache::caches::{Cache, RegistrySubCache, RegistrySuperCache};
+use crate::cache::git_checkouts::checkouts_by_repo;
+use crate::cache::*;
+use crate::library::{size_of_path, size_opts};
+use crate::output::{to_json, OutputFormat};
+use crate::relative_paths;
+use crate::remove::{
+    limit_removals, parse_version, remove_file, report_failed_removals, report_removal_limit,
+    DryRunMessage,
+};
+use crate::settings::Settings;
+use crate::warnings;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::read::GzDecoder;
+use humansize::FileSize;
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// the checks that are run by the doctor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Check {
+    /// extracted sources that have no .crate archive
+    Orphans,
+    /// extracted sources that have no .crate archive and no registry index
+    FullyOrphaned,
+    /// extracted sources that are older than their .crate archive
+    StaleSources,
+    /// .crate archives that are empty or temporary files of an interrupted download
+    PartialDownloads,
+    /// .crate archives that can not be decompressed
+    CorruptArchives,
+    /// several checkouts of the same git repo
+    DuplicateCheckouts,
+    /// registry indices whose registry has no .crate archives
+    UnusedIndices,
+    /// archive caches without an index of the same registry and the other way around
+    UnpairedRegistries,
+}
+
+impl Check {
+    /// short description of what was found
+    fn description(self) -> &'static str {
+        match self {
+            Self::Orphans => "Orphaned sources (no .crate archive)",
+            Self::FullyOrphaned => {
+                "Fully orphaned sources (no .crate archive and no registry index)"
+            }
+            Self::StaleSources => "Stale sources (older than their .crate archive)",
+            Self::PartialDownloads => {
+                "Partially downloaded .crate archives (empty or temporary files)"
+            }
+            Self::CorruptArchives => "Corrupt .crate archives",
+            Self::DuplicateCheckouts => "Duplicate git checkouts (all but the newest)",
+            Self::UnusedIndices => "Registry indices without any .crate archives",
+            Self::UnpairedRegistries => "Registries with only an archive cache or only an index",
+        }
+    }
+
+    /// what the user can do about the findings
+    fn suggestion(self) -> &'static str {
+        match self {
+            Self::Orphans | Self::StaleSources | Self::DuplicateCheckouts => {
+                "cargo cache --autoclean"
+            }
+            Self::FullyOrphaned => {
+                "remove the sources listed above, their registry is gone so cargo will never use them again"
+            }
+            Self::CorruptArchives => {
+                "remove the archives listed above, cargo will download them again when needed"
+            }
+            Self::PartialDownloads => "cargo cache doctor --remove",
+            Self::UnusedIndices => "cargo cache clean-index --unused",
+            Self::UnpairedRegistries => {
+                "remove the directories listed above if their registry is not used anymore"
+            }
+        }
+    }
+}
+
+/// a single item a check complained about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Finding {
+    /// the path of the item
+    pub(crate) path: PathBuf,
+    /// size of the item, this is what could be reclaimed by removing it
+    pub(crate) size: u64,
+}
+
+impl Finding {
+    fn new(settings: &Settings, path: PathBuf) -> Self {
+        let size = size_of_path(settings, &path);
+        Self { path, size }
+    }
+}
+
+/// the findings of a single check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CheckReport {
+    pub(crate) check: Check,
+    pub(crate) findings: Vec<Finding>,
+}
+
+impl CheckReport {
+    fn new(settings: &Settings, check: Check, paths: Vec<PathBuf>) -> Self {
+        let mut findings = paths
+            .into_par_iter()
+            .map(|path| Finding::new(settings, path))
+            .collect::<Vec<Finding>>();
+        findings.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { check, findings }
+    }
+
+    /// total size of all the findings
+    pub(crate) fn reclaimable(&self) -> u64 {
+        self.findings.iter().map(|finding| finding.size).sum()
+    }
+}
+
+/// the findings of a single check, serialized for "--format json"
+#[derive(Debug, Serialize)]
+struct CheckReportJson {
+    check: Check,
+    /// the findings with their paths as they are shown
+    findings: Vec<Finding>,
+    reclaimable: u64,
+}
+
+/// a checkout of a git repo, serialized for "--format json"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct CheckoutJson {
+    rev: String,
+    size: u64,
+    /// RFC 3339 time of the last modification
+    mtime: Option<String>,
+}
+
+/// all checkouts of a repo that has several, newest first, serialized for "--format json"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct DuplicateCheckoutsJson {
+    /// the name of the checkouts dir of the repo, which is the name of its bare repo
+    repo: String,
+    checkouts: Vec<CheckoutJson>,
+    /// size of all checkouts but the newest
+    reclaimable_if_pruned: u64,
+}
+
+impl DuplicateCheckoutsJson {
+    fn new(settings: &Settings, repo: &Path, revs: &[&PathBuf]) -> Self {
+        let checkouts = revs
+            .iter()
+            .map(|rev| CheckoutJson {
+                rev: file_name(rev),
+                size: size_of_path(settings, rev),
+                mtime: modification_time(rev).map(rfc3339),
+            })
+            .collect::<Vec<_>>();
+        Self {
+            repo: file_name(repo),
+            reclaimable_if_pruned: checkouts.iter().skip(1).map(|rev| rev.size).sum(),
+            checkouts,
+        }
+    }
+}
+
+/// RFC 3339 time in UTC, the format of the times in the json output
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// the findings of all checks, serialized for "--format json"
+#[derive(Debug, Serialize)]
+struct DoctorReportJson<'a> {
+    cargo_home: &'a Path,
+    checks: Vec<CheckReportJson>,
+    total_reclaimable: u64,
+    /// the checkouts of every repo that has duplicates
+    duplicate_checkouts: Vec<DuplicateCheckoutsJson>,
+    /// the number of removed partial downloads, only with "--remove"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    removed: Option<usize>,
+    /// the number of entries whose permissions were fixed, only with "--fix-perms"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permissions_fixed: Option<usize>,
+}
+
+/// JSON Schema (draft 7) of the findings of all checks
+#[cfg(test)]
+pub(crate) const DOCTOR_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache doctor --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "cargo_home": { "type": "string" },
+    "checks": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "check": { "type": "string" },
+          "findings": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "properties": {
+                "path": { "type": "string" },
+                "size": { "type": "integer", "minimum": 0 }
+              },
+              "required": ["path", "size"],
+              "additionalProperties": false
+            }
+          },
+          "reclaimable": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["check", "findings", "reclaimable"],
+        "additionalProperties": false
+      }
+    },
+    "total_reclaimable": { "type": "integer", "minimum": 0 },
+    "duplicate_checkouts": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "repo": { "type": "string" },
+          "checkouts": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "properties": {
+                "rev": { "type": "string" },
+                "size": { "type": "integer", "minimum": 0 },
+                "mtime": { "type": ["string", "null"], "format": "date-time" }
+              },
+              "required": ["rev", "size", "mtime"],
+              "additionalProperties": false
+            }
+          },
+          "reclaimable_if_pruned": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["repo", "checkouts", "reclaimable_if_pruned"],
+        "additionalProperties": false
+      }
+    },
+    "removed": { "type": "integer", "minimum": 0 },
+    "permissions_fixed": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": [
+    "schema_version",
+    "generated_at",
+    "cargo_home",
+    "checks",
+    "total_reclaimable",
+    "duplicate_checkouts"
+  ],
+  "additionalProperties": false
+}"#;
+
+impl<'a> DoctorReportJson<'a> {
+    fn new(
+        settings: &Settings,
+        cargo_home: &'a Path,
+        reports: &[CheckReport],
+        checkouts: &[PathBuf],
+    ) -> Self {
+        Self {
+            cargo_home,
+            checks: reports
+                .iter()
+                .map(|report| CheckReportJson {
+                    check: report.check,
+                    findings: report
+                        .findings
+                        .iter()
+                        .map(|finding| Finding {
+                            path: relative_paths::shown(settings, &finding.path).to_path_buf(),
+                            size: finding.size,
+                        })
+                        .collect(),
+                    reclaimable: report.reclaimable(),
+                })
+                .collect(),
+            total_reclaimable: reports.iter().map(CheckReport::reclaimable).sum(),
+            duplicate_checkouts: checkouts_by_repo(checkouts)
+                .into_iter()
+                .filter(|(_repo, revs)| revs.len() > 1)
+                .map(|(repo, revs)| DuplicateCheckoutsJson::new(settings, repo, &revs))
+                .collect(),
+            removed: None,
+            permissions_fixed: None,
+        }
+    }
+}
+
+/// a source directory without a .crate archive, serialized for "doctor --orphans --format json"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct OrphanJson {
+    name: String,
+    version: String,
+    source_path: PathBuf,
+    size: u64,
+}
+
+impl OrphanJson {
+    fn new(settings: &Settings, finding: &Finding) -> Self {
+        // parse_version() strips the extension, so turn "foo-0.1.0" into "foo-0.1.0.crate"
+        let file_name = file_name(&finding.path);
+        let (name, version) =
+            parse_version(&finding.path.with_file_name(format!("{file_name}.crate")))
+                .unwrap_or((file_name, String::new()));
+        Self {
+            name,
+            version,
+            source_path: relative_paths::shown(settings, &finding.path).to_path_buf(),
+            size: finding.size,
+        }
+    }
+}
+
+/// the orphaned and fully orphaned sources, serialized for "doctor --orphans --format json"
+#[derive(Debug, Serialize)]
+struct OrphanReportJson {
+    orphans: Vec<OrphanJson>,
+    total_reclaimable: u64,
+}
+
+/// JSON Schema (draft 7) of the orphaned sources
+#[cfg(test)]
+pub(crate) const ORPHANS_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache doctor --orphans --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "orphans": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "version": { "type": "string" },
+          "source_path": { "type": "string" },
+          "size": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["name", "version", "source_path", "size"],
+        "additionalProperties": false
+      }
+    },
+    "total_reclaimable": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "orphans", "total_reclaimable"],
+  "additionalProperties": false
+}"#;
+
+impl OrphanReportJson {
+    fn new(settings: &Settings, reports: &[CheckReport]) -> Self {
+        let mut orphans = reports
+            .iter()
+            .filter(|report| matches!(report.check, Check::Orphans | Check::FullyOrphaned))
+            .flat_map(|report| {
+                report
+                    .findings
+                    .iter()
+                    .map(|finding| OrphanJson::new(settings, finding))
+            })
+            .collect::<Vec<_>>();
+        orphans.sort_by(|a, b| a.source_path.cmp(&b.source_path));
+        Self {
+            total_reclaimable: orphans.iter().map(|orphan| orphan.size).sum(),
+            orphans,
+        }
+    }
+}
+
+/// a source directory that is older than its .crate archive, serialized for "doctor --stale --format json"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct StaleJson {
+    name: String,
+    version: String,
+    source_path: PathBuf,
+    /// RFC 3339 time of the last modification of the archive
+    archive_mtime: String,
+    /// RFC 3339 time of the last modification of the source
+    source_mtime: String,
+    size: u64,
+}
+
+impl StaleJson {
+    fn new(settings: &Settings, stale: &StaleSource) -> Self {
+        let (name, version) = parse_version(&stale.archive)
+            .unwrap_or_else(|_| (file_name(&stale.source), String::new()));
+        Self {
+            name,
+            version,
+            source_path: relative_paths::shown(settings, &stale.source).to_path_buf(),
+            archive_mtime: rfc3339(stale.archive_mtime),
+            source_mtime: rfc3339(stale.source_mtime),
+            size: size_of_path(settings, &stale.source),
+        }
+    }
+}
+
+/// the stale sources, serialized for "doctor --stale --format json"
+#[derive(Debug, Serialize)]
+struct StaleReportJson {
+    stale: Vec<StaleJson>,
+    total_reclaimable: u64,
+}
+
+/// JSON Schema (draft 7) of the stale sources
+#[cfg(test)]
+pub(crate) const STALE_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache doctor --stale --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "stale": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "version": { "type": "string" },
+          "source_path": { "type": "string" },
+          "archive_mtime": { "type": "string", "format": "date-time" },
+          "source_mtime": { "type": "string", "format": "date-time" },
+          "size": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["name", "version", "source_path", "archive_mtime", "source_mtime", "size"],
+        "additionalProperties": false
+      }
+    },
+    "total_reclaimable": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "stale", "total_reclaimable"],
+  "additionalProperties": false
+}"#;
+
+impl StaleReportJson {
+    fn new(settings: &Settings, stale_sources: &[StaleSource]) -> Self {
+        let mut stale = stale_sources
+            .par_iter()
+            .map(|stale| StaleJson::new(settings, stale))
+            .collect::<Vec<_>>();
+        stale.sort_by(|a, b| a.source_path.cmp(&b.source_path));
+        Self {
+            total_reclaimable: stale.iter().map(|source| source.size).sum(),
+            stale,
+        }
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// extracted sources which have no archive that they could be re-extracted from
+/// fully orphaned sources are not included, see `fully_orphaned_sources()`
+pub(crate) fn orphaned_sources(
+    settings: &Settings,
+    archives: &[PathBuf],
+    indices: &[PathBuf],
+    sources: &[PathBuf],
+) -> CheckReport {
+    let fully_orphaned = sources_without_archive_or_index(archives, indices, sources)
+        .into_iter()
+        .collect::<HashSet<PathBuf>>();
+    let orphans = sources_without_archive(archives, sources)
+        .into_iter()
+        .filter(|source| !fully_orphaned.contains(source))
+        .collect();
+
+    CheckReport::new(settings, Check::Orphans, orphans)
+}
+
+/// extracted sources which have neither an archive nor a registry index, probably from a removed registry
+pub(crate) fn fully_orphaned_sources(
+    settings: &Settings,
+    archives: &[PathBuf],
+    indices: &[PathBuf],
+    sources: &[PathBuf],
+) -> CheckReport {
+    CheckReport::new(
+        settings,
+        Check::FullyOrphaned,
+        sources_without_archive_or_index(archives, indices, sources),
+    )
+}
+
+/// an extracted source that is older than the .crate archive it was extracted from
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StaleSource {
+    archive: PathBuf,
+    source: PathBuf,
+    archive_mtime: SystemTime,
+    source_mtime: SystemTime,
+}
+
+/// pair the archives with their sources and keep the sources that are older than their archive
+fn find_stale_sources(archives: &[PathBuf], sources: &[PathBuf]) -> Vec<StaleSource> {
+    match_archives_to_sources(archives, sources)
+        .into_iter()
+        .filter_map(|pair| {
+            let source = pair.source?;
+            let archive_mtime = modification_time(&pair.archive)?;
+            let source_mtime = modification_time(&source)?;
+            (source_mtime < archive_mtime).then_some(StaleSource {
+                archive: pair.archive,
+                source,
+                archive_mtime,
+                source_mtime,
+            })
+        })
+        .collect()
+}
+
+/// extracted sources that are older than their archive, the archive was probably replaced
+pub(crate) fn stale_sources(
+    settings: &Settings,
+    archives: &[PathBuf],
+    sources: &[PathBuf],
+) -> CheckReport {
+    let stale = find_stale_sources(archives, sources)
+        .into_iter()
+        .map(|stale| stale.source)
+        .collect();
+
+    CheckReport::new(settings, Check::StaleSources, stale)
+}
+
+/// unpack a .crate archive without writing anything to disk and count its entries
+/// returns None if the archive could not be unpacked completely
+fn number_of_entries(archive: &Path) -> Option<usize> {
+    let file = File::open(archive).ok()?;
+    let mut tarball = tar::Archive::new(GzDecoder::new(file));
+    let mut number = 0;
+    for entry in tarball.entries().ok()? {
+        let mut content = entry.ok()?;
+        let _ = io::copy(&mut content, &mut io::sink()).ok()?;
+        number += 1;
+    }
+    Some(number)
+}
+
+/// extensions of the temporary files an interrupted download leaves behind
+const PARTIAL_DOWNLOAD_EXTENSIONS: &[&str] = &["part", "tmp"];
+
+/// check if a file in the archive directory is an empty archive or the temporary file of a download
+fn is_partial_download(file: &Path) -> bool {
+    let temporary = file.extension().is_some_and(|extension| {
+        PARTIAL_DOWNLOAD_EXTENSIONS
+            .iter()
+            .any(|temporary| extension == *temporary)
+    });
+    temporary || fs::metadata(file).is_ok_and(|metadata| metadata.is_file() && metadata.len() == 0)
+}
+
+/// .crate archives that are empty and temporary files of interrupted downloads
+pub(crate) fn partial_downloads(settings: &Settings, archives: &[PathBuf]) -> CheckReport {
+    let partial = archives
+        .iter()
+        .filter(|archive| is_partial_download(archive))
+        .cloned()
+        .collect();
+
+    CheckReport::new(settings, Check::PartialDownloads, partial)
+}
+
+/// check if a .crate archive can be decompressed and unpacked completely
+fn is_corrupt(archive: &Path) -> bool {
+    // every crate contains at least a Cargo.toml
+    number_of_entries(archive).unwrap_or(0) == 0
+}
+
+/// .crate archives that can not be unpacked, partial downloads are reported by `partial_downloads()`
+pub(crate) fn corrupt_archives(settings: &Settings, archives: &[PathBuf]) -> CheckReport {
+    let corrupt = archives
+        .par_iter()
+        .filter(|archive| !is_partial_download(archive) && is_corrupt(archive))
+        .cloned()
+        .collect();
+
+    CheckReport::new(settings, Check::CorruptArchives, corrupt)
+}
+
+/// repos that have more than one checkout, all checkouts but the most recent one are reported
+pub(crate) fn duplicate_checkouts(settings: &Settings, checkouts: &[PathBuf]) -> CheckReport {
+    let duplicates = checkouts_by_repo(checkouts)
+        .into_iter()
+        .filter(|(_repo, revs)| revs.len() > 1)
+        .flat_map(|(_repo, revs)| revs.into_iter().skip(1).cloned())
+        .collect();
+
+    CheckReport::new(settings, Check::DuplicateCheckouts, duplicates)
+}
+
+/// registry indices that take space although no crate of their registry is cached
+pub(crate) fn unused_indices(
+    settings: &Settings,
+    archives: &[PathBuf],
+    indices: &[PathBuf],
+) -> CheckReport {
+    CheckReport::new(
+        settings,
+        Check::UnusedIndices,
+        indices_without_archives(archives, indices),
+    )
+}
+
+/// archive caches and indices whose counterpart of the same registry hash is missing,
+/// the two may be out of sync, for example after restoring only one of them from a backup
+pub(crate) fn unpaired_registries(
+    settings: &Settings,
+    pkg_caches: &[PathBuf],
+    indices: &[PathBuf],
+) -> CheckReport {
+    CheckReport::new(
+        settings,
+        Check::UnpairedRegistries,
+        unpaired_registry_dirs(pkg_caches, indices),
+    )
+}
+
+/// format the reports of all the checks
+fn format_reports(settings: &Settings, cargo_home: &Path, reports: &[CheckReport]) -> String {
+    let mut lines = vec![format!("Cargo cache doctor '{}':", cargo_home.display())];
+
+    for report in reports {
+        lines.push(String::new());
+        if report.findings.is_empty() {
+            lines.push(format!("{}: none found", report.check.description()));
+            continue;
+        }
+
+        lines.push(format!(
+            "{}: {} found, {} reclaimable",
+            report.check.description(),
+            report.findings.len(),
+            report.reclaimable().file_size(size_opts(settings)).unwrap()
+        ));
+        lines.extend(report.findings.iter().map(|finding| {
+            format!(
+                "  {}: {}",
+                relative_paths::shown(settings, &finding.path).display(),
+                finding.size.file_size(size_opts(settings)).unwrap()
+            )
+        }));
+        lines.push(format!("  Suggestion: {}", report.check.suggestion()));
+    }
+
+    let total_reclaimable: u64 = reports.iter().map(CheckReport::reclaimable).sum();
+    lines.push(String::new());
+    lines.push(format!(
+        "Total reclaimable: {}",
+        total_reclaimable.file_size(size_opts(settings)).unwrap()
+    ));
+
+    lines.join("\n")
+}
+
+/// remove the partial downloads that were found, returns the number of removed items and their size
+fn remove_partial_downloads(
+    settings: &Settings,
+    report: &CheckReport,
+    output_format: OutputFormat,
+    dry_run: bool,
+    size_changed: &mut bool,
+) -> (usize, u64) {
+    let (findings, remaining) = limit_removals(settings, &report.findings);
+    // keep the json output parsable
+    let dry_run_msg = match output_format {
+        OutputFormat::Text => DryRunMessage::Default,
+        OutputFormat::Json => DryRunMessage::None,
+    };
+    // failed removals are reported and do not count as removed
+    let removed = findings
+        .iter()
+        .filter(|finding| {
+            remove_file(
+                settings,
+                &finding.path,
+                dry_run,
+                size_changed,
+                None,
+                &dry_run_msg,
+                Some(finding.size),
+            )
+        })
+        .collect::<Vec<_>>();
+    report_failed_removals(settings);
+    if output_format == OutputFormat::Text {
+        report_removal_limit(settings, remaining);
+    }
+
+    let size = removed.iter().map(|finding| finding.size).sum();
+    (removed.len(), size)
+}
+
+/// the mode cargo needs to manage an extracted source or checkout entry, None if its mode is fine already
+/// directories need rwx and files rw for the owner, executable files stay executable
+#[cfg(unix)]
+fn repaired_mode(mode: u32, is_dir: bool) -> Option<u32> {
+    if is_dir {
+        (mode & 0o700 != 0o700).then_some(0o755)
+    } else if mode & 0o600 != 0o600 {
+        Some(if mode & 0o111 == 0 { 0o644 } else { 0o755 })
+    } else {
+        None
+    }
+}
+
+/// give `path` and everything below it the permissions cargo needs, returns the number of adjusted entries
+/// symlinks are not followed, entries that can not be read or changed are skipped with a warning
+#[cfg(unix)]
+fn fix_permissions(settings: &Settings, path: &Path, dry_run: bool) -> usize {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            warnings::warn(settings, format!("skipped '{}': {}", path.display(), error));
+            return 0;
+        }
+    };
+    if metadata.file_type().is_symlink() {
+        return 0;
+    }
+
+    let mut adjusted = 0;
+    if let Some(mode) = repaired_mode(metadata.permissions().mode(), metadata.is_dir()) {
+        if dry_run {
+            adjusted += 1;
+        } else {
+            match fs::set_permissions(path, fs::Permissions::from_mode(mode)) {
+                Ok(()) => adjusted += 1,
+                Err(error) => warnings::warn(
+                    settings,
+                    format!(
+                        "failed to fix the permissions of '{}': {}",
+                        path.display(),
+                        error
+                    ),
+                ),
+            }
+        }
+    }
+
+    if metadata.is_dir() {
+        // in a dry run, directories without read permission can not be listed
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                adjusted += entries
+                    .filter_map(Result::ok)
+                    .map(|entry| fix_permissions(settings, &entry.path(), dry_run))
+                    .sum::<usize>();
+            }
+            Err(error) => {
+                warnings::warn(settings, format!("skipped '{}': {}", path.display(), error));
+            }
+        }
+    }
+    adjusted
+}
+
+/// permissions can only be fixed on unix
+#[cfg(not(unix))]
+fn fix_permissions(_settings: &Settings, _path: &Path, _dry_run: bool) -> usize {
+    0
+}
+
+/// fix the permissions of the extracted sources and the git checkouts, returns the adjusted entries of both
+fn fix_cache_permissions(
+    settings: &Settings,
+    dry_run: bool,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> (usize, usize) {
+    let sources = registry_sources_caches
+        .items()
+        .iter()
+        .map(|source| fix_permissions(settings, source, dry_run))
+        .sum();
+    let checkouts = checkouts_cache
+        .items()
+        .iter()
+        .map(|checkout| fix_permissions(settings, checkout, dry_run))
+        .sum();
+    (sources, checkouts)
+}
+
+/// run all the checks and print a report, with `remove` the partial downloads are removed
+/// with `orphans_only` only the sources without a .crate archive are reported,
+/// with `stale_only` only the sources that are older than their .crate archive
+/// with `fix_perms` the permissions of the extracted sources and git checkouts are fixed
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
+pub(crate) fn doctor(
+    settings: &Settings,
+    cargo_home: &Path,
+    output_format: OutputFormat,
+    orphans_only: bool,
+    stale_only: bool,
+    remove: bool,
+    fix_perms: bool,
+    dry_run: bool,
+    size_changed: &mut bool,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) {
+    let archives = registry_pkg_caches.files();
+    let indices = registry_index_caches
+        .caches()
+        .iter()
+        .map(|index| index.path().clone())
+        .collect::<Vec<PathBuf>>();
+    let pkg_caches = registry_pkg_caches
+        .caches()
+        .iter()
+        .map(|pkg_cache| pkg_cache.path().clone())
+        .collect::<Vec<PathBuf>>();
+    let sources = registry_sources_caches.items().to_vec();
+    let checkouts = checkouts_cache.items().to_vec();
+
+    if orphans_only {
+        // clap makes sure "--remove" is not passed as well
+        let orphan_reports = vec![
+            orphaned_sources(settings, &archives, &indices, &sources),
+            fully_orphaned_sources(settings, &archives, &indices, &sources),
+        ];
+        match output_format {
+            OutputFormat::Text => {
+                println!("{}", format_reports(settings, cargo_home, &orphan_reports));
+            }
+            OutputFormat::Json => {
+                println!(
+                    "{}",
+                    to_json(settings, &OrphanReportJson::new(settings, &orphan_reports))
+                );
+            }
+        }
+        return;
+    }
+
+    if stale_only {
+        match output_format {
+            OutputFormat::Text => println!(
+                "{}",
+                format_reports(
+                    settings,
+                    cargo_home,
+                    &[stale_sources(settings, &archives, &sources)]
+                )
+            ),
+            OutputFormat::Json => {
+                let stale = find_stale_sources(&archives, &sources);
+                println!(
+                    "{}",
+                    to_json(settings, &StaleReportJson::new(settings, &stale))
+                );
+            }
+        }
+        return;
+    }
+
+    let reports = vec![
+        orphaned_sources(settings, &archives, &indices, &sources),
+        fully_orphaned_sources(settings, &archives, &indices, &sources),
+        stale_sources(settings, &archives, &sources),
+        partial_downloads(settings, &archives),
+        corrupt_archives(settings, &archives),
+        duplicate_checkouts(settings, &checkouts),
+        unused_indices(settings, &archives, &indices),
+        unpaired_registries(settings, &pkg_caches, &indices),
+    ];
+
+    // with json output the report is printed last so it can contain the results of the actions
+    if output_format == OutputFormat::Text {
+        println!("{}", format_reports(settings, cargo_home, &reports));
+    }
+
+    let mut removed_partial = None;
+    if remove {
+        let partial = reports
+            .iter()
+            .find(|report| report.check == Check::PartialDownloads)
+            .expect("the partial downloads are always checked");
+        let (removed, size) =
+            remove_partial_downloads(settings, partial, output_format, dry_run, size_changed);
+        removed_partial = Some(removed);
+        if output_format == OutputFormat::Text {
+            println!(
+                "\n{} {} partial {} totalling {}",
+                if dry_run { "Would remove" } else { "Removed" },
+                removed,
+                if removed == 1 {
+                    "download"
+                } else {
+                    "downloads"
+                },
+                size.file_size(size_opts(settings)).unwrap()
+            );
+        }
+    }
+
+    let mut permissions_fixed = None;
+    if fix_perms {
+        let (fixed_sources, fixed_checkouts) =
+            fix_cache_permissions(settings, dry_run, checkouts_cache, registry_sources_caches);
+        permissions_fixed = Some(fixed_sources + fixed_checkouts);
+        if output_format == OutputFormat::Text {
+            println!(
+                "\n{} the permissions of {} {} of the extracted sources and {} of the git checkouts",
+                if dry_run { "Would fix" } else { "Fixed" },
+                fixed_sources,
+                if fixed_sources == 1 {
+                    "entry"
+                } else {
+                    "entries"
+                },
+                fixed_checkouts
+            );
+        }
+    }
+
+    if output_format == OutputFormat::Json {
+        let report = DoctorReportJson {
+            removed: removed_partial,
+            permissions_fixed,
+            ..DoctorReportJson::new(settings, cargo_home, &reports, &checkouts)
+        };
+        println!("{}", to_json(settings, &report));
+    }
+}
+
+#[cfg(test)]
+mod doctor_tests {
+    use super::*;
+    use crate::output::{test_time, to_json_at};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn garbage_archive_is_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let empty = dir.path().join("empty-0.1.0.crate");
+        let _ = File::create(&empty).unwrap();
+        let garbage = dir.path().join("garbage-0.1.0.crate");
+        fs::write(&garbage, b"this is not a gzip file").unwrap();
+
+        // the empty archive is a partial download
+        let report = corrupt_archives(&Settings::default(), &[empty, garbage.clone()]);
+        assert_eq!(
+            report.findings,
+            vec![Finding {
+                path: garbage,
+                size: 23
+            }]
+        );
+    }
+
+    /// a registry cache dir with an empty archive, temporary files of a download and a valid archive
+    fn partial_download_fixture(dir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let empty = dir.join("empty-0.1.0.crate");
+        let _ = File::create(&empty).unwrap();
+        let part = dir.join("foo-0.2.0.crate.part");
+        fs::write(&part, b"\x1f\x8b").unwrap();
+        let tmp = dir.join(".tmpAbC123.tmp");
+        let _ = File::create(&tmp).unwrap();
+        let valid = dir.join("bar-0.1.0.crate");
+        fs::write(&valid, b"not checked here").unwrap();
+        (
+            vec![empty.clone(), part.clone(), tmp.clone(), valid],
+            vec![empty, part, tmp],
+        )
+    }
+
+    #[test]
+    fn partial_downloads_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let (archives, partial) = partial_download_fixture(dir.path());
+
+        let report = partial_downloads(&Settings::default(), &archives);
+        let mut found = report
+            .findings
+            .iter()
+            .map(|finding| finding.path.clone())
+            .collect::<Vec<_>>();
+        found.sort();
+        let mut expected = partial;
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(report.reclaimable(), 2);
+    }
+
+    #[test]
+    fn partial_downloads_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (archives, partial) = partial_download_fixture(dir.path());
+        let report = partial_downloads(&Settings::default(), &archives);
+
+        let mut size_changed = false;
+        assert_eq!(
+            remove_partial_downloads(
+                &Settings::default(),
+                &report,
+                OutputFormat::Json,
+                true,
+                &mut size_changed
+            ),
+            (3, 2)
+        );
+        assert!(partial.iter().all(|file| file.exists()));
+
+        assert_eq!(
+            remove_partial_downloads(
+                &Settings::default(),
+                &report,
+                OutputFormat::Json,
+                false,
+                &mut size_changed
+            ),
+            (3, 2)
+        );
+        assert!(size_changed);
+        assert!(partial.iter().all(|file| !file.exists()));
+        assert!(dir.path().join("bar-0.1.0.crate").exists());
+    }
+
+    #[test]
+    fn duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkouts = vec![
+            dir.path().join("foo-0123456789abcdef").join("aaaaaaa"),
+            dir.path().join("bar-0123456789abcdef").join("bbbbbbb"),
+            dir.path().join("foo-0123456789abcdef").join("ccccccc"),
+        ];
+        for checkout in &checkouts {
+            fs::create_dir_all(checkout).unwrap();
+        }
+
+        let report = duplicate_checkouts(&Settings::default(), &checkouts);
+        // only one of the foo checkouts is a duplicate
+        assert_eq!(report.findings.len(), 1);
+        assert!(report.findings[0]
+            .path
+            .starts_with(dir.path().join("foo-0123456789abcdef")));
+    }
+
+    #[test]
+    fn duplicates_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("foo-0123456789abcdef");
+        let old = repo.join("aaaaaaa");
+        let new = repo.join("ccccccc");
+        let single = dir.path().join("bar-0123456789abcdef").join("bbbbbbb");
+        for checkout in &[&old, &new, &single] {
+            fs::create_dir_all(checkout).unwrap();
+        }
+        fs::write(old.join("lib.rs"), b"12345").unwrap();
+        // make sure the old checkout is older
+        let an_hour_ago = SystemTime::now() - std::time::Duration::from_hours(1);
+        File::open(&old).unwrap().set_modified(an_hour_ago).unwrap();
+
+        let checkouts = vec![old.clone(), single, new];
+        let report = DoctorReportJson::new(&Settings::default(), dir.path(), &[], &checkouts);
+        assert_eq!(report.duplicate_checkouts.len(), 1);
+        let duplicates = &report.duplicate_checkouts[0];
+        assert_eq!(duplicates.repo, "foo-0123456789abcdef");
+        let revs = duplicates
+            .checkouts
+            .iter()
+            .map(|checkout| checkout.rev.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(revs, vec!["ccccccc", "aaaaaaa"]);
+        assert_eq!(
+            duplicates.reclaimable_if_pruned,
+            size_of_path(&Settings::default(), &old)
+        );
+        assert!(duplicates.checkouts.iter().all(|rev| rev.mtime.is_some()));
+    }
+
+    #[test]
+    fn stale_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = "github.com-1ecc6299db9ec823";
+        let archive = |name: &str| {
+            let archive = dir
+                .path()
+                .join("cache")
+                .join(registry)
+                .join(format!("{name}.crate"));
+            fs::create_dir_all(archive.parent().unwrap()).unwrap();
+            fs::write(&archive, b"1").unwrap();
+            archive
+        };
+        let source = |name: &str| {
+            let source = dir.path().join("src").join(registry).join(name);
+            fs::create_dir_all(&source).unwrap();
+            fs::write(source.join("lib.rs"), b"12345").unwrap();
+            source
+        };
+        let set_mtime = |path: &Path, secs: u64| {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            File::open(path).unwrap().set_modified(time).unwrap();
+        };
+        // the archive of "heim-runtime" was replaced after it was extracted, "foo" is up to date
+        let stale_archive = archive("heim-runtime-0.1.0-beta.1");
+        let stale_source = source("heim-runtime-0.1.0-beta.1");
+        let fresh_archive = archive("foo-0.1.0");
+        let fresh_source = source("foo-0.1.0");
+        set_mtime(&stale_archive, 1_620_000_000);
+        set_mtime(&stale_source, 1_619_900_000);
+        set_mtime(&fresh_archive, 1_619_900_000);
+
+        let stale = find_stale_sources(
+            &[fresh_archive, stale_archive],
+            &[fresh_source, stale_source.clone()],
+        );
+        let report = StaleReportJson::new(&Settings::default(), &stale);
+        assert_eq!(
+            report.stale,
+            vec![StaleJson {
+                name: String::from("heim-runtime"),
+                version: String::from("0.1.0-beta.1"),
+                source_path: stale_source.clone(),
+                archive_mtime: String::from("2021-05-03T00:00:00Z"),
+                source_mtime: String::from("2021-05-01T20:13:20Z"),
+                size: size_of_path(&Settings::default(), &stale_source),
+            }]
+        );
+        assert_eq!(
+            report.total_reclaimable,
+            size_of_path(&Settings::default(), &stale_source)
+        );
+        let output = to_json_at(&report, test_time());
+        assert_eq!(
+            crate::output::schema_violations(STALE_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn indices_without_archives_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir
+            .path()
+            .join("cache")
+            .join("github.com-1ecc6299db9ec823")
+            .join("foo-0.1.0.crate");
+        fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        fs::write(&archive, b"1").unwrap();
+        let used = dir.path().join("index").join("github.com-1ecc6299db9ec823");
+        let unused = dir.path().join("index").join("other-0123456789abcdef");
+        for index in &[&used, &unused] {
+            fs::create_dir_all(index.join(".cache")).unwrap();
+        }
+        fs::write(unused.join(".cache").join("bar"), b"12345").unwrap();
+
+        let report = unused_indices(&Settings::default(), &[archive], &[used, unused.clone()]);
+        // directory entries are counted as well, so the size is more than the 5 bytes of the file
+        assert_eq!(
+            report.findings,
+            vec![Finding::new(&Settings::default(), unused)]
+        );
+        assert!(report.reclaimable() >= 5);
+    }
+
+    #[test]
+    fn unpaired_registries_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let paired = "github.com-1ecc6299db9ec823";
+        let pkg_caches = vec![
+            dir.path().join("cache").join(paired),
+            dir.path().join("cache").join("mirror-0123456789abcdef"),
+        ];
+        let indices = vec![
+            dir.path().join("index").join(paired),
+            dir.path().join("index").join("other-fedcba9876543210"),
+        ];
+        for registry_dir in pkg_caches.iter().chain(&indices) {
+            fs::create_dir_all(registry_dir).unwrap();
+        }
+
+        let report = unpaired_registries(&Settings::default(), &pkg_caches, &indices);
+        assert_eq!(report.check, Check::UnpairedRegistries);
+        assert_eq!(
+            report
+                .findings
+                .iter()
+                .map(|finding| finding.path.clone())
+                .collect::<Vec<_>>(),
+            vec![pkg_caches[1].clone(), indices[1].clone()]
+        );
+    }
+
+    #[test]
+    fn format_no_findings() {
+        let reports = vec![
+            CheckReport::new(&Settings::default(), Check::Orphans, Vec::new()),
+            CheckReport::new(&Settings::default(), Check::DuplicateCheckouts, Vec::new()),
+        ];
+        let output = format_reports(
+            &Settings::default(),
+            Path::new("/home/user/.cargo"),
+            &reports,
+        );
+
+        let desired = "Cargo cache doctor '/home/user/.cargo':
+
+Orphaned sources (no .crate archive): none found
+
+Duplicate git checkouts (all but the newest): none found
+
+Total reclaimable: 0 B";
+        assert_eq!(output, desired);
+    }
+
+    #[test]
+    fn format_findings() {
+        let reports = vec![CheckReport {
+            check: Check::CorruptArchives,
+            findings: vec![Finding {
+                path: PathBuf::from("/home/user/.cargo/registry/cache/reg/foo-0.1.0.crate"),
+                size: 1500,
+            }],
+        }];
+        let output = format_reports(
+            &Settings::default(),
+            Path::new("/home/user/.cargo"),
+            &reports,
+        );
+
+        let desired = "Cargo cache doctor '/home/user/.cargo':
+
+Corrupt .crate archives: 1 found, 1.50 KB reclaimable
+  /home/user/.cargo/registry/cache/reg/foo-0.1.0.crate: 1.50 KB
+  Suggestion: remove the archives listed above, cargo will download them again when needed
+
+Total reclaimable: 1.50 KB";
+        assert_eq!(output, desired);
+    }
+
+    #[test]
+    fn format_json() {
+        let reports = vec![
+            CheckReport::new(&Settings::default(), Check::Orphans, Vec::new()),
+            CheckReport {
+                check: Check::CorruptArchives,
+                findings: vec![Finding {
+                    path: PathBuf::from("/home/user/.cargo/registry/cache/reg/foo-0.1.0.crate"),
+                    size: 1500,
+                }],
+            },
+        ];
+        let output = to_json_at(
+            &DoctorReportJson::new(
+                &Settings::default(),
+                Path::new("/home/user/.cargo"),
+                &reports,
+                &[],
+            ),
+            test_time(),
+        );
+
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "cargo_home": "/home/user/.cargo",
+  "checks": [
+    {
+      "check": "orphans",
+      "findings": [],
+      "reclaimable": 0
+    },
+    {
+      "check": "corrupt_archives",
+      "findings": [
+        {
+          "path": "/home/user/.cargo/registry/cache/reg/foo-0.1.0.crate",
+          "size": 1500
+        }
+      ],
+      "reclaimable": 1500
+    }
+  ],
+  "total_reclaimable": 1500,
+  "duplicate_checkouts": []
+}"#;
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(DOCTOR_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn format_json_with_actions() {
+        let report = DoctorReportJson {
+            removed: Some(2),
+            permissions_fixed: Some(0),
+            ..DoctorReportJson::new(
+                &Settings::default(),
+                Path::new("/home/user/.cargo"),
+                &[],
+                &[],
+            )
+        };
+        let output = to_json_at(&report, test_time());
+
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "cargo_home": "/home/user/.cargo",
+  "checks": [],
+  "total_reclaimable": 0,
+  "duplicate_checkouts": [],
+  "removed": 2,
+  "permissions_fixed": 0
+}"#;
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(DOCTOR_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn repaired_modes() {
+        assert_eq!(repaired_mode(0o755, true), None);
+        assert_eq!(repaired_mode(0o700, true), None);
+        assert_eq!(repaired_mode(0o555, true), Some(0o755));
+        assert_eq!(repaired_mode(0o644, false), None);
+        assert_eq!(repaired_mode(0o444, false), Some(0o644));
+        // executables stay executable
+        assert_eq!(repaired_mode(0o555, false), Some(0o755));
+        assert_eq!(repaired_mode(0o775, false), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions_are_fixed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo-0.1.0");
+        fs::create_dir_all(source.join("src")).unwrap();
+        fs::write(source.join("Cargo.toml"), b"").unwrap();
+        fs::write(source.join("src").join("lib.rs"), b"").unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let set_mode = |path: &Path, permissions: u32| {
+            fs::set_permissions(path, fs::Permissions::from_mode(permissions)).unwrap();
+        };
+        set_mode(&source.join("Cargo.toml"), 0o444);
+        set_mode(&source.join("src").join("lib.rs"), 0o444);
+        set_mode(&source.join("src"), 0o555);
+
+        // the dry run only counts
+        assert_eq!(fix_permissions(&Settings::default(), &source, true), 3);
+        assert_eq!(mode(&source.join("src")), 0o555);
+
+        assert_eq!(fix_permissions(&Settings::default(), &source, false), 3);
+        assert_eq!(mode(&source.join("src")), 0o755);
+        assert_eq!(mode(&source.join("Cargo.toml")), 0o644);
+        assert_eq!(mode(&source.join("src").join("lib.rs")), 0o644);
+        assert_eq!(fix_permissions(&Settings::default(), &source, false), 0);
+    }
+
+    #[test]
+    fn orphans_json() {
+        let source = |name: &str, size: u64| Finding {
+            path: PathBuf::from("/home/user/.cargo/registry/src/reg").join(name),
+            size,
+        };
+        let reports = vec![
+            CheckReport {
+                check: Check::Orphans,
+                findings: vec![source("heim-runtime-0.1.0-beta.1", 300)],
+            },
+            CheckReport {
+                check: Check::FullyOrphaned,
+                findings: vec![source("foo-0.1.0", 1200)],
+            },
+            CheckReport {
+                check: Check::CorruptArchives,
+                findings: vec![source("bar-0.1.0", 1500)],
+            },
+        ];
+        let output = to_json_at(
+            &OrphanReportJson::new(&Settings::default(), &reports),
+            test_time(),
+        );
+
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "orphans": [
+    {
+      "name": "foo",
+      "version": "0.1.0",
+      "source_path": "/home/user/.cargo/registry/src/reg/foo-0.1.0",
+      "size": 1200
+    },
+    {
+      "name": "heim-runtime",
+      "version": "0.1.0-beta.1",
+      "source_path": "/home/user/.cargo/registry/src/reg/heim-runtime-0.1.0-beta.1",
+      "size": 300
+    }
+  ],
+  "total_reclaimable": 1500
+}"#;
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(ORPHANS_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "cargo cache export-files <file>" which writes the path and size of every
+/// file in the cache as tab separated values, for analysis with other tools.
+/// With "--format jsonl" every file is a {"path", "size"} json object on its own line, with
+/// "--format json" they are written as a single json document.
+/// Except for "--format json", the lines are written cache by cache while going over their files,
+/// no list of all files is built.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cache::caches::{Cache, RegistrySubCache, RegistrySuperCache};
+use crate::cache::*;
+use crate::library::{size_opts, Error};
+use crate::output::to_json;
+use crate::relative_paths;
+use crate::settings::Settings;
+use crate::size_source;
+
+use humansize::FileSize;
+use serde::Serialize;
+
+/// the first line of the tab separated export
+const HEADER: &str = "path\tsize";
+
+/// the format of the export ("--format")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExportFormat {
+    /// tab separated values with a header line, the default
+    Tsv,
+    /// a single json document
+    Json,
+    /// one json object per line
+    Jsonl,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "tsv" => Ok(Self::Tsv),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            other => Err(other.to_string()),
+        }
+    }
+}
+
+/// a file of the cache, serialized for "--format json" and "--format jsonl"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct FileRecord {
+    path: PathBuf,
+    size: u64,
+}
+
+/// all files of the cache, serialized for "--format json"
+#[derive(Debug, Serialize)]
+struct ExportJson {
+    files: Vec<FileRecord>,
+}
+
+/// writes the files in the format of the export and counts them
+struct Exporter<'a, W: Write> {
+    settings: &'a Settings,
+    out: W,
+    format: ExportFormat,
+    /// the files of a json export, the document is written by `finish()`
+    records: Vec<FileRecord>,
+    number_of_files: u64,
+    total_size: u64,
+}
+
+impl<'a, W: Write> Exporter<'a, W> {
+    fn new(settings: &'a Settings, mut out: W, format: ExportFormat) -> io::Result<Self> {
+        if format == ExportFormat::Tsv {
+            writeln!(out, "{HEADER}")?;
+        }
+        Ok(Self {
+            settings,
+            out,
+            format,
+            records: Vec::new(),
+            number_of_files: 0,
+            total_size: 0,
+        })
+    }
+
+    /// write a record for every file
+    fn write_files(&mut self, files: &[PathBuf]) -> io::Result<()> {
+        // the git caches list their directories as well
+        for file in files.iter().filter(|file| file.is_file()) {
+            let size = size_source::file_size(self.settings, file);
+            let path = relative_paths::shown(self.settings, file);
+            match self.format {
+                ExportFormat::Tsv => {
+                    writeln!(self.out, "{}\t{}", path.display(), size)?;
+                }
+                ExportFormat::Jsonl => {
+                    let record = FileRecord {
+                        path: path.to_path_buf(),
+                        size,
+                    };
+                    serde_json::to_writer(&mut self.out, &record)?;
+                    writeln!(self.out)?;
+                }
+                ExportFormat::Json => self.records.push(FileRecord {
+                    path: path.to_path_buf(),
+                    size,
+                }),
+            }
+            self.number_of_files += 1;
+            self.total_size += size;
+        }
+        Ok(())
+    }
+
+    /// finish the export, returns the number of files and their total size
+    fn finish(mut self) -> io::Result<(u64, u64)> {
+        if self.format == ExportFormat::Json {
+            let records = std::mem::take(&mut self.records);
+            writeln!(
+                self.out,
+                "{}",
+                to_json(self.settings, &ExportJson { files: records })
+            )?;
+        }
+        self.out.flush()?;
+        Ok((self.number_of_files, self.total_size))
+    }
+}
+
+/// write the files of all caches, returns the number of files and their total size
+fn write_all_files(
+    exporter: &mut Exporter<'_, impl Write>,
+    bin_cache: &mut bin::BinaryCache,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    bare_repos_cache: &mut git_bare_repos::GitRepoCache,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> io::Result<()> {
+    exporter.write_files(bin_cache.files())?;
+    exporter.write_files(checkouts_cache.files())?;
+    exporter.write_files(bare_repos_cache.files())?;
+    // one registry at a time
+    for cache in registry_index_caches.caches() {
+        exporter.write_files(cache.files())?;
+    }
+    for cache in registry_pkg_caches.caches() {
+        exporter.write_files(cache.files())?;
+    }
+    for cache in registry_sources_caches.caches() {
+        exporter.write_files(cache.files())?;
+    }
+    Ok(())
+}
+
+/// write the path and size of every file in the cache to `target`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn export_files(
+    settings: &Settings,
+    target: &Path,
+    format: ExportFormat,
+    bin_cache: &mut bin::BinaryCache,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    bare_repos_cache: &mut git_bare_repos::GitRepoCache,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<(), Error> {
+    let failed = |error: io::Error| Error::ExportFailed(target.to_path_buf(), error);
+
+    let out = BufWriter::new(File::create(target).map_err(failed)?);
+    let mut exporter = Exporter::new(settings, out, format).map_err(failed)?;
+    write_all_files(
+        &mut exporter,
+        bin_cache,
+        checkouts_cache,
+        bare_repos_cache,
+        registry_pkg_caches,
+        registry_index_caches,
+        registry_sources_caches,
+    )
+    .map_err(failed)?;
+    let (number_of_files, total_size) = exporter.finish().map_err(failed)?;
+
+    println!(
+        "Exported {} files ({}) to '{}'",
+        number_of_files,
+        total_size.file_size(size_opts(settings)).unwrap(),
+        target.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod export_files_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    /// an archive, an empty archive and a source directory, which is skipped
+    fn export_fixture(dir: &Path) -> (PathBuf, PathBuf, Vec<PathBuf>) {
+        let first = dir.join("foo-0.1.0.crate");
+        let second = dir.join("bar-0.1.0.crate");
+        fs::write(&first, b"12345").unwrap();
+        fs::write(&second, b"").unwrap();
+        fs::create_dir(dir.join("foo-0.1.0")).unwrap();
+        let files = vec![first.clone(), dir.join("foo-0.1.0"), second.clone()];
+        (first, second, files)
+    }
+
+    /// export the files in `format`, returns the number of files, their size and the export
+    fn export(files: &[PathBuf], format: ExportFormat) -> (u64, u64, String) {
+        let settings = Settings::default();
+        let mut out = Vec::new();
+        let mut exporter = Exporter::new(&settings, &mut out, format).unwrap();
+        exporter.write_files(files).unwrap();
+        let (number_of_files, total_size) = exporter.finish().unwrap();
+        (number_of_files, total_size, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn files_as_tsv() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second, files) = export_fixture(dir.path());
+
+        let (number_of_files, total_size, output) = export(&files, ExportFormat::Tsv);
+        assert_eq!((number_of_files, total_size), (2, 5));
+        assert_eq!(
+            output,
+            format!(
+                "path\tsize\n{}\t5\n{}\t0\n",
+                first.display(),
+                second.display()
+            )
+        );
+    }
+
+    #[test]
+    fn files_as_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second, files) = export_fixture(dir.path());
+
+        let (number_of_files, total_size, output) = export(&files, ExportFormat::Jsonl);
+        assert_eq!((number_of_files, total_size), (2, 5));
+        assert_eq!(
+            output,
+            format!(
+                "{{\"path\":\"{}\",\"size\":5}}\n{{\"path\":\"{}\",\"size\":0}}\n",
+                first.display(),
+                second.display()
+            )
+        );
+    }
+
+    #[test]
+    fn files_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second, files) = export_fixture(dir.path());
+
+        let (number_of_files, total_size, output) = export(&files, ExportFormat::Json);
+        assert_eq!((number_of_files, total_size), (2, 5));
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            json["files"],
+            serde_json::json!([
+                { "path": first, "size": 5 },
+                { "path": second, "size": 0 },
+            ])
+        );
+    }
+}
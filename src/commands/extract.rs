@@ -0,0 +1,324 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "cargo cache extract <name>@<version> --to <dir>" which unpacks a cached
+/// .crate archive into a directory, without fetching anything from the network.
+/// With "--format json" the result is printed as an object, a failure as an object with an "error" field.
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::cache::caches::RegistrySuperCache;
+use crate::cache::*;
+use crate::library::Error;
+use crate::output::{to_json, OutputFormat};
+use crate::relative_paths;
+use crate::remove::parse_version;
+use crate::settings::Settings;
+
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+/// an extracted crate, serialized for "--format json", the paths are the shown ones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct ExtractJson {
+    name: String,
+    version: String,
+    source_archive: PathBuf,
+    /// the directory the sources were unpacked to
+    extracted_to: PathBuf,
+    /// number of files in the extracted directory
+    files: usize,
+    /// total size of the files in the extracted directory
+    bytes: u64,
+}
+
+/// a crate that could not be extracted, serialized for "--format json"
+#[derive(Debug, Serialize)]
+struct ExtractErrorJson<'a> {
+    /// "<name>@<version>" as it was passed
+    #[serde(rename = "crate")]
+    spec: &'a str,
+    error: String,
+}
+
+/// JSON Schema (draft 7) of the output of "extract", an extracted crate or the error
+#[cfg(test)]
+pub(crate) const EXTRACT_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache extract --format json",
+  "type": "object",
+  "oneOf": [
+    {
+      "properties": {
+        "schema_version": { "const": 1 },
+        "generated_at": { "type": "string", "format": "date-time" },
+        "name": { "type": "string" },
+        "version": { "type": "string" },
+        "source_archive": { "type": "string" },
+        "extracted_to": { "type": "string" },
+        "files": { "type": "integer", "minimum": 0 },
+        "bytes": { "type": "integer", "minimum": 0 },
+        "warnings": {
+          "type": "array",
+          "items": { "type": "string" }
+        }
+      },
+      "required": [
+        "schema_version",
+        "generated_at",
+        "name",
+        "version",
+        "source_archive",
+        "extracted_to",
+        "files",
+        "bytes"
+      ],
+      "additionalProperties": false
+    },
+    {
+      "properties": {
+        "schema_version": { "const": 1 },
+        "generated_at": { "type": "string", "format": "date-time" },
+        "crate": { "type": "string" },
+        "error": { "type": "string" },
+        "warnings": {
+          "type": "array",
+          "items": { "type": "string" }
+        }
+      },
+      "required": ["schema_version", "generated_at", "crate", "error"],
+      "additionalProperties": false
+    }
+  ]
+}"#;
+
+/// split "<name>@<version>" into name and version
+fn parse_crate_spec(spec: &str) -> Result<(&str, &str), Error> {
+    match spec.split_once('@') {
+        Some((name, version)) if !name.is_empty() && !version.is_empty() => Ok((name, version)),
+        _ => Err(Error::InvalidCrateSpec(spec.to_string())),
+    }
+}
+
+/// the first archive of the crate, registries are searched in the order of their directory names
+fn find_archive(archives: &[PathBuf], name: &str, version: &str) -> Option<PathBuf> {
+    archives
+        .iter()
+        .find(|archive| {
+            parse_version(archive).is_ok_and(|(archive_name, archive_version)| {
+                archive_name == name && archive_version == version
+            })
+        })
+        .cloned()
+}
+
+/// unpack the archive into `target_dir`, returns the directory the sources were unpacked to
+fn unpack(archive: &Path, target_dir: &Path) -> Result<PathBuf, Error> {
+    let failed = |error: std::io::Error| Error::ExtractionFailed(archive.to_path_buf(), error);
+
+    fs::create_dir_all(target_dir).map_err(failed)?;
+    let file = File::open(archive).map_err(failed)?;
+    // entries that would end up outside of the target dir are skipped by unpack()
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(target_dir)
+        .map_err(failed)?;
+
+    // "foo-0.1.0.crate" contains the directory "foo-0.1.0"
+    let stem = archive.file_stem().unwrap_or_default();
+    Ok(target_dir.join(stem))
+}
+
+/// the number and the total size of the files below `dir`
+fn files_and_bytes(dir: &Path) -> (usize, u64) {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .fold((0, 0), |(files, bytes), entry| {
+            (files + 1, bytes + entry.metadata().map_or(0, |m| m.len()))
+        })
+}
+
+/// find the archive of the crate and unpack it into `target_dir`
+fn extract_crate(
+    settings: &Settings,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    spec: &str,
+    target_dir: &Path,
+) -> Result<ExtractJson, Error> {
+    let (name, version) = parse_crate_spec(spec)?;
+    let archive = find_archive(&registry_pkg_caches.files_sorted(), name, version)
+        .ok_or_else(|| Error::ArchiveNotCached(spec.to_string()))?;
+
+    let sources = unpack(&archive, target_dir)?;
+    let (files, bytes) = files_and_bytes(&sources);
+    Ok(ExtractJson {
+        name: name.to_string(),
+        version: version.to_string(),
+        source_archive: relative_paths::shown(settings, &archive).to_path_buf(),
+        extracted_to: relative_paths::shown(settings, &sources).to_path_buf(),
+        files,
+        bytes,
+    })
+}
+
+/// extract a cached crate ("<name>@<version>") into `target_dir`
+/// with "--format json", a failure is printed as json as well before the error is returned
+pub(crate) fn extract(
+    settings: &Settings,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    spec: &str,
+    target_dir: &Path,
+    output_format: OutputFormat,
+) -> Result<(), Error> {
+    match (
+        output_format,
+        extract_crate(settings, registry_pkg_caches, spec, target_dir),
+    ) {
+        (OutputFormat::Text, result) => {
+            let extracted = result?;
+            println!(
+                "Extracted {} {} from '{}' to '{}'",
+                extracted.name,
+                extracted.version,
+                extracted.source_archive.display(),
+                extracted.extracted_to.display()
+            );
+        }
+        (OutputFormat::Json, Ok(extracted)) => println!("{}", to_json(settings, &extracted)),
+        (OutputFormat::Json, Err(error)) => {
+            let failed = ExtractErrorJson {
+                spec,
+                error: error.to_string(),
+            };
+            println!("{}", to_json(settings, &failed));
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod extract_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn crate_specs() {
+        assert_eq!(
+            parse_crate_spec("serde@1.0.130").unwrap(),
+            ("serde", "1.0.130")
+        );
+        assert_eq!(
+            parse_crate_spec("heim-runtime@0.1.0-beta.1").unwrap(),
+            ("heim-runtime", "0.1.0-beta.1")
+        );
+        for invalid in &["serde", "serde@", "@1.0.0", ""] {
+            assert!(matches!(
+                parse_crate_spec(invalid),
+                Err(Error::InvalidCrateSpec(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn archives_are_found() {
+        let archives = vec![
+            PathBuf::from("registry/cache/a/foo-bar-0.1.0.crate"),
+            PathBuf::from("registry/cache/a/foo-0.1.0.crate"),
+            PathBuf::from("registry/cache/b/foo-0.1.0.crate"),
+        ];
+        assert_eq!(
+            find_archive(&archives, "foo", "0.1.0"),
+            Some(archives[1].clone())
+        );
+        assert_eq!(find_archive(&archives, "foo", "0.2.0"), None);
+    }
+
+    #[test]
+    fn archive_is_unpacked() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("foo-0.1.0.crate");
+
+        // a .crate archive is a gzipped tarball of the "<name>-<version>" directory
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        let content = b"[package]\nname = \"foo\"\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "foo-0.1.0/Cargo.toml", &content[..])
+            .unwrap();
+        let _ = builder.into_inner().unwrap().finish().unwrap();
+
+        let target = dir.path().join("out");
+        let sources = unpack(&archive, &target).unwrap();
+        assert_eq!(sources, target.join("foo-0.1.0"));
+        assert_eq!(
+            fs::read(sources.join("Cargo.toml")).unwrap(),
+            content.to_vec()
+        );
+
+        assert!(matches!(
+            unpack(&dir.path().join("missing-0.1.0.crate"), &target),
+            Err(Error::ExtractionFailed(..))
+        ));
+        assert_eq!(files_and_bytes(&sources), (1, content.len() as u64));
+    }
+
+    #[test]
+    fn extracted_json() {
+        let extracted = ExtractJson {
+            name: String::from("foo"),
+            version: String::from("0.1.0"),
+            source_archive: PathBuf::from(
+                "/home/user/.cargo/registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate",
+            ),
+            extracted_to: PathBuf::from("/tmp/out/foo-0.1.0"),
+            files: 3,
+            bytes: 1234,
+        };
+        let output_is = crate::output::to_json_at(&extracted, crate::output::test_time());
+        let output_should = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "name": "foo",
+  "version": "0.1.0",
+  "source_archive": "/home/user/.cargo/registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate",
+  "extracted_to": "/tmp/out/foo-0.1.0",
+  "files": 3,
+  "bytes": 1234
+}"#;
+        assert_eq!(output_is, output_should);
+        assert_eq!(
+            crate::output::schema_violations(EXTRACT_SCHEMA, &output_is),
+            Vec::<String>::new()
+        );
+
+        let failed = ExtractErrorJson {
+            spec: "foo@0.2.0",
+            error: Error::ArchiveNotCached(String::from("foo@0.2.0")).to_string(),
+        };
+        let failed_output = crate::output::to_json_at(&failed, crate::output::test_time());
+        assert!(failed_output.contains(r#""crate": "foo@0.2.0","#));
+        assert!(failed_output.contains(r#""error": ""#));
+        assert_eq!(
+            crate::output::schema_violations(EXTRACT_SCHEMA, &failed_output),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -29,11 +29,12 @@ use std::fs::read_dir;
 use std::path::{Path, PathBuf};
 
 use cargo_metadata::MetadataCommand;
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use walkdir::WalkDir;
 
 use crate::library;
-use crate::library::Error;
+use crate::library::{size_opts, Error};
+use crate::settings::Settings;
 use crate::tables::*;
 
 /// Checks if a cargo manifest named "Cargo.toml" is found in the current directory.
@@ -76,7 +77,7 @@ pub(crate) fn get_manifest() -> Result<PathBuf, Error> {
 
 /// gather the sizes of subdirs of the `target` directory and prints a formatted table
 /// of the data to stdout
-pub(crate) fn local_subcmd() -> Result<(), Error> {
+pub(crate) fn local_subcmd(settings: &Settings) -> Result<(), Error> {
     // padding of the final formatting of the table
     const MIN_PADDING: usize = 6;
 
@@ -107,9 +108,9 @@ pub(crate) fn local_subcmd() -> Result<(), Error> {
     // println!("Found target dir: '{}'", target_dir.display());
 
     // get the size
-    let dirinfo = library::cumulative_dir_size(&target_dir);
+    let dirinfo = library::cumulative_dir_size(settings, &target_dir);
     // and the human readable size
-    let size_hr = dirinfo.dir_size.file_size(file_size_opts::DECIMAL).unwrap();
+    let size_hr = dirinfo.dir_size.file_size(size_opts(settings)).unwrap();
 
     let mut stdout = String::new();
 
@@ -138,48 +139,48 @@ pub(crate) fn local_subcmd() -> Result<(), Error> {
     let target_dir_doc = p.join("doc");
 
     // gather the sizes of all these directories, `TableLine` will be used for formatting
-    let size_debug = library::cumulative_dir_size(&target_dir_debug).dir_size;
+    let size_debug = library::cumulative_dir_size(settings, &target_dir_debug).dir_size;
     if size_debug > 0 {
         lines.push(TableLine::new(
             0,
             &"debug: ".to_string(),
-            &size_debug.file_size(file_size_opts::DECIMAL).unwrap(),
+            &size_debug.file_size(size_opts(settings)).unwrap(),
         ));
     }
 
-    let size_rls = library::cumulative_dir_size(&target_dir_rls).dir_size;
+    let size_rls = library::cumulative_dir_size(settings, &target_dir_rls).dir_size;
     if size_rls > 0 {
         lines.push(TableLine::new(
             0,
             &"rls: ".to_string(),
-            &size_rls.file_size(file_size_opts::DECIMAL).unwrap(),
+            &size_rls.file_size(size_opts(settings)).unwrap(),
         ));
     }
 
-    let size_release = library::cumulative_dir_size(&target_dir_release).dir_size;
+    let size_release = library::cumulative_dir_size(settings, &target_dir_release).dir_size;
     if size_release > 0 {
         lines.push(TableLine::new(
             0,
             &"release: ".to_string(),
-            &size_release.file_size(file_size_opts::DECIMAL).unwrap(),
+            &size_release.file_size(size_opts(settings)).unwrap(),
         ));
     }
 
-    let size_package = library::cumulative_dir_size(&target_dir_package).dir_size;
+    let size_package = library::cumulative_dir_size(settings, &target_dir_package).dir_size;
     if size_package > 0 {
         lines.push(TableLine::new(
             0,
             &"package: ".to_string(),
-            &size_package.file_size(file_size_opts::DECIMAL).unwrap(),
+            &size_package.file_size(size_opts(settings)).unwrap(),
         ));
     }
 
-    let size_doc = library::cumulative_dir_size(&target_dir_doc).dir_size;
+    let size_doc = library::cumulative_dir_size(settings, &target_dir_doc).dir_size;
     if size_doc > 0 {
         lines.push(TableLine::new(
             0,
             &"doc: ".to_string(),
-            &size_doc.file_size(file_size_opts::DECIMAL).unwrap(),
+            &size_doc.file_size(size_opts(settings)).unwrap(),
         ));
     }
 
@@ -219,7 +220,7 @@ pub(crate) fn local_subcmd() -> Result<(), Error> {
         lines.push(TableLine::new(
             0,
             &"other: ".to_string(),
-            &size_other.file_size(file_size_opts::DECIMAL).unwrap(),
+            &size_other.file_size(size_opts(settings)).unwrap(),
         ));
     }
 
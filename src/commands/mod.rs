@@ -8,8 +8,15 @@
 // except according to those terms.
 
 // code related to subcommands is located here
+pub(crate) mod biggest_crate;
+pub(crate) mod clean_index;
+pub(crate) mod compression;
+pub(crate) mod doctor;
+pub(crate) mod export_files;
+pub(crate) mod extract;
 pub(crate) mod local;
 pub(crate) mod query;
 pub(crate) mod sccache;
+pub(crate) mod size;
 pub(crate) mod toolchains;
 pub(crate) mod trim;
@@ -12,7 +12,9 @@ use std::path::{Path, PathBuf};
 
 use crate::cache::caches::{Cache, RegistrySuperCache};
 use crate::cache::*;
-use crate::library::Error;
+use crate::config::Config;
+use crate::library::{cmp_crate_names, size_opts, Error};
+use crate::settings::Settings;
 
 use clap::ArgMatches;
 use humansize::{file_size_opts, FileSize};
@@ -134,25 +136,35 @@ fn registry_source_cache_to_file(path: &Path) -> File<'_> {
     }
 }
 
+/// crate names end in "-<version>", so versions of a crate are sorted by semver, equal names by path
 fn sort_files_by_name(v: &mut Vec<File<'_>>) {
-    v.sort_by_key(|f| f.name.clone() /* @TODO: don't clone*/);
+    v.sort_by(|a, b| cmp_crate_names(&a.name, &b.name).then_with(|| a.path.cmp(b.path)));
 }
 
+/// equal sizes are sorted by name
 fn sort_files_by_size(v: &mut Vec<File<'_>>) {
-    v.sort_by_key(|f| f.size);
+    v.sort_by(|a, b| {
+        a.size
+            .cmp(&b.size)
+            .then_with(|| cmp_crate_names(&a.name, &b.name))
+            .then_with(|| a.path.cmp(b.path))
+    });
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn run_query(
+    settings: &Settings,
     query_config: &ArgMatches<'_>,
+    config_file: &Config,
     bin_cache: &mut bin::BinaryCache,
     checkouts_cache: &mut git_checkouts::GitCheckoutCache,
     bare_repos_cache: &mut git_bare_repos::GitRepoCache,
     registry_pkg_cache: &mut registry_pkg_cache::RegistryPkgCaches,
     registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
 ) -> Result<(), Error> {
-    let sorting = query_config.value_of("sort");
+    let sorting = config_file.sort_by(query_config);
     let query = query_config.value_of("QUERY").unwrap_or("" /* default */);
-    let hr_size = query_config.is_present("hr");
+    let hr_size = config_file.human_readable(query_config);
 
     let mut output = String::new();
 
@@ -201,7 +213,7 @@ pub(crate) fn run_query(
 
     let humansize_opts = file_size_opts::FileSizeOpts {
         allow_negative: true,
-        ..file_size_opts::DECIMAL
+        ..size_opts(settings)
     };
 
     match sorting {
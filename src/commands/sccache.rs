@@ -14,10 +14,12 @@ use std::fs;
 use std::path::PathBuf;
 
 use chrono::prelude::*;
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use walkdir::WalkDir;
 
 use crate::library;
+use crate::library::size_opts;
+use crate::settings::Settings;
 use crate::tables::format_table;
 
 #[derive(Debug, Clone)]
@@ -53,7 +55,7 @@ fn sccache_dir() -> Result<PathBuf, library::Error> {
         .ok_or(library::Error::NoSccacheDir)
 }
 
-pub(crate) fn sccache_stats() -> Result<(), library::Error> {
+pub(crate) fn sccache_stats(settings: &Settings) -> Result<(), library::Error> {
     let sccache_path: PathBuf = sccache_dir()?;
 
     // of all the files inside the sccache cache, gather last access time and path
@@ -123,7 +125,7 @@ pub(crate) fn sccache_stats() -> Result<(), library::Error> {
             // calculate total file size sum for the summary
             total_size += total_size_bytes;
 
-            let size_human_readable = total_size_bytes.file_size(file_size_opts::DECIMAL).unwrap();
+            let size_human_readable = total_size_bytes.file_size(size_opts(settings)).unwrap();
 
             let percentage = percentage_of_as_string(total_size_bytes, total_size_entire_cache);
 
@@ -168,7 +170,7 @@ pub(crate) fn sccache_stats() -> Result<(), library::Error> {
     table_vec.push(vec![
         number_of_files.to_string(),
         String::new(),
-        total_size.file_size(file_size_opts::DECIMAL).unwrap(),
+        total_size.file_size(size_opts(settings)).unwrap(),
         "100 %".into(),
     ]);
 
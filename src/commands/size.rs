@@ -0,0 +1,361 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "cargo cache size <path>" which sizes any directory
+/// with the same parallel walker that is used to size the cache, similar to "du -sh"
+/// with "--max-depth N", the sizes of the directories up to N levels below the path are listed as well
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::library::{parse_size, size_opts, DirInfo, Error};
+use crate::output::{to_json, OutputFormat};
+use crate::settings::Settings;
+use crate::size_source::size_of_metadata;
+use crate::tables::{two_row_table, TableLine};
+
+use humansize::FileSize;
+use rayon::prelude::*;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+/// the units that "size --unit" accepts
+pub(crate) const SIZE_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "KiB", "MiB", "GiB", "TiB"];
+
+/// how the size should be printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SizeFormat<'a> {
+    /// human readable, as everywhere else
+    Human,
+    /// plain number of bytes
+    Bytes,
+    /// a fixed unit such as "MiB"
+    Unit(&'a str),
+}
+
+/// the size of a path, serialized for "--format json"
+#[derive(Debug, Clone, Serialize)]
+struct PathSize {
+    path: PathBuf,
+    bytes: u64,
+    files: u64,
+    /// the directories below the path with "--max-depth"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    directories: Vec<PathSize>,
+}
+
+/// JSON Schema (draft 7) of the size of a path
+#[cfg(test)]
+pub(crate) const PATH_SIZE_SCHEMA: &str = r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache size --format json",
+  "type": "object",
+  "definitions": {
+    "path_size": {
+      "type": "object",
+      "properties": {
+        "path": { "type": "string" },
+        "bytes": { "type": "integer", "minimum": 0 },
+        "files": { "type": "integer", "minimum": 0 },
+        "directories": {
+          "type": "array",
+          "items": { "$ref": "#/definitions/path_size" }
+        }
+      },
+      "required": ["path", "bytes", "files"],
+      "additionalProperties": false
+    }
+  },
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "path": { "type": "string" },
+    "bytes": { "type": "integer", "minimum": 0 },
+    "files": { "type": "integer", "minimum": 0 },
+    "directories": {
+      "type": "array",
+      "items": { "$ref": "#/definitions/path_size" }
+    },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "path", "bytes", "files"],
+  "additionalProperties": false
+}"##;
+
+/// total size and total number of files below a path, the path may also be a single file
+fn size_and_files(settings: &Settings, path: &Path) -> DirInfo {
+    if path.is_file() {
+        return DirInfo {
+            dir_size: fs::metadata(path).map_or(0, |m| size_of_metadata(settings, &m)),
+            file_number: 1,
+        };
+    }
+
+    let file_sizes = WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.path().to_owned())
+        .collect::<Vec<_>>()
+        .par_iter()
+        // files may have been removed since we walked the dir
+        .filter_map(|file| fs::metadata(file).ok())
+        .map(|metadata| size_of_metadata(settings, &metadata))
+        .collect::<Vec<u64>>();
+
+    DirInfo {
+        dir_size: file_sizes.iter().sum(),
+        file_number: file_sizes.len() as u64,
+    }
+}
+
+/// total size and number of files of every directory that is at most `max_depth` levels below `path`
+/// (`path` itself is at depth 0 and not included), sorted by path
+fn sizes_by_directory(
+    settings: &Settings,
+    path: &Path,
+    max_depth: usize,
+) -> BTreeMap<PathBuf, DirInfo> {
+    let mut directories = BTreeMap::new();
+    for entry in WalkDir::new(path)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        if entry.file_type().is_dir() && entry.depth() <= max_depth {
+            let _ = directories
+                .entry(entry.path().to_path_buf())
+                .or_insert(DirInfo {
+                    dir_size: 0,
+                    file_number: 0,
+                });
+        } else if entry.file_type().is_file() {
+            // files may have been removed since we walked the dir
+            let size = entry
+                .metadata()
+                .map_or(0, |metadata| size_of_metadata(settings, &metadata));
+            // add the file to all its parent directories that are listed
+            for parent in entry.path().ancestors().skip(1).take(entry.depth() - 1) {
+                if let Some(info) = directories.get_mut(parent) {
+                    info.dir_size += size;
+                    info.file_number += 1;
+                }
+            }
+        }
+    }
+    directories
+}
+
+/// format a number of bytes in the requested way
+fn format_size(settings: &Settings, size: u64, format: SizeFormat<'_>) -> String {
+    match format {
+        SizeFormat::Human => size.file_size(size_opts(settings)).unwrap(),
+        SizeFormat::Bytes => size.to_string(),
+        SizeFormat::Unit(unit) => {
+            // clap only lets valid units through
+            let bytes_per_unit = parse_size(&format!("1{unit}")).unwrap_or(1);
+            // precision loss does not matter, we only print 2 decimal places
+            #[allow(clippy::cast_precision_loss)]
+            let value = size as f64 / bytes_per_unit as f64;
+            format!("{value:.2} {unit}")
+        }
+    }
+}
+
+fn files_text(file_number: u64) -> String {
+    format!(
+        "{} {}",
+        file_number,
+        if file_number == 1 { "file" } else { "files" }
+    )
+}
+
+/// print the size and number of files of a path
+/// and with `max_depth`, of the directories up to that depth below it
+pub(crate) fn size_of_dir(
+    settings: &Settings,
+    path: &str,
+    size_format: SizeFormat<'_>,
+    output_format: OutputFormat,
+    max_depth: Option<usize>,
+) -> Result<(), Error> {
+    let dir = PathBuf::from(path);
+    if !dir.exists() {
+        return Err(Error::SizeNoSuchPath(dir));
+    }
+
+    let info = size_and_files(settings, &dir);
+    let directories = match max_depth {
+        Some(depth) if depth > 0 => sizes_by_directory(settings, &dir, depth),
+        _ => BTreeMap::new(),
+    };
+
+    match output_format {
+        OutputFormat::Json => println!(
+            "{}",
+            to_json(
+                settings,
+                &PathSize {
+                    path: dir,
+                    bytes: info.dir_size,
+                    files: info.file_number,
+                    directories: directories
+                        .into_iter()
+                        .map(|(subdir, subdir_info)| PathSize {
+                            path: subdir,
+                            bytes: subdir_info.dir_size,
+                            files: subdir_info.file_number,
+                            directories: Vec::new(),
+                        })
+                        .collect(),
+                }
+            )
+        ),
+        OutputFormat::Text => {
+            println!(
+                "Size of '{}': {} ({})",
+                dir.display(),
+                format_size(settings, info.dir_size, size_format),
+                files_text(info.file_number)
+            );
+            if !directories.is_empty() {
+                let table = directories
+                    .iter()
+                    .map(|(subdir, subdir_info)| {
+                        TableLine::new(
+                            1,
+                            &format!("{}:", subdir.display()),
+                            &format_size(settings, subdir_info.dir_size, size_format),
+                        )
+                    })
+                    .collect();
+                print!("{}", two_row_table(2, table, true));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod size_tests {
+    use super::*;
+    use crate::output::{test_time, to_json_at};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn size_of_tempdir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), b"12345").unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub").join("b"), b"123").unwrap();
+
+        let info = size_and_files(&Settings::default(), dir.path());
+        assert_eq!(info.dir_size, 8);
+        assert_eq!(info.file_number, 2);
+
+        let file = size_and_files(&Settings::default(), &dir.path().join("a"));
+        assert_eq!(file.dir_size, 5);
+        assert_eq!(file.file_number, 1);
+    }
+
+    #[test]
+    fn sizes_up_to_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a"), b"12345").unwrap();
+        fs::create_dir_all(root.join("sub").join("deeper").join("deepest")).unwrap();
+        fs::write(root.join("sub").join("b"), b"123").unwrap();
+        fs::write(root.join("sub").join("deeper").join("c"), b"12").unwrap();
+        fs::write(
+            root.join("sub").join("deeper").join("deepest").join("d"),
+            b"1",
+        )
+        .unwrap();
+        fs::create_dir(root.join("empty")).unwrap();
+
+        let sizes = |max_depth| {
+            sizes_by_directory(&Settings::default(), root, max_depth)
+                .into_iter()
+                .map(|(path, info)| {
+                    (
+                        path.strip_prefix(root).unwrap().to_path_buf(),
+                        info.dir_size,
+                        info.file_number,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sizes(1),
+            vec![(PathBuf::from("empty"), 0, 0), (PathBuf::from("sub"), 6, 3)]
+        );
+        assert_eq!(
+            sizes(2),
+            vec![
+                (PathBuf::from("empty"), 0, 0),
+                (PathBuf::from("sub"), 6, 3),
+                (PathBuf::from("sub/deeper"), 3, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn size_formats() {
+        assert_eq!(
+            format_size(&Settings::default(), 1_500_000, SizeFormat::Human),
+            "1.50 MB"
+        );
+        assert_eq!(
+            format_size(&Settings::default(), 1_500_000, SizeFormat::Bytes),
+            "1500000"
+        );
+        assert_eq!(
+            format_size(&Settings::default(), 1_500_000, SizeFormat::Unit("KB")),
+            "1500.00 KB"
+        );
+        assert_eq!(
+            format_size(&Settings::default(), 1_048_576, SizeFormat::Unit("MiB")),
+            "1.00 MiB"
+        );
+        assert_eq!(
+            format_size(&Settings::default(), 512, SizeFormat::Unit("B")),
+            "512.00 B"
+        );
+    }
+
+    #[test]
+    fn size_json() {
+        let output = to_json_at(
+            &PathSize {
+                path: PathBuf::from("/home/user/project/target"),
+                bytes: 1_500_000,
+                files: 42,
+                directories: Vec::new(),
+            },
+            test_time(),
+        );
+
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "path": "/home/user/project/target",
+  "bytes": 1500000,
+  "files": 42
+}"#;
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(PATH_SIZE_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -10,11 +10,13 @@
 use std::path::PathBuf;
 
 use chrono::prelude::*;
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use walkdir::WalkDir;
 
 use crate::library;
+use crate::library::size_opts;
 use crate::sccache::percentage_of_as_string;
+use crate::settings::Settings;
 use crate::tables::format_table;
 
 #[derive(Debug, Clone)]
@@ -69,7 +71,7 @@ impl<'a> Toolchain {
     }
 }
 
-pub(crate) fn toolchain_stats() {
+pub(crate) fn toolchain_stats(settings: &Settings) {
     // get a list of toolchains, sorted by size
     let toolchains = {
         let mut tcs = toolchains()
@@ -92,7 +94,7 @@ pub(crate) fn toolchain_stats() {
             vec![
                 toolchain.name.clone(),
                 toolchain.number_files.to_string(),
-                toolchain.size.file_size(file_size_opts::DECIMAL).unwrap(),
+                toolchain.size.file_size(size_opts(settings)).unwrap(),
                 percentage_of_as_string(toolchain.size, total_size),
             ]
         })
@@ -128,7 +130,7 @@ pub(crate) fn toolchain_stats() {
     table_vec.push(vec![
         String::from("Total"),
         number_of_files.to_string(),
-        total_size.file_size(file_size_opts::DECIMAL).unwrap(),
+        total_size.file_size(size_opts(settings)).unwrap(),
         "100 %".into(),
     ]);
 
@@ -11,14 +11,22 @@
 // trim the size of the cargo cache down to a certain limit.
 // note that this does not take account the registry indices and the installed binaries in calculations
 
+use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
+use crate::cache::archive_sources::{match_archives_to_sources, ArchiveSource};
 use crate::cache::caches::*;
 use crate::cache::*;
+use crate::clean_unref::referenced_items;
 use crate::library::*;
+use crate::output::to_json;
 use crate::remove::*;
+use crate::settings::Settings;
+use crate::size_source;
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
+use serde::Serialize;
 use walkdir::WalkDir;
 
 fn get_last_access_of_item(path: &Path) -> std::time::SystemTime {
@@ -65,49 +73,18 @@ fn parse_size_limit_to_bytes(limit: Option<&str>) -> Result<u64, Error> {
     match limit {
         None => unreachable!("No trim --limit was supplied although clap should enforce that!"),
         Some(limit) => {
-            // figure out the unit
-            let unit_multiplicator: Result<u64, Error> = match limit.chars().last() {
-                // we have no limit
-                None => Ok(0),
-                // we expect a unit such as B, K, M, G, T...
-                Some(c) => {
-                    if c.is_alphabetic() {
-                        match c {
-                            'b' | 'B' => Ok(1),
-                            'k' | 'K' => Ok(1024),
-                            'm' | 'M' => Ok(1024 * 1024),
-                            'g' | 'G' => Ok(1024 * 1024 * 1024),
-                            't' | 'T' => Ok(1024 * 1024 * 1024 * 1024),
-                            _ => Err(Error::TrimLimitUnitParseFailure(limit.to_string())),
-                        }
-                    } else {
-                        Err(Error::TrimLimitUnitParseFailure(limit.to_string()))
-                    }
-                }
-            };
-
-            let value: f64 = match limit[0..(limit.len() - 1)].parse() {
-                Ok(val) => val,
-                Err(_) => {
-                    return Err(Error::TrimLimitUnitParseFailure(limit.to_string()));
-                }
-            };
-            if value == 0.0 {
-                return Ok(0);
-            }
-            // we may truncate the value here but that's ok
-            #[allow(clippy::cast_lossless)]
-            #[allow(clippy::cast_sign_loss)]
-            #[allow(clippy::cast_possible_truncation)]
-            #[allow(clippy::cast_precision_loss)]
-            Ok((value * unit_multiplicator? as f64) as u64)
+            parse_size(limit).map_err(|_| Error::TrimLimitUnitParseFailure(limit.to_string()))
         }
     }
 }
 
-/// trim the cache to a certain limit and invalidate caches
+/// trim the cache to a certain limit and invalidate caches, the `keep_crates` are never removed
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn trim_cache<'a>(
+    settings: &Settings,
     unparsed_size_limit: Option<&'a str>,
+    // crates that are never removed (--keep-crate)
+    keep_crates: &[&str],
     git_checkouts_cache: &mut git_checkouts::GitCheckoutCache,
     bare_repos_cache: &mut git_bare_repos::GitRepoCache,
     registry_pkg_cache: &mut registry_pkg_cache::RegistryPkgCaches,
@@ -141,38 +118,51 @@ pub(crate) fn trim_cache<'a>(
 
     // delete everything that is unneeded
     let mut cache_size = 0;
-    let mut removed_size: u64 = 0;
-    let mut removed_item_count = 0;
 
     // walk the items and collect items until we have reached the size limit
-    all_cache_items
+    let mut to_remove = all_cache_items
         // walk through the files, youngest item comes first, oldest item comes last
-        .iter()
-        .filter(|path| {
+        .into_iter()
+        .filter_map(|path| {
             //@TODO query cache for size!
-            let item_size = size_of_path(path);
+            let item_size = size_of_path(settings, path);
             // add the item size to the cache size
             cache_size += item_size;
             // keep all items (for deletion) once we have exceeded the cache size
-            let keep_file = cache_size > size_limit;
-            if keep_file {
-                removed_size += item_size;
-                removed_item_count += 1;
-            }
-            keep_file
+            // crates that are kept still take up space, but are never removed
+            (cache_size > size_limit && !is_kept_crate(path, keep_crates))
+                .then_some((path, item_size))
         })
-        // .for_each(|path| println!("{}", path.display().to_string()));
-        // for debugging: the smaller the size limit is, the more items we keep for deletion
-        .for_each(|path| {
-            remove_file(
+        .collect::<Vec<_>>();
+    // remove the oldest items first, so that stopping at "--max-removals" keeps the younger ones
+    to_remove.reverse();
+    let (limited, remaining) = limit_removals(settings, &to_remove);
+
+    // failed removals are reported and do not count as removed
+    let removed_item_count = AtomicUsize::new(0);
+    let removed_size = AtomicU64::new(0);
+    remove_all(
+        settings,
+        limited,
+        dry_run,
+        size_changed,
+        |(path, size), changed| {
+            if remove_file(
+                settings,
                 path,
                 dry_run,
-                size_changed,
+                changed,
                 None,
                 &DryRunMessage::Default,
                 None,
-            );
-        });
+            ) {
+                let _ = removed_item_count.fetch_add(1, Ordering::Relaxed);
+                let _ = removed_size.fetch_add(*size, Ordering::Relaxed);
+            }
+        },
+    );
+    report_failed_removals(settings);
+    report_removal_limit(settings, remaining);
 
     // invalidate caches that we might have touched
     git_checkouts_cache.invalidate();
@@ -182,12 +172,479 @@ pub(crate) fn trim_cache<'a>(
 
     println!(
         "Removed {} items totalling {}",
-        removed_item_count,
-        removed_size.file_size(file_size_opts::DECIMAL).unwrap()
+        removed_item_count.into_inner(),
+        removed_size
+            .into_inner()
+            .file_size(size_opts(settings))
+            .unwrap()
     );
     Ok(())
 }
 
+/// last access of an archive and its extracted sources, whichever was accessed more recently
+fn last_access_of_crate(krate: &ArchiveSource) -> std::time::SystemTime {
+    let archive_access = get_last_access_of_item(&krate.archive);
+    match &krate.source {
+        Some(source) => archive_access.max(get_last_access_of_item(source)),
+        None => archive_access,
+    }
+}
+
+/// pick the crates to remove, least recently accessed first, until the size drops below the target
+/// returns the crates with their sizes and the size they take up together
+fn crates_to_remove(
+    crates: Vec<(ArchiveSource, u64)>,
+    mut current_size: u64,
+    target_size: u64,
+) -> (Vec<(ArchiveSource, u64)>, u64) {
+    let mut removed_size = 0;
+    let to_remove = crates
+        .into_iter()
+        .take_while(|(_krate, size)| {
+            if current_size <= target_size {
+                return false;
+            }
+            current_size = current_size.saturating_sub(*size);
+            removed_size += size;
+            true
+        })
+        .collect();
+    (to_remove, removed_size)
+}
+
+/// a crate that "trim-to" removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct TrimmedCrate {
+    name: String,
+    version: String,
+    /// size of the archive and its sources
+    size: u64,
+}
+
+impl TrimmedCrate {
+    fn new(krate: &ArchiveSource, size: u64) -> Self {
+        // keep archives with unexpected names in the list, with their file name as name
+        let (name, version) = parse_version(&krate.archive).unwrap_or_else(|_| {
+            let stem = krate.archive.file_stem().unwrap_or_default();
+            (stem.to_string_lossy().into_owned(), String::new())
+        });
+        Self {
+            name,
+            version,
+            size,
+        }
+    }
+}
+
+/// what "trim-to --format json" prints instead of the text messages
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct TrimResult {
+    target_bytes: u64,
+    dry_run: bool,
+    /// with "--dry-run", the crates that would be removed
+    removed: Vec<TrimmedCrate>,
+    /// only what was actually removed, failed removals do not count
+    reclaimed_bytes: u64,
+    /// size of the registry afterwards
+    final_size: u64,
+    /// the crates that were left because of "--max-removals"
+    remaining: usize,
+}
+
+/// JSON Schema (draft 7) of the result of "trim-to"
+#[cfg(test)]
+pub(crate) const TRIM_RESULT_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache trim-to --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "target_bytes": { "type": "integer", "minimum": 0 },
+    "dry_run": { "type": "boolean" },
+    "removed": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "version": { "type": "string" },
+          "size": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["name", "version", "size"],
+        "additionalProperties": false
+      }
+    },
+    "reclaimed_bytes": { "type": "integer", "minimum": 0 },
+    "final_size": { "type": "integer", "minimum": 0 },
+    "remaining": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": [
+    "schema_version",
+    "generated_at",
+    "target_bytes",
+    "dry_run",
+    "removed",
+    "reclaimed_bytes",
+    "final_size",
+    "remaining"
+  ],
+  "additionalProperties": false
+}"#;
+
+/// what "trim-to" trims the registry to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrimTarget<'a> {
+    /// an absolute size, for example "500MB"
+    Size(&'a str),
+    /// "--trim-by 30%", remove this percentage of the registry
+    Percent(u64),
+}
+
+impl TrimTarget<'_> {
+    /// the size the registry should have afterwards
+    fn target_size(self, registry_size: u64) -> Result<u64, Error> {
+        match self {
+            Self::Size(size) => parse_size(size),
+            Self::Percent(percent) => {
+                let target = u128::from(registry_size) * u128::from(100 - percent) / 100;
+                // smaller than the registry size
+                Ok(u64::try_from(target).unwrap_or(registry_size))
+            }
+        }
+    }
+}
+
+/// "30%" or "30" => 30, only 1% to 99% are accepted
+pub(crate) fn parse_percent(percent: &str) -> Option<u64> {
+    percent
+        .strip_suffix('%')
+        .unwrap_or(percent)
+        .parse()
+        .ok()
+        .filter(|parsed| (1..=99).contains(parsed))
+}
+
+/// remove the least recently accessed crate archives and their sources until the registry
+/// is smaller than the target size, registry indices are never touched
+/// if a manifest is passed, crates it references are never removed either, neither are the `keep_crates`
+/// with `print_json`, a `TrimResult` is printed instead of the messages
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn trim_to<'a>(
+    settings: &Settings,
+    cargo_cache_paths: &CargoCachePaths,
+    target: TrimTarget<'a>,
+    keep_referenced_by: Option<&'a str>,
+    // crates that are never removed (--keep-crate)
+    keep_crates: &[&str],
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_pkg_cache: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_cache: &mut registry_sources::RegistrySourceCaches,
+    dry_run: bool,
+    print_json: bool,
+    size_changed: &mut bool,
+) -> Result<(), Error> {
+    let registry_size = registry_index_caches.total_size()
+        + registry_pkg_cache.total_size()
+        + registry_sources_cache.total_size();
+    let target_size = target.target_size(registry_size)?;
+
+    let referenced_crates = match keep_referenced_by {
+        Some(manifest) => referenced_items(cargo_cache_paths, Some(manifest), true)?.0,
+        None => Vec::new(),
+    };
+
+    let archives = registry_pkg_cache.files();
+    let sources = registry_sources_cache.items().to_vec();
+    // sized like the caches size the registry, so the sizes of the crates add up to it
+    let source_sizes = registry_sources_cache.item_sizes();
+    let extracted_size = |krate: &ArchiveSource| {
+        krate
+            .source
+            .as_ref()
+            .and_then(|source| source_sizes.get(source))
+            .copied()
+            .unwrap_or(0)
+    };
+
+    // (crate, size of archive and sources), oldest access first
+    let mut crates = match_archives_to_sources(&archives, &sources)
+        .into_iter()
+        .filter(|krate| !referenced_crates.contains(&krate.archive))
+        .filter(|krate| {
+            !parse_version(&krate.archive)
+                .is_ok_and(|(name, _version)| keep_crates.contains(&name.as_str()))
+        })
+        .map(|krate| {
+            let size = size_source::file_size(settings, &krate.archive) + extracted_size(&krate);
+            (krate, size)
+        })
+        .collect::<Vec<_>>();
+    crates.sort_by_cached_key(|(krate, _size)| last_access_of_crate(krate));
+
+    let (to_remove, _planned_size) = crates_to_remove(crates, registry_size, target_size);
+    // with "--max-removals" only some of the crates are removed, so the size is summed up below
+    let (limited, remaining) = limit_removals(settings, &to_remove);
+
+    let dry_run_msg = if print_json {
+        DryRunMessage::None
+    } else {
+        DryRunMessage::Default
+    };
+    let progress = RemovalProgress::new(settings, limited.len(), dry_run);
+    remove_all(
+        settings,
+        limited,
+        dry_run,
+        size_changed,
+        |(krate, size), changed| {
+            let archive_size = size_source::file_size(settings, &krate.archive);
+            // failed removals are reported and do not count as reclaimed
+            let mut reclaimed = 0;
+            if remove_file(
+                settings,
+                &krate.archive,
+                dry_run,
+                changed,
+                None,
+                &dry_run_msg,
+                Some(archive_size),
+            ) {
+                reclaimed += archive_size;
+            }
+            if let Some(source) = &krate.source {
+                let sources_size = size - archive_size;
+                if remove_file(
+                    settings,
+                    source,
+                    dry_run,
+                    changed,
+                    None,
+                    &dry_run_msg,
+                    Some(sources_size),
+                ) {
+                    reclaimed += sources_size;
+                }
+            }
+            progress.removed(reclaimed);
+        },
+    );
+    progress.finish();
+    let removed_size = progress.reclaimed();
+
+    registry_pkg_cache.invalidate();
+    registry_sources_cache.invalidate();
+
+    let new_registry_size = registry_size.saturating_sub(removed_size);
+    if print_json {
+        let result = TrimResult {
+            target_bytes: target_size,
+            dry_run,
+            removed: limited
+                .iter()
+                // crates that could not be removed are reported as failed removals
+                .filter(|(krate, _size)| dry_run || !krate.archive.exists())
+                .map(|(krate, size)| TrimmedCrate::new(krate, *size))
+                .collect(),
+            reclaimed_bytes: removed_size,
+            final_size: new_registry_size,
+            remaining,
+        };
+        println!("{}", to_json(settings, &result));
+        return Ok(());
+    }
+
+    report_removal_limit(settings, remaining);
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    println!(
+        "{} {} crates totalling {}, registry size: {} => {}",
+        verb,
+        limited.len(),
+        removed_size.file_size(size_opts(settings)).unwrap(),
+        registry_size.file_size(size_opts(settings)).unwrap(),
+        new_registry_size.file_size(size_opts(settings)).unwrap()
+    );
+    // stopping at "--max-removals" is not a failure to reach the target
+    if remaining == 0 && new_registry_size > target_size {
+        println!(
+            "Could not trim the registry to {}, the indices and the crates that are kept are bigger than that.",
+            target_size.file_size(size_opts(settings)).unwrap()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod trim_cache_tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn trim_keeps_crates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = tmp.path().join("registry");
+        let archives = registry.join("cache").join("github.com-1ecc6299db9ec823");
+        std::fs::create_dir_all(&archives).unwrap();
+        std::fs::write(archives.join("foo-0.1.0.crate"), b"1234").unwrap();
+        std::fs::write(archives.join("bar-0.2.0.crate"), b"1234").unwrap();
+
+        let mut checkouts =
+            git_checkouts::GitCheckoutCache::new(tmp.path().join("git/checkouts"), Arc::default());
+        let mut bare_repos =
+            git_bare_repos::GitRepoCache::new(tmp.path().join("git/db"), Arc::default());
+        let mut pkgs =
+            registry_pkg_cache::RegistryPkgCaches::new(registry.join("cache"), Arc::default());
+        let mut sources =
+            registry_sources::RegistrySourceCaches::new(registry.join("src"), Arc::default());
+
+        let mut size_changed = false;
+        trim_cache(
+            &Settings::default(),
+            Some("1B"),
+            &["foo"],
+            &mut checkouts,
+            &mut bare_repos,
+            &mut pkgs,
+            &mut sources,
+            false,
+            &mut size_changed,
+        )
+        .unwrap();
+
+        assert!(size_changed);
+        assert!(archives.join("foo-0.1.0.crate").exists());
+        assert!(!archives.join("bar-0.2.0.crate").exists());
+    }
+}
+
+#[cfg(test)]
+mod trim_to_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+    fn krate(name: &str) -> ArchiveSource {
+        ArchiveSource {
+            archive: PathBuf::from(format!("{name}.crate")),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn removes_oldest_until_below_target() {
+        let crates = vec![(krate("old"), 40), (krate("older"), 30), (krate("new"), 30)];
+
+        // 150 => 110 => 80, done
+        let (removed, size) = crates_to_remove(crates.clone(), 150, 100);
+        assert_eq!(removed, vec![(krate("old"), 40), (krate("older"), 30)]);
+        assert_eq!(size, 70);
+
+        // already below the target
+        let (nothing_removed, no_size) = crates_to_remove(crates.clone(), 100, 100);
+        assert!(nothing_removed.is_empty());
+        assert_eq!(no_size, 0);
+
+        // target can not be reached, remove everything
+        let (all_removed, total_size) = crates_to_remove(crates, 150, 10);
+        assert_eq!(all_removed.len(), 3);
+        assert_eq!(total_size, 100);
+    }
+
+    #[test]
+    fn crate_sizes_add_up_to_total_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = tmp.path().join("github.com-1ecc6299db9ec823");
+        let foo = registry.join("foo-0.1.0");
+        let bar = registry.join("bar-0.2.0");
+        std::fs::create_dir_all(foo.join("src")).unwrap();
+        std::fs::create_dir_all(&bar).unwrap();
+        std::fs::write(foo.join("Cargo.toml"), b"1234").unwrap();
+        std::fs::write(foo.join("src").join("lib.rs"), b"12345678").unwrap();
+        std::fs::write(bar.join("Cargo.toml"), b"12").unwrap();
+
+        let mut sources =
+            registry_sources::RegistrySourceCaches::new(tmp.path().to_path_buf(), Arc::default());
+        let sizes = sources.item_sizes();
+
+        assert_eq!(sizes.get(&foo), Some(&12));
+        assert_eq!(sizes.get(&bar), Some(&2));
+        assert_eq!(sizes.values().sum::<u64>(), sources.total_size());
+    }
+
+    #[test]
+    fn trim_by_percent() {
+        assert_eq!(parse_percent("30%"), Some(30));
+        assert_eq!(parse_percent("30"), Some(30));
+        assert_eq!(parse_percent("1%"), Some(1));
+        assert_eq!(parse_percent("99%"), Some(99));
+        assert_eq!(parse_percent("0%"), None);
+        assert_eq!(parse_percent("100%"), None);
+        assert_eq!(parse_percent("-5%"), None);
+        assert_eq!(parse_percent("30MB"), None);
+
+        assert_eq!(TrimTarget::Percent(30).target_size(1000).unwrap(), 700);
+        assert_eq!(
+            TrimTarget::Percent(99).target_size(u64::MAX).unwrap(),
+            u64::MAX / 100
+        );
+        assert_eq!(TrimTarget::Size("1KB").target_size(5).unwrap(), 1000);
+    }
+
+    #[test]
+    fn trim_result_json() {
+        let removed = vec![
+            TrimmedCrate::new(
+                &ArchiveSource {
+                    archive: PathBuf::from("registry/cache/a/heim-runtime-0.1.0-beta.1.crate"),
+                    source: Some(PathBuf::from("registry/src/a/heim-runtime-0.1.0-beta.1")),
+                },
+                40,
+            ),
+            TrimmedCrate::new(&krate("unexpected"), 2),
+        ];
+        let result = TrimResult {
+            target_bytes: 100,
+            dry_run: true,
+            removed,
+            reclaimed_bytes: 42,
+            final_size: 98,
+            remaining: 3,
+        };
+
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "target_bytes": 100,
+  "dry_run": true,
+  "removed": [
+    {
+      "name": "heim-runtime",
+      "version": "0.1.0-beta.1",
+      "size": 40
+    },
+    {
+      "name": "unexpected",
+      "version": "",
+      "size": 2
+    }
+  ],
+  "reclaimed_bytes": 42,
+  "final_size": 98,
+  "remaining": 3
+}"#;
+        let output = crate::output::to_json_at(&result, crate::output::test_time());
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(TRIM_RESULT_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+}
+
 #[cfg(test)]
 mod parse_size_limit {
     use super::*;
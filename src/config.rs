@@ -0,0 +1,232 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file provides the optional config file which can set defaults for command line options.
+/// Options passed explicitly on the command line always take precedence over the config file.
+///
+/// The config file is looked up at "$CARGO_HOME/cargo-cache.toml" first and at
+/// "~/.config/cargo-cache/config.toml" (or the platform equivalent) second:
+/// ```toml
+/// format = "json"
+/// sort-by = "size"
+/// human-readable = true
+/// dry-run = true
+/// ```
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::library::Error;
+use crate::output::OutputFormat;
+
+use clap::ArgMatches;
+use serde::Deserialize;
+
+/// name of the config file when placed inside of the cargo home
+const CARGO_HOME_CONFIG_FILE: &str = "cargo-cache.toml";
+
+/// the defaults read from a config file
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Config {
+    /// default for "--format"
+    pub(crate) format: Option<OutputFormat>,
+    /// default for "query --sort-by"
+    pub(crate) sort_by: Option<SortBy>,
+    /// default for "query --human-readable"
+    pub(crate) human_readable: Option<bool>,
+    /// default for "--dry-run", can be overridden with "--no-dry-run"
+    pub(crate) dry_run: Option<bool>,
+}
+
+/// the orders "query --sort-by" accepts
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SortBy {
+    Name,
+    Size,
+}
+
+impl SortBy {
+    /// the name of the order as it is accepted by "--sort-by"
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+        }
+    }
+}
+
+impl Config {
+    /// parse a config from the content of a config file
+    fn parse(content: &str, path: &Path) -> Result<Self, Error> {
+        toml::from_str(content)
+            .map_err(|e| Error::ConfigParseFailure(path.to_path_buf(), e.to_string()))
+    }
+
+    /// the locations a config file is looked up at, in order of precedence
+    fn candidate_paths() -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Ok(cargo_home) = home::cargo_home() {
+            paths.push(cargo_home.join(CARGO_HOME_CONFIG_FILE));
+        }
+        if let Some(config_dir) = dirs_next::config_dir() {
+            paths.push(config_dir.join("cargo-cache").join("config.toml"));
+        }
+        paths
+    }
+
+    /// load the first config file that exists, if there is none, return the empty config
+    pub(crate) fn load() -> Result<Self, Error> {
+        match Self::candidate_paths().iter().find(|path| path.is_file()) {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| Error::ConfigParseFailure(path.clone(), e.to_string()))?;
+                Self::parse(&content, path)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// the output format, the command line overrides the config
+    pub(crate) fn format(&self, matches: &ArgMatches<'_>) -> OutputFormat {
+        matches
+            .value_of("format")
+            .and_then(|format| format.parse().ok())
+            .or(self.format)
+            .unwrap_or(OutputFormat::Text)
+    }
+
+    /// the order to sort query results by, the command line overrides the config
+    pub(crate) fn sort_by<'a>(&self, matches: &'a ArgMatches<'_>) -> Option<&'a str> {
+        matches
+            .value_of("sort")
+            .or_else(|| self.sort_by.map(SortBy::as_str))
+    }
+
+    /// whether to only pretend to remove items, the command line overrides the config
+    /// "--dry-run" and "--no-dry-run" may be passed to "cargo cache" as well as to a subcommand,
+    /// "--fail-if-removable" implies "--dry-run"
+    pub(crate) fn dry_run(&self, matches: &[&ArgMatches<'_>]) -> bool {
+        if matches.iter().any(|m| m.is_present("no-dry-run")) {
+            false
+        } else if matches
+            .iter()
+            .any(|m| m.is_present("dry-run") || m.is_present("fail-if-removable"))
+        {
+            true
+        } else {
+            self.dry_run.unwrap_or(false)
+        }
+    }
+
+    /// whether to print sizes in human readable format, the command line overrides the config
+    pub(crate) fn human_readable(&self, matches: &ArgMatches<'_>) -> bool {
+        if matches.is_present("no-hr") {
+            false
+        } else {
+            matches.is_present("hr") || self.human_readable.unwrap_or(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn empty_config() {
+        let cfg = Config::parse("", Path::new("config.toml")).unwrap();
+        assert_eq!(cfg, Config::default());
+    }
+
+    #[test]
+    fn full_config() {
+        let cfg = Config::parse(
+            "format = \"json\"\nsort-by = \"size\"\nhuman-readable = true\ndry-run = true\n",
+            Path::new("config.toml"),
+        )
+        .unwrap();
+        assert_eq!(
+            cfg,
+            Config {
+                format: Some(OutputFormat::Json),
+                sort_by: Some(SortBy::Size),
+                human_readable: Some(true),
+                dry_run: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_configs() {
+        // unknown key
+        assert!(Config::parse("colour = \"always\"", Path::new("config.toml")).is_err());
+        // unknown sort order
+        assert!(Config::parse("sort-by = \"date\"", Path::new("config.toml")).is_err());
+        // wrong type
+        assert!(Config::parse("human-readable = \"yes\"", Path::new("config.toml")).is_err());
+    }
+
+    #[test]
+    fn dry_run_precedence() {
+        use clap::{App, Arg};
+
+        let app = App::new("test")
+            .arg(Arg::with_name("dry-run").long("dry-run"))
+            .arg(Arg::with_name("no-dry-run").long("no-dry-run"))
+            .arg(Arg::with_name("fail-if-removable").long("fail-if-removable"));
+        let dry_run_by_default = Config {
+            dry_run: Some(true),
+            ..Config::default()
+        };
+
+        let nothing = app.clone().get_matches_from(vec!["test"]);
+        assert!(!Config::default().dry_run(&[&nothing]));
+        assert!(dry_run_by_default.dry_run(&[&nothing]));
+
+        let dry_run = app.clone().get_matches_from(vec!["test", "--dry-run"]);
+        assert!(Config::default().dry_run(&[&dry_run]));
+
+        let fail_if_removable = app
+            .clone()
+            .get_matches_from(vec!["test", "--fail-if-removable"]);
+        assert!(Config::default().dry_run(&[&fail_if_removable]));
+
+        let no_dry_run = app.get_matches_from(vec!["test", "--no-dry-run"]);
+        assert!(!dry_run_by_default.dry_run(&[&no_dry_run]));
+        // "--no-dry-run" wins, no matter where it was passed
+        assert!(!dry_run_by_default.dry_run(&[&dry_run, &no_dry_run]));
+    }
+
+    #[test]
+    fn human_readable_precedence() {
+        use clap::{App, Arg};
+
+        let app = App::new("test")
+            .arg(Arg::with_name("hr").long("human-readable"))
+            .arg(Arg::with_name("no-hr").long("no-human-readable"));
+        let human_readable_by_default = Config {
+            human_readable: Some(true),
+            ..Config::default()
+        };
+
+        let nothing = app.clone().get_matches_from(vec!["test"]);
+        assert!(!Config::default().human_readable(&nothing));
+        assert!(human_readable_by_default.human_readable(&nothing));
+
+        let human_readable = app
+            .clone()
+            .get_matches_from(vec!["test", "--human-readable"]);
+        assert!(Config::default().human_readable(&human_readable));
+
+        let no_human_readable = app.get_matches_from(vec!["test", "--no-human-readable"]);
+        assert!(!human_readable_by_default.human_readable(&no_human_readable));
+    }
+}
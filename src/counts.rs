@@ -0,0 +1,232 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--counts-only": only the number of files and items in the cache are reported.
+/// The entries of a directory listing already carry their file type, so no file is stat-ed
+/// for its size, which is a lot faster than the full scan on large caches.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::library::CargoCachePaths;
+
+use serde::Serialize;
+use walkdir::WalkDir;
+
+/// the number of files and items of the cache, serialized for "--format json"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct CacheCounts {
+    cargo_home: PathBuf,
+    /// all files of the bin, registry and git directories
+    files: u64,
+    binaries: u64,
+    index_files: u64,
+    archives: u64,
+    sources: u64,
+    repos: u64,
+    checkouts: u64,
+}
+
+/// JSON Schema (draft 7) of the counts
+#[cfg(test)]
+pub(crate) const COUNTS_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache --counts-only --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "cargo_home": { "type": "string" },
+    "files": { "type": "integer", "minimum": 0 },
+    "binaries": { "type": "integer", "minimum": 0 },
+    "index_files": { "type": "integer", "minimum": 0 },
+    "archives": { "type": "integer", "minimum": 0 },
+    "sources": { "type": "integer", "minimum": 0 },
+    "repos": { "type": "integer", "minimum": 0 },
+    "checkouts": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": [
+    "schema_version",
+    "generated_at",
+    "cargo_home",
+    "files",
+    "binaries",
+    "index_files",
+    "archives",
+    "sources",
+    "repos",
+    "checkouts"
+  ],
+  "additionalProperties": false
+}"#;
+
+/// the number of entries `depth` levels below `dir` that are directories (`dirs`) or files,
+/// with `depth` None, the files at any depth are counted
+fn count_entries(dir: &Path, depth: Option<usize>, dirs: bool) -> u64 {
+    let walker = match depth {
+        Some(level) => WalkDir::new(dir).min_depth(level).max_depth(level),
+        None => WalkDir::new(dir).min_depth(1),
+    };
+    walker
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            if dirs {
+                entry.file_type().is_dir()
+            } else {
+                entry.file_type().is_file()
+            }
+        })
+        .count() as u64
+}
+
+impl CacheCounts {
+    /// count the files and items of the cache from the directory listings
+    pub(crate) fn new(paths: &CargoCachePaths) -> Self {
+        let files = |dir: &Path| count_entries(dir, None, false);
+        Self {
+            cargo_home: paths.cargo_home.clone(),
+            files: files(&paths.bin_dir)
+                + files(&paths.registry)
+                + files(&paths.git_repos_bare)
+                + files(&paths.git_checkouts),
+            binaries: count_entries(&paths.bin_dir, Some(1), false),
+            index_files: files(&paths.registry_index),
+            // registry/cache/<registry>/<archive>
+            archives: count_entries(&paths.registry_pkg_cache, Some(2), false),
+            // registry/src/<registry>/<crate>
+            sources: count_entries(&paths.registry_sources, Some(2), true),
+            // git/db/<repo>
+            repos: count_entries(&paths.git_repos_bare, Some(1), true),
+            // git/checkouts/<repo>/<revision>
+            checkouts: count_entries(&paths.git_checkouts, Some(2), true),
+        }
+    }
+}
+
+impl fmt::Display for CacheCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cargo cache '{}':\n", self.cargo_home.display())?;
+        writeln!(f, "Total files: {}", self.files)?;
+        writeln!(f, "  {} installed binaries", self.binaries)?;
+        writeln!(f, "  Registry:")?;
+        writeln!(f, "    {} registry index files", self.index_files)?;
+        writeln!(f, "    {} crate archives", self.archives)?;
+        writeln!(f, "    {} crate source checkouts", self.sources)?;
+        writeln!(f, "  Git db:")?;
+        writeln!(f, "    {} bare git repos", self.repos)?;
+        writeln!(f, "    {} git repo checkouts", self.checkouts)
+    }
+}
+
+#[cfg(test)]
+mod counts_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn counts_of_cargo_home() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path();
+        let paths = CargoCachePaths {
+            cargo_home: home.to_path_buf(),
+            bin_dir: home.join("bin"),
+            registry: home.join("registry"),
+            registry_pkg_cache: home.join("registry").join("cache"),
+            registry_sources: home.join("registry").join("src"),
+            registry_index: home.join("registry").join("index"),
+            git_repos_bare: home.join("git").join("db"),
+            git_checkouts: home.join("git").join("checkouts"),
+        };
+        let registry = "github.com-1ecc6299db9ec823";
+        let source = paths.registry_sources.join(registry).join("foo-0.1.0");
+        let checkout = paths
+            .git_checkouts
+            .join("bar-0123456789abcdef")
+            .join("3a6eccd");
+        let repo = paths.git_repos_bare.join("bar-0123456789abcdef");
+        for item_dir in &[
+            &paths.bin_dir,
+            &paths.registry_pkg_cache.join(registry),
+            &paths.registry_index.join(registry).join("fo"),
+            &source.join("src"),
+            &checkout,
+            &repo,
+        ] {
+            fs::create_dir_all(item_dir).unwrap();
+        }
+        for file in &[
+            paths.bin_dir.join("cargo-cache"),
+            paths
+                .registry_pkg_cache
+                .join(registry)
+                .join("foo-0.1.0.crate"),
+            paths
+                .registry_pkg_cache
+                .join(registry)
+                .join("foo-0.2.0.crate"),
+            paths.registry_index.join(registry).join("fo").join("foo"),
+            source.join("src").join("lib.rs"),
+            source.join("Cargo.toml"),
+            checkout.join("Cargo.toml"),
+            repo.join("HEAD"),
+        ] {
+            fs::write(file, b"").unwrap();
+        }
+
+        let counts = CacheCounts::new(&paths);
+        assert_eq!(
+            counts,
+            CacheCounts {
+                cargo_home: home.to_path_buf(),
+                files: 8,
+                binaries: 1,
+                index_files: 1,
+                archives: 2,
+                sources: 1,
+                repos: 1,
+                checkouts: 1,
+            }
+        );
+        assert_eq!(
+            counts.to_string(),
+            format!(
+                "Cargo cache '{}':
+
+Total files: 8
+  1 installed binaries
+  Registry:
+    1 registry index files
+    2 crate archives
+    1 crate source checkouts
+  Git db:
+    1 bare git repos
+    1 git repo checkouts
+",
+                home.display()
+            )
+        );
+        let output = crate::output::to_json_at(&counts, crate::output::test_time());
+        assert_eq!(
+            crate::output::schema_violations(COUNTS_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn missing_dirs_count_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(count_entries(&dir.path().join("missing"), None, false), 0);
+        assert_eq!(count_entries(&dir.path().join("missing"), Some(1), true), 0);
+    }
+}
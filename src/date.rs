@@ -11,6 +11,7 @@ use crate::cache::caches::{Cache, RegistrySuperCache};
 use crate::cache::*;
 use crate::library::*;
 use crate::remove::*;
+use crate::settings::Settings;
 
 use chrono::{prelude::*, NaiveDateTime};
 use regex::Regex;
@@ -129,8 +130,10 @@ fn filter_files_by_date<'a>(
 }
 
 /// removes files that are older than $date from the cache, dirs can be specified
+/// files of the `keep_crates` are never removed
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn remove_files_by_dates(
+    settings: &Settings,
     // we need to know which part of the cargo-cache we need to clear out!
     checkouts_cache: &mut git_checkouts::GitCheckoutCache,
     bare_repos_cache: &mut git_bare_repos::GitRepoCache,
@@ -138,6 +141,8 @@ pub(crate) fn remove_files_by_dates(
     registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
     arg_younger: Option<&str>,
     arg_older: Option<&str>,
+    // crates that are never removed (--keep-crate)
+    keep_crates: &[&str],
     dry_run: bool,
     dirs: Option<&str>,
     mut size_changed: &mut bool,
@@ -183,6 +188,7 @@ pub(crate) fn remove_files_by_dates(
     // for each file, get the access time
     let mut dates: Vec<FileWithDate> = files_of_components
         .into_iter()
+        .filter(|path| !is_kept_crate(path, keep_crates))
         .map(|path| {
             let access_time = path.metadata().unwrap().accessed().unwrap();
             let naive_datetime = chrono::DateTime::<Local>::from(access_time).naive_local();
@@ -229,7 +235,8 @@ pub(crate) fn remove_files_by_dates(
             .map(|fwd| &fwd.file)
             //.inspect(|p| println!("{}", p.display()))
             .for_each(|path| {
-                remove_file(
+                let _ = remove_file(
+                    settings,
                     path,
                     false,
                     &mut size_changed,
@@ -265,9 +272,57 @@ pub(crate) fn remove_files_by_dates(
 #[cfg(test)]
 mod libtests {
     use super::*;
+    use std::sync::Arc;
 
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn remove_by_date_keeps_crates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = tmp.path().join("registry");
+        let archives = registry.join("cache").join("github.com-1ecc6299db9ec823");
+        let foo = registry
+            .join("src")
+            .join("github.com-1ecc6299db9ec823")
+            .join("foo-0.1.0");
+        std::fs::create_dir_all(&archives).unwrap();
+        std::fs::create_dir_all(&foo).unwrap();
+        std::fs::write(archives.join("foo-0.1.0.crate"), b"1234").unwrap();
+        std::fs::write(archives.join("bar-0.2.0.crate"), b"1234").unwrap();
+        std::fs::write(foo.join("Cargo.toml"), b"1234").unwrap();
+
+        let mut checkouts =
+            git_checkouts::GitCheckoutCache::new(tmp.path().join("git/checkouts"), Arc::default());
+        let mut bare_repos =
+            git_bare_repos::GitRepoCache::new(tmp.path().join("git/db"), Arc::default());
+        let mut pkgs =
+            registry_pkg_cache::RegistryPkgCaches::new(registry.join("cache"), Arc::default());
+        let mut sources =
+            registry_sources::RegistrySourceCaches::new(registry.join("src"), Arc::default());
+
+        let mut size_changed = false;
+        // everything was accessed before 2100
+        remove_files_by_dates(
+            &Settings::default(),
+            &mut checkouts,
+            &mut bare_repos,
+            &mut pkgs,
+            &mut sources,
+            None,
+            Some("2100.01.01"),
+            &["foo"],
+            false,
+            Some("registry"),
+            &mut size_changed,
+        )
+        .unwrap();
+
+        assert!(size_changed);
+        assert!(archives.join("foo-0.1.0.crate").exists());
+        assert!(foo.join("Cargo.toml").exists());
+        assert!(!archives.join("bar-0.2.0.crate").exists());
+    }
+
     #[test]
     fn parse_dates() {
         assert!(parse_date(&String::new()).is_err());
@@ -12,15 +12,22 @@
 /// The new() method does parallel processing to a bit of time
 use std::fmt;
 
+use crate::cache::archive_sources::{archives_without_source, match_archives_to_sources};
 use crate::cache::caches::Cache;
 use crate::cache::caches::RegistrySubCache;
 use crate::cache::caches::RegistrySuperCache;
 
 use crate::cache::*;
+use crate::exclude;
 use crate::library::*;
+use crate::network_fs;
+use crate::output::{to_json, OutputFormat};
+use crate::profile;
+use crate::settings::Settings;
 use crate::tables::*;
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
+use serde::Serialize;
 
 /// Holds the sizes and the number of files of the components of the cargo cache
 // useful for saving a "snapshot" of the current state of the cache
@@ -58,11 +65,16 @@ pub(crate) struct DirSizes<'a> {
     numb_reg_src_checkouts: usize,
     /// root path of the cache
     root_path: &'a std::path::PathBuf,
+    /// the network filesystem of the git db if its bare repos were not sized, see "--force-git"
+    git_skipped: Option<String>,
+    /// the settings the sizes are shown with
+    settings: &'a Settings,
 }
 
 impl<'a> DirSizes<'a> {
     /// create a new `DirSize` object by querying the caches for their data, done in parallel
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         bin_cache: &mut bin::BinaryCache,
         checkouts_cache: &mut git_checkouts::GitCheckoutCache,
@@ -71,6 +83,7 @@ impl<'a> DirSizes<'a> {
         registry_index_caches: &mut registry_index::RegistryIndicesCache,
         registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
         ccd: &'a CargoCachePaths,
+        settings: &'a Settings,
     ) -> Self {
         let mut reg_index_size: Option<u64> = None;
         let mut bin_dir_size: Option<u64> = None;
@@ -83,34 +96,54 @@ impl<'a> DirSizes<'a> {
         let mut total_reg_cache_entries: Option<usize> = None;
         let mut total_reg_src_size: Option<u64> = None;
         let mut numb_reg_src_checkouts: Option<usize> = None;
+        let git_skipped = network_fs::skipped_git_fs(settings, &ccd.git_repos_bare);
 
         rayon::scope(|s| {
-            // spawn one thread per cache
-            s.spawn(|_| reg_index_size = Some(registry_index_caches.total_size()));
+            // spawn one thread per cache, "--profile" measures each of them
+            s.spawn(|_| {
+                profile::time(settings, "registry_index", || {
+                    reg_index_size = Some(registry_index_caches.total_size());
+                });
+            });
 
             s.spawn(|_| {
-                bin_dir_size = Some(bin_cache.total_size());
-                numb_bins = Some(bin_cache.number_of_files());
+                profile::time(settings, "bin", || {
+                    bin_dir_size = Some(bin_cache.total_size());
+                    numb_bins = Some(bin_cache.number_of_files());
+                });
             });
 
             s.spawn(|_| {
-                total_git_repos_bare_size = Some(bare_repos_cache.total_size());
-                numb_git_repos_bare_repos = Some(bare_repos_cache.number_of_items());
+                profile::time(settings, "git_db", || {
+                    if git_skipped.is_some() {
+                        total_git_repos_bare_size = Some(0);
+                        numb_git_repos_bare_repos = Some(0);
+                    } else {
+                        total_git_repos_bare_size = Some(bare_repos_cache.total_size());
+                        numb_git_repos_bare_repos = Some(bare_repos_cache.number_of_items());
+                    }
+                });
             });
 
             s.spawn(|_| {
-                total_git_chk_size = Some(checkouts_cache.total_size());
-                numb_git_checkouts = Some(checkouts_cache.number_of_items());
+                profile::time(settings, "git_checkouts", || {
+                    total_git_chk_size = Some(checkouts_cache.total_size());
+                    numb_git_checkouts = Some(checkouts_cache.number_of_items());
+                });
             });
 
             s.spawn(|_| {
-                total_reg_cache_size = Some(registry_pkg_cache.total_size());
-                total_reg_cache_entries = Some(registry_pkg_cache.total_number_of_files());
+                profile::time(settings, "registry_cache", || {
+                    total_reg_cache_size = Some(registry_pkg_cache.total_size());
+                    total_reg_cache_entries = Some(registry_pkg_cache.total_number_of_files());
+                });
             });
 
             s.spawn(|_| {
-                total_reg_src_size = Some(registry_sources_caches.total_size());
-                numb_reg_src_checkouts = Some(registry_sources_caches.number_of_items());
+                profile::time(settings, "registry_sources", || {
+                    total_reg_src_size = Some(registry_sources_caches.total_size());
+                    numb_reg_src_checkouts = Some(registry_sources_caches.number_of_items());
+                });
             });
         });
 
@@ -139,6 +172,8 @@ impl<'a> DirSizes<'a> {
             numb_reg_cache_entries: total_reg_cache_entries.unwrap(), // number of source archives
             numb_reg_src_checkouts: numb_reg_src_checkouts.unwrap(),  // number of source checkouts
             root_path,
+            git_skipped,
+            settings,
         }
     }
 
@@ -181,6 +216,10 @@ impl<'a> DirSizes<'a> {
     pub(crate) fn total_reg_index_num(&self) -> u64 {
         self.total_reg_index_num
     }
+    /// the network filesystem of the git db if its bare repos were not sized
+    pub(crate) fn git_skipped(&self) -> Option<&str> {
+        self.git_skipped.as_deref()
+    }
     pub(crate) fn numb_reg_cache_entries(&self) -> usize {
         self.numb_reg_cache_entries
     }
@@ -192,109 +231,399 @@ impl<'a> DirSizes<'a> {
     }
 }
 
+/// the default summary in a machine readable form (cmd: "cargo cache --format json")
+/// sizes are in bytes, counts are the number of items the size was gathered from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct SummaryJson {
+    /// the cargo home the summary describes
+    cargo_home: std::path::PathBuf,
+    total_size: u64,
+    /// sum of the other counts, except for the number of registry indices
+    total_count: usize,
+    bin_size: u64,
+    bin_count: usize,
+    registry_size: u64,
+    registry_index_size: u64,
+    /// the number of registry indices, not of their files, so it is not part of the total count
+    registry_indices: usize,
+    registry_cache_size: u64,
+    registry_cache_count: usize,
+    registry_sources_size: u64,
+    registry_sources_count: usize,
+    git_size: u64,
+    git_db_size: u64,
+    git_db_count: usize,
+    git_checkouts_size: u64,
+    git_checkouts_count: usize,
+    /// the network filesystem the git db is on, its bare repos were not scanned and their size and count are 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_skipped: Option<String>,
+    /// the "--exclude-glob" patterns, the sizes and counts do not include the files they match
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    excluded_globs: Vec<String>,
+}
+
+/// JSON Schema (draft 7) of the json summary, including the "timings" of "--profile" and the "warnings"
+/// the tests check the output against it, so fields can not be added or renamed by accident
+#[cfg(test)]
+pub(crate) const SUMMARY_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "cargo_home": { "type": "string" },
+    "total_size": { "type": "integer", "minimum": 0 },
+    "total_count": { "type": "integer", "minimum": 0 },
+    "bin_size": { "type": "integer", "minimum": 0 },
+    "bin_count": { "type": "integer", "minimum": 0 },
+    "registry_size": { "type": "integer", "minimum": 0 },
+    "registry_index_size": { "type": "integer", "minimum": 0 },
+    "registry_indices": { "type": "integer", "minimum": 0 },
+    "registry_cache_size": { "type": "integer", "minimum": 0 },
+    "registry_cache_count": { "type": "integer", "minimum": 0 },
+    "registry_sources_size": { "type": "integer", "minimum": 0 },
+    "registry_sources_count": { "type": "integer", "minimum": 0 },
+    "git_size": { "type": "integer", "minimum": 0 },
+    "git_db_size": { "type": "integer", "minimum": 0 },
+    "git_db_count": { "type": "integer", "minimum": 0 },
+    "git_checkouts_size": { "type": "integer", "minimum": 0 },
+    "git_checkouts_count": { "type": "integer", "minimum": 0 },
+    "git_skipped": { "type": "string" },
+    "excluded_globs": {
+      "type": "array",
+      "items": { "type": "string" }
+    },
+    "timings": {
+      "type": "object",
+      "additionalProperties": { "type": "integer", "minimum": 0 }
+    },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": [
+    "schema_version",
+    "generated_at",
+    "cargo_home",
+    "total_size",
+    "total_count",
+    "bin_size",
+    "bin_count",
+    "registry_size",
+    "registry_index_size",
+    "registry_indices",
+    "registry_cache_size",
+    "registry_cache_count",
+    "registry_sources_size",
+    "registry_sources_count",
+    "git_size",
+    "git_db_size",
+    "git_db_count",
+    "git_checkouts_size",
+    "git_checkouts_count"
+  ],
+  "additionalProperties": false
+}"#;
+
+/// only the total size, for status checks (cmd: "cargo cache --quiet --format json")
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct TotalJson {
+    total_size: u64,
+}
+
+/// JSON Schema (draft 7) of the total size
+#[cfg(test)]
+pub(crate) const TOTAL_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache --quiet --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "total_size": { "type": "integer", "minimum": 0 },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "total_size"],
+  "additionalProperties": false
+}"#;
+
+impl DirSizes<'_> {
+    /// returns only the human readable total size (cmd: "cargo cache --quiet")
+    pub(crate) fn total_summary(&self) -> String {
+        human_size(self.settings, self.total_size())
+    }
+
+    /// returns the total size as a struct that can be serialized
+    pub(crate) fn total_json(&self) -> TotalJson {
+        TotalJson {
+            total_size: self.total_size(),
+        }
+    }
+
+    /// returns the summary as a struct that can be serialized
+    pub(crate) fn summary_json(&self) -> SummaryJson {
+        SummaryJson {
+            cargo_home: self.root_path().clone(),
+            total_size: self.total_size(),
+            total_count: self.numb_bins()
+                + self.numb_reg_cache_entries()
+                + self.numb_reg_src_checkouts()
+                + self.numb_git_repos_bare_repos()
+                + self.numb_git_checkouts(),
+            bin_size: self.total_bin_size(),
+            bin_count: self.numb_bins(),
+            registry_size: self.total_reg_size(),
+            registry_index_size: self.total_reg_index_size(),
+            #[allow(clippy::cast_possible_truncation)]
+            registry_indices: self.total_reg_index_num() as usize,
+            registry_cache_size: self.total_reg_cache_size(),
+            registry_cache_count: self.numb_reg_cache_entries(),
+            registry_sources_size: self.total_reg_src_size(),
+            registry_sources_count: self.numb_reg_src_checkouts(),
+            git_size: self.total_git_db_size(),
+            git_db_size: self.total_git_repos_bare_size(),
+            git_db_count: self.numb_git_repos_bare_repos(),
+            git_checkouts_size: self.total_git_chk_size(),
+            git_checkouts_count: self.numb_git_checkouts(),
+            git_skipped: self.git_skipped.clone(),
+            excluded_globs: exclude::patterns(self.settings),
+        }
+    }
+}
+
+/// the size of a part of the cache before and after removing items, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct SizeChange {
+    before: u64,
+    after: u64,
+    /// negative if the size shrunk
+    delta: i64,
+}
+
+impl SizeChange {
+    fn new(before: u64, after: u64) -> Self {
+        #[allow(clippy::cast_possible_wrap)]
+        let delta = after as i64 - before as i64;
+        Self {
+            before,
+            after,
+            delta,
+        }
+    }
+}
+
+/// how the size of the cache changed (cmd: "cargo cache --autoclean --format json" and other removals)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct SizeDifferenceJson {
+    cargo_home: std::path::PathBuf,
+    total: SizeChange,
+    bin: SizeChange,
+    registry: SizeChange,
+    registry_index: SizeChange,
+    registry_cache: SizeChange,
+    registry_sources: SizeChange,
+    git: SizeChange,
+    git_db: SizeChange,
+    git_checkouts: SizeChange,
+}
+
+/// JSON Schema (draft 7) of how the size of the cache changed
+#[cfg(test)]
+pub(crate) const SIZE_DIFFERENCE_SCHEMA: &str = r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache --autoclean --format json",
+  "type": "object",
+  "definitions": {
+    "size_change": {
+      "type": "object",
+      "properties": {
+        "before": { "type": "integer", "minimum": 0 },
+        "after": { "type": "integer", "minimum": 0 },
+        "delta": { "type": "integer" }
+      },
+      "required": ["before", "after", "delta"],
+      "additionalProperties": false
+    }
+  },
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "cargo_home": { "type": "string" },
+    "total": { "$ref": "#/definitions/size_change" },
+    "bin": { "$ref": "#/definitions/size_change" },
+    "registry": { "$ref": "#/definitions/size_change" },
+    "registry_index": { "$ref": "#/definitions/size_change" },
+    "registry_cache": { "$ref": "#/definitions/size_change" },
+    "registry_sources": { "$ref": "#/definitions/size_change" },
+    "git": { "$ref": "#/definitions/size_change" },
+    "git_db": { "$ref": "#/definitions/size_change" },
+    "git_checkouts": { "$ref": "#/definitions/size_change" },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": [
+    "schema_version",
+    "generated_at",
+    "cargo_home",
+    "total",
+    "bin",
+    "registry",
+    "registry_index",
+    "registry_cache",
+    "registry_sources",
+    "git",
+    "git_db",
+    "git_checkouts"
+  ],
+  "additionalProperties": false
+}"##;
+
+impl SizeDifferenceJson {
+    fn new(old: &DirSizes<'_>, new: &DirSizes<'_>) -> Self {
+        Self {
+            cargo_home: new.root_path().clone(),
+            total: SizeChange::new(old.total_size(), new.total_size()),
+            bin: SizeChange::new(old.total_bin_size(), new.total_bin_size()),
+            registry: SizeChange::new(old.total_reg_size(), new.total_reg_size()),
+            registry_index: SizeChange::new(old.total_reg_index_size(), new.total_reg_index_size()),
+            registry_cache: SizeChange::new(old.total_reg_cache_size(), new.total_reg_cache_size()),
+            registry_sources: SizeChange::new(old.total_reg_src_size(), new.total_reg_src_size()),
+            git: SizeChange::new(old.total_git_db_size(), new.total_git_db_size()),
+            git_db: SizeChange::new(
+                old.total_git_repos_bare_size(),
+                new.total_git_repos_bare_size(),
+            ),
+            git_checkouts: SizeChange::new(old.total_git_chk_size(), new.total_git_chk_size()),
+        }
+    }
+}
+
+/// the human readable sizes of the lines of the default summary
+struct ShownSizes {
+    total: String,
+    bin: String,
+    registry: String,
+    index: String,
+    archives: String,
+    sources: String,
+    git_db: String,
+    bare_repos: String,
+    checkouts: String,
+}
+
 impl<'a> DirSizes<'a> {
+    /// the human readable sizes of the lines of the default summary, "--reconcile" makes the sub-lines
+    /// add up to the shown totals
+    fn shown_sizes(&self) -> ShownSizes {
+        let total = human_size(self.settings, self.total_size());
+        let [bin, registry, git_db] = sub_line_sizes(
+            self.settings,
+            &total,
+            [
+                self.total_bin_size(),
+                self.total_reg_size(),
+                self.total_git_db_size(),
+            ],
+        );
+        let [index, archives, sources] = sub_line_sizes(
+            self.settings,
+            &registry,
+            [
+                self.total_reg_index_size(),
+                self.total_reg_cache_size(),
+                self.total_reg_src_size(),
+            ],
+        );
+        let [bare_repos, checkouts] = sub_line_sizes(
+            self.settings,
+            &git_db,
+            [self.total_git_repos_bare_size(), self.total_git_chk_size()],
+        );
+        ShownSizes {
+            total,
+            bin,
+            registry,
+            index,
+            archives,
+            sources,
+            git_db,
+            bare_repos,
+            checkouts,
+        }
+    }
+
     /// returns the header of the summary which contains the path to the cache and its total size
-    fn header(&self) -> Vec<TableLine> {
+    fn header(&self, shown: &ShownSizes) -> Vec<TableLine> {
         vec![
             TableLine::new(
                 0,
                 &format!("Cargo cache '{}':\n\n", &self.root_path().display()),
                 &String::new(),
             ),
-            TableLine::new(
-                0,
-                &"Total: ".to_string(),
-                &self
-                    .total_size()
-                    .file_size(file_size_opts::DECIMAL)
-                    .unwrap(),
-            ),
+            TableLine::new(0, &"Total: ".to_string(), &shown.total),
         ]
     }
 
     /// returns amount and size of installed crate binaries
-    fn bin(&self) -> Vec<TableLine> {
+    fn bin(&self, shown: &ShownSizes) -> Vec<TableLine> {
         vec![TableLine::new(
             1,
             &format!("{} installed binaries: ", self.numb_bins()),
-            &self
-                .total_bin_size()
-                .file_size(file_size_opts::DECIMAL)
-                .unwrap(),
+            &shown.bin,
         )]
     }
 
     /// returns amount and size of bare git repos and git repo checkouts
-    fn git(&self) -> Vec<TableLine> {
-        vec![
-            TableLine::new(
-                1,
-                &"Git db: ".to_string(),
-                &self
-                    .total_git_db_size()
-                    .file_size(file_size_opts::DECIMAL)
-                    .unwrap(),
-            ),
+    fn git(&self, shown: &ShownSizes) -> Vec<TableLine> {
+        let bare_repos = if self.git_skipped.is_some() {
+            TableLine::new(2, &"bare git repos: ", &"skipped (network fs)")
+        } else {
             TableLine::new(
                 2,
                 &format!("{} bare git repos: ", self.numb_git_repos_bare_repos()),
-                &self
-                    .total_git_repos_bare_size()
-                    .file_size(file_size_opts::DECIMAL)
-                    .unwrap(),
-            ),
+                &shown.bare_repos,
+            )
+        };
+        vec![
+            TableLine::new(1, &"Git db: ".to_string(), &shown.git_db),
+            bare_repos,
             TableLine::new(
                 2,
                 &format!("{} git repo checkouts: ", self.numb_git_checkouts()),
-                &self
-                    .total_git_chk_size()
-                    .file_size(file_size_opts::DECIMAL)
-                    .unwrap(),
+                &shown.checkouts,
             ),
         ]
     }
 
     /// returns summary of sizes of registry indices and registries (both, .crate archives and the extracted sources)
-    fn registries_summary(&self) -> Vec<TableLine> {
-        let tl1 = TableLine::new(
-            1,
-            &"Registry: ".to_string(),
-            &self
-                .total_reg_size()
-                .file_size(file_size_opts::DECIMAL)
-                .unwrap(),
-        );
+    fn registries_summary(&self, shown: &ShownSizes) -> Vec<TableLine> {
+        let tl1 = TableLine::new(1, &"Registry: ".to_string(), &shown.registry);
 
         let left = if let 1 = self.total_reg_index_num {
             String::from("Registry index: ")
         } else {
             format!("{} registry indices: ", &self.total_reg_index_num())
         };
-        let tl2 = TableLine::new(
-            2,
-            &left,
-            &self
-                .total_reg_index_size()
-                .file_size(file_size_opts::DECIMAL)
-                .unwrap(),
-        );
+        let tl2 = TableLine::new(2, &left, &shown.index);
 
         let tl3 = TableLine::new(
             2,
             &format!("{} crate archives: ", self.numb_reg_cache_entries()),
-            &self
-                .total_reg_cache_size()
-                .file_size(file_size_opts::DECIMAL)
-                .unwrap(),
+            &shown.archives,
         );
 
         let tl4 = TableLine::new(
             2,
             &format!("{} crate source checkouts: ", self.numb_reg_src_checkouts()),
-            &self
-                .total_reg_src_size()
-                .file_size(file_size_opts::DECIMAL)
-                .unwrap(),
+            &shown.sources,
         );
 
         vec![tl1, tl2, tl3, tl4]
@@ -306,128 +635,108 @@ impl<'a> DirSizes<'a> {
         index_caches: &mut registry_index::RegistryIndicesCache,
         registry_sources: &mut registry_sources::RegistrySourceCaches,
         pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+        line_order: RegistryLineOrder,
+        extraction_ratio: bool,
+        split_index: bool,
     ) -> Vec<TableLine> {
         let mut v: Vec<TableLine> = vec![];
 
-        // we need to match the separate registries together somehow
-        // do this by folder names
-        let mut registries: Vec<String> = vec![];
-        index_caches.caches().iter().for_each(|registry| {
-            registries.push(
-                registry
-                    .path()
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-            );
-        });
-
-        pkg_caches.caches().iter().for_each(|registry| {
-            registries.push(
-                registry
-                    .path()
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-            );
-        });
-
-        registry_sources.caches().iter().for_each(|registry| {
-            registries.push(
-                registry
-                    .path()
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-            );
-        });
-        // we now collected all the folder names of the registries and can match a single registry across multiple
-        // caches by this
-
-        /*
-          Registry:                         1.52 GB
-            5 registry indices:           250.20 MB
-            5399 crate archives:          805.46 MB
-            901 crate source checkouts:   460.77 MB
-        */
-
-        registries.sort();
-        registries.dedup();
-
-        for registry in &registries {
-            let mut total_size = 0;
-
-            let mut temp_vec: Vec<TableLine> = Vec::new();
-            let mut registry_name: Option<String> = None;
-
-            for index in index_caches.caches().iter_mut().filter(|r| {
-                &r.path().file_name().unwrap().to_str().unwrap().to_string() == registry
-            }) {
-                temp_vec.push(TableLine::new(
-                    2,
-                    &String::from("Registry index:"),
-                    &index
-                        .total_size()
-                        .file_size(file_size_opts::DECIMAL)
-                        .unwrap(),
+        for registry in registry_summaries(
+            index_caches,
+            registry_sources,
+            pkg_caches,
+            extraction_ratio,
+            split_index,
+        ) {
+            // (size, line) so that the lines can be sorted by size
+            let mut temp_vec: Vec<(u64, TableLine)> = Vec::new();
+
+            if let Some(split) = registry.index_split {
+                temp_vec.push((
+                    split.git_size,
+                    TableLine::new(
+                        2,
+                        &String::from("index (git): "),
+                        &split.git_size.file_size(size_opts(self.settings)).unwrap(),
+                    ),
+                ));
+                temp_vec.push((
+                    split.cache_size,
+                    TableLine::new(
+                        2,
+                        &String::from("index (.cache): "),
+                        &split
+                            .cache_size
+                            .file_size(size_opts(self.settings))
+                            .unwrap(),
+                    ),
+                ));
+            } else if registry.has_index {
+                temp_vec.push((
+                    registry.index_size,
+                    TableLine::new(
+                        2,
+                        &String::from("Registry index:"),
+                        &registry
+                            .index_size
+                            .file_size(size_opts(self.settings))
+                            .unwrap(),
+                    ),
                 ));
-                total_size += index.total_size();
-                if registry_name.is_none() {
-                    registry_name = Some(index.name().into());
-                }
             }
-
-            for pkg_cache in pkg_caches.caches().iter_mut().filter(|p| {
-                &p.path().file_name().unwrap().to_str().unwrap().to_string() == registry
-            }) {
-                temp_vec.push(TableLine::new(
-                    2,
-                    &format!("{} crate archives: ", pkg_cache.number_of_files()),
-                    &pkg_cache
-                        .total_size()
-                        .file_size(file_size_opts::DECIMAL)
-                        .unwrap(),
+            if registry.has_archives {
+                temp_vec.push((
+                    registry.registry_cache_size,
+                    TableLine::new(
+                        2,
+                        &format!("{} crate archives: ", registry.registry_cache_count),
+                        &registry
+                            .registry_cache_size
+                            .file_size(size_opts(self.settings))
+                            .unwrap(),
+                    ),
                 ));
-                total_size += pkg_cache.total_size();
-                if registry_name.is_none() {
-                    registry_name = Some(pkg_cache.name().into());
-                }
             }
-
-            for registry_source in registry_sources.caches().iter_mut().filter(|s| {
-                &s.path().file_name().unwrap().to_str().unwrap().to_string() == registry
-            }) {
-                temp_vec.push(TableLine::new(
-                    2,
-                    &format!(
-                        "{} crate source checkouts: ",
-                        registry_source.number_of_items()
+            if registry.has_sources {
+                temp_vec.push((
+                    registry.registry_sources_size,
+                    TableLine::new(
+                        2,
+                        &format!(
+                            "{} crate source checkouts: ",
+                            registry.registry_sources_count
+                        ),
+                        &registry
+                            .registry_sources_size
+                            .file_size(size_opts(self.settings))
+                            .unwrap(),
                     ),
-                    &registry_source
-                        .total_size()
-                        .file_size(file_size_opts::DECIMAL)
-                        .unwrap(),
                 ));
-                total_size += registry_source.total_size();
-                if registry_name.is_none() {
-                    registry_name = Some(registry_source.name().into());
-                }
             }
 
             let header_line = TableLine::new(
                 1,
-                &format!("Registry: {}", registry_name.unwrap_or_default()),
-                &total_size.file_size(file_size_opts::DECIMAL).unwrap(),
+                &format!("Registry: {}", registry.display_name),
+                &registry
+                    .total_size
+                    .file_size(size_opts(self.settings))
+                    .unwrap(),
             );
 
+            if line_order == RegistryLineOrder::Size {
+                // biggest first, the sort is stable so equal sizes keep the default order
+                temp_vec.sort_by_key(|(size, _line)| std::cmp::Reverse(*size));
+            }
+
             v.push(header_line);
-            v.extend(temp_vec);
+            v.extend(temp_vec.into_iter().map(|(_size, line)| line));
+            if let Some(extracted) = registry.extracted_archives {
+                v.push(TableLine::new(
+                    2,
+                    &String::from("extracted: "),
+                    &extracted.to_string(),
+                ));
+            }
         }
 
         v
@@ -443,6 +752,7 @@ impl<'a> DirSizes<'a> {
         mut registry_pkgs_cache: &mut registry_pkg_cache::RegistryPkgCaches,
         mut registry_index_caches: &mut registry_index::RegistryIndicesCache,
         mut registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+        output_format: OutputFormat,
     ) {
         // Total:           x Mb => y MB
         fn cmp_total(old: &DirSizes<'_>, new: &DirSizes<'_>) -> Vec<TableLine> {
@@ -456,12 +766,12 @@ impl<'a> DirSizes<'a> {
                     0,
                     &"Total: ".to_string(),
                     &if old.total_size() == new.total_size() {
-                        old.total_size().file_size(file_size_opts::DECIMAL).unwrap()
+                        old.total_size().file_size(size_opts(old.settings)).unwrap()
                     } else {
                         format!(
                             "{} => {}",
-                            &old.total_size().file_size(file_size_opts::DECIMAL).unwrap(),
-                            &new.total_size().file_size(file_size_opts::DECIMAL).unwrap()
+                            &old.total_size().file_size(size_opts(old.settings)).unwrap(),
+                            &new.total_size().file_size(size_opts(old.settings)).unwrap()
                         )
                     },
                 ),
@@ -476,16 +786,16 @@ impl<'a> DirSizes<'a> {
                     &"Git db: ".to_string(),
                     &if old.total_git_db_size() == new.total_git_db_size() {
                         new.total_git_db_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap()
                     } else {
                         format!(
                             "{} => {}",
                             &old.total_git_db_size()
-                                .file_size(file_size_opts::DECIMAL)
+                                .file_size(size_opts(old.settings))
                                 .unwrap(),
                             &new.total_git_db_size()
-                                .file_size(file_size_opts::DECIMAL)
+                                .file_size(size_opts(old.settings))
                                 .unwrap()
                         )
                     },
@@ -506,16 +816,16 @@ impl<'a> DirSizes<'a> {
                     },
                     &if old.total_git_repos_bare_size() == new.total_git_repos_bare_size() {
                         new.total_git_repos_bare_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap()
                     } else {
                         format!(
                             "{} => {}",
                             &old.total_git_repos_bare_size()
-                                .file_size(file_size_opts::DECIMAL)
+                                .file_size(size_opts(old.settings))
                                 .unwrap(),
                             &new.total_git_repos_bare_size()
-                                .file_size(file_size_opts::DECIMAL)
+                                .file_size(size_opts(old.settings))
                                 .unwrap()
                         )
                     },
@@ -536,16 +846,16 @@ impl<'a> DirSizes<'a> {
                     },
                     &if old.total_git_chk_size() == new.total_git_chk_size() {
                         new.total_git_chk_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap()
                     } else {
                         format!(
                             "{} => {}",
                             &old.total_git_chk_size()
-                                .file_size(file_size_opts::DECIMAL)
+                                .file_size(size_opts(old.settings))
                                 .unwrap(),
                             &new.total_git_chk_size()
-                                .file_size(file_size_opts::DECIMAL)
+                                .file_size(size_opts(old.settings))
                                 .unwrap()
                         )
                     },
@@ -559,16 +869,16 @@ impl<'a> DirSizes<'a> {
                 &"Registry: ".to_string(),
                 &if old.total_reg_size() == new.total_reg_size() {
                     new.total_reg_size()
-                        .file_size(file_size_opts::DECIMAL)
+                        .file_size(size_opts(old.settings))
                         .unwrap()
                 } else {
                     format!(
                         "{} => {}",
                         &old.total_reg_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap(),
                         &new.total_reg_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap()
                     )
                 },
@@ -583,16 +893,16 @@ impl<'a> DirSizes<'a> {
                 },
                 &if old.total_reg_index_size() == new.total_reg_index_size() {
                     old.total_reg_index_size()
-                        .file_size(file_size_opts::DECIMAL)
+                        .file_size(size_opts(old.settings))
                         .unwrap()
                 } else {
                     format!(
                         "{} => {}",
                         &old.total_reg_index_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap(),
                         &new.total_reg_index_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap()
                     )
                 },
@@ -611,16 +921,16 @@ impl<'a> DirSizes<'a> {
                 },
                 &if old.total_reg_cache_size() == new.total_reg_cache_size() {
                     new.total_reg_cache_size()
-                        .file_size(file_size_opts::DECIMAL)
+                        .file_size(size_opts(old.settings))
                         .unwrap()
                 } else {
                     format!(
                         "{} => {}",
                         &old.total_reg_cache_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap(),
                         &new.total_reg_cache_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap(),
                     )
                 },
@@ -639,16 +949,16 @@ impl<'a> DirSizes<'a> {
                 },
                 &if old.total_reg_src_size() == new.total_reg_src_size() {
                     old.total_reg_src_size()
-                        .file_size(file_size_opts::DECIMAL)
+                        .file_size(size_opts(old.settings))
                         .unwrap()
                 } else {
                     format!(
                         "{} => {}",
                         &old.total_reg_src_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap(),
                         &new.total_reg_src_size()
-                            .file_size(file_size_opts::DECIMAL)
+                            .file_size(size_opts(old.settings))
                             .unwrap(),
                     )
                 },
@@ -657,6 +967,10 @@ impl<'a> DirSizes<'a> {
             vec![tl1, tl2, tl3, tl4]
         } // fn regs()
 
+        // list the items that could not be removed before the new sizes
+        let settings = cache_sizes_old.settings;
+        crate::remove::report_failed_removals(settings);
+
         // and requery it to let it do its thing
         let cache_sizes_new = DirSizes::new(
             &mut bin_cache,
@@ -666,11 +980,23 @@ impl<'a> DirSizes<'a> {
             &mut registry_index_caches,
             &mut registry_sources_caches,
             cargo_cache,
+            settings,
         );
 
+        if output_format == OutputFormat::Json {
+            println!(
+                "{}",
+                to_json(
+                    settings,
+                    &SizeDifferenceJson::new(cache_sizes_old, &cache_sizes_new)
+                )
+            );
+            return;
+        }
+
         let mut v = Vec::new();
         v.extend(cmp_total(cache_sizes_old, &cache_sizes_new));
-        v.extend(cache_sizes_new.bin());
+        v.extend(cache_sizes_new.bin(&cache_sizes_new.shown_sizes()));
         v.extend(regs(cache_sizes_old, &cache_sizes_new));
         v.extend(git(cache_sizes_old, &cache_sizes_new));
 
@@ -686,7 +1012,7 @@ impl<'a> DirSizes<'a> {
             // final summary line
             let final_line = format!(
                 "Size changed {}",
-                size_diff_format(total_size_old, total_size_new, true)
+                size_diff_format(settings, total_size_old, total_size_new, true)
             );
             summary.push_str(&final_line);
         }
@@ -695,19 +1021,381 @@ impl<'a> DirSizes<'a> {
     }
 } // print_size_difference()
 
+impl DirSizes<'_> {
+    /// the bin, registry and git blocks of the default summary in the requested order,
+    /// every block keeps its sub-lines
+    fn category_blocks(&self, order: CategoryOrder, shown: &ShownSizes) -> Vec<Vec<TableLine>> {
+        let mut blocks = vec![
+            (self.total_bin_size(), self.bin(shown)),
+            (self.total_reg_size(), self.registries_summary(shown)),
+            (self.total_git_db_size(), self.git(shown)),
+        ];
+        if order == CategoryOrder::Size {
+            // stable, blocks of the same size stay in the fixed order
+            blocks.sort_by(|(size_a, _), (size_b, _)| size_b.cmp(size_a));
+        }
+        blocks.into_iter().map(|(_size, lines)| lines).collect()
+    }
+
+    /// returns the default summary of cargo-cache (cmd: "cargo cache [--sort-categories size]")
+    pub(crate) fn summary(&self, order: CategoryOrder) -> String {
+        let shown = self.shown_sizes();
+        let mut table: Vec<TableLine> = vec![];
+        table.extend(self.header(&shown));
+        for block in self.category_blocks(order, &shown) {
+            table.extend(block);
+        }
+
+        two_row_table(2, table, false)
+    }
+}
+
 impl<'a> fmt::Display for DirSizes<'a> {
     /// returns the default summary of cargo-cache (cmd: "cargo cache")
     fn fmt(&self, f: &'_ mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut table: Vec<TableLine> = vec![];
-        table.extend(self.header());
-        table.extend(self.bin());
-        table.extend(self.registries_summary());
-        table.extend(self.git());
+        write!(f, "{}", self.summary(CategoryOrder::Fixed))?;
+        Ok(())
+    }
+}
 
-        let string: String = two_row_table(2, table, false);
+/// how many .crate archives were never extracted (cmd: "cargo cache --verbose")
+pub(crate) fn unextracted_archives_summary(
+    settings: &Settings,
+    pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources: &mut registry_sources::RegistrySourceCaches,
+) -> String {
+    let archives = pkg_caches.files();
+    let unextracted = archives_without_source(&archives, registry_sources.items());
+    let size: u64 = unextracted
+        .iter()
+        .map(|archive| size_of_path(settings, archive))
+        .sum();
+
+    format!(
+        "{} of {} crate archives were downloaded but never extracted: {}",
+        unextracted.len(),
+        archives.len(),
+        human_size(settings, size)
+    )
+}
 
-        write!(f, "{}", string)?;
-        Ok(())
+/// order of the bin, registry and git blocks of the default summary ("--sort-categories")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CategoryOrder {
+    /// bin, registry, git
+    Fixed,
+    /// biggest first
+    Size,
+}
+
+/// order of the index, archives and sources lines of each registry in "cargo cache registry"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RegistryLineOrder {
+    /// index, archives, sources
+    Type,
+    /// biggest first
+    Size,
+}
+
+/// the sizes of a single registry, gathered from its index, its archives and its sources
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct RegistrySummary {
+    /// the directory name of the registry, stable across runs: "github.com-1ecc6299db9ec823"
+    id: String,
+    /// the name of the registry for humans: "github.com"
+    display_name: String,
+    total_size: u64,
+    index_size: u64,
+    registry_cache_size: u64,
+    registry_cache_count: usize,
+    registry_sources_size: u64,
+    registry_sources_count: usize,
+    // which parts of the registry exist, a registry may for example only have archives
+    #[serde(skip)]
+    has_index: bool,
+    #[serde(skip)]
+    has_archives: bool,
+    #[serde(skip)]
+    has_sources: bool,
+    /// only computed with "--extraction-ratio"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extracted_archives: Option<ExtractionRatio>,
+    /// only computed with "--split-index"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index_split: Option<IndexSplit>,
+}
+
+/// the index of a registry split into the index itself and the ".cache" dir cargo keeps for lookups
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct IndexSplit {
+    git_size: u64,
+    cache_size: u64,
+}
+
+/// how many of the .crate archives of a registry have been extracted to sources
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct ExtractionRatio {
+    extracted: usize,
+    archives: usize,
+}
+
+impl ExtractionRatio {
+    /// match the archives of a single registry to its sources
+    fn new(archives: &[std::path::PathBuf], sources: &[std::path::PathBuf]) -> Self {
+        Self {
+            extracted: match_archives_to_sources(archives, sources)
+                .iter()
+                .filter(|archive| archive.source.is_some())
+                .count(),
+            archives: archives.len(),
+        }
+    }
+
+    /// the rounded percentage of extracted archives, 0 if there are no archives
+    fn percent(self) -> usize {
+        (self.extracted * 100 + self.archives / 2)
+            .checked_div(self.archives)
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for ExtractionRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} ({}%)",
+            self.extracted,
+            self.archives,
+            self.percent()
+        )
+    }
+}
+
+/// directory name of a registry cache, the same registry has the same one in all caches
+fn registry_dir_name(path: &std::path::Path) -> String {
+    path.file_name().unwrap().to_str().unwrap().to_string()
+}
+
+/// match the registries of the index, archive and source caches by their directory names
+/// and sum up their sizes, sorted by directory name ("id") so the json array has a stable order
+/// with `extraction_ratio`, the archives of each registry are matched to its sources as well
+/// with `split_index`, the ".cache" dir of each index is sized separately
+fn registry_summaries(
+    index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_sources: &mut registry_sources::RegistrySourceCaches,
+    pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    extraction_ratio: bool,
+    split_index: bool,
+) -> Vec<RegistrySummary> {
+    // we need to match the separate registries together somehow
+    // do this by folder names
+    let mut registries: Vec<String> = index_caches
+        .caches()
+        .iter()
+        .map(|registry| registry_dir_name(registry.path()))
+        .chain(
+            pkg_caches
+                .caches()
+                .iter()
+                .map(|registry| registry_dir_name(registry.path())),
+        )
+        .chain(
+            registry_sources
+                .caches()
+                .iter()
+                .map(|registry| registry_dir_name(registry.path())),
+        )
+        .collect();
+    registries.sort();
+    registries.dedup();
+
+    registries
+        .into_iter()
+        .map(|registry| {
+            let mut summary = RegistrySummary {
+                id: registry.clone(),
+                display_name: String::new(),
+                total_size: 0,
+                index_size: 0,
+                registry_cache_size: 0,
+                registry_cache_count: 0,
+                registry_sources_size: 0,
+                registry_sources_count: 0,
+                has_index: false,
+                has_archives: false,
+                has_sources: false,
+                extracted_archives: None,
+                index_split: None,
+            };
+            // the archives and sources of only this registry, for the extraction ratio
+            let mut archives = Vec::new();
+            let mut sources = Vec::new();
+            // the first cache that knows the name of the registry names it
+            let mut registry_name: Option<String> = None;
+
+            for index in index_caches
+                .caches()
+                .iter_mut()
+                .filter(|r| registry_dir_name(r.path()) == registry)
+            {
+                summary.index_size += index.total_size();
+                summary.has_index = true;
+                if split_index {
+                    let (git_size, cache_size) = index.split_size();
+                    let split = summary.index_split.get_or_insert_with(IndexSplit::default);
+                    split.git_size += git_size;
+                    split.cache_size += cache_size;
+                }
+                if registry_name.is_none() {
+                    registry_name = Some(index.name().into());
+                }
+            }
+
+            for pkg_cache in pkg_caches
+                .caches()
+                .iter_mut()
+                .filter(|p| registry_dir_name(p.path()) == registry)
+            {
+                summary.registry_cache_size += pkg_cache.total_size();
+                summary.registry_cache_count += pkg_cache.number_of_files();
+                summary.has_archives = true;
+                if extraction_ratio {
+                    archives.extend_from_slice(pkg_cache.files());
+                }
+                if registry_name.is_none() {
+                    registry_name = Some(pkg_cache.name().into());
+                }
+            }
+
+            for registry_source in registry_sources
+                .caches()
+                .iter_mut()
+                .filter(|s| registry_dir_name(s.path()) == registry)
+            {
+                summary.registry_sources_size += registry_source.total_size();
+                summary.registry_sources_count += registry_source.number_of_items();
+                summary.has_sources = true;
+                if extraction_ratio {
+                    sources.extend_from_slice(registry_source.items());
+                }
+                if registry_name.is_none() {
+                    registry_name = Some(registry_source.name().into());
+                }
+            }
+
+            if extraction_ratio && summary.has_archives {
+                summary.extracted_archives = Some(ExtractionRatio::new(&archives, &sources));
+            }
+            summary.total_size =
+                summary.index_size + summary.registry_cache_size + summary.registry_sources_size;
+            // fall back to the directory name instead of printing an empty name
+            summary.display_name = registry_name
+                .filter(|name| !name.is_empty())
+                .unwrap_or(registry);
+            summary
+        })
+        .collect()
+}
+
+/// the per-registry summary in a machine readable form (cmd: "cargo cache registry --format json")
+#[derive(Debug, Serialize)]
+pub(crate) struct RegistriesJson {
+    /// the cargo home the summary describes
+    cargo_home: std::path::PathBuf,
+    registries: Vec<RegistrySummary>,
+    /// the "--exclude-glob" patterns, the sizes and counts do not include the files they match
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    excluded_globs: Vec<String>,
+}
+
+/// JSON Schema (draft 7) of the per-registry summary
+#[cfg(test)]
+pub(crate) const REGISTRIES_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache registry --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "cargo_home": { "type": "string" },
+    "registries": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "id": { "type": "string" },
+          "display_name": { "type": "string" },
+          "total_size": { "type": "integer", "minimum": 0 },
+          "index_size": { "type": "integer", "minimum": 0 },
+          "registry_cache_size": { "type": "integer", "minimum": 0 },
+          "registry_cache_count": { "type": "integer", "minimum": 0 },
+          "registry_sources_size": { "type": "integer", "minimum": 0 },
+          "registry_sources_count": { "type": "integer", "minimum": 0 },
+          "extracted_archives": {
+            "type": "object",
+            "properties": {
+              "extracted": { "type": "integer", "minimum": 0 },
+              "archives": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["extracted", "archives"],
+            "additionalProperties": false
+          },
+          "index_split": {
+            "type": "object",
+            "properties": {
+              "git_size": { "type": "integer", "minimum": 0 },
+              "cache_size": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["git_size", "cache_size"],
+            "additionalProperties": false
+          }
+        },
+        "required": [
+          "id",
+          "display_name",
+          "total_size",
+          "index_size",
+          "registry_cache_size",
+          "registry_cache_count",
+          "registry_sources_size",
+          "registry_sources_count"
+        ],
+        "additionalProperties": false
+      }
+    },
+    "excluded_globs": {
+      "type": "array",
+      "items": { "type": "string" }
+    },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "cargo_home", "registries"],
+  "additionalProperties": false
+}"#;
+
+/// returns the per-registry summary as a struct that can be serialized
+pub(crate) fn per_registry_json(
+    settings: &Settings,
+    cargo_home: &std::path::Path,
+    index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_sources: &mut registry_sources::RegistrySourceCaches,
+    pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    extraction_ratio: bool,
+    split_index: bool,
+) -> RegistriesJson {
+    RegistriesJson {
+        cargo_home: cargo_home.to_path_buf(),
+        registries: registry_summaries(
+            index_caches,
+            registry_sources,
+            pkg_caches,
+            extraction_ratio,
+            split_index,
+        ),
+        excluded_globs: exclude::patterns(settings),
     }
 }
 
@@ -715,33 +1403,186 @@ impl<'a> fmt::Display for DirSizes<'a> {
 pub(crate) fn per_registry_summary(
     dir_size: &DirSizes<'_>,
     mut index_caches: &mut registry_index::RegistryIndicesCache,
-    mut pkg_caches: &mut registry_sources::RegistrySourceCaches,
-    mut registry_sources: &mut registry_pkg_cache::RegistryPkgCaches,
+    mut registry_sources: &mut registry_sources::RegistrySourceCaches,
+    mut pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    line_order: RegistryLineOrder,
+    extraction_ratio: bool,
+    split_index: bool,
 ) -> String {
+    let shown = dir_size.shown_sizes();
     let mut table: Vec<TableLine> = vec![];
-    table.extend(dir_size.header());
-    table.extend(dir_size.bin());
+    table.extend(dir_size.header(&shown));
+    table.extend(dir_size.bin(&shown));
     table.extend(dir_size.registries_seperate(
         &mut index_caches,
-        &mut pkg_caches,
         &mut registry_sources,
+        &mut pkg_caches,
+        line_order,
+        extraction_ratio,
+        split_index,
     ));
-    table.extend(dir_size.git());
+    table.extend(dir_size.git(&shown));
 
     two_row_table(2, table, false)
 }
 
+/// the parts of the cache that "--only" can select
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Category {
+    Bin,
+    Registry,
+    Index,
+    Sources,
+    Git,
+    Checkouts,
+}
+
+/// the values "--only" accepts
+pub(crate) const CATEGORIES: &[&str] = &["git", "registry", "bin", "sources", "index", "checkouts"];
+
+impl std::str::FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "bin" => Ok(Self::Bin),
+            "registry" => Ok(Self::Registry),
+            "index" => Ok(Self::Index),
+            "sources" => Ok(Self::Sources),
+            "git" => Ok(Self::Git),
+            "checkouts" => Ok(Self::Checkouts),
+            other => Err(other.to_string()),
+        }
+    }
+}
+
+fn human_size(settings: &Settings, size: u64) -> String {
+    size.file_size(size_opts(settings)).unwrap()
+}
+
+/// returns a summary of only the selected categories (cmd: "cargo cache --only git")
+/// caches of categories that were not selected are not scanned at all
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn only_summary(
+    settings: &Settings,
+    categories: &[Category],
+    cargo_home: &std::path::Path,
+    bin_cache: &mut bin::BinaryCache,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    bare_repos_cache: &mut git_bare_repos::GitRepoCache,
+    registry_pkg_cache: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> String {
+    let mut selected = categories.to_vec();
+    selected.sort();
+    selected.dedup();
+
+    let mut table = vec![];
+
+    for category in selected {
+        match category {
+            Category::Bin => table.push(TableLine::new(
+                1,
+                &format!("{} installed binaries: ", bin_cache.number_of_files()),
+                &human_size(settings, bin_cache.total_size()),
+            )),
+            Category::Registry => {
+                let index_size = registry_index_caches.total_size();
+                let archives_size = registry_pkg_cache.total_size();
+                let sources_size = registry_sources_caches.total_size();
+                table.push(TableLine::new(
+                    1,
+                    &"Registry: ".to_string(),
+                    &human_size(settings, index_size + archives_size + sources_size),
+                ));
+                table.push(TableLine::new(
+                    2,
+                    &format!(
+                        "{} registry indices: ",
+                        registry_index_caches.number_of_subcaches()
+                    ),
+                    &human_size(settings, index_size),
+                ));
+                table.push(TableLine::new(
+                    2,
+                    &format!(
+                        "{} crate archives: ",
+                        registry_pkg_cache.total_number_of_files()
+                    ),
+                    &human_size(settings, archives_size),
+                ));
+                table.push(TableLine::new(
+                    2,
+                    &format!(
+                        "{} crate source checkouts: ",
+                        registry_sources_caches.number_of_items()
+                    ),
+                    &human_size(settings, sources_size),
+                ));
+            }
+            Category::Index => table.push(TableLine::new(
+                1,
+                &format!(
+                    "{} registry indices: ",
+                    registry_index_caches.number_of_subcaches()
+                ),
+                &human_size(settings, registry_index_caches.total_size()),
+            )),
+            Category::Sources => table.push(TableLine::new(
+                1,
+                &format!(
+                    "{} crate source checkouts: ",
+                    registry_sources_caches.number_of_items()
+                ),
+                &human_size(settings, registry_sources_caches.total_size()),
+            )),
+            Category::Git => {
+                let bare_repos_size = bare_repos_cache.total_size();
+                let checkouts_size = checkouts_cache.total_size();
+                table.push(TableLine::new(
+                    1,
+                    &"Git db: ".to_string(),
+                    &human_size(settings, bare_repos_size + checkouts_size),
+                ));
+                table.push(TableLine::new(
+                    2,
+                    &format!("{} bare git repos: ", bare_repos_cache.number_of_items()),
+                    &human_size(settings, bare_repos_size),
+                ));
+                table.push(TableLine::new(
+                    2,
+                    &format!("{} git repo checkouts: ", checkouts_cache.number_of_items()),
+                    &human_size(settings, checkouts_size),
+                ));
+            }
+            Category::Checkouts => table.push(TableLine::new(
+                1,
+                &format!("{} git repo checkouts: ", checkouts_cache.number_of_items()),
+                &human_size(settings, checkouts_cache.total_size()),
+            )),
+        }
+    }
+
+    format!(
+        "Cargo cache '{}':\n\n{}",
+        cargo_home.display(),
+        two_row_table(2, table, true)
+    )
+}
+
 #[cfg(test)]
 mod libtests {
     use super::*;
+    use std::sync::Arc;
 
     use pretty_assertions::assert_eq;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     impl<'a> DirSizes<'a> {
         #[allow(clippy::cast_possible_truncation, clippy::ptr_arg)]
         #[allow(non_snake_case)]
-        pub(super) fn new_manually(
+        pub(crate) fn new_manually(
             DI_bindir: &DirInfo,
             DI_git_repos_bare: &DirInfo,
             DI_git_checkout: &DirInfo,
@@ -783,10 +1624,117 @@ mod libtests {
                 total_reg_index_size: reg_index.dir_size,
                 total_reg_index_num: 1,
                 root_path: path,
+                git_skipped: None,
+                settings: Settings::for_tests(),
             }
         }
     }
 
+    #[test]
+    fn new_agrees_with_new_manually() {
+        // the other tests build their DirSizes with new_manually(), make sure new() agrees with it
+        // on a cargo home on disk whose sizes and counts are known
+        let dir = tempfile::tempdir().unwrap();
+        let ccd = CargoCachePaths::new(dir.path().to_path_buf()).unwrap();
+        let registry = "github.com-1ecc6299db9ec823";
+        let files: &[(PathBuf, usize)] = &[
+            (ccd.bin_dir.join("cargo-cache"), 3),
+            (ccd.bin_dir.join("rg"), 4),
+            (
+                ccd.git_repos_bare.join("bar-0123456789abcdef").join("HEAD"),
+                5,
+            ),
+            (
+                ccd.git_repos_bare
+                    .join("bar-0123456789abcdef")
+                    .join("objects")
+                    .join("pack-1.pack"),
+                10,
+            ),
+            (
+                ccd.git_checkouts
+                    .join("bar-0123456789abcdef")
+                    .join("3a6eccd")
+                    .join("lib.rs"),
+                6,
+            ),
+            (
+                ccd.registry_pkg_cache
+                    .join(registry)
+                    .join("foo-0.1.0.crate"),
+                8,
+            ),
+            (
+                ccd.registry_pkg_cache
+                    .join(registry)
+                    .join("foo-0.2.0.crate"),
+                9,
+            ),
+            (
+                ccd.registry_sources
+                    .join(registry)
+                    .join("foo-0.1.0")
+                    .join("lib.rs"),
+                11,
+            ),
+            (ccd.registry_index.join(registry).join("config.json"), 2),
+            (
+                ccd.registry_index
+                    .join(registry)
+                    .join(".cache")
+                    .join("fo")
+                    .join("foo"),
+                12,
+            ),
+        ];
+        for (file, size) in files {
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, vec![0; *size]).unwrap();
+        }
+
+        let mut bin_cache = bin::BinaryCache::new(ccd.bin_dir.clone(), Arc::default());
+        let mut checkouts_cache =
+            git_checkouts::GitCheckoutCache::new(ccd.git_checkouts.clone(), Arc::default());
+        let mut bare_repos_cache =
+            git_bare_repos::GitRepoCache::new(ccd.git_repos_bare.clone(), Arc::default());
+        let mut pkg_caches = registry_pkg_cache::RegistryPkgCaches::new(
+            ccd.registry_pkg_cache.clone(),
+            Arc::default(),
+        );
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(ccd.registry_index.clone(), Arc::default());
+        let mut source_caches = registry_sources::RegistrySourceCaches::new(
+            ccd.registry_sources.clone(),
+            Arc::default(),
+        );
+        let dir_sizes = DirSizes::new(
+            &mut bin_cache,
+            &mut checkouts_cache,
+            &mut bare_repos_cache,
+            &mut pkg_caches,
+            &mut index_caches,
+            &mut source_caches,
+            &ccd,
+            Settings::for_tests(),
+        );
+
+        // (size, number of items) of each cache
+        let info = |dir_size, file_number| DirInfo {
+            dir_size,
+            file_number,
+        };
+        let manually = DirSizes::new_manually(
+            &info(7, 2),  // 2 binaries
+            &info(15, 1), // 1 bare repo
+            &info(6, 1),  // 1 checkout
+            &info(17, 2), // 2 .crate archives
+            &info(11, 1), // 1 extracted source
+            &info(14, 1), // 1 index
+            &ccd.cargo_home,
+        );
+        assert_eq!(dir_sizes.summary_json(), manually.summary_json());
+    }
+
     #[allow(non_snake_case)]
     #[test]
     fn test_DirSizes() {
@@ -846,6 +1794,587 @@ Total:                                    1.94 GB
         assert_eq!(output_is, output_should);
     }
 
+    #[test]
+    fn summary_sorted_by_category_size() {
+        let dir_info = |dir_size, file_number| DirInfo {
+            dir_size,
+            file_number,
+        };
+        let pb = PathBuf::from("/home/user/.cargo");
+        // git > bin > registry
+        let dir_sizes = DirSizes::new_manually(
+            &dir_info(2100, 2),
+            &dir_info(3300, 3),
+            &dir_info(1200, 1),
+            &dir_info(100, 4),
+            &dir_info(200, 5),
+            &dir_info(300, 6),
+            &pb,
+        );
+
+        let output_should = "Cargo cache '/home/user/.cargo':
+
+Total:                          7.20 KB
+  Git db:                       4.50 KB
+    3 bare git repos:           3.30 KB
+    1 git repo checkouts:       1.20 KB
+  2 installed binaries:         2.10 KB
+  Registry:                      600  B
+    Registry index:              300  B
+    4 crate archives:            100  B
+    5 crate source checkouts:    200  B\n";
+        assert_eq!(dir_sizes.summary(CategoryOrder::Size), output_should);
+
+        // the default order is not changed
+        assert_eq!(
+            dir_sizes.summary(CategoryOrder::Fixed),
+            format!("{}", dir_sizes)
+        );
+        let fixed = dir_sizes.summary(CategoryOrder::Fixed);
+        assert!(fixed.find("installed binaries") < fixed.find("Git db"));
+    }
+
+    #[test]
+    fn skipped_git_db() {
+        let reg_cache = DirInfo {
+            dir_size: 77_000_000,
+            file_number: 23_445,
+        };
+        let empty = DirInfo {
+            dir_size: 0,
+            file_number: 0,
+        };
+        let root = PathBuf::from("/home/user/.cargo");
+        let mut dir_sizes =
+            DirSizes::new_manually(&empty, &empty, &empty, &reg_cache, &empty, &empty, &root);
+        dir_sizes.git_skipped = Some(String::from("nfs4"));
+
+        // only the bare repos are skipped, the checkouts are still sized
+        let summary = dir_sizes.summary(CategoryOrder::Fixed);
+        let git_lines = summary.lines().rev().take(3).collect::<Vec<_>>();
+        assert!(git_lines[2].starts_with("  Git db: "), "{}", summary);
+        assert!(
+            git_lines[1].starts_with("    bare git repos: ")
+                && git_lines[1].ends_with(" skipped (network fs)"),
+            "{}",
+            summary
+        );
+        assert!(
+            git_lines[0].starts_with("    0 git repo checkouts: "),
+            "{}",
+            summary
+        );
+        let json = serde_json::to_value(dir_sizes.summary_json()).unwrap();
+        assert_eq!(json["git_skipped"], "nfs4");
+    }
+
+    #[test]
+    fn total_json_is_only_the_total() {
+        let reg_cache = DirInfo {
+            dir_size: 77_000_000,
+            file_number: 23_445,
+        };
+        let empty = DirInfo {
+            dir_size: 0,
+            file_number: 0,
+        };
+        let root = PathBuf::from("/home/user/.cargo");
+        let dir_sizes =
+            DirSizes::new_manually(&empty, &empty, &empty, &reg_cache, &empty, &empty, &root);
+
+        let output = crate::output::to_json_at(&dir_sizes.total_json(), crate::output::test_time());
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "total_size": 77000000
+}"#;
+        assert_eq!(output, desired);
+        assert_eq!(
+            crate::output::schema_violations(TOTAL_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn summary_json_matches_text_summary() {
+        let bindir = DirInfo {
+            dir_size: 121_212,
+            file_number: 31,
+        };
+        let git_repos_bare = DirInfo {
+            dir_size: 5_432_100,
+            file_number: 37,
+        };
+        let git_checkouts = DirInfo {
+            dir_size: 34_984,
+            file_number: 8,
+        };
+        let reg_cache = DirInfo {
+            dir_size: 77_000_000,
+            file_number: 23_445,
+        };
+        let reg_src = DirInfo {
+            dir_size: 1_938_493_989,
+            file_number: 1_234,
+        };
+        let reg_index = DirInfo {
+            dir_size: 250_000_000,
+            file_number: 12_345,
+        };
+        let root = PathBuf::from("/home/user/.cargo");
+        let dir_sizes = DirSizes::new_manually(
+            &bindir,
+            &git_repos_bare,
+            &git_checkouts,
+            &reg_cache,
+            &reg_src,
+            &reg_index,
+            &root,
+        );
+
+        let json = serde_json::to_value(dir_sizes.summary_json()).unwrap();
+        let field = |name: &str| json[name].as_u64().unwrap();
+
+        // the json sizes add up
+        assert_eq!(
+            field("bin_size") + field("registry_size") + field("git_size"),
+            field("total_size")
+        );
+        assert_eq!(
+            field("registry_index_size")
+                + field("registry_cache_size")
+                + field("registry_sources_size"),
+            field("registry_size")
+        );
+        assert_eq!(
+            field("git_db_size") + field("git_checkouts_size"),
+            field("git_size")
+        );
+        assert_eq!(
+            field("bin_count")
+                + field("registry_cache_count")
+                + field("registry_sources_count")
+                + field("git_db_count")
+                + field("git_checkouts_count"),
+            field("total_count")
+        );
+
+        // the json counts are the counts of the DirSizes
+        assert_eq!(field("bin_count"), dir_sizes.numb_bins() as u64);
+        assert_eq!(
+            field("registry_cache_count"),
+            dir_sizes.numb_reg_cache_entries() as u64
+        );
+        assert_eq!(
+            field("registry_sources_count"),
+            dir_sizes.numb_reg_src_checkouts() as u64
+        );
+        assert_eq!(
+            field("git_db_count"),
+            dir_sizes.numb_git_repos_bare_repos() as u64
+        );
+        assert_eq!(
+            field("git_checkouts_count"),
+            dir_sizes.numb_git_checkouts() as u64
+        );
+
+        // every line of the text summary shows the same numbers as the json
+        let text = format!("{dir_sizes}");
+        let text_lines = text
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(label, size)| (label.trim().to_string(), size.trim().to_string()))
+            .collect::<std::collections::HashMap<_, _>>();
+        let expected_lines = vec![
+            (String::from("Total"), field("total_size")),
+            (
+                format!("{} installed binaries", field("bin_count")),
+                field("bin_size"),
+            ),
+            (String::from("Registry"), field("registry_size")),
+            (String::from("Registry index"), field("registry_index_size")),
+            (
+                format!("{} crate archives", field("registry_cache_count")),
+                field("registry_cache_size"),
+            ),
+            (
+                format!("{} crate source checkouts", field("registry_sources_count")),
+                field("registry_sources_size"),
+            ),
+            (String::from("Git db"), field("git_size")),
+            (
+                format!("{} bare git repos", field("git_db_count")),
+                field("git_db_size"),
+            ),
+            (
+                format!("{} git repo checkouts", field("git_checkouts_count")),
+                field("git_checkouts_size"),
+            ),
+        ];
+        for (label, size) in expected_lines {
+            assert_eq!(
+                text_lines.get(&label),
+                Some(&human_size(&Settings::default(), size)),
+                "text summary line \"{label}\" does not match the json"
+            );
+        }
+    }
+
+    #[test]
+    fn per_registry_json_has_id_and_display_name() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let registry_dir = cargo_home.path().join("registry");
+        let archives = registry_dir
+            .join("cache")
+            .join("github.com-1ecc6299db9ec823");
+        std::fs::create_dir_all(&archives).unwrap();
+        std::fs::write(archives.join("foo-0.1.0.crate"), b"12345").unwrap();
+        let index = registry_dir
+            .join("index")
+            .join("github.com-1ecc6299db9ec823")
+            .join(".cache");
+        std::fs::create_dir_all(&index).unwrap();
+        std::fs::write(index.join("foo"), b"123").unwrap();
+
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(registry_dir.join("index"), Arc::default());
+        let mut source_caches =
+            registry_sources::RegistrySourceCaches::new(registry_dir.join("src"), Arc::default());
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(registry_dir.join("cache"), Arc::default());
+
+        let registries = per_registry_json(
+            &Settings::default(),
+            Path::new("/home/user/.cargo"),
+            &mut index_caches,
+            &mut source_caches,
+            &mut pkg_caches,
+            false,
+            false,
+        );
+        let output_is = crate::output::to_json_at(&registries, crate::output::test_time());
+
+        let output_should = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "cargo_home": "/home/user/.cargo",
+  "registries": [
+    {
+      "id": "github.com-1ecc6299db9ec823",
+      "display_name": "github.com",
+      "total_size": 8,
+      "index_size": 3,
+      "registry_cache_size": 5,
+      "registry_cache_count": 1,
+      "registry_sources_size": 0,
+      "registry_sources_count": 0
+    }
+  ]
+}"#;
+        assert_eq!(output_is, output_should);
+        assert_eq!(
+            crate::output::schema_violations(REGISTRIES_SCHEMA, &output_is),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn per_registry_json_is_sorted_by_id() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let registry_dir = cargo_home.path().join("registry");
+        // registries that only have archives, only an index or only sources are merged into the same order
+        for (kind, registry) in &[
+            ("cache", "zeta-0123456789abcdef"),
+            ("index", "github.com-1ecc6299db9ec823"),
+            ("src", "alpha-0123456789abcdef"),
+            ("cache", "github.com-1ecc6299db9ec823"),
+            ("index", "my-registry-0123456789abcdef"),
+        ] {
+            std::fs::create_dir_all(registry_dir.join(kind).join(registry)).unwrap();
+        }
+
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(registry_dir.join("index"), Arc::default());
+        let mut source_caches =
+            registry_sources::RegistrySourceCaches::new(registry_dir.join("src"), Arc::default());
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(registry_dir.join("cache"), Arc::default());
+
+        let registries = per_registry_json(
+            &Settings::default(),
+            cargo_home.path(),
+            &mut index_caches,
+            &mut source_caches,
+            &mut pkg_caches,
+            false,
+            false,
+        );
+        let ids = registries
+            .registries
+            .iter()
+            .map(|registry| registry.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                "alpha-0123456789abcdef",
+                "github.com-1ecc6299db9ec823",
+                "my-registry-0123456789abcdef",
+                "zeta-0123456789abcdef",
+            ]
+        );
+    }
+
+    #[test]
+    fn registries_seperate_partial_registry() {
+        // a registry that only has .crate archives, but neither an index nor sources
+        let cargo_home = tempfile::tempdir().unwrap();
+        let pkg_cache_dir = cargo_home.path().join("registry").join("cache");
+        let registry = pkg_cache_dir.join("my-registry-0123456789abcdef");
+        std::fs::create_dir_all(&registry).unwrap();
+        std::fs::write(registry.join("foo-0.1.0.crate"), b"12345").unwrap();
+
+        let mut index_caches = registry_index::RegistryIndicesCache::new(
+            cargo_home.path().join("registry").join("index"),
+            Arc::default(),
+        );
+        let mut source_caches = registry_sources::RegistrySourceCaches::new(
+            cargo_home.path().join("registry").join("src"),
+            Arc::default(),
+        );
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(pkg_cache_dir, Arc::default());
+
+        let empty = DirInfo {
+            dir_size: 0,
+            file_number: 0,
+        };
+        let root = cargo_home.path().to_path_buf();
+        let dir_sizes =
+            DirSizes::new_manually(&empty, &empty, &empty, &empty, &empty, &empty, &root);
+
+        let table = dir_sizes.registries_seperate(
+            &mut index_caches,
+            &mut source_caches,
+            &mut pkg_caches,
+            RegistryLineOrder::Type,
+            false,
+            false,
+        );
+        let output_is = two_row_table(2, table, true);
+
+        let output_should = "  Registry: my-registry    5  B
+    1 crate archives:      5  B\n";
+
+        assert_eq!(output_is, output_should);
+    }
+
+    #[test]
+    fn registries_seperate_sorted_by_size() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let registry_dir = cargo_home.path().join("registry");
+        let archives = registry_dir
+            .join("cache")
+            .join("my-registry-0123456789abcdef");
+        std::fs::create_dir_all(&archives).unwrap();
+        std::fs::write(archives.join("foo-0.1.0.crate"), b"12345").unwrap();
+        let sources = registry_dir
+            .join("src")
+            .join("my-registry-0123456789abcdef")
+            .join("foo-0.1.0");
+        std::fs::create_dir_all(&sources).unwrap();
+        std::fs::write(sources.join("lib.rs"), b"1234567890").unwrap();
+
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(registry_dir.join("index"), Arc::default());
+        let mut source_caches =
+            registry_sources::RegistrySourceCaches::new(registry_dir.join("src"), Arc::default());
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(registry_dir.join("cache"), Arc::default());
+
+        let empty = DirInfo {
+            dir_size: 0,
+            file_number: 0,
+        };
+        let root = cargo_home.path().to_path_buf();
+        let dir_sizes =
+            DirSizes::new_manually(&empty, &empty, &empty, &empty, &empty, &empty, &root);
+
+        let table = dir_sizes.registries_seperate(
+            &mut index_caches,
+            &mut source_caches,
+            &mut pkg_caches,
+            RegistryLineOrder::Size,
+            false,
+            false,
+        );
+        let output_is = two_row_table(2, table, true);
+
+        // the sources are bigger than the archives, so they come first
+        let output_should = "  Registry: my-registry         15  B
+    1 crate source checkouts:   10  B
+    1 crate archives:            5  B\n";
+
+        assert_eq!(output_is, output_should);
+    }
+
+    #[test]
+    fn registries_seperate_split_index() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let registry_dir = cargo_home.path().join("registry");
+        let index = registry_dir
+            .join("index")
+            .join("my-registry-0123456789abcdef");
+        std::fs::create_dir_all(index.join(".cache").join("fo")).unwrap();
+        std::fs::write(index.join(".cache").join("fo").join("foo"), b"1234567890").unwrap();
+        std::fs::write(index.join("config.json"), b"12345").unwrap();
+
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(registry_dir.join("index"), Arc::default());
+        let mut source_caches =
+            registry_sources::RegistrySourceCaches::new(registry_dir.join("src"), Arc::default());
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(registry_dir.join("cache"), Arc::default());
+
+        let empty = DirInfo {
+            dir_size: 0,
+            file_number: 0,
+        };
+        let root = cargo_home.path().to_path_buf();
+        let dir_sizes =
+            DirSizes::new_manually(&empty, &empty, &empty, &empty, &empty, &empty, &root);
+
+        let table = dir_sizes.registries_seperate(
+            &mut index_caches,
+            &mut source_caches,
+            &mut pkg_caches,
+            RegistryLineOrder::Type,
+            false,
+            true,
+        );
+        let output_is = two_row_table(2, table, true);
+
+        // the single "Registry index" line is replaced by the two parts
+        let output_should = "  Registry: my-registry    15  B
+    index (git):            5  B
+    index (.cache):        10  B\n";
+
+        assert_eq!(output_is, output_should);
+    }
+
+    #[test]
+    fn registries_seperate_extraction_ratio() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let registry_dir = cargo_home.path().join("registry");
+        let archives = registry_dir
+            .join("cache")
+            .join("my-registry-0123456789abcdef");
+        std::fs::create_dir_all(&archives).unwrap();
+        std::fs::write(archives.join("foo-0.1.0.crate"), b"12345").unwrap();
+        std::fs::write(archives.join("bar-0.1.0.crate"), b"12345").unwrap();
+        // an archive of another registry with the same name is not counted as extracted
+        let other_sources = registry_dir
+            .join("src")
+            .join("other-0123456789abcdef")
+            .join("bar-0.1.0");
+        let sources = registry_dir
+            .join("src")
+            .join("my-registry-0123456789abcdef")
+            .join("foo-0.1.0");
+        for dir in &[&sources, &other_sources] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(registry_dir.join("index"), Arc::default());
+        let mut source_caches =
+            registry_sources::RegistrySourceCaches::new(registry_dir.join("src"), Arc::default());
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(registry_dir.join("cache"), Arc::default());
+
+        let empty = DirInfo {
+            dir_size: 0,
+            file_number: 0,
+        };
+        let root = cargo_home.path().to_path_buf();
+        let dir_sizes =
+            DirSizes::new_manually(&empty, &empty, &empty, &empty, &empty, &empty, &root);
+
+        let table = dir_sizes.registries_seperate(
+            &mut index_caches,
+            &mut source_caches,
+            &mut pkg_caches,
+            RegistryLineOrder::Type,
+            true,
+            false,
+        );
+        let output_is = two_row_table(2, table, true);
+
+        let output_should = "  Registry: my-registry             10  B
+    2 crate archives:               10  B
+    1 crate source checkouts:        0  B
+    extracted:                  1/2 (50%)
+  Registry: other                    0  B
+    1 crate source checkouts:        0  B\n";
+
+        assert_eq!(output_is, output_should);
+    }
+
+    #[test]
+    fn extraction_ratio_is_rounded() {
+        let ratio = |extracted, archives| ExtractionRatio {
+            extracted,
+            archives,
+        };
+        assert_eq!(ratio(901, 5399).to_string(), "901/5399 (17%)");
+        assert_eq!(ratio(1, 3).to_string(), "1/3 (33%)");
+        assert_eq!(ratio(0, 0).to_string(), "0/0 (0%)");
+    }
+
+    #[test]
+    fn only_selected_categories() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let bin_dir = cargo_home.path().join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join("cargo-foo"), b"12345").unwrap();
+
+        let registry = cargo_home.path().join("registry");
+        let mut bin_cache = bin::BinaryCache::new(bin_dir, Arc::default());
+        let mut checkouts_cache = git_checkouts::GitCheckoutCache::new(
+            cargo_home.path().join("git").join("checkouts"),
+            Arc::default(),
+        );
+        let mut bare_repos_cache = git_bare_repos::GitRepoCache::new(
+            cargo_home.path().join("git").join("db"),
+            Arc::default(),
+        );
+        let mut pkg_caches =
+            registry_pkg_cache::RegistryPkgCaches::new(registry.join("cache"), Arc::default());
+        let mut index_caches =
+            registry_index::RegistryIndicesCache::new(registry.join("index"), Arc::default());
+        let mut source_caches =
+            registry_sources::RegistrySourceCaches::new(registry.join("src"), Arc::default());
+
+        let output_is = only_summary(
+            &Settings::default(),
+            // duplicates are only printed once
+            &[Category::Index, Category::Bin, Category::Bin],
+            cargo_home.path(),
+            &mut bin_cache,
+            &mut checkouts_cache,
+            &mut bare_repos_cache,
+            &mut pkg_caches,
+            &mut index_caches,
+            &mut source_caches,
+        );
+
+        let output_should = format!(
+            "Cargo cache '{}':\n\n  1 installed binaries:   5  B\n  0 registry indices:     0  B\n",
+            cargo_home.path().display()
+        );
+        assert_eq!(output_is, output_should);
+    }
+
     #[allow(non_snake_case)]
     #[test]
     fn test_DirSizes_gigs() {
@@ -1021,6 +2550,199 @@ Total:                          0  B
 
         assert_eq!(output_is, output_should);
     }
+
+    #[test]
+    fn size_difference_json() {
+        let info = |dir_size| DirInfo {
+            dir_size,
+            file_number: 1,
+        };
+        let pb = PathBuf::from("/home/user/.cargo");
+        let old = DirSizes::new_manually(
+            &info(10),
+            &info(20),
+            &info(30),
+            &info(40),
+            &info(50),
+            &info(60),
+            &pb,
+        );
+        // checkouts and registry sources were removed
+        let new = DirSizes::new_manually(
+            &info(10),
+            &info(20),
+            &info(0),
+            &info(40),
+            &info(0),
+            &info(60),
+            &pb,
+        );
+
+        let diff = SizeDifferenceJson::new(&old, &new);
+        assert_eq!(diff.total, SizeChange::new(210, 130));
+        assert_eq!(diff.total.delta, -80);
+        assert_eq!(diff.bin.delta, 0);
+        assert_eq!(diff.registry, SizeChange::new(150, 100));
+        assert_eq!(diff.registry_sources.delta, -50);
+        assert_eq!(diff.git, SizeChange::new(50, 20));
+        assert_eq!(diff.git_checkouts.delta, -30);
+
+        let json = crate::output::to_json_at(&diff, crate::output::test_time());
+        assert!(json.contains(
+            "\"total\": {\n    \"before\": 210,\n    \"after\": 130,\n    \"delta\": -80\n  }"
+        ));
+        assert_eq!(
+            crate::output::schema_violations(SIZE_DIFFERENCE_SCHEMA, &json),
+            Vec::<String>::new()
+        );
+    }
+
+    #[allow(non_snake_case)]
+    #[test]
+    fn test_DirSizes_summary_json() {
+        let bindir = DirInfo {
+            dir_size: 121_212,
+            file_number: 31,
+        };
+        let git_repos_bare = DirInfo {
+            dir_size: 121_212,
+            file_number: 37,
+        };
+        let git_checkouts = DirInfo {
+            dir_size: 34984,
+            file_number: 8,
+        };
+        let reg_cache = DirInfo {
+            dir_size: 89,
+            file_number: 23445,
+        };
+        let reg_src = DirInfo {
+            dir_size: 1_938_493_989,
+            file_number: 123_909_849,
+        };
+        let reg_index = DirInfo {
+            dir_size: 23,
+            file_number: 12345,
+        };
+
+        let pb = PathBuf::from("/home/user/.cargo");
+
+        let dirSizes = DirSizes::new_manually(
+            &bindir,
+            &git_repos_bare,
+            &git_checkouts,
+            &reg_cache,
+            &reg_src,
+            &reg_index,
+            &pb,
+        );
+
+        let output_is =
+            crate::output::to_json_at(&dirSizes.summary_json(), crate::output::test_time());
+
+        let output_should = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "cargo_home": "/home/user/.cargo",
+  "total_size": 1938771509,
+  "total_count": 123933370,
+  "bin_size": 121212,
+  "bin_count": 31,
+  "registry_size": 1938494101,
+  "registry_index_size": 23,
+  "registry_indices": 1,
+  "registry_cache_size": 89,
+  "registry_cache_count": 23445,
+  "registry_sources_size": 1938493989,
+  "registry_sources_count": 123909849,
+  "git_size": 156196,
+  "git_db_size": 121212,
+  "git_db_count": 37,
+  "git_checkouts_size": 34984,
+  "git_checkouts_count": 8
+}"#;
+
+        assert_eq!(output_is, output_should);
+    }
+
+    /// the messages of all the violations of the summary schema
+    fn schema_violations(json: &str) -> Vec<String> {
+        crate::output::schema_violations(SUMMARY_SCHEMA, json)
+    }
+
+    #[test]
+    fn summary_json_matches_schema() {
+        let dir_info = |dir_size, file_number| DirInfo {
+            dir_size,
+            file_number,
+        };
+        let cargo_home = PathBuf::from("/home/user/.cargo");
+        let dir_sizes = DirSizes::new_manually(
+            &dir_info(100, 2),
+            &dir_info(200, 3),
+            &dir_info(300, 4),
+            &dir_info(400, 5),
+            &dir_info(500, 6),
+            &dir_info(600, 7),
+            &cargo_home,
+        );
+        let summary = dir_sizes.summary_json();
+
+        let plain = crate::output::to_json_at(&summary, crate::output::test_time());
+        assert_eq!(schema_violations(&plain), Vec::<String>::new());
+
+        let timings = profile::Timings(std::iter::once(("total", 20)).collect());
+        let with_timings = crate::output::to_json_at(
+            &profile::WithTimings {
+                output: &summary,
+                timings: Some(timings),
+            },
+            crate::output::test_time(),
+        );
+        assert!(with_timings.contains("\"timings\""));
+        assert_eq!(schema_violations(&with_timings), Vec::<String>::new());
+    }
+
+    #[test]
+    fn schema_rejects_drift() {
+        let valid = serde_json::json!({
+            "schema_version": 1,
+            "generated_at": "2021-05-01T12:30:00Z",
+            "cargo_home": "/home/user/.cargo",
+            "total_size": 0, "total_count": 0,
+            "bin_size": 0, "bin_count": 0,
+            "registry_size": 0,
+            "registry_index_size": 0, "registry_indices": 0,
+            "registry_cache_size": 0, "registry_cache_count": 0,
+            "registry_sources_size": 0, "registry_sources_count": 0,
+            "git_size": 0,
+            "git_db_size": 0, "git_db_count": 0,
+            "git_checkouts_size": 0, "git_checkouts_count": 0
+        });
+        assert!(schema_violations(&valid.to_string()).is_empty());
+
+        // a new field
+        let mut added = valid.clone();
+        let _ = added
+            .as_object_mut()
+            .unwrap()
+            .insert("bin_files".into(), 0.into());
+        assert_eq!(schema_violations(&added.to_string()).len(), 1);
+
+        // a renamed field
+        let mut renamed = valid.clone();
+        let size = renamed.as_object_mut().unwrap().remove("git_size").unwrap();
+        let _ = renamed
+            .as_object_mut()
+            .unwrap()
+            .insert("git_total_size".into(), size);
+        assert_eq!(schema_violations(&renamed.to_string()).len(), 2);
+
+        // a size that is not a number of bytes
+        let mut wrong_type = valid;
+        wrong_type["total_size"] = "1.5 MB".into();
+        assert_eq!(schema_violations(&wrong_type.to_string()).len(), 1);
+    }
 }
 
 #[cfg(all(test, feature = "bench"))]
@@ -0,0 +1,232 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--discover-homes" which looks for cargo homes in the usual places and prints
+/// the size of each one, for example to find the cache of an old `$CARGO_HOME` setting that still uses disk space.
+/// The candidates are `$CARGO_HOME`, "~/.cargo", a cargo home next to `$RUSTUP_HOME`, `$XDG_DATA_HOME/cargo`
+/// and "/usr/local/cargo" of the rust docker images. Candidates that do not exist are skipped quietly.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::library::size_opts;
+use crate::settings::Settings;
+use crate::size_source;
+use crate::tables::{two_row_table, TableLine};
+use crate::warnings;
+
+use humansize::FileSize;
+
+/// a path that may be a cargo home
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Candidate {
+    path: PathBuf,
+    /// why the path was checked
+    found_via: &'static str,
+}
+
+/// the paths that may be a cargo home, `var` looks up an environment variable
+fn candidates(var: impl Fn(&str) -> Option<PathBuf>, home_dir: Option<&Path>) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    let mut add = |path: PathBuf, found_via| candidates.push(Candidate { path, found_via });
+
+    if let Some(cargo_home) = var("CARGO_HOME") {
+        add(cargo_home, "$CARGO_HOME");
+    }
+    if let Some(home) = home_dir {
+        add(home.join(".cargo"), "default");
+    }
+    // custom rustup installs usually keep both next to each other, "/opt/rust/rustup" and "/opt/rust/cargo"
+    if let Some(parent) = var("RUSTUP_HOME").as_deref().and_then(Path::parent) {
+        add(parent.join("cargo"), "next to $RUSTUP_HOME");
+        add(parent.join(".cargo"), "next to $RUSTUP_HOME");
+    }
+    if let Some(data_dir) =
+        var("XDG_DATA_HOME").or_else(|| home_dir.map(|home| home.join(".local").join("share")))
+    {
+        add(data_dir.join("cargo"), "$XDG_DATA_HOME");
+    }
+    add(PathBuf::from("/usr/local/cargo"), "rust docker image");
+
+    candidates
+}
+
+/// the candidates that exist, a cargo home that was found several times is only kept once
+fn existing_homes(candidates: Vec<Candidate>) -> Vec<Candidate> {
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.path.is_dir())
+        .filter(|candidate| {
+            seen.insert(
+                candidate
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| candidate.path.clone()),
+            )
+        })
+        .collect()
+}
+
+/// the directories of a cargo home that make up the total of the summary
+const CACHE_DIRS: [&str; 6] = [
+    "bin",
+    "registry/index",
+    "registry/cache",
+    "registry/src",
+    "git/db",
+    "git/checkouts",
+];
+
+/// the size of the caches of a cargo home, the total of its summary
+fn cache_size(settings: &Settings, cargo_home: &Path) -> u64 {
+    CACHE_DIRS
+        .iter()
+        .map(|dir| cargo_home.join(dir))
+        .filter(|dir| dir.is_dir())
+        .flat_map(|dir| warnings::readable_entries(settings, &dir))
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| size_source::file_size(settings, entry.path()))
+        .sum()
+}
+
+/// the table of the found cargo homes and their sizes, followed by the total of all of them
+fn homes_summary(settings: &Settings, homes: &[(Candidate, u64)]) -> String {
+    if homes.is_empty() {
+        return String::from("No cargo homes found\n");
+    }
+
+    let mut table = vec![TableLine::new(0, &"Cargo homes:\n\n", &"")];
+    for (home, size) in homes {
+        table.push(TableLine::new(
+            1,
+            &format!("{} ({}): ", home.path.display(), home.found_via),
+            &size.file_size(size_opts(settings)).unwrap(),
+        ));
+    }
+    let total: u64 = homes.iter().map(|(_home, size)| size).sum();
+    table.push(TableLine::new(
+        0,
+        &"Total: ",
+        &total.file_size(size_opts(settings)).unwrap(),
+    ));
+
+    two_row_table(2, table, false)
+}
+
+/// look for cargo homes in the usual places and summarize their sizes (cmd: "cargo cache --discover-homes")
+pub(crate) fn discover_homes(settings: &Settings) -> String {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    let homes = existing_homes(candidates(var, home::home_dir().as_deref()))
+        .into_iter()
+        .map(|home| {
+            let size = cache_size(settings, &home.path);
+            (home, size)
+        })
+        .collect::<Vec<_>>();
+
+    homes_summary(settings, &homes)
+}
+
+#[cfg(test)]
+mod discover_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn candidates_of_env() {
+        let var = |name: &str| match name {
+            "CARGO_HOME" => Some(PathBuf::from("/custom/cargo")),
+            "RUSTUP_HOME" => Some(PathBuf::from("/opt/rust/rustup")),
+            _ => None,
+        };
+        let paths = candidates(var, Some(Path::new("/home/user")))
+            .into_iter()
+            .map(|candidate| (candidate.path, candidate.found_via))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                (PathBuf::from("/custom/cargo"), "$CARGO_HOME"),
+                (PathBuf::from("/home/user/.cargo"), "default"),
+                (PathBuf::from("/opt/rust/cargo"), "next to $RUSTUP_HOME"),
+                (PathBuf::from("/opt/rust/.cargo"), "next to $RUSTUP_HOME"),
+                (
+                    PathBuf::from("/home/user/.local/share/cargo"),
+                    "$XDG_DATA_HOME"
+                ),
+                (PathBuf::from("/usr/local/cargo"), "rust docker image"),
+            ]
+        );
+    }
+
+    #[test]
+    fn homes_are_found_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let cargo_home = dir.path().join(".cargo");
+        fs::create_dir_all(cargo_home.join("registry").join("cache")).unwrap();
+        fs::write(
+            cargo_home
+                .join("registry")
+                .join("cache")
+                .join("foo-0.1.0.crate"),
+            b"12345",
+        )
+        .unwrap();
+        fs::create_dir(cargo_home.join("bin")).unwrap();
+        fs::write(cargo_home.join("bin").join("cargo-cache"), b"123").unwrap();
+        // not part of the cache
+        fs::write(cargo_home.join("config.toml"), b"[net]").unwrap();
+
+        let var = |name: &str| (name == "CARGO_HOME").then(|| cargo_home.clone());
+        // leave out "/usr/local/cargo" which may exist on the machine running the tests
+        let inside_dir = candidates(var, Some(dir.path()))
+            .into_iter()
+            .filter(|candidate| candidate.path.starts_with(dir.path()))
+            .collect();
+        let homes = existing_homes(inside_dir);
+        // "$CARGO_HOME" is the default cargo home, the other candidates do not exist
+        assert_eq!(
+            homes,
+            vec![Candidate {
+                path: cargo_home.clone(),
+                found_via: "$CARGO_HOME",
+            }]
+        );
+        assert_eq!(cache_size(&Settings::default(), &cargo_home), 8);
+    }
+
+    #[test]
+    fn summary_of_homes() {
+        let home = |path: &str, found_via| Candidate {
+            path: PathBuf::from(path),
+            found_via,
+        };
+        let homes = vec![
+            (home("/home/user/.cargo", "default"), 1_200_000),
+            (home("/opt/rust/cargo", "next to $RUSTUP_HOME"), 34_000),
+        ];
+        assert_eq!(
+            homes_summary(&Settings::default(), &homes),
+            "Cargo homes:
+
+  /home/user/.cargo (default):              1.20 MB
+  /opt/rust/cargo (next to $RUSTUP_HOME):     34 KB
+Total:                                      1.23 MB\n"
+        );
+        assert_eq!(
+            homes_summary(&Settings::default(), &[]),
+            "No cargo homes found\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--exclude-glob <pattern>" which leaves files out of the sizes and counts
+/// of the caches, for example to see the size of the registry without a huge vendored crate.
+/// A pattern without a '/' is matched against every file and directory name below the cargo home,
+/// a pattern with a '/' against the path relative to the cargo home. If a directory matches,
+/// everything inside of it is excluded. '*' matches within a name, '**' across '/', '?' a single character.
+use std::path::{Component, Path, PathBuf};
+
+use crate::settings::Settings;
+
+use regex::Regex;
+
+/// a single "--exclude-glob" pattern
+#[derive(Debug)]
+struct ExcludeGlob {
+    /// the pattern as it was passed
+    pattern: String,
+    regex: Regex,
+    /// the pattern contains a '/' and is matched against the relative path instead of names
+    is_path: bool,
+}
+
+impl ExcludeGlob {
+    fn new(pattern: &str) -> Self {
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    let _ = chars.next();
+                    regex.push_str(".*");
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                other => regex.push_str(&regex::escape(&other.to_string())),
+            }
+        }
+        regex.push('$');
+
+        Self {
+            pattern: pattern.to_string(),
+            // every other character is escaped, so this is always a valid regex
+            regex: Regex::new(&regex).unwrap(),
+            is_path: pattern.contains('/'),
+        }
+    }
+
+    /// check if the path relative to the cargo home, or one of its parent directories, matches
+    fn matches(&self, relative: &Path) -> bool {
+        if self.is_path {
+            relative
+                .ancestors()
+                .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                .any(|ancestor| self.regex.is_match(&ancestor.to_string_lossy()))
+        } else {
+            relative.components().any(|component| match component {
+                Component::Normal(name) => self.regex.is_match(&name.to_string_lossy()),
+                _ => false,
+            })
+        }
+    }
+}
+
+/// the patterns of "--exclude-glob" and the cargo home they are relative to
+#[derive(Debug)]
+pub(crate) struct Excludes {
+    cargo_home: PathBuf,
+    globs: Vec<ExcludeGlob>,
+}
+
+impl Excludes {
+    /// exclude the files below `cargo_home` that match `patterns`
+    pub(crate) fn new(cargo_home: &Path, patterns: &[&str]) -> Self {
+        Self {
+            cargo_home: cargo_home.to_path_buf(),
+            globs: patterns
+                .iter()
+                .map(|pattern| ExcludeGlob::new(pattern))
+                .collect(),
+        }
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.cargo_home).unwrap_or(path);
+        self.globs.iter().any(|glob| glob.matches(relative))
+    }
+}
+
+/// check if the file or directory at `path` is left out of the sizes and counts
+pub(crate) fn is_excluded(settings: &Settings, path: &Path) -> bool {
+    settings
+        .excludes
+        .as_ref()
+        .is_some_and(|excludes| excludes.is_excluded(path))
+}
+
+/// the "--exclude-glob" patterns, empty if nothing is excluded
+pub(crate) fn patterns(settings: &Settings) -> Vec<String> {
+    settings
+        .excludes
+        .as_ref()
+        .map(|excludes| {
+            excludes
+                .globs
+                .iter()
+                .map(|glob| glob.pattern.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// a note for the text output that the sizes are incomplete, None if nothing is excluded
+pub(crate) fn note(settings: &Settings) -> Option<String> {
+    let patterns = patterns(settings);
+    if patterns.is_empty() {
+        return None;
+    }
+    let quoted = patterns
+        .iter()
+        .map(|pattern| format!("'{pattern}'"))
+        .collect::<Vec<_>>();
+    Some(format!(
+        "Note: files matching {} are excluded from the sizes and counts",
+        quoted.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod exclude_tests {
+    use super::*;
+
+    #[test]
+    fn names_and_paths_are_excluded() {
+        let home = Path::new("/home/user/.cargo");
+        let globs = Excludes::new(home, &["windows-0.48.*", "registry/src/*/big-*/**.rs"]);
+        let excluded = |path: &str| globs.is_excluded(&home.join(path));
+
+        // a pattern without '/' matches any file or directory name
+        assert!(excluded("registry/cache/reg/windows-0.48.0.crate"));
+        assert!(excluded("registry/src/reg/windows-0.48.0/src/lib.rs"));
+        assert!(!excluded("registry/src/reg/windows-0.52.0/src/lib.rs"));
+        // a pattern with '/' matches the relative path
+        assert!(excluded("registry/src/reg/big-1.0.0/src/deep/lib.rs"));
+        assert!(!excluded("registry/src/reg/big-1.0.0/Cargo.toml"));
+        assert!(!excluded("registry/cache/reg/big-1.0.0.crate"));
+        // the components of the cargo home itself are not matched
+        let home_excludes = Excludes::new(home, &["user"]);
+        assert!(!home_excludes.is_excluded(&home.join("bin/cargo-cache")));
+    }
+
+    #[test]
+    fn single_characters_do_not_cross_directories() {
+        let glob = ExcludeGlob::new("a?b");
+        assert!(glob.matches(Path::new("x/a_b")));
+        assert!(!glob.matches(Path::new("x/a/b")));
+        let path_glob = ExcludeGlob::new("bin/*");
+        assert!(path_glob.matches(Path::new("bin/cargo-cache")));
+        assert!(!path_glob.matches(Path::new("git/bin/cargo-cache")));
+    }
+}
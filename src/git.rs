@@ -12,12 +12,13 @@ use std::io::{stdout, Write};
 use std::path::Path;
 use std::process::Command;
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 
 use crate::library::Error;
 use crate::library::*;
+use crate::settings::Settings;
 
-fn gc_repo(path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
+fn gc_repo(settings: &Settings, path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
     // get name of the repo (last item of path)
     let repo_name = match path.iter().last() {
         Some(name) => name.to_str().unwrap().to_string(),
@@ -32,8 +33,8 @@ fn gc_repo(path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
     }
 
     // get size before
-    let repo_size_before = cumulative_dir_size(path).dir_size;
-    let sb_human_readable = repo_size_before.file_size(file_size_opts::DECIMAL).unwrap();
+    let repo_size_before = cumulative_dir_size(settings, path).dir_size;
+    let sb_human_readable = repo_size_before.file_size(size_opts(settings)).unwrap();
     print!("{} => ", sb_human_readable);
 
     // we need to flush stdout manually for incremental print();
@@ -103,10 +104,10 @@ fn gc_repo(path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
             return Err(Error::GitRepackFailed(path.into(), e));
         }
 
-        let repo_size_after = cumulative_dir_size(path).dir_size;
+        let repo_size_after = cumulative_dir_size(settings, path).dir_size;
         println!(
             "{}",
-            size_diff_format(repo_size_before, repo_size_after, false)
+            size_diff_format(settings, repo_size_before, repo_size_after, false)
         );
 
         Ok((repo_size_before, repo_size_after))
@@ -115,13 +116,14 @@ fn gc_repo(path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
 
 #[allow(clippy::module_name_repetitions)]
 pub(crate) fn git_gc_everything(
+    settings: &Settings,
     git_repos_bare_dir: &Path,
     registry_pkg_cache_dir: &Path,
     dry_run: bool,
 ) -> Result<(), Error> {
     // gc repos and registries inside cargo cache
 
-    fn gc_subdirs(path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
+    fn gc_subdirs(settings: &Settings, path: &Path, dry_run: bool) -> Result<(u64, u64), Error> {
         if path.is_file() {
             return Err(Error::GitGCFile(path.to_path_buf()));
         } else if !path.is_dir() {
@@ -141,7 +143,7 @@ pub(crate) fn git_gc_everything(
 
         for repo in git_repos {
             // compress
-            let (size_before, size_after) = match gc_repo(&repo, dry_run) {
+            let (size_before, size_after) = match gc_repo(settings, &repo, dry_run) {
                 // run gc
                 Ok((before, after)) => (before, after),
                 Err(error) => match error {
@@ -173,7 +175,7 @@ pub(crate) fn git_gc_everything(
 
     println!("\nRecompressing repositories. This may take some time...");
     // gc git repos of crates
-    let (repos_before, repos_after) = gc_subdirs(git_repos_bare_dir, dry_run)?;
+    let (repos_before, repos_after) = gc_subdirs(settings, git_repos_bare_dir, dry_run)?;
     total_size_before += repos_before;
     total_size_after += repos_after;
 
@@ -183,16 +185,14 @@ pub(crate) fn git_gc_everything(
     let _ = repo_index.pop();
     repo_index.push("index");
     // gc registries
-    let (regs_before, regs_after) = gc_subdirs(&repo_index, dry_run)?;
+    let (regs_before, regs_after) = gc_subdirs(settings, &repo_index, dry_run)?;
     total_size_before += regs_before;
     total_size_after += regs_after;
 
     println!(
         "\nCompressed {} to {}",
-        total_size_before
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap(),
-        size_diff_format(total_size_before, total_size_after, false)
+        total_size_before.file_size(size_opts(settings)).unwrap(),
+        size_diff_format(settings, total_size_before, total_size_after, false)
     );
     Ok(())
 }
@@ -361,6 +361,7 @@ mod gittest {
         );
 
         let (dryrun_before, dryrun_after) = match gc_repo(
+            &Settings::default(),
             &PathBuf::from("target/gitrepo_gc/"),
             true, /* dry run */
         ) {
@@ -372,6 +373,7 @@ mod gittest {
         assert_eq!(dryrun_after, 0);
 
         let (before, after) = match gc_repo(
+            &Settings::default(),
             &PathBuf::from("target/gitrepo_gc/"),
             false, /* dry run */
         ) {
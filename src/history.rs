@@ -0,0 +1,130 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file provides the removal history: every item that is actually removed (not dry-run)
+/// is appended to "$CARGO_HOME/cargo-cache-history.log", one tab separated line per item:
+/// `<RFC 3339 time>\t<command>\t<path>\t<bytes>`
+/// The history is kept across runs and never truncated by cargo-cache.
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::relative_paths;
+use crate::settings::Settings;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// name of the history file inside the cargo home
+pub(crate) const HISTORY_FILE: &str = "cargo-cache-history.log";
+
+/// appends removals to the history file
+#[derive(Debug)]
+pub(crate) struct HistoryLog {
+    file: File,
+    /// the command line cargo-cache was invoked with
+    command: String,
+}
+
+impl HistoryLog {
+    fn open(cargo_home: &Path, command: String) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(cargo_home.join(HISTORY_FILE))?;
+        Ok(Self { file, command })
+    }
+
+    fn line(time: DateTime<Utc>, command: &str, path: &Path, bytes: u64) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\n",
+            time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            command,
+            path.display(),
+            bytes
+        )
+    }
+
+    fn record(&mut self, path: &Path, bytes: u64) -> io::Result<()> {
+        let line = Self::line(Utc::now(), &self.command, path, bytes);
+        self.file.write_all(line.as_bytes())
+    }
+}
+
+/// the history file in the cargo home that every removal of this run is logged to
+/// None with a warning if it can not be opened, the removals are not logged then
+pub(crate) fn open(cargo_home: &Path) -> Option<HistoryLog> {
+    let command = std::env::args().collect::<Vec<_>>().join(" ");
+    match HistoryLog::open(cargo_home, command) {
+        Ok(log) => Some(log),
+        Err(error) => {
+            eprintln!(
+                "Warning: failed to open the removal history \"{}\": {}",
+                cargo_home.join(HISTORY_FILE).display(),
+                error
+            );
+            None
+        }
+    }
+}
+
+/// whether removals are logged
+pub(crate) fn is_enabled(settings: &Settings) -> bool {
+    settings.removals.history.is_some()
+}
+
+/// append a removed item to the history, does nothing if the history is disabled
+pub(crate) fn record(settings: &Settings, path: &Path, bytes: u64) {
+    if let Some(Ok(mut log)) = settings.removals.history.as_ref().map(|log| log.lock()) {
+        if let Err(error) = log.record(path, bytes) {
+            eprintln!(
+                "Warning: failed to log the removal of \"{}\": {}",
+                relative_paths::shown(settings, path).display(),
+                error
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use crate::output::test_time;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn history_lines() {
+        assert_eq!(
+            HistoryLog::line(
+                test_time(),
+                "cargo-cache cache --autoclean",
+                Path::new("/home/user/.cargo/registry/src"),
+                1234
+            ),
+            "2021-05-01T12:30:00Z\tcargo-cache cache --autoclean\t/home/user/.cargo/registry/src\t1234\n"
+        );
+    }
+
+    #[test]
+    fn history_is_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(HISTORY_FILE), "earlier run\n").unwrap();
+
+        let mut log = HistoryLog::open(dir.path(), String::from("cargo cache trim")).unwrap();
+        log.record(Path::new("foo-0.1.0.crate"), 5).unwrap();
+        log.record(Path::new("bar-0.1.0.crate"), 3).unwrap();
+
+        let history = fs::read_to_string(dir.path().join(HISTORY_FILE)).unwrap();
+        let lines = history.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "earlier run");
+        assert!(lines[1].ends_with("\tcargo cache trim\tfoo-0.1.0.crate\t5"));
+        assert!(lines[2].ends_with("\tcargo cache trim\tbar-0.1.0.crate\t3"));
+    }
+}
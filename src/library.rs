@@ -15,6 +15,9 @@ use std::path::{Path, PathBuf};
 use crate::cache::caches::{Cache, RegistrySuperCache};
 use crate::cache::*;
 use crate::dirsizes::DirSizes;
+use crate::relative_paths;
+use crate::settings::Settings;
+use crate::size_source;
 
 use humansize::{file_size_opts, FileSize};
 use rayon::iter::*;
@@ -84,6 +87,11 @@ pub(crate) struct CargoCachePaths {
     pub(crate) git_checkouts: PathBuf,
 }
 
+/// the forms of sizes that `parse_size()` accepts, used in error messages
+const ACCEPTED_SIZES: &str = "Should be a number of bytes optionally followed by a unit: \
+    KB, MB, GB, TB (powers of 1000), KiB, MiB, GiB, TiB (powers of 1024) \
+    or B, K, M, G, T (powers of 1024).";
+
 /// possible errors the crate may encounter, most of them unrecoverable
 #[derive(Debug)]
 pub(crate) enum Error {
@@ -107,6 +115,8 @@ pub(crate) enum Error {
     MalformedPackageName(String),
     /// could not get the cargo home directory
     GetCargoHomeFailed,
+    /// cargo-home does not exist (yet), cargo has not been run
+    NoCargoCache(PathBuf),
     /// cargo-home exists but is not a directory
     CargoHomeNotDirectory(PathBuf),
     /// one of the parameters of --remove-dir was not recognized
@@ -133,6 +143,36 @@ pub(crate) enum Error {
     NoRustupHome,
     // trim failed to parse the given unit
     TrimLimitUnitParseFailure(String),
+    // the config file could not be read or parsed
+    ConfigParseFailure(PathBuf, String),
+    // failed to parse a size such as "10MB"
+    SizeParseFailure(String),
+    // failed to read or parse a Cargo.lock
+    UnparsableLockfile(PathBuf, String),
+    // "size" was passed a path that does not exist
+    SizeNoSuchPath(PathBuf),
+    // the cargo home does not look like a cargo home, refuse to remove anything from it
+    SuspiciousCargoHome(PathBuf, &'static str),
+    // "clean-index" was passed a registry that has no index
+    NoSuchRegistryIndex(String),
+    // the removal of the indices was not confirmed
+    IndexRemovalAborted,
+    // "--fields" selected a field that the json output does not have (field, known fields)
+    UnknownJsonField(String, Vec<String>),
+    // failed to read or parse the du output passed to "--size-source"
+    UnparsableSizeSource(PathBuf, String),
+    // "extract" was passed something that is not "<name>@<version>"
+    InvalidCrateSpec(String),
+    // "extract" was passed a crate that has no .crate archive in the cache
+    ArchiveNotCached(String),
+    // failed to unpack a .crate archive
+    ExtractionFailed(PathBuf, std::io::Error),
+    // failed to read or replace the json array of "--append-json"
+    AppendJsonFailed(PathBuf, String),
+    // failed to write the file list of "export-files"
+    ExportFailed(PathBuf, std::io::Error),
+    // "--fail-if-removable" was passed and the dry run would have removed this many items
+    RemovableItems(usize),
 }
 
 impl fmt::Display for Error {
@@ -192,9 +232,13 @@ impl fmt::Display for Error {
 
             Self::GetCargoHomeFailed => write!(f, "Failed to get CARGO_HOME!"),
 
+            Self::NoCargoCache(path) => {
+                write!(f, "No cargo cache found at '{}'", path.display())
+            }
+
             Self::CargoHomeNotDirectory(path) => write!(
                 f,
-                "CARGO_HOME \"{}\" is not an existing directory!",
+                "CARGO_HOME \"{}\" is not a directory!",
                 path.display()
             ),
 
@@ -248,14 +292,126 @@ impl fmt::Display for Error {
             Self::NoRustupHome => write!(f, "Failed to determine rustup home directory"),
             Self::TrimLimitUnitParseFailure(limit) => write!(
                 f,
-                "Failed to parse limit: \"{}\". \
-                Should be of the form 123X where X is one of B,K,M,G or T.",
-                limit
+                "Failed to parse limit: \"{}\". {}",
+                limit, ACCEPTED_SIZES
+            ),
+            Self::UnparsableLockfile(path, error) => write!(
+                f,
+                "Failed to parse Cargo.lock at '{}': {}",
+                path.display(),
+                error
+            ),
+            Self::SizeNoSuchPath(path) => {
+                write!(
+                    f,
+                    "Can not get size of \"{}\": no such file or directory",
+                    path.display()
+                )
+            }
+            Self::SuspiciousCargoHome(path, reason) => write!(
+                f,
+                "Refusing to remove anything from cargo home '{}': {}.\n\
+                 Please check that $CARGO_HOME points to the cargo home and not for example to '/' or to your home directory.",
+                path.display(),
+                reason
+            ),
+            Self::NoSuchRegistryIndex(registry) => {
+                write!(f, "No index found for registry \"{registry}\"")
+            }
+            Self::IndexRemovalAborted => write!(f, "Aborted, nothing was removed"),
+            Self::UnknownJsonField(field, known) => write!(
+                f,
+                "Unknown field \"{}\" passed to \"--fields\", known fields: {}",
+                field,
+                known.join(", ")
+            ),
+            Self::UnparsableSizeSource(path, error) => write!(
+                f,
+                "Failed to read sizes from du output '{}': {}",
+                path.display(),
+                error
+            ),
+            Self::InvalidCrateSpec(spec) => write!(
+                f,
+                "\"{spec}\" is not a crate, expected \"<name>@<version>\", for example \"serde@1.0.130\""
+            ),
+            Self::ArchiveNotCached(spec) => {
+                write!(f, "No .crate archive of \"{spec}\" found in the cache")
+            }
+            Self::ExtractionFailed(archive, error) => write!(
+                f,
+                "Failed to extract \"{}\": {}",
+                archive.display(),
+                error
+            ),
+            Self::AppendJsonFailed(file, error) => write!(
+                f,
+                "Failed to append the summary to \"{}\": {}",
+                file.display(),
+                error
+            ),
+            Self::ExportFailed(file, error) => write!(
+                f,
+                "Failed to write the file list to \"{}\": {}",
+                file.display(),
+                error
+            ),
+            Self::RemovableItems(count) => write!(
+                f,
+                "Would remove {} {}, failing because of \"--fail-if-removable\"",
+                count,
+                if *count == 1 { "item" } else { "items" }
+            ),
+            Self::SizeParseFailure(size) => {
+                write!(f, "Failed to parse size: \"{size}\". {ACCEPTED_SIZES}")
+            }
+            Self::ConfigParseFailure(path, error) => write!(
+                f,
+                "Failed to parse config file \"{}\": {}",
+                path.display(),
+                error
             ),
         }
     }
 }
 
+impl std::error::Error for Error {
+    /// the underlying error, if the variant wraps one
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GitGCFailed(_, error)
+            | Self::GitPackRefsFailed(_, error)
+            | Self::GitReflogFailed(_, error)
+            | Self::GitFsckFailed(_, error)
+            | Self::GitRepackFailed(_, error)
+            | Self::ExtractionFailed(_, error)
+            | Self::ExportFailed(_, error) => Some(error),
+            Self::UnparsableManifest(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// make sure the cargo home looks like a cargo home before anything is removed from it
+/// guards against `$CARGO_HOME` accidentally being set to '/' or the home directory
+pub(crate) fn check_cargo_home_is_sane(cargo_home: &Path) -> Result<(), Error> {
+    let canonical = cargo_home
+        .canonicalize()
+        .unwrap_or_else(|_| cargo_home.to_path_buf());
+    let home_dir = home::home_dir().and_then(|dir| dir.canonicalize().ok());
+
+    let reason = if canonical.parent().is_none() {
+        "it is the root of the filesystem"
+    } else if home_dir.as_deref() == Some(canonical.as_path()) {
+        "it is the home directory"
+    } else if !canonical.join("registry").is_dir() && !canonical.join("git").is_dir() {
+        "it contains neither a 'registry' nor a 'git' directory"
+    } else {
+        return Ok(());
+    };
+    Err(Error::SuspiciousCargoHome(cargo_home.to_path_buf(), reason))
+}
+
 impl CargoCachePaths {
     /// returns `CargoCachePaths` object which makes all the subpaths accessible to the crate
     pub(crate) fn default() -> Result<Self, Error> {
@@ -265,6 +421,9 @@ impl CargoCachePaths {
             return Err(Error::GetCargoHomeFailed);
         };
 
+        if !cargo_home.exists() {
+            return Err(Error::NoCargoCache(cargo_home));
+        }
         if !cargo_home.is_dir() {
             return Err(Error::CargoHomeNotDirectory(cargo_home));
         }
@@ -288,46 +447,66 @@ impl CargoCachePaths {
             git_checkouts,
         })
     }
+
+    /// the directories of the cargo home, as shown by `cargo cache --list-dirs`
+    pub(crate) fn listing<'a>(&'a self, settings: &'a Settings) -> DirListing<'a> {
+        DirListing {
+            paths: self,
+            settings,
+        }
+    }
 } // impl CargoCachePaths
 
+/// the directories of a cargo home, with their paths shown as the settings say
+#[derive(Debug)]
+pub(crate) struct DirListing<'a> {
+    paths: &'a CargoCachePaths,
+    settings: &'a Settings,
+}
+
 // this is the output of `cargo cache --list-dirs`
-impl std::fmt::Display for CargoCachePaths {
+impl fmt::Display for DirListing<'_> {
     fn fmt(&self, f: &'_ mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = |path| relative_paths::shown(self.settings, path).display();
         writeln!(
             f,
             "\ncargo home:                 {}",
-            &self.cargo_home.display()
+            &self.paths.cargo_home.display()
+        )?;
+        writeln!(
+            f,
+            "binaries directory:         {}",
+            shown(&self.paths.bin_dir)
         )?;
-        writeln!(f, "binaries directory:         {}", &self.bin_dir.display())?;
         writeln!(
             f,
             "registry directory:         {}",
-            &self.registry.display()
+            shown(&self.paths.registry)
         )?;
         writeln!(
             f,
             "registry index:             {}",
-            &self.registry_index.display()
+            shown(&self.paths.registry_index)
         )?;
         writeln!(
             f,
             "crate source archives:      {}",
-            &self.registry_pkg_cache.display()
+            shown(&self.paths.registry_pkg_cache)
         )?;
         writeln!(
             f,
             "unpacked crate sources:     {}",
-            &self.registry_sources.display()
+            shown(&self.paths.registry_sources)
         )?;
         writeln!(
             f,
             "bare git repos:             {}",
-            &self.git_repos_bare.display()
+            shown(&self.paths.git_repos_bare)
         )?;
         writeln!(
             f,
             "git repo checkouts:         {}",
-            &self.git_checkouts.display()
+            shown(&self.paths.git_checkouts)
         )?;
 
         Ok(())
@@ -460,20 +639,98 @@ pub(crate) fn components_from_groups(input: Option<&str>) -> Result<Vec<Componen
     Ok(mapped_dirs)
 }
 
+/// parse a human readable size such as "1000", "1.5K", "10MB" or "4GiB" into bytes
+/// KB, MB, GB and TB are powers of 1000, KiB, MiB, GiB and TiB are powers of 1024
+/// the single letter units B, K, M, G and T are powers of 1024 as well, units are case insensitive
+pub(crate) fn parse_size(size: &str) -> Result<u64, Error> {
+    const KIB: u64 = 1024;
+    const KB: u64 = 1000;
+
+    let trimmed = size.trim();
+    let unit_start = trimmed
+        .find(|c: char| c.is_alphabetic())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(unit_start);
+
+    let multiplicator: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => KB,
+        "mb" => KB.pow(2),
+        "gb" => KB.pow(3),
+        "tb" => KB.pow(4),
+        "k" | "kib" => KIB,
+        "m" | "mib" => KIB.pow(2),
+        "g" | "gib" => KIB.pow(3),
+        "t" | "tib" => KIB.pow(4),
+        _ => return Err(Error::SizeParseFailure(size.to_string())),
+    };
+
+    let value: f64 = match number.trim().parse::<f64>() {
+        Ok(parsed) if parsed.is_finite() && parsed >= 0.0 => parsed,
+        _ => return Err(Error::SizeParseFailure(size.to_string())),
+    };
+
+    // we may truncate the value here but that's ok
+    #[allow(clippy::cast_sign_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_precision_loss)]
+    Ok((value * multiplicator as f64) as u64)
+}
+
+/// compare crate versions by their semver precedence, so "0.9.0" comes before "0.10.0"
+/// versions that are not valid semver come after the valid ones and are compared as strings
+pub(crate) fn cmp_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        // build metadata does not take part in the precedence
+        (Ok(version_a), Ok(version_b)) => version_a.cmp(&version_b).then_with(|| a.cmp(b)),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// compare "<name>-<version>" of crate archives (without ".crate") and sources by name, then by version
+/// the version starts at the first segment that starts with a digit, like in `parse_version()`
+pub(crate) fn cmp_crate_names(a: &str, b: &str) -> std::cmp::Ordering {
+    fn split(name: &str) -> (&str, &str) {
+        name.match_indices('-')
+            .find(|(index, _dash)| name[index + 1..].starts_with(|c: char| c.is_ascii_digit()))
+            .map_or((name, ""), |(index, _dash)| {
+                (&name[..index], &name[index + 1..])
+            })
+    }
+    let (name_a, version_a) = split(a);
+    let (name_b, version_b) = split(b);
+    name_a
+        .cmp(name_b)
+        .then_with(|| cmp_versions(version_a, version_b))
+}
+
+/// humansize options with the given number of decimal places
+fn size_opts_with_precision(precision: usize) -> file_size_opts::FileSizeOpts {
+    file_size_opts::FileSizeOpts {
+        decimal_places: precision,
+        ..file_size_opts::DECIMAL
+    }
+}
+
+/// the humansize options that every human readable size is formatted with
+pub(crate) fn size_opts(settings: &Settings) -> file_size_opts::FileSizeOpts {
+    size_opts_with_precision(settings.precision)
+}
+
 /// get the total size of a directory or a file
-pub(crate) fn size_of_path(path: &Path) -> u64 {
+pub(crate) fn size_of_path(settings: &Settings, path: &Path) -> u64 {
     // if the path is a directory, use cumulative_dir_size
     if path.is_dir() {
-        cumulative_dir_size(path).dir_size
+        cumulative_dir_size(settings, path).dir_size
     } else {
-        fs::metadata(&path)
-            .unwrap_or_else(|_| panic!("Failed to get metadata of file '{}'", &path.display()))
-            .len()
+        size_source::file_size(settings, path)
     }
 }
 
 /// get the total size and number of files of a directory
-pub(crate) fn cumulative_dir_size(dir: &Path) -> DirInfo {
+pub(crate) fn cumulative_dir_size(settings: &Settings, dir: &Path) -> DirInfo {
     // Note: using a hashmap to cache dirsizes does apparently not pay out performance-wise
     if !dir.is_dir() {
         return DirInfo {
@@ -495,9 +752,12 @@ pub(crate) fn cumulative_dir_size(dir: &Path) -> DirInfo {
         // path, some time may have passed and if we have a "cargo build" operation
         // running in the directory, a temporary file may be gone already and failing to unwrap() (#43)
         .map(|f| {
-            fs::metadata(f)
-                .unwrap_or_else(|_| panic!("Failed to get metadata of file '{}'", &f.display()))
-                .len()
+            size_source::size_of_metadata(
+                settings,
+                &fs::metadata(f).unwrap_or_else(|_| {
+                    panic!("Failed to get metadata of file '{}'", &f.display())
+                }),
+            )
         })
         .sum();
 
@@ -521,7 +781,7 @@ pub(crate) fn cumulative_dir_size(dir: &Path) -> DirInfo {
 }
 
 /// "cargo cache --info" output
-pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
+pub(crate) fn get_info(settings: &Settings, c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     let mut strn = String::with_capacity(1500);
 
     if let Ok(cache_path) = std::env::var("CARGO_HOME") {
@@ -540,7 +800,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
 
     strn.push_str(&format!(
         "Total cache size: {}\n\n",
-        s.total_size().file_size(file_size_opts::DECIMAL).unwrap()
+        s.total_size().file_size(size_opts(settings)).unwrap()
     ));
 
     strn.push_str(&c.bin_dir.display().to_string());
@@ -548,9 +808,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push_str(&format!(
         "\t{} binaries installed in binary directory, total size: {}\n",
         s.numb_bins(),
-        s.total_bin_size()
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap()
+        s.total_bin_size().file_size(size_opts(settings)).unwrap()
     ));
     strn.push_str("\tThese are the binaries installed via 'cargo install'.\n");
     strn.push_str("\tUse 'cargo uninstall' to remove binaries if needed.\n");
@@ -560,9 +818,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push('\n');
     strn.push_str(&format!(
         "\tRegistry root dir, size: {}\n",
-        s.total_reg_size()
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap()
+        s.total_reg_size().file_size(size_opts(settings)).unwrap()
     ));
     strn.push_str("\tCrate registries are stored here.\n");
     strn.push('\n');
@@ -572,7 +828,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push_str(&format!(
         "\tRegistry index, size: {}\n",
         s.total_reg_index_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
     strn.push_str("\tA git repo holding information on what crates are available.\n");
@@ -586,7 +842,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push_str(&format!(
         "\tCrate source package archive, size: {}\n",
         s.total_reg_cache_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
 
@@ -600,7 +856,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push_str(&format!(
         "\tCrate sources, size: {}\n",
         s.total_reg_src_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
     strn.push_str("\tSource archives are extracted into this dir.\n");
@@ -612,7 +868,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push_str(&format!(
         "\tGit database, size: {}\n",
         s.total_git_repos_bare_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
     strn.push_str("\tBare repos of git dependencies are stored here.\n");
@@ -624,7 +880,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
     strn.push_str(&format!(
         "\tGit repo checkouts, size: {}\n",
         s.total_git_chk_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
     strn.push_str("\tSpecific commits of the bare repos will be checked out into here.\n");
@@ -636,6 +892,7 @@ pub(crate) fn get_info(c: &CargoCachePaths, s: &DirSizes<'_>) -> String {
 //@TODO add tests
 /// provides a textual summary of changes (of file sizes)
 pub(crate) fn size_diff_format(
+    settings: &Settings,
     size_before: u64,
     size_after: u64,
     display_size_before: bool,
@@ -643,13 +900,13 @@ pub(crate) fn size_diff_format(
     #[allow(clippy::cast_possible_wrap)]
     let size_diff: i64 = size_after as i64 - size_before as i64;
     let sign = if size_diff > 0 { "+" } else { "" };
-    let size_after_human_readable = size_after.file_size(file_size_opts::DECIMAL).unwrap();
+    let size_after_human_readable = size_after.file_size(size_opts(settings)).unwrap();
     let humansize_opts = file_size_opts::FileSizeOpts {
         allow_negative: true,
-        ..file_size_opts::DECIMAL
+        ..size_opts(settings)
     };
     let size_diff_human_readable = size_diff.file_size(humansize_opts).unwrap();
-    let size_before_human_readabel = size_before.file_size(file_size_opts::DECIMAL).unwrap();
+    let size_before_human_readabel = size_before.file_size(size_opts(settings)).unwrap();
     // calculate change in percentage
     // when printing, we are going to cut off everything but a few decimal places anyway, so
     // precision is not much of an issue.
@@ -689,6 +946,7 @@ pub(crate) fn size_diff_format(
 // @TODO make this function obsolete
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn print_size_changed_summary(
+    settings: &Settings,
     previous_total_size: u64,
     cargo_cache: &CargoCachePaths,
     mut bin_cache: &mut bin::BinaryCache,
@@ -715,16 +973,15 @@ pub(crate) fn print_size_changed_summary(
         &mut registry_index_caches,
         &mut registry_sources_caches,
         cargo_cache,
+        settings,
     )
     .total_size();
 
-    let size_old_human_readable = previous_total_size
-        .file_size(file_size_opts::DECIMAL)
-        .unwrap();
+    let size_old_human_readable = previous_total_size.file_size(size_opts(settings)).unwrap();
     println!(
         "\nSize changed from {} to {}",
         size_old_human_readable,
-        size_diff_format(previous_total_size, cache_size_new, false)
+        size_diff_format(settings, previous_total_size, cache_size_new, false)
     );
 }
 
@@ -779,6 +1036,20 @@ mod libtests {
         assert_eq!(x.file_number, 20);
     }
 
+    #[test]
+    fn size_precision() {
+        let size: u64 = 1_940_000_000;
+        assert_eq!(
+            size.file_size(size_opts_with_precision(2)).unwrap(),
+            "1.94 GB"
+        );
+        assert_eq!(size.file_size(size_opts_with_precision(0)).unwrap(), "2 GB");
+        assert_eq!(
+            size.file_size(size_opts_with_precision(3)).unwrap(),
+            "1.940 GB"
+        );
+    }
+
     #[allow(non_snake_case)]
     #[test]
     fn test_CargoCachePaths_gen() {
@@ -884,7 +1155,7 @@ mod libtests {
         // set cargo home to this directory
         let ccp = CargoCachePaths::new(PathBuf::from(CH_string)).unwrap();
 
-        let output = ccp.to_string();
+        let output = ccp.listing(&Settings::default()).to_string();
         let mut iter = output.lines().skip(1); // ??
 
         let cargo_home = iter.next().unwrap();
@@ -968,6 +1239,107 @@ mod libtests {
         let last = iter.next();
         assert!(!last.is_some(), "found another directory?!: '{:?}'", last);
     }
+
+    #[test]
+    fn versions_are_ordered_by_semver() {
+        use std::cmp::Ordering::*;
+
+        assert_eq!(cmp_versions("0.9.0", "0.10.0"), Less);
+        assert_eq!(cmp_versions("1.0.0-alpha.1", "1.0.0"), Less);
+        assert_eq!(cmp_versions("1.0.0", "1.0.0"), Equal);
+        // not semver
+        assert_eq!(cmp_versions("1.0.0", "abc"), Less);
+        assert_eq!(cmp_versions("abd", "abc"), Greater);
+
+        assert_eq!(cmp_crate_names("foo-0.9.0", "foo-0.10.0"), Less);
+        assert_eq!(cmp_crate_names("foo-bar-0.1.0", "foo-0.2.0"), Greater);
+        assert_eq!(cmp_crate_names("foo-0.1.0", "foo-0.1.0"), Equal);
+        assert_eq!(cmp_crate_names("rg", "cargo-cache"), Greater);
+
+        let mut names = vec!["foo-0.10.0", "foo-0.9.0", "bar-1.0.0", "foo-0.9.0-rc.1"];
+        names.sort_by(|a, b| cmp_crate_names(a, b));
+        assert_eq!(
+            names,
+            vec!["bar-1.0.0", "foo-0.9.0-rc.1", "foo-0.9.0", "foo-0.10.0"]
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        // bare bytes
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("1000").unwrap(), 1000);
+        assert_eq!(parse_size("1000B").unwrap(), 1000);
+        // decimal units
+        assert_eq!(parse_size("1KB").unwrap(), 1000);
+        assert_eq!(parse_size("1kb").unwrap(), 1000);
+        assert_eq!(parse_size("1.5MB").unwrap(), 1_500_000);
+        assert_eq!(parse_size("2GB").unwrap(), 2_000_000_000);
+        assert_eq!(parse_size("1TB").unwrap(), 1_000_000_000_000);
+        // binary units
+        assert_eq!(parse_size("1KiB").unwrap(), 1024);
+        assert_eq!(parse_size("1kib").unwrap(), 1024);
+        assert_eq!(parse_size("1MiB").unwrap(), 1_048_576);
+        assert_eq!(parse_size("1GiB").unwrap(), 1_073_741_824);
+        assert_eq!(parse_size("1TiB").unwrap(), 1_099_511_627_776);
+        // single letters are binary
+        assert_eq!(parse_size("1K").unwrap(), 1024);
+        assert_eq!(parse_size("1.5k").unwrap(), 1536);
+        // whitespace between value and unit
+        assert_eq!(parse_size(" 10 MB ").unwrap(), 10_000_000);
+        // boundaries: 1000 bytes are not 1 KiB
+        assert!(parse_size("1000").unwrap() < parse_size("1KiB").unwrap());
+        assert_eq!(parse_size("1KB").unwrap(), parse_size("1000").unwrap());
+    }
+
+    #[test]
+    fn test_parse_size_failure() {
+        for invalid in &[
+            "", "MB", "1XB", "1_", "-1MB", "1 M B", "1KIBB", "inf", "NaN",
+        ] {
+            match parse_size(invalid) {
+                Err(Error::SizeParseFailure(size)) => assert_eq!(&size, invalid),
+                other => panic!("expected parse failure for '{}', got {:?}", invalid, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_cargo_home_is_sane() {
+        let dir = tempfile::tempdir().unwrap();
+        // empty dir
+        assert!(matches!(
+            check_cargo_home_is_sane(dir.path()),
+            Err(Error::SuspiciousCargoHome(..))
+        ));
+        fs::create_dir(dir.path().join("git")).unwrap();
+        assert!(check_cargo_home_is_sane(dir.path()).is_ok());
+
+        assert!(matches!(
+            check_cargo_home_is_sane(Path::new("/")),
+            Err(Error::SuspiciousCargoHome(
+                _,
+                "it is the root of the filesystem"
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_error_source() {
+        use std::error::Error as _;
+
+        let error = Error::GitGCFailed(
+            PathBuf::from("git/db/foo-0123456789abcdef"),
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        assert!(Error::GitNotInstalled.source().is_none());
+    }
 }
 
 #[cfg(all(test, feature = "bench"))]
@@ -0,0 +1,274 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file reads a Cargo.lock directly (without "cargo metadata") and decides which items
+/// of the cargo home are pinned by it.
+/// registry items are matched by registry host, crate name and exact version,
+/// git items by repo name and commit.
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::library::Error;
+
+use serde::Deserialize;
+
+/// a package entry of a Cargo.lock
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// None for path dependencies and workspace members
+    source: Option<String>,
+}
+
+/// the parts of a Cargo.lock that we care about
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// the items of the cargo home a Cargo.lock pins
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct LockfilePins {
+    /// (registry host, "name-version")
+    crates: HashSet<(String, String)>,
+    /// (repo name, full commit hash)
+    git: HashSet<(String, String)>,
+}
+
+/// crates.io is stored as "github.com-<hash>" (git index) or "index.crates.io-<hash>" (sparse index)
+/// depending on the cargo version, but the lockfile always names the git index
+fn canonical_registry(host: &str) -> String {
+    match host {
+        "github.com" | "index.crates.io" => String::from("crates.io"),
+        other => other.to_string(),
+    }
+}
+
+/// `registry+https://github.com/rust-lang/crates.io-index` => "crates.io"
+fn registry_host(source: &str) -> Option<String> {
+    let index_url = source
+        .strip_prefix("registry+")
+        .or_else(|| source.strip_prefix("sparse+"))?;
+    let without_scheme = index_url.split("://").nth(1)?;
+    let host = without_scheme.split('/').next()?;
+    Some(canonical_registry(&host.to_lowercase()))
+}
+
+/// `git+https://github.com/rust-lang/cargo?rev=abc#abcdef` => ("cargo", "abcdef")
+fn git_repo_and_commit(source: &str) -> Option<(String, String)> {
+    let url_and_commit = source.strip_prefix("git+")?;
+    let (url_and_query, commit) = url_and_commit.split_at(url_and_commit.find('#')?);
+    let repo_url = url_and_query.split('?').next()?.trim_end_matches('/');
+    let repo = repo_url.rsplit('/').next()?.trim_end_matches(".git");
+    Some((
+        repo.to_lowercase(),
+        commit.trim_start_matches('#').to_string(),
+    ))
+}
+
+/// the name of a registry or repo dir without the hash: "github.com-1ecc6299db9ec823" => "github.com"
+fn strip_hash(dir_name: &str) -> &str {
+    match dir_name.rfind('-') {
+        Some(pos) if dir_name.len() - pos == 17 => &dir_name[..pos],
+        _ => dir_name,
+    }
+}
+
+/// file or dir name of a path as a String
+fn name_of(path: &Path) -> Option<String> {
+    Some(path.file_name()?.to_str()?.to_string())
+}
+
+/// name of the parent dir of a path with the hash stripped, lowercased
+fn parent_name_of(path: &Path) -> Option<String> {
+    let parent = name_of(path.parent()?)?;
+    Some(strip_hash(&parent).to_lowercase())
+}
+
+impl LockfilePins {
+    /// collect the pins from the content of a Cargo.lock
+    fn parse(content: &str, path: &Path) -> Result<Self, Error> {
+        let lockfile: Lockfile = toml::from_str(content)
+            .map_err(|e| Error::UnparsableLockfile(path.to_path_buf(), e.to_string()))?;
+
+        let mut pins = Self::default();
+        // path dependencies have no source and are not cached in the cargo home
+        for (package, source) in lockfile
+            .package
+            .iter()
+            .filter_map(|package| Some((package, package.source.as_ref()?)))
+        {
+            if let Some(host) = registry_host(source) {
+                let _ = pins
+                    .crates
+                    .insert((host, format!("{}-{}", package.name, package.version)));
+            } else if let Some(repo_and_commit) = git_repo_and_commit(source) {
+                let _ = pins.git.insert(repo_and_commit);
+            }
+        }
+        Ok(pins)
+    }
+
+    /// read the Cargo.lock at the given path
+    pub(crate) fn from_lockfile(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::UnparsableLockfile(path.to_path_buf(), e.to_string()))?;
+        Self::parse(&content, path)
+    }
+
+    fn pins_crate(&self, registry_dir: Option<String>, dir_name: Option<String>) -> bool {
+        match (registry_dir, dir_name) {
+            (Some(registry), Some(name_version)) => self.crates.contains(&(registry, name_version)),
+            _ => false,
+        }
+    }
+
+    /// registry/cache/<registry>/<name-version>.crate
+    pub(crate) fn pins_archive(&self, archive: &Path) -> bool {
+        let name_version = archive
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(ToString::to_string);
+        let registry = parent_name_of(archive).map(|host| canonical_registry(&host));
+        self.pins_crate(registry, name_version)
+    }
+
+    /// registry/src/<registry>/<name-version>
+    pub(crate) fn pins_source(&self, source: &Path) -> bool {
+        let registry = parent_name_of(source).map(|host| canonical_registry(&host));
+        self.pins_crate(registry, name_of(source))
+    }
+
+    /// git/db/<repo>
+    pub(crate) fn pins_bare_repo(&self, repo: &Path) -> bool {
+        match name_of(repo) {
+            Some(dir_name) => {
+                let name = strip_hash(&dir_name).to_lowercase();
+                self.git
+                    .iter()
+                    .any(|(pinned_repo, _commit)| *pinned_repo == name)
+            }
+            None => false,
+        }
+    }
+
+    /// git/checkouts/<repo>/<short commit>
+    pub(crate) fn pins_checkout(&self, checkout: &Path) -> bool {
+        match (parent_name_of(checkout), name_of(checkout)) {
+            (Some(repo), Some(rev)) => self
+                .git
+                .iter()
+                .any(|(pinned_repo, commit)| *pinned_repo == repo && commit.starts_with(&rev)),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod lockfile_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "cargo-cache"
+version = "0.6.2"
+dependencies = ["home"]
+
+[[package]]
+name = "home"
+version = "0.5.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "2456aef2e6b6a9784192ae780c0f15bc57df0e918585282325e8c8ac27737654"
+
+[[package]]
+name = "cargo"
+version = "0.52.0"
+source = "git+https://github.com/rust-lang/cargo?branch=master#258c89644c4587273a3ed3ee9522d2640facba43"
+"#;
+
+    fn pins() -> LockfilePins {
+        LockfilePins::parse(LOCKFILE, Path::new("Cargo.lock")).unwrap()
+    }
+
+    #[test]
+    fn parse_sources() {
+        assert_eq!(
+            registry_host("registry+https://github.com/rust-lang/crates.io-index"),
+            Some(String::from("crates.io"))
+        );
+        assert_eq!(
+            registry_host("sparse+https://index.crates.io/"),
+            Some(String::from("crates.io"))
+        );
+        assert_eq!(
+            registry_host("sparse+https://my-registry.example.com/index/"),
+            Some(String::from("my-registry.example.com"))
+        );
+        assert_eq!(registry_host("git+https://github.com/a/b#abc"), None);
+        assert_eq!(
+            git_repo_and_commit("git+https://github.com/rust-lang/cargo.git?rev=abc#abcdef"),
+            Some((String::from("cargo"), String::from("abcdef")))
+        );
+        // no commit, cargo always adds one to the lockfile
+        assert_eq!(git_repo_and_commit("git+https://github.com/a/b"), None);
+    }
+
+    #[test]
+    fn pinned_registry_items() {
+        let pins = pins();
+        assert!(pins.pins_archive(&PathBuf::from(
+            "registry/cache/github.com-1ecc6299db9ec823/home-0.5.3.crate"
+        )));
+        assert!(pins.pins_source(&PathBuf::from(
+            "registry/src/github.com-1ecc6299db9ec823/home-0.5.3"
+        )));
+        // crates.io with the sparse index
+        assert!(pins.pins_archive(&PathBuf::from(
+            "registry/cache/index.crates.io-6f17d22bba15001f/home-0.5.3.crate"
+        )));
+        // other version
+        assert!(!pins.pins_archive(&PathBuf::from(
+            "registry/cache/github.com-1ecc6299db9ec823/home-0.5.1.crate"
+        )));
+        // other registry
+        assert!(!pins.pins_source(&PathBuf::from(
+            "registry/src/my-registry-eb2e5ba3d5b6d218/home-0.5.3"
+        )));
+        // path dependencies are not pinned
+        assert!(!pins.pins_source(&PathBuf::from(
+            "registry/src/github.com-1ecc6299db9ec823/cargo-cache-0.6.2"
+        )));
+    }
+
+    #[test]
+    fn pinned_git_items() {
+        let pins = pins();
+        assert!(pins.pins_bare_repo(&PathBuf::from("git/db/cargo-e7ff1db891893a9e")));
+        assert!(!pins.pins_bare_repo(&PathBuf::from("git/db/rustfmt-e7ff1db891893a9e")));
+        assert!(pins.pins_checkout(&PathBuf::from(
+            "git/checkouts/cargo-e7ff1db891893a9e/258c896"
+        )));
+        // other commit of the same repo
+        assert!(!pins.pins_checkout(&PathBuf::from(
+            "git/checkouts/cargo-e7ff1db891893a9e/1234567"
+        )));
+    }
+
+    #[test]
+    fn invalid_lockfile() {
+        assert!(LockfilePins::parse("[[package]]\nname = 3", Path::new("Cargo.lock")).is_err());
+    }
+}
@@ -74,20 +74,38 @@ cfg_if::cfg_if! {
         mod top_items_summary;
         mod date;
         mod clean_unref;
+        mod config;
+        mod lockfile;
+        mod output;
+        mod history;
+        mod trend;
+        mod size_source;
+        mod profile;
+        mod counts;
+        mod warnings;
+        mod exclude;
+        mod discover;
+        mod relative_paths;
+        mod network_fs;
+        mod settings;
 
         // use
         use crate::cache::caches::{Cache, RegistrySuperCache};
         use std::process;
+        use std::sync::{Arc, Mutex};
         use std::time::SystemTime;
         use walkdir::WalkDir;
         use crate::cache::*;
-        use crate::commands::{local, query, sccache, trim, toolchains};
+        use crate::commands::{biggest_crate, clean_index, compression, doctor, export_files, extract, local, query, sccache, size, trim, toolchains};
         use crate::git::*;
         use crate::library::*;
         use crate::remove::*;
         use crate::top_items_summary::*;
         use crate::clean_unref::*;
         use crate::cli::{CargoCacheCommands};
+        use crate::config::Config;
+        use crate::output::OutputFormat;
+        use crate::settings::Settings;
     }
 }
 
@@ -107,7 +125,50 @@ fn main() {
     // we need this in case we call "cargo-cache" binary directly
     let config = config.subcommand_matches("cache").unwrap_or(&config);
 
-    let config_enum = cli::clap_to_enum(config);
+    // defaults for options that were not passed on the command line
+    let config_file = match Config::load() {
+        // a broken config file only stops the commands that read it
+        Err(error) if !cli::clap_to_enum(config, &Config::default()).reads_config() => {
+            // the warnings are not collected yet
+            eprintln!("Warning: {error}");
+            Config::default()
+        }
+        config_file => config_file.unwrap_or_fatal_error(),
+    };
+
+    let config_enum = cli::clap_to_enum(config, &config_file);
+    let output_format = config_file.format(config);
+    // "--format json" may be passed to "cargo cache" or to the subcommand
+    let json_output = output_format == OutputFormat::Json
+        || matches!(config.subcommand(), (_, Some(subcommand)) if subcommand.value_of("format") == Some("json"));
+    let mut run_settings = Settings {
+        // clap already made sure that this is a number
+        precision: config
+            .value_of("precision")
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(settings::DEFAULT_PRECISION),
+        reconcile: config.is_present("reconcile"),
+        force_git: config.is_present("force-git"),
+        json_output,
+        sizes: size_source::SizeSource::new(
+            config.value_of("size-source").map(std::path::Path::new),
+            config.is_present("dedup-hardlinks"),
+            config.is_present("disk"),
+        )
+        .unwrap_or_fatal_error(),
+        warnings: if json_output {
+            warnings::Warnings::collected()
+        } else {
+            warnings::Warnings::default()
+        },
+        profile: if config.is_present("profile") {
+            profile::Profile::enabled()
+        } else {
+            profile::Profile::default()
+        },
+        ..Settings::default()
+    };
+    let profile_start = std::time::Instant::now();
 
     // handle hidden "version" subcommand
     if config.is_present("version") {
@@ -125,44 +186,215 @@ fn main() {
     };
 
     match &config_enum {
-        CargoCacheCommands::SCCache => sccache::sccache_stats().exit_or_fatal_error(),
+        CargoCacheCommands::SCCache => sccache::sccache_stats(&run_settings).exit_or_fatal_error(),
         CargoCacheCommands::Toolchain => {
-            toolchains::toolchain_stats();
+            toolchains::toolchain_stats(&run_settings);
+            process::exit(0);
+        }
+        CargoCacheCommands::DiscoverHomes => {
+            // does not need a cargo home of its own
+            print!("{}", discover::discover_homes(&run_settings));
             process::exit(0);
         }
+        CargoCacheCommands::Size {
+            path,
+            size_format,
+            output_format: size_output_format,
+            max_depth,
+        } => {
+            // "cargo cache size --format json" and "cargo cache --format json size" both work
+            size::size_of_dir(
+                &run_settings,
+                path,
+                *size_format,
+                size_output_format.unwrap_or(output_format),
+                *max_depth,
+            )
+            .exit_or_fatal_error();
+        }
         _ => {}
     }
 
     // indicates if size changed and whether we should print a before/after size diff
     let mut size_changed: bool = false;
 
-    let cargo_cache = CargoCachePaths::default().unwrap_or_fatal_error();
+    let cargo_cache = match CargoCachePaths::default() {
+        // cargo creates its home on the first run, an empty cache is not an error
+        Err(error @ Error::NoCargoCache(_)) => {
+            println!("{error}");
+            process::exit(0);
+        }
+        paths => paths.unwrap_or_fatal_error(),
+    };
+
+    if let Some(patterns) = config.values_of("exclude-glob") {
+        if config_enum.removes_files() {
+            warnings::warn(
+                &run_settings,
+                String::from(
+                    "--exclude-glob only applies to the sizes, nothing is excluded from removals",
+                ),
+            );
+        } else {
+            run_settings.excludes = Some(exclude::Excludes::new(
+                &cargo_cache.cargo_home,
+                &patterns.collect::<Vec<_>>(),
+            ));
+        }
+    }
+
+    if config.is_present("relative-paths") {
+        run_settings.relative_to = Some(cargo_cache.cargo_home.clone());
+    }
 
     if let CargoCacheCommands::ListDirs = config_enum {
         // only print the directories and exit, don't calculate anything else
-        println!("{}", cargo_cache);
+        println!("{}", cargo_cache.listing(&run_settings));
         process::exit(0);
     }
 
+    if config_enum.removes_files() {
+        check_cargo_home_is_sane(&cargo_cache.cargo_home).unwrap_or_fatal_error();
+        // "--no-history" may be passed to "cargo cache" or to the subcommand
+        let no_history = config.is_present("no-history")
+            || matches!(config.subcommand(), (_, Some(subcommand)) if subcommand.is_present("no-history"));
+        if !config_enum.dry_run() && !no_history {
+            run_settings.removals.history = history::open(&cargo_cache.cargo_home).map(Mutex::new);
+        }
+        // "--max-removals" as well, clap already made sure that it is a number
+        let max_removals = config.value_of("max-removals").or_else(|| {
+            config
+                .subcommand()
+                .1
+                .and_then(|subcommand| subcommand.value_of("max-removals"))
+        });
+        run_settings.removals.limit = max_removals.and_then(|limit| limit.parse().ok());
+        // "--remove-jobs" as well
+        let remove_jobs = config.value_of("remove-jobs").or_else(|| {
+            config
+                .subcommand()
+                .1
+                .and_then(|subcommand| subcommand.value_of("remove-jobs"))
+        });
+        run_settings.removals.jobs = remove_jobs.and_then(|jobs| jobs.parse().ok());
+        // "--trash" as well
+        run_settings.removals.move_to_trash = config.is_present("trash")
+            || matches!(config.subcommand(), (_, Some(subcommand)) if subcommand.is_present("trash"));
+    }
+
+    // "--fail-if-removable" may be passed to "cargo cache" or to the subcommand,
+    // only the removal plans of these commands are checked
+    if config.is_present("fail-if-removable")
+        || matches!(config.subcommand(), (_, Some(subcommand)) if subcommand.is_present("fail-if-removable"))
+    {
+        if matches!(
+            config_enum,
+            CargoCacheCommands::CleanUnref { .. }
+                | CargoCacheCommands::AutoClean { .. }
+                | CargoCacheCommands::RemoveDir { .. }
+        ) {
+            run_settings.removals.fail_if_removable = true;
+        } else {
+            warnings::warn(
+                &run_settings,
+                String::from(
+                    "--fail-if-removable only applies to clean-unref, --autoclean and --remove-dir",
+                ),
+            );
+        }
+    }
+
+    // the settings are final from here on, the caches keep them around
+    let settings = Arc::new(run_settings);
+
     // create cache
+    let discovery_start = std::time::Instant::now();
     let p = CargoCachePaths::default().unwrap();
 
-    let mut bin_cache = bin::BinaryCache::new(p.bin_dir);
-    let mut checkouts_cache = git_checkouts::GitCheckoutCache::new(p.git_checkouts);
-    let mut bare_repos_cache = git_bare_repos::GitRepoCache::new(p.git_repos_bare);
+    let mut bin_cache = bin::BinaryCache::new(p.bin_dir, Arc::clone(&settings));
+    let mut checkouts_cache =
+        git_checkouts::GitCheckoutCache::new(p.git_checkouts, Arc::clone(&settings));
+    let mut bare_repos_cache =
+        git_bare_repos::GitRepoCache::new(p.git_repos_bare, Arc::clone(&settings));
 
-    let mut registry_pkgs_cache =
-        registry_pkg_cache::RegistryPkgCaches::new(p.registry_pkg_cache.clone());
+    let mut registry_pkgs_cache = registry_pkg_cache::RegistryPkgCaches::new(
+        p.registry_pkg_cache.clone(),
+        Arc::clone(&settings),
+    );
 
     //let mut registry_index_cache = registry_index::RegistryIndexCache::new(p.registry_index);
 
     let mut registry_sources_caches =
-        registry_sources::RegistrySourceCaches::new(p.registry_sources);
+        registry_sources::RegistrySourceCaches::new(p.registry_sources, Arc::clone(&settings));
 
     let p2 = CargoCachePaths::default().unwrap(); //@TODO remove this
 
     let mut registry_index_caches: registry_index::RegistryIndicesCache =
-        registry_index::RegistryIndicesCache::new(p2.registry_index);
+        registry_index::RegistryIndicesCache::new(p2.registry_index, Arc::clone(&settings));
+    profile::record(&settings, "discovery", discovery_start.elapsed());
+
+    if let CargoCacheCommands::Extract {
+        spec,
+        target_dir,
+        output_format: extract_output_format,
+    } = config_enum
+    {
+        // only needs the archives, skip the full scan below
+        // "cargo cache extract --format json" and "cargo cache --format json extract" both work
+        extract::extract(
+            &settings,
+            &mut registry_pkgs_cache,
+            spec,
+            std::path::Path::new(target_dir),
+            extract_output_format.unwrap_or(output_format),
+        )
+        .exit_or_fatal_error();
+    }
+
+    if let CargoCacheCommands::ExportFiles { target, format } = config_enum {
+        // the sizes are read while writing the file, skip the full scan below
+        export_files::export_files(
+            &settings,
+            std::path::Path::new(target),
+            format,
+            &mut bin_cache,
+            &mut checkouts_cache,
+            &mut bare_repos_cache,
+            &mut registry_pkgs_cache,
+            &mut registry_index_caches,
+            &mut registry_sources_caches,
+        )
+        .exit_or_fatal_error();
+    }
+
+    if let CargoCacheCommands::CountsOnly = config_enum {
+        // only list the directories, no file is sized
+        let counts = counts::CacheCounts::new(&cargo_cache);
+        match output_format {
+            OutputFormat::Json => println!("{}", output::to_json(&settings, &counts)),
+            OutputFormat::Text => print!("{counts}"),
+        }
+        process::exit(0);
+    }
+
+    if let CargoCacheCommands::Only { categories } = &config_enum {
+        // skip the full scan below, only look at the selected caches
+        print!(
+            "{}",
+            dirsizes::only_summary(
+                &settings,
+                categories,
+                &cargo_cache.cargo_home,
+                &mut bin_cache,
+                &mut checkouts_cache,
+                &mut bare_repos_cache,
+                &mut registry_pkgs_cache,
+                &mut registry_index_caches,
+                &mut registry_sources_caches,
+            )
+        );
+        process::exit(0);
+    }
 
     // this should populate the entire cache, not very happy about this, wen we do this more lazily?
     let dir_sizes_original = dirsizes::DirSizes::new(
@@ -173,15 +405,19 @@ fn main() {
         &mut registry_index_caches,
         &mut registry_sources_caches,
         &cargo_cache,
+        &settings,
     );
 
     match config_enum {
         CargoCacheCommands::Trim {
             dry_run,
             trim_limit,
+            ref keep_crates,
         } => {
             let trim_result = trim::trim_cache(
+                &settings,
                 trim_limit,
+                keep_crates,
                 &mut checkouts_cache,
                 &mut bare_repos_cache,
                 &mut registry_pkgs_cache,
@@ -198,42 +434,145 @@ fn main() {
                 &mut registry_pkgs_cache,
                 &mut registry_index_caches,
                 &mut registry_sources_caches,
+                output_format,
             );
             trim_result.exit_or_fatal_error();
         }
-        CargoCacheCommands::CleanUnref {
+        CargoCacheCommands::TrimTo {
             dry_run,
-            manifest_path,
+            target,
+            keep_referenced_by,
+            ref keep_crates,
+            output_format: trim_output_format,
         } => {
-            let clean_unref_result = clean_unref(
+            // "cargo cache trim-to --format json" and "cargo cache --format json trim-to" both work
+            let print_json = trim_output_format.unwrap_or(output_format) == OutputFormat::Json;
+            let trim_result = trim::trim_to(
+                &settings,
                 &cargo_cache,
-                manifest_path,
-                &mut bin_cache,
-                &mut checkouts_cache,
-                &mut bare_repos_cache,
-                &mut registry_pkgs_cache,
+                target,
+                keep_referenced_by,
+                keep_crates,
                 &mut registry_index_caches,
+                &mut registry_pkgs_cache,
                 &mut registry_sources_caches,
                 dry_run,
+                print_json,
                 &mut size_changed,
             );
-            dirsizes::DirSizes::print_size_difference(
-                &dir_sizes_original,
-                &cargo_cache,
-                &mut bin_cache,
-                &mut checkouts_cache,
-                &mut bare_repos_cache,
-                &mut registry_pkgs_cache,
+            // trim_to prints its own summary with the registry size before and after
+            trim_result.exit_or_fatal_error();
+        }
+        CargoCacheCommands::CleanIndex {
+            dry_run,
+            registry,
+            unused_only,
+            assume_yes,
+        } => {
+            let clean_result = clean_index::clean_index(
+                &settings,
                 &mut registry_index_caches,
-                &mut registry_sources_caches,
+                &mut registry_pkgs_cache,
+                registry,
+                unused_only,
+                assume_yes,
+                dry_run,
+                &mut size_changed,
             );
-            clean_unref_result.exit_or_fatal_error();
+            if clean_result.is_ok() {
+                dirsizes::DirSizes::print_size_difference(
+                    &dir_sizes_original,
+                    &cargo_cache,
+                    &mut bin_cache,
+                    &mut checkouts_cache,
+                    &mut bare_repos_cache,
+                    &mut registry_pkgs_cache,
+                    &mut registry_index_caches,
+                    &mut registry_sources_caches,
+                    output_format,
+                );
+            }
+            clean_result.exit_or_fatal_error();
+        }
+        CargoCacheCommands::CleanUnref {
+            dry_run,
+            manifest_path,
+            lockfile,
+            use_metadata_cache,
+            targets,
+            output_format: clean_unref_output_format,
+        } => {
+            let clean_unref_format = clean_unref_output_format.unwrap_or(output_format);
+            // with "--format json", a dry run prints its plan instead of the dry run messages
+            let print_plan = dry_run && clean_unref_format == OutputFormat::Json;
+            let clean_unref_result = match lockfile {
+                Some(lockfile_path) => clean_unref_lockfile(
+                    &settings,
+                    &cargo_cache,
+                    std::path::Path::new(lockfile_path),
+                    &mut bin_cache,
+                    &mut checkouts_cache,
+                    &mut bare_repos_cache,
+                    &mut registry_pkgs_cache,
+                    &mut registry_index_caches,
+                    &mut registry_sources_caches,
+                    targets,
+                    dry_run,
+                    print_plan,
+                    &mut size_changed,
+                ),
+                None => clean_unref(
+                    &settings,
+                    &cargo_cache,
+                    manifest_path,
+                    use_metadata_cache,
+                    &mut bin_cache,
+                    &mut checkouts_cache,
+                    &mut bare_repos_cache,
+                    &mut registry_pkgs_cache,
+                    &mut registry_index_caches,
+                    &mut registry_sources_caches,
+                    targets,
+                    dry_run,
+                    print_plan,
+                    &mut size_changed,
+                ),
+            };
+            if !print_plan {
+                dirsizes::DirSizes::print_size_difference(
+                    &dir_sizes_original,
+                    &cargo_cache,
+                    &mut bin_cache,
+                    &mut checkouts_cache,
+                    &mut bare_repos_cache,
+                    &mut registry_pkgs_cache,
+                    &mut registry_index_caches,
+                    &mut registry_sources_caches,
+                    clean_unref_format,
+                );
+            }
+            clean_unref_result
+                .and_then(|()| check_nothing_removable(&settings))
+                .exit_or_fatal_error();
+        }
+        CargoCacheCommands::TopCacheItems { limit } if output_format == OutputFormat::Json => {
+            let top_crates = get_top_crates_json(
+                &settings,
+                &cargo_cache.cargo_home,
+                limit,
+                &mut registry_pkgs_cache,
+                &mut registry_sources_caches,
+            )
+            .unwrap_or_fatal_error();
+            println!("{}", output::to_json(&settings, &top_crates));
+            process::exit(0);
         }
         CargoCacheCommands::TopCacheItems { limit } => {
             if limit > 0 {
                 println!(
                     "{}",
                     get_top_crates(
+                        &settings,
                         limit,
                         &cargo_cache,
                         &mut bin_cache,
@@ -249,7 +588,9 @@ fn main() {
         }
         CargoCacheCommands::Query { query_config } => {
             query::run_query(
+                &settings,
                 query_config,
+                &config_file,
                 &mut bin_cache,
                 &mut checkouts_cache,
                 &mut bare_repos_cache,
@@ -259,15 +600,63 @@ fn main() {
             .exit_or_fatal_error();
         }
         CargoCacheCommands::Local => {
-            local::local_subcmd().exit_or_fatal_error();
+            local::local_subcmd(&settings).exit_or_fatal_error();
+        }
+        CargoCacheCommands::Doctor {
+            doctor_config,
+            orphans,
+            stale,
+            remove,
+            fix_perms,
+            dry_run,
+        } => {
+            // "cargo cache doctor --format json" and "cargo cache --format json doctor" both work
+            let doctor_format = doctor_config
+                .value_of("format")
+                .and_then(|format| format.parse().ok())
+                .unwrap_or(output_format);
+            doctor::doctor(
+                &settings,
+                &cargo_cache.cargo_home,
+                doctor_format,
+                orphans,
+                stale,
+                remove,
+                fix_perms,
+                dry_run,
+                &mut size_changed,
+                &mut checkouts_cache,
+                &mut registry_index_caches,
+                &mut registry_pkgs_cache,
+                &mut registry_sources_caches,
+            );
+            process::exit(0);
+        }
+        CargoCacheCommands::BiggestCrate => {
+            biggest_crate::biggest_crate(
+                &settings,
+                &mut registry_pkgs_cache,
+                &mut registry_sources_caches,
+            )
+            .exit_or_fatal_error();
+        }
+        CargoCacheCommands::Compression => {
+            compression::compression_stats(
+                &settings,
+                &mut registry_pkgs_cache,
+                &mut registry_sources_caches,
+            )
+            .exit_or_fatal_error();
         }
         CargoCacheCommands::RemoveIfDate {
             dry_run,
             arg_younger,
             arg_older,
             dirs,
+            ref keep_crates,
         } => {
             let res = crate::date::remove_files_by_dates(
+                &settings,
                 &mut checkouts_cache,
                 &mut bare_repos_cache,
                 &mut registry_pkgs_cache,
@@ -275,6 +664,7 @@ fn main() {
                 &mut registry_sources_caches,
                 arg_younger,
                 arg_older,
+                keep_crates,
                 dry_run,
                 dirs,
                 &mut size_changed,
@@ -289,18 +679,33 @@ fn main() {
                 &mut registry_pkgs_cache,
                 &mut registry_index_caches,
                 &mut registry_sources_caches,
+                output_format,
             );
             // don't run --remove-dir stuff (since we also required that parameter)
 
             res.exit_or_fatal_error();
         }
         CargoCacheCommands::Info => {
-            println!("{}", get_info(&cargo_cache, &dir_sizes_original));
+            println!("{}", get_info(&settings, &cargo_cache, &dir_sizes_original));
+            process::exit(0);
+        }
+        CargoCacheCommands::EstimateRedownload => {
+            let estimate = estimate_redownload(
+                config.value_of("remove-dir"),
+                &mut checkouts_cache,
+                &mut bare_repos_cache,
+                &mut registry_index_caches,
+                &mut registry_pkgs_cache,
+                &mut registry_sources_caches,
+            )
+            .unwrap_or_fatal_error();
+            println!("{}", estimate.summary(&settings));
             process::exit(0);
         }
         // This one must come BEFORE RemoveIfDate because that one also uses --remove dir
         CargoCacheCommands::RemoveDir { dry_run } => {
             let res = remove_dir_via_cmdline(
+                &settings,
                 config.value_of("remove-dir"),
                 dry_run,
                 &cargo_cache,
@@ -321,8 +726,11 @@ fn main() {
                 &mut registry_pkgs_cache,
                 &mut registry_index_caches,
                 &mut registry_sources_caches,
+                output_format,
             );
-            res.unwrap_or_fatal_error();
+            // the size difference is printed only once
+            res.and_then(|()| check_nothing_removable(&settings))
+                .exit_or_fatal_error();
         }
         CargoCacheCommands::FSCKRepos => {
             git_fsck_everything(&cargo_cache.git_repos_bare, &cargo_cache.registry_pkg_cache)
@@ -331,6 +739,7 @@ fn main() {
         CargoCacheCommands::GitGCRepos { dry_run } => {
             //@TODO deduplicate between autoclean-expensive!
             let res = git_gc_everything(
+                &settings,
                 &cargo_cache.git_repos_bare,
                 &cargo_cache.registry_pkg_cache,
                 dry_run,
@@ -351,12 +760,16 @@ fn main() {
             let git_checkouts = &cargo_cache.git_checkouts;
 
             // depending on the size of the cache and the system (SSD, HDD...) this can take a few seconds.
-            println!("Clearing cache...\n");
+            // keep stdout parsable for json output
+            if output_format == OutputFormat::Text {
+                println!("Clearing cache...\n");
+            }
 
             for dir in &[reg_srcs, git_checkouts] {
-                let size = cumulative_dir_size(dir);
+                let size = cumulative_dir_size(&settings, dir);
                 if dir.is_dir() {
-                    remove_file(
+                    let _ = remove_file(
+                        &settings,
                         dir,
                         dry_run,
                         &mut size_changed,
@@ -378,11 +791,26 @@ fn main() {
                 &mut registry_pkgs_cache,
                 &mut registry_index_caches,
                 &mut registry_sources_caches,
+                output_format,
             );
-            std::process::exit(0);
+            check_nothing_removable(&settings).exit_or_fatal_error();
+        }
+        CargoCacheCommands::PruneGitCheckouts { dry_run, keep_last } => {
+            prune_git_checkouts(
+                &settings,
+                keep_last,
+                dry_run,
+                &mut checkouts_cache,
+                &mut size_changed,
+            );
+        }
+        CargoCacheCommands::RemoveBrokenSymlinks { dry_run } => {
+            remove_broken_symlinks(&settings, &cargo_cache, dry_run, &mut size_changed);
+            process::exit(0);
         }
         CargoCacheCommands::AutoCleanExpensive { dry_run } => {
             let res = git_gc_everything(
+                &settings,
                 &cargo_cache.git_repos_bare,
                 &cargo_cache.registry_pkg_cache,
                 dry_run,
@@ -401,12 +829,16 @@ fn main() {
             let git_checkouts = &cargo_cache.git_checkouts;
 
             // depending on the size of the cache and the system (SSD, HDD...) this can take a few seconds.
-            println!("Clearing cache...\n");
+            // keep stdout parsable for json output
+            if output_format == OutputFormat::Text {
+                println!("Clearing cache...\n");
+            }
 
             for dir in &[reg_srcs, git_checkouts] {
-                let size = cumulative_dir_size(dir);
+                let size = cumulative_dir_size(&settings, dir);
                 if dir.is_dir() {
-                    remove_file(
+                    let _ = remove_file(
+                        &settings,
                         dir,
                         dry_run,
                         &mut size_changed,
@@ -428,12 +860,19 @@ fn main() {
                 &mut registry_pkgs_cache,
                 &mut registry_index_caches,
                 &mut registry_sources_caches,
+                output_format,
             );
             std::process::exit(0);
         }
-        CargoCacheCommands::KeepDuplicateCrates { dry_run, limit } => {
+        CargoCacheCommands::KeepDuplicateCrates {
+            dry_run,
+            limit,
+            ref keep_crates,
+        } => {
             let res = rm_old_crates(
+                &settings,
                 limit,
+                keep_crates,
                 dry_run,
                 &cargo_cache.registry_pkg_cache,
                 &mut size_changed,
@@ -450,6 +889,7 @@ fn main() {
                 &mut registry_pkgs_cache,
                 &mut registry_index_caches,
                 &mut registry_sources_caches,
+                output_format,
             );
 
             if let Err(error) = res {
@@ -470,7 +910,7 @@ fn main() {
         _ => (),
     }
 
-    if size_changed && !config.is_present("dry-run") {
+    if size_changed && !config_enum.dry_run() {
         // size has changed, print summary of how size has changed
 
         dirsizes::DirSizes::print_size_difference(
@@ -482,23 +922,133 @@ fn main() {
             &mut registry_pkgs_cache,
             &mut registry_index_caches,
             &mut registry_sources_caches,
+            output_format,
         );
     }
 
     // no println!() here!
     // print the default summary
-    if matches!(config_enum, CargoCacheCommands::Registries) {
-        // print per-registry summary
-        let output = dirsizes::per_registry_summary(
-            &dir_sizes_original,
-            &mut registry_index_caches,
-            &mut registry_sources_caches,
-            &mut registry_pkgs_cache,
-        );
-        print!("{}", output);
+    if let CargoCacheCommands::Registries {
+        line_order,
+        output_format: registry_output_format,
+        extraction_ratio,
+        split_index,
+    } = config_enum
+    {
+        // "cargo cache registry --format json" and "cargo cache --format json registry" both work
+        match registry_output_format.unwrap_or(output_format) {
+            OutputFormat::Json => {
+                let registries = dirsizes::per_registry_json(
+                    &settings,
+                    &cargo_cache.cargo_home,
+                    &mut registry_index_caches,
+                    &mut registry_sources_caches,
+                    &mut registry_pkgs_cache,
+                    extraction_ratio,
+                    split_index,
+                );
+                println!("{}", output::to_json(&settings, &registries));
+            }
+            OutputFormat::Text => {
+                // print per-registry summary
+                let output = dirsizes::per_registry_summary(
+                    &dir_sizes_original,
+                    &mut registry_index_caches,
+                    &mut registry_sources_caches,
+                    &mut registry_pkgs_cache,
+                    line_order,
+                    extraction_ratio,
+                    split_index,
+                );
+                print!("{}", output);
+                if let Some(note) = exclude::note(&settings) {
+                    println!("\n{note}");
+                }
+            }
+        }
     } else if matches!(config_enum, CargoCacheCommands::DefaultSummary) {
+        if config.is_present("log-trend") {
+            trend::log_trend(&cargo_cache.cargo_home, &dir_sizes_original.summary_json());
+        }
+        if let Some(file) = config.value_of("append-json") {
+            // clap already made sure that it is a number
+            let keep_runs = config
+                .value_of("keep-runs")
+                .and_then(|runs| runs.parse().ok());
+            trend::append_json(
+                std::path::Path::new(file),
+                &dir_sizes_original.summary_json(),
+                keep_runs,
+            )
+            .unwrap_or_fatal_error();
+        }
+        profile::record(&settings, "total", profile_start.elapsed());
         // default summary
-        print!("{}", dir_sizes_original);
+        let quiet = config.is_present("quiet");
+        match output_format {
+            OutputFormat::Text if quiet => println!("{}", dir_sizes_original.total_summary()),
+            OutputFormat::Json if quiet => {
+                println!(
+                    "{}",
+                    output::to_json(&settings, &dir_sizes_original.total_json())
+                );
+            }
+            OutputFormat::Text => {
+                let category_order = match config.value_of("sort-categories") {
+                    Some("size") => dirsizes::CategoryOrder::Size,
+                    _ => dirsizes::CategoryOrder::Fixed,
+                };
+                print!("{}", dir_sizes_original.summary(category_order));
+                if let Some(note) = exclude::note(&settings) {
+                    println!("\n{note}");
+                }
+                if let Some(fs_type) = dir_sizes_original.git_skipped() {
+                    println!("\n{}", network_fs::note(fs_type));
+                }
+                if config.is_present("verbose") {
+                    println!(
+                        "\n{}",
+                        dirsizes::unextracted_archives_summary(
+                            &settings,
+                            &mut registry_pkgs_cache,
+                            &mut registry_sources_caches
+                        )
+                    );
+                }
+                if let Some(timings) = profile::timings(&settings) {
+                    print!("\n{timings}");
+                }
+            }
+            OutputFormat::Json => {
+                let summary = dir_sizes_original.summary_json();
+                match config.values_of("fields") {
+                    Some(fields) => {
+                        let selected = output::select_fields(&summary, &fields.collect::<Vec<_>>())
+                            .unwrap_or_fatal_error();
+                        println!(
+                            "{}",
+                            output::to_json(
+                                &settings,
+                                &profile::WithTimings {
+                                    output: &selected,
+                                    timings: profile::timings(&settings),
+                                }
+                            )
+                        );
+                    }
+                    None => println!(
+                        "{}",
+                        output::to_json(
+                            &settings,
+                            &profile::WithTimings {
+                                output: &summary,
+                                timings: profile::timings(&settings),
+                            }
+                        )
+                    ),
+                }
+            }
+        }
     }
 
     if debug_mode {
@@ -0,0 +1,129 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file detects if the git db is on a network filesystem, bare git repos can be huge
+/// and sizing them over nfs or sshfs takes ages, so the git db is skipped there unless "--force-git" is passed.
+/// The filesystem is looked up in "/proc/mounts", on other platforms nothing is detected as a network filesystem.
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// the filesystem types of "/proc/mounts" that are network filesystems
+const NETWORK_FS_TYPES: &[&str] = &[
+    "9p",
+    "afs",
+    "ceph",
+    "cifs",
+    "fuse.glusterfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+    "fuse.sshfs",
+    "glusterfs",
+    "lustre",
+    "ncpfs",
+    "nfs",
+    "nfs4",
+    "smb3",
+    "smbfs",
+];
+
+/// undo the octal escapes of "/proc/mounts", a space in a mount point is written as "\040"
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(pos) = rest.find('\\') {
+        unescaped.push_str(&rest[..pos]);
+        let escape = rest.get(pos + 1..pos + 4);
+        if let Some(byte) = escape.and_then(|octal| u8::from_str_radix(octal, 8).ok()) {
+            unescaped.push(char::from(byte));
+            rest = &rest[pos + 4..];
+        } else {
+            unescaped.push('\\');
+            rest = &rest[pos + 1..];
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// the filesystem type of the mount `path` is on, the mount with the longest mount point wins
+fn mount_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            // device, mount point, type, options, ...
+            let mut fields = line.split_whitespace();
+            let mount_point = PathBuf::from(unescape(fields.nth(1)?));
+            let fs_type = fields.next()?;
+            path.starts_with(&mount_point)
+                .then(|| (mount_point.components().count(), fs_type))
+        })
+        // a later mount on the same mount point hides the earlier one
+        .max_by_key(|(depth, _fs_type)| *depth)
+        .map(|(_depth, fs_type)| fs_type)
+}
+
+/// the network filesystem `path` is on, None if it is on a local one or could not be found out
+fn network_fs_of(path: &Path) -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    let canonical = path.canonicalize().ok()?;
+    mount_type(&mounts, &canonical)
+        .filter(|fs_type| NETWORK_FS_TYPES.contains(fs_type))
+        .map(String::from)
+}
+
+/// the network filesystem of the git db if its bare repos are not sized in this run, None if they are sized
+/// with "--force-git", the git db is sized even on a network filesystem
+pub(crate) fn skipped_git_fs(settings: &Settings, git_db: &Path) -> Option<String> {
+    if settings.force_git {
+        return None;
+    }
+    network_fs_of(git_db)
+}
+
+/// a note for the text output that the bare repos of the git db were not sized
+pub(crate) fn note(fs_type: &str) -> String {
+    format!("Note: the git db is on a network filesystem ({fs_type}), its bare repos were not sized, pass --force-git to size them")
+}
+
+#[cfg(test)]
+mod network_fs_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const MOUNTS: &str = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+server:/export/home /home nfs4 rw,relatime,vers=4.2 0 0
+/dev/nvme0n1p3 /home/user/local\\040disk ext4 rw,relatime 0 0
+user@host:/cargo /mnt/cargo fuse.sshfs rw,nosuid,nodev 0 0
+";
+
+    #[test]
+    fn longest_mount_point_wins() {
+        let fs_of = |path: &str| mount_type(MOUNTS, Path::new(path));
+        assert_eq!(fs_of("/home/user/.cargo/git/db"), Some("nfs4"));
+        assert_eq!(fs_of("/home/user/local disk/.cargo/git/db"), Some("ext4"));
+        assert_eq!(fs_of("/mnt/cargo/git/db"), Some("fuse.sshfs"));
+        assert_eq!(fs_of("/root/.cargo/git/db"), Some("ext4"));
+        // "/home" is not a prefix of "/homework"
+        assert_eq!(fs_of("/homework/.cargo"), Some("ext4"));
+    }
+
+    #[test]
+    fn escaped_mount_points() {
+        assert_eq!(unescape("/mnt/a\\040b"), "/mnt/a b");
+        assert_eq!(unescape("/mnt/tab\\011"), "/mnt/tab\t");
+        // not an octal escape
+        assert_eq!(unescape("/mnt/a\\b"), "/mnt/a\\b");
+    }
+}
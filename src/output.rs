@@ -0,0 +1,254 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file provides the machine readable output formats of cargo-cache
+///
+/// Every json object printed by cargo-cache carries a top-level `schema_version` field
+/// and a `generated_at` RFC 3339 timestamp of when the output was created.
+/// Warnings of the scan are listed in a top-level `warnings` array, which is left out if there are none.
+/// The only exception is "--quiet --format json", which prints nothing but `{"total_size":<bytes>}`.
+/// Adding new fields is not considered a breaking change, renaming or removing fields
+/// or changing their meaning or type is and bumps the schema version.
+use crate::library::Error;
+use crate::settings::Settings;
+use crate::warnings;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// the version of the json output, bump this on every breaking change of any json output
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// wraps any output and puts the schema version and the time of creation next to its fields
+#[derive(Debug, Serialize)]
+struct Versioned<'a, T: Serialize> {
+    schema_version: u32,
+    generated_at: String,
+    #[serde(flatten)]
+    output: &'a T,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+/// the formats cargo-cache can print its results in (--format)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OutputFormat {
+    /// human readable tables, the default
+    Text,
+    /// json, for scripts and other tools
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(other.to_string()),
+        }
+    }
+}
+
+/// print a message for humans, with json output it goes to stderr so that stdout stays parseable
+pub(crate) fn print_message(settings: &Settings, message: &str) {
+    if settings.json_output {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// serialize a value into pretty printed json, adding the `schema_version` and `generated_at` fields
+/// the value must serialize into a json object
+pub(crate) fn to_json<T: Serialize>(settings: &Settings, value: &T) -> String {
+    // the scan is complete once we serialize its results
+    to_json_with_warnings(value, Utc::now(), warnings::take(settings))
+}
+
+/// same as `to_json()`, but with a fixed `generated_at` time and without warnings
+#[cfg(test)]
+pub(crate) fn to_json_at<T: Serialize>(value: &T, generated_at: DateTime<Utc>) -> String {
+    to_json_with_warnings(value, generated_at, Vec::new())
+}
+
+fn to_json_with_warnings<T: Serialize>(
+    value: &T,
+    generated_at: DateTime<Utc>,
+    warnings: Vec<String>,
+) -> String {
+    let versioned = Versioned {
+        schema_version: SCHEMA_VERSION,
+        generated_at: generated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        output: value,
+        warnings,
+    };
+    // our types only contain strings, numbers and lists, this can not fail
+    serde_json::to_string_pretty(&versioned).expect("failed to serialize json output")
+}
+
+/// same as `to_json_at()`, but the json is printed on a single line (json lines)
+pub(crate) fn to_json_line_at<T: Serialize>(value: &T, generated_at: DateTime<Utc>) -> String {
+    let versioned = Versioned {
+        schema_version: SCHEMA_VERSION,
+        generated_at: generated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        output: value,
+        warnings: Vec::new(),
+    };
+    serde_json::to_string(&versioned).expect("failed to serialize json output")
+}
+
+/// some of the top-level fields of a json object, in the order they were selected in
+#[derive(Debug)]
+pub(crate) struct SelectedFields(Vec<(String, serde_json::Value)>);
+
+impl Serialize for SelectedFields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+/// only keep the given top-level fields of a value (--fields), unknown fields are an error
+pub(crate) fn select_fields<T: Serialize>(
+    value: &T,
+    fields: &[&str],
+) -> Result<SelectedFields, Error> {
+    let Ok(serde_json::Value::Object(object)) = serde_json::to_value(value) else {
+        unreachable!("--fields is only supported for json objects")
+    };
+
+    fields
+        .iter()
+        .map(|field| match object.get(*field) {
+            Some(field_value) => Ok(((*field).to_string(), field_value.clone())),
+            None => Err(Error::UnknownJsonField(
+                (*field).to_string(),
+                object.keys().cloned().collect(),
+            )),
+        })
+        .collect::<Result<Vec<_>, Error>>()
+        .map(SelectedFields)
+}
+
+/// a fixed point in time for tests of json output
+#[cfg(test)]
+pub(crate) fn test_time() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2021-05-01T12:30:00Z")
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// the messages of all the violations of a JSON Schema by a json output
+#[cfg(test)]
+pub(crate) fn schema_violations(schema: &str, json: &str) -> Vec<String> {
+    let schema_json = serde_json::from_str(schema).unwrap();
+    let compiled = jsonschema::JSONSchema::compile(&schema_json).unwrap();
+    let instance = serde_json::from_str(json).unwrap();
+    let violations = match compiled.validate(&instance) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.map(|error| error.to_string()).collect(),
+    };
+    violations
+}
+
+#[cfg(test)]
+mod output_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_output_format() {
+        assert_eq!("text".parse::<OutputFormat>(), Ok(OutputFormat::Text));
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("yaml".parse::<OutputFormat>(), Err(String::from("yaml")));
+    }
+
+    #[test]
+    fn schema_version_comes_first() {
+        #[derive(Serialize)]
+        struct Output {
+            size: u64,
+        }
+
+        assert_eq!(
+            to_json_at(&Output { size: 3 }, test_time()),
+            "{\n  \"schema_version\": 1,\n  \"generated_at\": \"2021-05-01T12:30:00Z\",\n  \"size\": 3\n}"
+        );
+    }
+
+    #[test]
+    fn warnings_come_last() {
+        #[derive(Serialize)]
+        struct Output {
+            size: u64,
+        }
+
+        let warnings = vec![String::from(
+            "'/home/user/.cargo/bin/foo' vanished during the scan",
+        )];
+        assert_eq!(
+            to_json_with_warnings(&Output { size: 3 }, test_time(), warnings),
+            r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "size": 3,
+  "warnings": [
+    "'/home/user/.cargo/bin/foo' vanished during the scan"
+  ]
+}"#
+        );
+    }
+
+    #[test]
+    fn generated_at_is_rfc3339() {
+        #[derive(Serialize)]
+        struct Output {}
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&Settings::default(), &Output {})).unwrap();
+        let generated_at = json["generated_at"].as_str().unwrap();
+        assert!(DateTime::parse_from_rfc3339(generated_at).is_ok());
+    }
+
+    #[test]
+    fn selected_fields() {
+        #[derive(Serialize)]
+        struct Output {
+            total_size: u64,
+            registry_size: u64,
+            bin_count: u64,
+        }
+        let output = Output {
+            total_size: 10,
+            registry_size: 7,
+            bin_count: 3,
+        };
+
+        let selected = select_fields(&output, &["registry_size", "total_size"]).unwrap();
+        assert_eq!(
+            to_json_at(&selected, test_time()),
+            r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "registry_size": 7,
+  "total_size": 10
+}"#
+        );
+
+        match select_fields(&output, &["total_size", "nope"]) {
+            Err(Error::UnknownJsonField(field, known)) => {
+                assert_eq!(field, "nope");
+                assert_eq!(known, vec!["bin_count", "registry_size", "total_size"]);
+            }
+            other => panic!("expected UnknownJsonField, got {:?}", other),
+        }
+    }
+}
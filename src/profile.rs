@@ -0,0 +1,147 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--profile": the time each phase of the scan takes is measured and
+/// printed below the summary, with "--format json" it is added to the summary as "timings".
+/// The caches are sized in parallel, so the phases overlap and do not add up to "total".
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::settings::Settings;
+
+use serde::Serialize;
+
+/// the phases measured so far, disabled without "--profile"
+#[derive(Debug, Default)]
+pub(crate) struct Profile {
+    /// phase => milliseconds it took, None if "--profile" was not passed
+    phases: Option<Mutex<BTreeMap<&'static str, u64>>>,
+}
+
+impl Profile {
+    /// measure the phases of the run
+    pub(crate) fn enabled() -> Self {
+        Self {
+            phases: Some(Mutex::new(BTreeMap::new())),
+        }
+    }
+}
+
+fn milliseconds(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// record how long a phase took, does nothing if profiling is disabled
+pub(crate) fn record(settings: &Settings, phase: &'static str, duration: Duration) {
+    if let Some(Ok(mut phases)) = settings.profile.phases.as_ref().map(Mutex::lock) {
+        let _ = phases.insert(phase, milliseconds(duration));
+    }
+}
+
+/// run a phase and record how long it took
+pub(crate) fn time<T>(settings: &Settings, phase: &'static str, run: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = run();
+    record(settings, phase, start.elapsed());
+    result
+}
+
+/// the milliseconds of every phase, sorted by phase name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Timings(pub(crate) BTreeMap<&'static str, u64>);
+
+/// the timings recorded so far, None if profiling is disabled
+pub(crate) fn timings(settings: &Settings) -> Option<Timings> {
+    settings
+        .profile
+        .phases
+        .as_ref()
+        .and_then(|phases| phases.lock().ok())
+        .map(|phases| Timings(phases.clone()))
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Timings:")?;
+        for (phase, ms) in &self.0 {
+            writeln!(f, "  {phase}: {ms} ms")?;
+        }
+        Ok(())
+    }
+}
+
+/// json output with the timings of "--profile" next to its fields
+#[derive(Debug, Serialize)]
+pub(crate) struct WithTimings<'a, T: Serialize> {
+    #[serde(flatten)]
+    pub(crate) output: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) timings: Option<Timings>,
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use super::*;
+    use crate::output::{test_time, to_json_at};
+    use pretty_assertions::assert_eq;
+
+    #[derive(Serialize)]
+    struct Summary {
+        total_size: u64,
+    }
+
+    #[test]
+    fn timings_in_json() {
+        let mut phases = BTreeMap::new();
+        let _ = phases.insert("registry_index", 12);
+        let _ = phases.insert("bin", 3);
+        let summary = Summary { total_size: 42 };
+
+        let with_timings = to_json_at(
+            &WithTimings {
+                output: &summary,
+                timings: Some(Timings(phases)),
+            },
+            test_time(),
+        );
+        let desired = r#"{
+  "schema_version": 1,
+  "generated_at": "2021-05-01T12:30:00Z",
+  "total_size": 42,
+  "timings": {
+    "bin": 3,
+    "registry_index": 12
+  }
+}"#;
+        assert_eq!(with_timings, desired);
+
+        let without_timings = to_json_at(
+            &WithTimings {
+                output: &summary,
+                timings: None,
+            },
+            test_time(),
+        );
+        assert!(!without_timings.contains("timings"));
+    }
+
+    #[test]
+    fn timings_as_text() {
+        let mut phases = BTreeMap::new();
+        let _ = phases.insert("total", 20);
+        let _ = phases.insert("discovery", 1);
+        assert_eq!(
+            Timings(phases).to_string(),
+            "Timings:\n  discovery: 1 ms\n  total: 20 ms\n"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--relative-paths" which prints the paths inside the cargo home relative to it,
+/// in the text output as well as in the json output, so that reports of different machines can be compared.
+/// The cargo home itself and paths outside of it are printed as they are.
+use std::path::Path;
+
+use crate::settings::Settings;
+
+/// `path` relative to `cargo_home`, unchanged if it is not inside of it
+fn relative_to<'a>(path: &'a Path, cargo_home: &Path) -> &'a Path {
+    match path.strip_prefix(cargo_home) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => path,
+    }
+}
+
+/// `path` as it is printed, relative to the cargo home with "--relative-paths"
+/// the json output stores the paths as they are printed as well
+pub(crate) fn shown<'a>(settings: &Settings, path: &'a Path) -> &'a Path {
+    match &settings.relative_to {
+        Some(cargo_home) => relative_to(path, cargo_home),
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod relative_paths_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn paths_inside_the_cargo_home() {
+        let cargo_home = Path::new("/home/user/.cargo");
+        assert_eq!(
+            relative_to(
+                Path::new("/home/user/.cargo/registry/src/reg/foo-0.1.0"),
+                cargo_home
+            ),
+            Path::new("registry/src/reg/foo-0.1.0")
+        );
+        // the cargo home itself and paths outside of it are kept
+        assert_eq!(relative_to(cargo_home, cargo_home), cargo_home);
+        assert_eq!(
+            relative_to(Path::new("/home/user/.cargo-old/bin"), cargo_home),
+            Path::new("/home/user/.cargo-old/bin")
+        );
+        assert_eq!(
+            relative_to(Path::new("/tmp/target"), cargo_home),
+            Path::new("/tmp/target")
+        );
+    }
+}
@@ -8,13 +8,236 @@
 // except according to those terms.
 
 use std::fs;
+use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
 use crate::cache::caches::{Cache, RegistrySuperCache};
 use crate::cache::*;
+use crate::history::{self, HistoryLog};
 use crate::library::*;
+use crate::output::print_message;
+use crate::relative_paths;
+use crate::settings::Settings;
+use crate::warnings;
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rayon::prelude::*;
+
+/// how often a removal is tried if it fails with a transient error
+const REMOVAL_ATTEMPTS: u32 = 3;
+
+/// how many items are removed at the same time without "--remove-jobs", one by one because
+/// parallel unlinks speed up SSDs but make spinning disks seek a lot
+pub(crate) const DEFAULT_REMOVAL_JOBS: usize = 1;
+
+/// how items are removed in this run and what went wrong
+#[derive(Debug, Default)]
+pub(crate) struct Removals {
+    /// the maximum number of items a single removal operation removes per run, None without "--max-removals"
+    pub(crate) limit: Option<usize>,
+    /// how many items are removed at the same time ("--remove-jobs"), None if it was not passed
+    pub(crate) jobs: Option<usize>,
+    /// move removed items to the trash instead of deleting them ("--trash")
+    /// if the trash is not available (for example on headless systems), the removal fails and the item is kept
+    pub(crate) move_to_trash: bool,
+    /// fail the dry run if it would remove anything ("--fail-if-removable")
+    pub(crate) fail_if_removable: bool,
+    /// the removal history, None if removals are not logged
+    pub(crate) history: Option<Mutex<HistoryLog>>,
+    /// items that could not be removed, even after retrying, reported after the removals instead of aborting
+    failed: Mutex<Vec<(PathBuf, io::Error)>>,
+    /// how many items the dry run would have removed
+    planned: AtomicUsize,
+}
+
+/// remember that the dry run would have removed `count` items
+pub(crate) fn add_planned_removals(settings: &Settings, count: usize) {
+    let _ = settings
+        .removals
+        .planned
+        .fetch_add(count, Ordering::Relaxed);
+}
+
+/// whether a dry run would remove anything at `path`, empty directories do not count
+fn is_removable(path: &Path) -> bool {
+    match fs::read_dir(path) {
+        Ok(mut entries) => entries.next().is_some(),
+        // a file or a (broken) symlink
+        Err(_) => path.symlink_metadata().is_ok(),
+    }
+}
+
+/// with "--fail-if-removable", an error if the dry run would have removed anything
+pub(crate) fn check_nothing_removable(settings: &Settings) -> Result<(), Error> {
+    let planned = settings.removals.planned.load(Ordering::Relaxed);
+    if settings.removals.fail_if_removable && planned > 0 {
+        Err(Error::RemovableItems(planned))
+    } else {
+        Ok(())
+    }
+}
+
+/// delete a file or directory, or move it to the trash with "--trash"
+fn delete_path(settings: &Settings, path: &Path) -> io::Result<()> {
+    if settings.removals.move_to_trash {
+        // never fall back to deleting the item permanently
+        trash::delete(path).map_err(|error| {
+            if path.symlink_metadata().is_err() {
+                io::Error::from(ErrorKind::NotFound)
+            } else {
+                io::Error::other(format!("failed to move to the trash: {error}"))
+            }
+        })
+    } else if path.is_dir() {
+        remove_dir_all::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// errors that may go away if we try again, for example on flaky network mounts
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::WouldBlock
+            | ErrorKind::ResourceBusy
+            | ErrorKind::Interrupted
+            | ErrorKind::TimedOut
+    )
+}
+
+/// run a removal and retry it with backoff if it fails with a transient error
+/// if the item is already gone, it counts as removed
+fn remove_with_retries(mut remove: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    let mut attempt = 1;
+    loop {
+        match remove() {
+            Ok(()) => return Ok(()),
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) if is_transient(&error) && attempt < REMOVAL_ATTEMPTS => {
+                thread::sleep(Duration::from_millis(100 * 2_u64.pow(attempt - 1)));
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn record_failed_removal(settings: &Settings, path: &Path, error: io::Error) {
+    if let Ok(mut failed) = settings.removals.failed.lock() {
+        failed.push((path.to_path_buf(), error));
+    }
+}
+
+/// print the items that could not be removed since the last report
+pub(crate) fn report_failed_removals(settings: &Settings) {
+    let failed = match settings.removals.failed.lock() {
+        Ok(mut failed) => std::mem::take(&mut *failed),
+        Err(_) => return,
+    };
+    if failed.is_empty() {
+        return;
+    }
+    eprintln!(
+        "Warning: failed to remove {} {}:",
+        failed.len(),
+        if failed.len() == 1 { "item" } else { "items" }
+    );
+    for (path, error) in failed {
+        eprintln!(
+            "  {}: {}",
+            relative_paths::shown(settings, &path).display(),
+            error
+        );
+    }
+}
+
+/// the first `limit` items and the number of items that are left over
+fn split_at_limit<T>(items: &[T], limit: Option<usize>) -> (&[T], usize) {
+    match limit {
+        Some(max) if items.len() > max => (&items[..max], items.len() - max),
+        _ => (items, 0),
+    }
+}
+
+/// the items a removal operation may remove with "--max-removals" and the number of items that remain
+pub(crate) fn limit_removals<'a, T>(settings: &Settings, items: &'a [T]) -> (&'a [T], usize) {
+    split_at_limit(items, settings.removals.limit)
+}
+
+/// tell the user that a removal operation stopped early, running it again removes the next items
+pub(crate) fn report_removal_limit(settings: &Settings, remaining: usize) {
+    if remaining > 0 {
+        print_message(
+            settings,
+            &format!(
+                "Stopped at the removal limit; {} {} remain",
+                remaining,
+                if remaining == 1 { "item" } else { "items" }
+            ),
+        );
+    }
+}
+
+/// run `remove` for every item, with up to "--remove-jobs" items at the same time
+/// dry runs print every item instead of removing it, so they always run in order
+/// `remove` gets its own flag to set if it changed the cache, `size_changed` is set if any of them did
+pub(crate) fn remove_all<T: Sync>(
+    settings: &Settings,
+    items: &[T],
+    dry_run: bool,
+    size_changed: &mut bool,
+    remove: impl Fn(&T, &mut bool) + Sync,
+) {
+    let jobs = settings.removals.jobs.unwrap_or(DEFAULT_REMOVAL_JOBS);
+    remove_with_jobs(items, if dry_run { 1 } else { jobs }, size_changed, remove);
+}
+
+/// run `remove` for every item on `jobs` threads, in order if `jobs` is 1
+fn remove_with_jobs<T: Sync>(
+    items: &[T],
+    jobs: usize,
+    size_changed: &mut bool,
+    remove: impl Fn(&T, &mut bool) + Sync,
+) {
+    let thread_pool = (jobs > 1 && items.len() > 1)
+        .then(|| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs)
+                .build()
+                .ok()
+        })
+        .flatten();
+
+    match thread_pool {
+        Some(pool) => {
+            let changed = AtomicBool::new(false);
+            pool.install(|| {
+                items.par_iter().for_each(|item| {
+                    let mut item_changed = false;
+                    remove(item, &mut item_changed);
+                    if item_changed {
+                        changed.store(true, Ordering::Relaxed);
+                    }
+                });
+            });
+            if changed.into_inner() {
+                *size_changed = true;
+            }
+        }
+        // a single job or the threads could not be started
+        None => {
+            for item in items {
+                remove(item, size_changed);
+            }
+        }
+    }
+}
 
 /// dry run message setting
 pub(crate) enum DryRunMessage<'a> {
@@ -24,7 +247,55 @@ pub(crate) enum DryRunMessage<'a> {
     None, // no message
 }
 
-fn parse_version(path: &Path) -> Result<(String, String), Error> {
+/// progress bar for removals of many items, shows the number of removed items and the reclaimed size
+/// it is not drawn during dry runs (which print every item instead) or if stderr is not a terminal
+/// items may be removed in parallel, so the reclaimed size is counted atomically
+pub(crate) struct RemovalProgress<'a> {
+    settings: &'a Settings,
+    bar: ProgressBar,
+    reclaimed: AtomicU64,
+}
+
+impl<'a> RemovalProgress<'a> {
+    pub(crate) fn new(settings: &'a Settings, items: usize, dry_run: bool) -> Self {
+        let bar = if dry_run {
+            ProgressBar::hidden()
+        } else {
+            // hidden automatically if stderr is not a terminal
+            ProgressBar::with_draw_target(items as u64, ProgressDrawTarget::stderr())
+        };
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{bar:40} {pos}/{len} items removed, {msg} reclaimed"),
+        );
+        Self {
+            settings,
+            bar,
+            reclaimed: AtomicU64::new(0),
+        }
+    }
+
+    /// an item of the given size was removed
+    pub(crate) fn removed(&self, size: u64) {
+        let reclaimed = self.reclaimed.fetch_add(size, Ordering::Relaxed) + size;
+        self.bar
+            .set_message(reclaimed.file_size(size_opts(self.settings)).unwrap());
+        self.bar.inc(1);
+    }
+
+    /// the size of all items that were removed so far
+    pub(crate) fn reclaimed(&self) -> u64 {
+        self.reclaimed.load(Ordering::Relaxed)
+    }
+
+    /// remove the bar from the terminal
+    pub(crate) fn finish(&self) {
+        self.bar.finish_and_clear();
+        report_failed_removals(self.settings);
+    }
+}
+
+pub(crate) fn parse_version(path: &Path) -> Result<(String, String), Error> {
     #[allow(clippy::single_match_else)]
     let filename = match path.file_stem() {
         Some(name) => name.to_str().unwrap().to_string(),
@@ -61,8 +332,29 @@ fn parse_version(path: &Path) -> Result<(String, String), Error> {
     Ok((name, version))
 }
 
+/// whether `path` is the archive or the extracted sources (or a file inside of them) of a crate
+/// that is never removed (--keep-crate), registry items are at `registry/{cache,src}/<registry>/<crate>`
+pub(crate) fn is_kept_crate(path: &Path, keep_crates: &[&str]) -> bool {
+    if keep_crates.is_empty() {
+        return false;
+    }
+    let components = path
+        .components()
+        .map(std::path::Component::as_os_str)
+        .collect::<Vec<_>>();
+    components.windows(4).any(|window| {
+        window[0] == "registry"
+            && (window[1] == "cache" || window[1] == "src")
+            && parse_version(Path::new(window[3]))
+                .is_ok_and(|(name, _version)| keep_crates.contains(&name.as_str()))
+    })
+}
+
 pub(crate) fn rm_old_crates(
+    settings: &Settings,
     amount_to_keep: u64,
+    // crates that are never removed (--keep-crate)
+    keep_crates: &[&str],
     dry_run: bool,
     registry_src_path: &Path,
     size_changed: &mut bool,
@@ -71,7 +363,8 @@ pub(crate) fn rm_old_crates(
 
     // remove crate sources from cache
     // src can be completely removed since we can always rebuilt it from cache (by extracting packages)
-    let mut removed_size = 0;
+    // (path, size, dry run message) of everything that is queued for deletion
+    let mut to_remove: Vec<(PathBuf, u64, String)> = Vec::new();
     // walk registry repos
     for repo in fs::read_dir(&registry_src_path).unwrap() {
         let mut crate_list = fs::read_dir(&repo.unwrap().path())
@@ -88,8 +381,12 @@ pub(crate) fn rm_old_crates(
         for pkgpath in &crate_list {
             let (pkgname, pkgver) = parse_version(pkgpath)?;
 
+            if keep_crates.contains(&pkgname.as_str()) {
+                continue;
+            }
+
             if amount_to_keep == 0 {
-                removed_size += fs::metadata(pkgpath)
+                let size = fs::metadata(pkgpath)
                     .unwrap_or_else(|_| {
                         panic!("Failed to get metadata of file '{}'", &pkgpath.display())
                     })
@@ -99,16 +396,9 @@ pub(crate) fn rm_old_crates(
                     "dry run: not actually deleting {} {} at {}",
                     pkgname,
                     pkgver,
-                    pkgpath.display()
-                );
-                remove_file(
-                    pkgpath,
-                    dry_run,
-                    size_changed,
-                    None,
-                    &DryRunMessage::Custom(&dryrun_msg),
-                    None,
+                    relative_paths::shown(settings, pkgpath).display()
                 );
+                to_remove.push((pkgpath.clone(), size, dryrun_msg));
 
                 continue;
             }
@@ -118,7 +408,7 @@ pub(crate) fn rm_old_crates(
                 versions_of_this_package += 1;
                 if versions_of_this_package == amount_to_keep {
                     // we have seen this package too many times, queue for deletion
-                    removed_size += fs::metadata(pkgpath)
+                    let size = fs::metadata(pkgpath)
                         .unwrap_or_else(|_| {
                             panic!("Failed to get metadata of file '{}'", &pkgpath.display())
                         })
@@ -128,16 +418,9 @@ pub(crate) fn rm_old_crates(
                         "dry run: not actually deleting {} {} at {}",
                         pkgname,
                         pkgver,
-                        pkgpath.display()
-                    );
-                    remove_file(
-                        pkgpath,
-                        dry_run,
-                        size_changed,
-                        None,
-                        &DryRunMessage::Custom(&dryrun_msg),
-                        None,
+                        relative_paths::shown(settings, pkgpath).display()
                     );
+                    to_remove.push((pkgpath.clone(), size, dryrun_msg));
                 }
             } else {
                 // last_pkgname != pkgname, we got to a new package, reset counter
@@ -146,16 +429,72 @@ pub(crate) fn rm_old_crates(
             } // if last_pkgname == pkgname
         } // for pkgpath in &crate_list
     }
+
+    let (limited, remaining) = limit_removals(settings, &to_remove);
+    let progress = RemovalProgress::new(settings, limited.len(), dry_run);
+    remove_all(
+        settings,
+        limited,
+        dry_run,
+        size_changed,
+        |(pkgpath, size, dryrun_msg), changed| {
+            // failed removals are reported and do not count as reclaimed
+            if remove_file(
+                settings,
+                pkgpath,
+                dry_run,
+                changed,
+                None,
+                &DryRunMessage::Custom(dryrun_msg),
+                None,
+            ) {
+                progress.removed(*size);
+            }
+        },
+    );
+    progress.finish();
+    report_removal_limit(settings, remaining);
+
     println!(
         "Removed {} of compressed crate sources.",
-        removed_size.file_size(file_size_opts::DECIMAL).unwrap()
+        progress.reclaimed().file_size(size_opts(settings)).unwrap()
     );
     Ok(())
 }
 
+/// the checkouts of every repo except for the `keep_last` most recently modified ones
+fn old_checkouts(checkouts: &[PathBuf], keep_last: usize) -> Vec<PathBuf> {
+    git_checkouts::checkouts_by_repo(checkouts)
+        .into_values()
+        .flat_map(|revs| revs.into_iter().skip(keep_last).cloned())
+        .collect()
+}
+
+/// per git repo, remove all checkouts but the `keep_last` most recently modified ones
+/// the bare repos are kept, cargo checks out removed revisions again when needed
+pub(crate) fn prune_git_checkouts(
+    settings: &Settings,
+    keep_last: usize,
+    dry_run: bool,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    size_changed: &mut bool,
+) {
+    let to_remove = old_checkouts(checkouts_cache.items(), keep_last);
+
+    let progress = remove_with_progress(settings, &to_remove, dry_run, size_changed);
+    checkouts_cache.invalidate();
+
+    println!(
+        "{} {} of git checkouts.",
+        if dry_run { "Would remove" } else { "Removed" },
+        progress.reclaimed().file_size(size_opts(settings)).unwrap()
+    );
+}
+
 /// take a list of cache items via cmdline and remove them, invalidate caches too
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn remove_dir_via_cmdline(
+    settings: &Settings,
     directory: Option<&str>,
     dry_run: bool,
     ccd: &CargoCachePaths,
@@ -171,107 +510,215 @@ pub(crate) fn remove_dir_via_cmdline(
     let dirs_to_remove = components_from_groups(directory)?;
 
     let mut size_removed: u64 = 0;
+    // the directories to remove and their cache items
+    let mut to_remove = Vec::new();
 
     if dry_run {
-        println!(); // newline
+        print_message(settings, ""); // newline
     }
 
     for component in dirs_to_remove {
-        match component {
-            Component::RegistryCrateCache => {
-                let size = registry_pkgs_cache.total_size();
-                size_removed += size;
-                remove_with_default_message(
-                    &ccd.registry_pkg_cache,
-                    dry_run,
-                    size_changed,
-                    Some(size),
-                );
-                if !dry_run {
-                    registry_pkgs_cache.invalidate();
-                }
-            }
+        // the items are the crate archives, crate sources, indices, checkouts and bare repos
+        let (dir, size, items) = match component {
+            Component::RegistryCrateCache => (
+                &ccd.registry_pkg_cache,
+                registry_pkgs_cache.total_size(),
+                registry_pkgs_cache.items().to_vec(),
+            ),
+            Component::RegistrySources => (
+                &ccd.registry_sources,
+                registry_sources_caches.total_size(),
+                registry_sources_caches.items().to_vec(),
+            ),
+            // @TODO only remove specified index
+            Component::RegistryIndex => (
+                &ccd.registry_index,
+                registry_index_caches.total_size(),
+                registry_index_caches.items().to_vec(),
+            ),
+            Component::GitRepos => (
+                &ccd.git_checkouts,
+                checkouts_cache.total_size(),
+                checkouts_cache.items().to_vec(),
+            ),
+            Component::GitDB => (
+                &ccd.git_repos_bare,
+                bare_repos_cache.total_size(),
+                bare_repos_cache.items().to_vec(),
+            ),
+        };
+        size_removed += size;
 
-            Component::RegistrySources => {
-                let size = registry_sources_caches.total_size();
-                size_removed += size;
-                remove_with_default_message(
-                    &ccd.registry_sources,
-                    dry_run,
-                    size_changed,
-                    Some(size),
-                );
-                if !dry_run {
-                    registry_sources_caches.invalidate();
-                }
-            }
-            Component::RegistryIndex => {
-                // sum the sizes of the separate indices
-                let size_of_all_indices: u64 = registry_index_caches.total_size();
-
-                size_removed += size_of_all_indices;
-                // @TODO only remove specified index
-                remove_with_default_message(
-                    &ccd.registry_index,
-                    dry_run,
-                    size_changed,
-                    Some(size_of_all_indices),
-                );
-                if !dry_run {
-                    registry_index_caches.invalidate();
-                }
-            }
-            Component::GitRepos => {
-                let size = checkouts_cache.total_size();
-                size_removed += size;
-                remove_with_default_message(&ccd.git_checkouts, dry_run, size_changed, Some(size));
-                if !dry_run {
-                    checkouts_cache.invalidate();
-                }
-            }
-            Component::GitDB => {
-                let size = bare_repos_cache.total_size();
-                size_removed += size;
-                remove_with_default_message(&ccd.git_repos_bare, dry_run, size_changed, Some(size));
-                if !dry_run {
-                    bare_repos_cache.invalidate();
-                }
-            }
+        if dry_run {
+            let _ = remove_file(
+                settings,
+                dir,
+                dry_run,
+                size_changed,
+                None,
+                &DryRunMessage::Default,
+                Some(size),
+            );
+            continue;
         }
+
+        print_message(
+            settings,
+            &format!(
+                "removing: '{}'",
+                relative_paths::shown(settings, dir).display()
+            ),
+        );
+        to_remove.push((component, dir, items));
     }
 
     if dry_run {
-        println!(
-            "dry-run: would remove in total: {}",
-            size_removed.file_size(file_size_opts::DECIMAL).unwrap()
+        print_message(
+            settings,
+            &format!(
+                "dry-run: would remove in total: {}",
+                size_removed.file_size(size_opts(settings)).unwrap()
+            ),
         );
+        return Ok(());
+    }
+
+    // "--max-removals" counts the items of all directories together, an item is never removed partially
+    let all_items: Vec<PathBuf> = to_remove
+        .iter()
+        .flat_map(|(_component, _dir, items)| items.iter().cloned())
+        .collect();
+    let mut removed_items = limit_removals(settings, &all_items).0.len();
+    let _ = remove_with_progress(settings, &all_items, false, size_changed);
+    for (component, dir, items) in to_remove {
+        // if the limit stopped early, the directory is kept together with its remaining items
+        if items.len() <= removed_items {
+            remove_emptied_dir(settings, dir, size_changed);
+        }
+        removed_items = removed_items.saturating_sub(items.len());
+        match component {
+            Component::RegistryCrateCache => registry_pkgs_cache.invalidate(),
+            Component::RegistrySources => registry_sources_caches.invalidate(),
+            Component::RegistryIndex => registry_index_caches.invalidate(),
+            Component::GitRepos => checkouts_cache.invalidate(),
+            Component::GitDB => bare_repos_cache.invalidate(),
+        }
     }
 
     Ok(())
 }
 
-/// remove a file with a default "removing: {file}" message
-pub(crate) fn remove_with_default_message(
-    dir: &Path,
+/// how much of a removal would have to be downloaded again and how much can be restored locally
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RedownloadEstimate {
+    /// crate archives, registry indices and bare git repos, restoring them needs the network
+    pub(crate) network_bytes: u64,
+    /// extracted sources and git checkouts, cargo re-extracts them from the archives and repos
+    pub(crate) local_bytes: u64,
+}
+
+impl RedownloadEstimate {
+    fn add(&mut self, component: &Component, size: u64) {
+        match component {
+            Component::RegistryCrateCache | Component::RegistryIndex | Component::GitDB => {
+                self.network_bytes += size;
+            }
+            Component::RegistrySources | Component::GitRepos => self.local_bytes += size,
+        }
+    }
+}
+
+impl RedownloadEstimate {
+    /// the estimate for humans
+    pub(crate) fn summary(&self, settings: &Settings) -> String {
+        let human = |size: u64| size.file_size(size_opts(settings)).unwrap();
+        format!(
+            "Removing these directories would free {}:
+  network cost: {} (crate archives, registry indices and git repos are downloaded again)
+  CPU cost:     {} (sources and git checkouts are extracted again locally)",
+            human(self.network_bytes + self.local_bytes),
+            human(self.network_bytes),
+            human(self.local_bytes)
+        )
+    }
+}
+
+/// estimate how much of the directories passed to "--remove-dir" would have to be downloaded again,
+/// nothing is removed
+pub(crate) fn estimate_redownload(
+    directory: Option<&str>,
+    checkouts_cache: &mut git_checkouts::GitCheckoutCache,
+    bare_repos_cache: &mut git_bare_repos::GitRepoCache,
+    registry_index_caches: &mut registry_index::RegistryIndicesCache,
+    registry_pkgs_cache: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<RedownloadEstimate, Error> {
+    let mut estimate = RedownloadEstimate::default();
+    for component in components_from_groups(directory)? {
+        let size = match component {
+            Component::RegistryCrateCache => registry_pkgs_cache.total_size(),
+            Component::RegistrySources => registry_sources_caches.total_size(),
+            Component::RegistryIndex => registry_index_caches.total_size(),
+            Component::GitRepos => checkouts_cache.total_size(),
+            Component::GitDB => bare_repos_cache.total_size(),
+        };
+        estimate.add(&component, size);
+    }
+    Ok(estimate)
+}
+
+/// remove the items and show a progress bar that advances per item, at most "--max-removals" items are removed
+/// the returned progress knows how much was reclaimed
+pub(crate) fn remove_with_progress<'a, P: AsRef<Path> + Sync>(
+    settings: &'a Settings,
+    items: &[P],
     dry_run: bool,
     size_changed: &mut bool,
-    total_size_from_cache: Option<u64>,
-) {
-    // remove a specified subdirectory from cargo cache
-    let msg = Some(format!("removing: '{}'", dir.display()));
+) -> RemovalProgress<'a> {
+    let (limited, remaining) = limit_removals(settings, items);
+    let progress = RemovalProgress::new(settings, limited.len(), dry_run);
+    remove_all(settings, limited, dry_run, size_changed, |item, changed| {
+        let size = size_of_path(settings, item.as_ref());
+        // failed removals are reported and do not count as reclaimed
+        if remove_file(
+            settings,
+            item.as_ref(),
+            dry_run,
+            changed,
+            None,
+            &DryRunMessage::Default,
+            Some(size),
+        ) {
+            progress.removed(size);
+        }
+    });
+    progress.finish();
+    report_removal_limit(settings, remaining);
+    progress
+}
 
-    remove_file(
-        dir,
-        dry_run,
-        size_changed,
-        msg,
-        &DryRunMessage::Default,
-        total_size_from_cache,
-    );
+/// remove `dir` once all of its cache items were removed, together with what is left in it
+/// like the emptied registry and repo directories
+fn remove_emptied_dir(settings: &Settings, dir: &Path, size_changed: &mut bool) {
+    if dir.is_dir() {
+        let _ = remove_file(
+            settings,
+            dir,
+            false,
+            size_changed,
+            None,
+            &DryRunMessage::Default,
+            Some(0),
+        );
+    }
 }
 
 /// remove a file with a custom message
+/// returns false if the removal failed, the failure is reported together with the other failed removals
+/// dry runs remove nothing and return true
 pub(crate) fn remove_file(
+    // the settings of the run
+    settings: &Settings,
     // path of the file to be deleted
     path: &Path,
     // is this only a dry run? if yes, remove nothing
@@ -284,60 +731,139 @@ pub(crate) fn remove_file(
     dry_run_msg: &DryRunMessage<'_>,
     // size of the file according to cache
     total_size_from_cache: Option<u64>,
-) {
+) -> bool {
     if dry_run {
+        // listing the directory is only needed for "--fail-if-removable"
+        if settings.removals.fail_if_removable && is_removable(path) {
+            add_planned_removals(settings, 1);
+        }
         match dry_run_msg {
-            DryRunMessage::Custom(msg) => {
-                println!("{}", msg);
-            }
+            DryRunMessage::Custom(msg) => print_message(settings, msg),
             DryRunMessage::Default => {
                 #[allow(clippy::single_match_else)]
                 match total_size_from_cache {
                     Some(size) => {
                         // print the size that is saved from the cache before removing
-                        let size_hr = size.file_size(file_size_opts::DECIMAL).unwrap();
-                        println!("dry-run: would remove: '{}' ({})", path.display(), size_hr);
+                        let size_hr = size.file_size(size_opts(settings)).unwrap();
+                        print_message(
+                            settings,
+                            &format!(
+                                "dry-run: would remove: '{}' ({})",
+                                relative_paths::shown(settings, path).display(),
+                                size_hr
+                            ),
+                        );
                     }
                     None => {
                         // default case: print this message
-                        println!("dry-run: would remove: '{}'", path.display());
+                        print_message(
+                            settings,
+                            &format!(
+                                "dry-run: would remove: '{}'",
+                                relative_paths::shown(settings, path).display()
+                            ),
+                        );
                     }
                 }
             }
             DryRunMessage::None => {}
         }
+        true
     } else {
         // no dry run
         // print deletion message if we have one
         if let Some(msg) = deletion_msg {
-            println!("{}", msg);
+            print_message(settings, &msg);
         }
 
-        if path.is_file() && fs::remove_file(&path).is_err() {
-            eprintln!("Warning: failed to remove file \"{}\".", path.display());
+        // only walk the path to get its size if we actually need it
+        let history_size = if history::is_enabled(settings) {
+            Some(total_size_from_cache.unwrap_or_else(|| size_of_path(settings, path)))
         } else {
-            *size_changed = true;
-        }
+            None
+        };
 
-        if path.is_dir() {
-            if let Err(error) = remove_dir_all::remove_dir_all(&path) {
-                eprintln!(
-                    "Warning: failed to recursively remove directory \"{}\".",
-                    path.display()
-                );
-                eprintln!("error: {:?}", error);
-            } else {
+        let removed = match remove_with_retries(|| delete_path(settings, path)) {
+            Ok(()) => {
                 *size_changed = true;
+                true
             }
+            Err(error) => {
+                record_failed_removal(settings, path, error);
+                false
+            }
+        };
+
+        if let Some(size) = history_size.filter(|_| !path.exists()) {
+            history::record(settings, path, size);
         }
+        removed
     }
 }
 
+/// all symlinks below `dir` whose targets do not exist (anymore), the symlinks are not followed
+/// entries that can not be read are skipped with a warning
+pub(crate) fn broken_symlinks(settings: &Settings, dir: &Path) -> Vec<PathBuf> {
+    if !dir.exists() {
+        return Vec::new();
+    }
+    warnings::readable_entries(settings, dir)
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| {
+            fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+        })
+        // exists() follows the symlink
+        .filter(|symlink| !symlink.exists())
+        .collect()
+}
+
+/// remove all broken symlinks inside the registry and the git caches (--remove-broken-symlinks)
+/// the rest of the cargo home (bin dir, config, toolchains linked in by other tools) is not touched
+pub(crate) fn remove_broken_symlinks(
+    settings: &Settings,
+    ccd: &CargoCachePaths,
+    dry_run: bool,
+    size_changed: &mut bool,
+) {
+    let symlinks = [&ccd.registry, &ccd.git_repos_bare, &ccd.git_checkouts]
+        .iter()
+        .flat_map(|dir| broken_symlinks(settings, dir))
+        .collect::<Vec<_>>();
+
+    for symlink in &symlinks {
+        if dry_run {
+            println!(
+                "dry-run: would remove broken symlink: '{}'",
+                relative_paths::shown(settings, symlink).display()
+            );
+        } else if let Err(error) = remove_with_retries(|| delete_path(settings, symlink)) {
+            // is_dir() follows the broken symlink and is false, so the link itself is removed
+            record_failed_removal(settings, symlink, error);
+        } else {
+            *size_changed = true;
+            history::record(settings, symlink, 0);
+        }
+    }
+
+    println!(
+        "{} {} broken {}",
+        if dry_run { "Would remove" } else { "Removed" },
+        symlinks.len(),
+        if symlinks.len() == 1 {
+            "symlink"
+        } else {
+            "symlinks"
+        }
+    );
+    report_failed_removals(settings);
+}
+
 #[cfg(test)]
 mod libtests {
     use super::*;
 
     use pretty_assertions::assert_eq;
+    use std::sync::Arc;
 
     #[test]
     fn test_parse_version() {
@@ -353,4 +879,340 @@ mod libtests {
         assert_eq!(name, "cargo-cache");
         assert_eq!(version, "0.4.3");
     }
+
+    #[test]
+    fn removals_are_retried() {
+        // fails with the given errors, then succeeds
+        let attempts_until = |error_kinds: Vec<ErrorKind>| {
+            let mut errors = error_kinds.into_iter();
+            let mut attempts = 0;
+            let result = remove_with_retries(|| {
+                attempts += 1;
+                errors
+                    .next()
+                    .map_or(Ok(()), |kind| Err(io::Error::from(kind)))
+            });
+            (result.map_err(|error| error.kind()), attempts)
+        };
+
+        assert_eq!(attempts_until(vec![]), (Ok(()), 1));
+        assert_eq!(
+            attempts_until(vec![ErrorKind::ResourceBusy, ErrorKind::WouldBlock]),
+            (Ok(()), 3)
+        );
+        // gave up after 3 attempts
+        assert_eq!(
+            attempts_until(vec![ErrorKind::ResourceBusy; 3]),
+            (Err(ErrorKind::ResourceBusy), 3)
+        );
+        // already gone
+        assert_eq!(attempts_until(vec![ErrorKind::NotFound]), (Ok(()), 1));
+        // permanent errors are not retried
+        assert_eq!(
+            attempts_until(vec![ErrorKind::PermissionDenied]),
+            (Err(ErrorKind::PermissionDenied), 1)
+        );
+    }
+
+    #[test]
+    fn removals_stop_at_limit() {
+        let items = [1, 2, 3, 4, 5];
+        assert_eq!(split_at_limit(&items, None), (&items[..], 0));
+        assert_eq!(split_at_limit(&items, Some(2)), (&items[..2], 3));
+        assert_eq!(split_at_limit(&items, Some(5)), (&items[..], 0));
+        assert_eq!(split_at_limit(&items, Some(10)), (&items[..], 0));
+        assert_eq!(split_at_limit(&items, Some(0)), (&items[..0], 5));
+    }
+
+    #[test]
+    fn only_non_empty_items_are_removable() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty");
+        fs::create_dir(&empty).unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, b"").unwrap();
+
+        assert!(!is_removable(&empty));
+        assert!(!is_removable(&dir.path().join("missing")));
+        assert!(is_removable(&file));
+        // the dir is not empty anymore
+        assert!(is_removable(dir.path()));
+    }
+
+    #[test]
+    fn redownload_estimate() {
+        let mut estimate = RedownloadEstimate::default();
+        estimate.add(&Component::RegistryCrateCache, 1000);
+        estimate.add(&Component::RegistryIndex, 200);
+        estimate.add(&Component::GitDB, 30);
+        estimate.add(&Component::RegistrySources, 4000);
+        estimate.add(&Component::GitRepos, 500);
+        assert_eq!(
+            estimate,
+            RedownloadEstimate {
+                network_bytes: 1230,
+                local_bytes: 4500,
+            }
+        );
+        assert_eq!(
+            estimate.summary(&Settings::default()),
+            "Removing these directories would free 5.73 KB:
+  network cost: 1.23 KB (crate archives, registry indices and git repos are downloaded again)
+  CPU cost:     4.50 KB (sources and git checkouts are extracted again locally)"
+        );
+    }
+
+    #[test]
+    fn parallel_removal() {
+        for jobs in &[1, 4] {
+            let dir = tempfile::tempdir().unwrap();
+            let mut files = (0..20)
+                .map(|number| {
+                    let file = dir.path().join(format!("{number}.crate"));
+                    fs::write(&file, vec![0; number]).unwrap();
+                    (file, number as u64)
+                })
+                .collect::<Vec<_>>();
+            // can not be removed because its parent is a file
+            let blocker = dir.path().join("blocker");
+            fs::write(&blocker, b"").unwrap();
+            files.push((blocker.join("100.crate"), 100));
+
+            let settings = Settings::default();
+            let progress = RemovalProgress::new(&settings, files.len(), false);
+            let mut size_changed = false;
+            remove_with_jobs(&files, *jobs, &mut size_changed, |(file, size), changed| {
+                if remove_file(
+                    &settings,
+                    file,
+                    false,
+                    changed,
+                    None,
+                    &DryRunMessage::None,
+                    Some(*size),
+                ) {
+                    progress.removed(*size);
+                }
+            });
+
+            assert!(size_changed);
+            // the failed removal is not reclaimed
+            assert_eq!(progress.reclaimed(), (0..20).sum::<u64>());
+            assert_eq!(settings.removals.failed.lock().unwrap().len(), 1);
+            assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        }
+    }
+
+    /// remove the directories of `groups` with "--max-removals `limit`"
+    fn remove_dirs_with_limit(cargo_home: &Path, groups: &str, limit: usize) {
+        let settings = Arc::new(Settings {
+            removals: Removals {
+                limit: Some(limit),
+                ..Removals::default()
+            },
+            ..Settings::default()
+        });
+        let ccd = CargoCachePaths::new(cargo_home.to_path_buf()).unwrap();
+        let mut size_changed = false;
+        remove_dir_via_cmdline(
+            &settings,
+            Some(groups),
+            false,
+            &ccd,
+            &mut size_changed,
+            &mut git_checkouts::GitCheckoutCache::new(
+                ccd.git_checkouts.clone(),
+                Arc::clone(&settings),
+            ),
+            &mut git_bare_repos::GitRepoCache::new(
+                ccd.git_repos_bare.clone(),
+                Arc::clone(&settings),
+            ),
+            &mut registry_index::RegistryIndicesCache::new(
+                ccd.registry_index.clone(),
+                Arc::clone(&settings),
+            ),
+            &mut registry_pkg_cache::RegistryPkgCaches::new(
+                ccd.registry_pkg_cache.clone(),
+                Arc::clone(&settings),
+            ),
+            &mut registry_sources::RegistrySourceCaches::new(
+                ccd.registry_sources.clone(),
+                Arc::clone(&settings),
+            ),
+        )
+        .unwrap();
+        assert!(size_changed);
+    }
+
+    #[test]
+    fn max_removals_only_removes_whole_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = dir.path().join("registry/src/github.com-1ecc6299db9ec823");
+        let sources = ["humansize-1.1.1", "foo-0.1.0", "bar-0.2.0"].map(|name| registry.join(name));
+        for krate in &sources {
+            fs::create_dir_all(krate.join("src")).unwrap();
+            fs::write(krate.join("src").join("lib.rs"), b"12345").unwrap();
+            fs::write(krate.join("Cargo.toml"), b"123").unwrap();
+            fs::write(krate.join(".cargo-ok"), b"").unwrap();
+        }
+        let repo = dir.path().join("git/checkouts/cargo-e7ff1db891893a9e");
+        let checkouts = [repo.join("258adf1"), repo.join("5f6a4b2")];
+        for checkout in &checkouts {
+            fs::create_dir_all(checkout).unwrap();
+            fs::write(checkout.join("Cargo.toml"), b"123").unwrap();
+        }
+
+        // a crate source is removed entirely or not at all
+        remove_dirs_with_limit(dir.path(), "registry-sources", 1);
+        assert_eq!(sources.iter().filter(|krate| krate.exists()).count(), 2);
+
+        // the limit counts the items of all directories, the checkouts come first
+        // a directory is only removed together with all of its items
+        remove_dirs_with_limit(dir.path(), "registry-sources,git-repos", 3);
+        assert!(!dir.path().join("git/checkouts").exists());
+        let left = sources
+            .iter()
+            .filter(|krate| krate.exists())
+            .collect::<Vec<_>>();
+        assert_eq!(left.len(), 1);
+        for file in &["src/lib.rs", "Cargo.toml", ".cargo-ok"] {
+            assert!(left[0].join(file).is_file());
+        }
+        assert_eq!(
+            checkouts
+                .iter()
+                .filter(|checkout| checkout.exists())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn keep_duplicate_crates_skips_kept_crates() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = dir.path().join("github.com-1ecc6299db9ec823");
+        fs::create_dir(&registry).unwrap();
+        for krate in &[
+            "foo-0.1.0.crate",
+            "foo-0.2.0.crate",
+            "bar-0.1.0.crate",
+            "bar-0.2.0.crate",
+        ] {
+            fs::write(registry.join(krate), b"123").unwrap();
+        }
+
+        let mut size_changed = false;
+        rm_old_crates(
+            &Settings::default(),
+            1,
+            &["foo"],
+            false,
+            dir.path(),
+            &mut size_changed,
+        )
+        .unwrap();
+
+        assert!(size_changed);
+        assert!(registry.join("foo-0.1.0.crate").exists());
+        assert!(registry.join("foo-0.2.0.crate").exists());
+        assert!(!registry.join("bar-0.1.0.crate").exists());
+        assert!(registry.join("bar-0.2.0.crate").exists());
+    }
+
+    #[test]
+    fn kept_crates() {
+        let registry = Path::new("/home/user/.cargo/registry");
+        let archive = registry.join("cache/github.com-1ecc6299db9ec823/serde-1.0.100.crate");
+        let source = registry.join("src/github.com-1ecc6299db9ec823/serde-1.0.100");
+        let source_file = source.join("src").join("lib.rs");
+
+        for path in &[&archive, &source, &source_file] {
+            assert!(is_kept_crate(path, &["serde"]), "{}", path.display());
+            assert!(!is_kept_crate(path, &["serde_json"]), "{}", path.display());
+            assert!(!is_kept_crate(path, &[]), "{}", path.display());
+        }
+        // the registry directories themselves and the index are not crates
+        assert!(!is_kept_crate(&registry.join("src"), &["serde"]));
+        assert!(!is_kept_crate(&registry.join("index/serde"), &["serde"]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn find_and_remove_broken_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        fs::write(&target, b"123").unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+
+        symlink(&target, dir.path().join("working")).unwrap();
+        symlink(dir.path().join("gone"), sub.join("broken")).unwrap();
+
+        assert_eq!(
+            broken_symlinks(&Settings::default(), dir.path()),
+            vec![sub.join("broken")]
+        );
+        assert!(broken_symlinks(&Settings::default(), &dir.path().join("missing")).is_empty());
+
+        // only the registry and the git caches are cleaned up
+        let ccd = CargoCachePaths::new(dir.path().to_path_buf()).unwrap();
+        fs::create_dir_all(&ccd.registry_sources).unwrap();
+        fs::create_dir_all(&ccd.git_checkouts).unwrap();
+        let in_registry = ccd.registry_sources.join("broken");
+        let in_checkouts = ccd.git_checkouts.join("broken");
+        symlink(dir.path().join("gone"), &in_registry).unwrap();
+        symlink(dir.path().join("gone"), &in_checkouts).unwrap();
+
+        let mut size_changed = false;
+        remove_broken_symlinks(&Settings::default(), &ccd, true, &mut size_changed);
+        assert!(!size_changed);
+        assert!(fs::symlink_metadata(&in_registry).is_ok());
+
+        remove_broken_symlinks(&Settings::default(), &ccd, false, &mut size_changed);
+        assert!(size_changed);
+        assert!(fs::symlink_metadata(&in_registry).is_err());
+        assert!(fs::symlink_metadata(&in_checkouts).is_err());
+        // symlinks outside of the caches, the working symlink and its target are kept
+        assert_eq!(
+            broken_symlinks(&Settings::default(), dir.path()),
+            vec![sub.join("broken")]
+        );
+        assert!(dir.path().join("working").exists());
+        assert!(target.exists());
+    }
+
+    #[test]
+    fn only_the_newest_checkouts_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("foo-0123456789abcdef");
+        let checkouts = ["aaaaaaa", "bbbbbbb", "ccccccc"]
+            .iter()
+            .enumerate()
+            .map(|(age, rev)| {
+                let checkout = repo.join(rev);
+                fs::create_dir_all(&checkout).unwrap();
+                // aaaaaaa is the newest, ccccccc the oldest
+                let modified = std::time::SystemTime::now() - Duration::from_hours(age as u64);
+                fs::File::open(&checkout)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+                checkout
+            })
+            .collect::<Vec<_>>();
+        let other = dir.path().join("bar-0123456789abcdef").join("ddddddd");
+        fs::create_dir_all(&other).unwrap();
+        let mut all = checkouts.clone();
+        all.push(other);
+
+        assert_eq!(
+            old_checkouts(&all, 1),
+            vec![checkouts[1].clone(), checkouts[2].clone()]
+        );
+        assert_eq!(old_checkouts(&all, 2), vec![checkouts[2].clone()]);
+        assert!(old_checkouts(&all, 3).is_empty());
+    }
 }
@@ -0,0 +1,73 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file provides the settings of a run: the options that change how items are sized, shown and
+/// removed, and what is collected while cargo-cache runs (warnings, timings, failed removals).
+/// They are created once in `main()` and passed down to everything that needs them.
+use std::path::PathBuf;
+
+use crate::exclude::Excludes;
+use crate::profile::Profile;
+use crate::remove::Removals;
+use crate::size_source::SizeSource;
+use crate::warnings::Warnings;
+
+/// the number of decimal places of human readable sizes without "--precision"
+pub(crate) const DEFAULT_PRECISION: usize = 2;
+
+/// everything the command line options change for the whole run
+#[derive(Debug)]
+pub(crate) struct Settings {
+    /// number of decimal places of human readable sizes ("--precision")
+    pub(crate) precision: usize,
+    /// the sub-lines of the summary are adjusted to add up to their shown total ("--reconcile")
+    pub(crate) reconcile: bool,
+    /// the git db is sized even if it is on a network filesystem ("--force-git")
+    pub(crate) force_git: bool,
+    /// the results are printed as json ("--format json"), messages for humans go to stderr
+    pub(crate) json_output: bool,
+    /// paths inside of this cargo home are shown relative to it ("--relative-paths")
+    pub(crate) relative_to: Option<PathBuf>,
+    /// the files that are left out of the sizes and counts ("--exclude-glob")
+    pub(crate) excludes: Option<Excludes>,
+    /// where the sizes of files come from
+    pub(crate) sizes: SizeSource,
+    /// how items are removed and which removals failed
+    pub(crate) removals: Removals,
+    /// the warnings of the scan
+    pub(crate) warnings: Warnings,
+    /// how long the phases of the scan took ("--profile")
+    pub(crate) profile: Profile,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+            reconcile: false,
+            force_git: false,
+            json_output: false,
+            relative_to: None,
+            excludes: None,
+            sizes: SizeSource::default(),
+            removals: Removals::default(),
+            warnings: Warnings::default(),
+            profile: Profile::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Settings {
+    /// the default settings, for tests that build results which keep them around
+    pub(crate) fn for_tests() -> &'static Self {
+        static SETTINGS: std::sync::OnceLock<Settings> = std::sync::OnceLock::new();
+        SETTINGS.get_or_init(Self::default)
+    }
+}
@@ -0,0 +1,340 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file implements "--size-source <FILE>": file sizes are taken from the saved output of
+/// "du -ab <cargo home>" instead of asking the filesystem for the metadata of every file,
+/// which helps on very slow (network) filesystems.
+/// Files that are not listed in the du output are still stat-ed.
+///
+/// It also implements "--dedup-hardlinks": a file with several hard links is only counted
+/// for the first of its paths that is sized, every other link counts as 0 bytes.
+/// This needs the device and inode numbers of files, so it only has an effect on unix.
+///
+/// With "--disk", the space allocated to a file (its blocks) is used instead of its apparent
+/// size, like "du" does by default. Only unix has the block count, elsewhere "--disk" is a no-op.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::library::Error;
+use crate::relative_paths;
+use crate::settings::Settings;
+use crate::warnings;
+
+/// where the sizes of files come from ("--size-source", "--dedup-hardlinks" and "--disk")
+#[derive(Debug, Default)]
+pub(crate) struct SizeSource {
+    /// the sizes read from the du output, None if "--size-source" was not passed
+    du: Option<DuOutput>,
+    /// (device, inode) => the path the file is counted for, None if "--dedup-hardlinks" was not passed
+    counted_inodes: Option<Mutex<HashMap<(u64, u64), PathBuf>>>,
+    /// whether the allocated size is used instead of the apparent size ("--disk")
+    disk_usage: bool,
+}
+
+/// the parsed du output
+#[derive(Debug)]
+struct DuOutput {
+    /// path => size, for directories this is the size of everything below them
+    sizes: HashMap<PathBuf, u64>,
+    /// the listed paths that have listed entries, so these are directories
+    dirs: HashSet<PathBuf>,
+}
+
+impl DuOutput {
+    fn new(sizes: HashMap<PathBuf, u64>) -> Self {
+        let dirs = sizes
+            .keys()
+            .filter_map(|path| path.parent())
+            .filter(|parent| sizes.contains_key(*parent))
+            .map(Path::to_path_buf)
+            .collect();
+        Self { sizes, dirs }
+    }
+}
+
+/// parse the output of "du -ab": one "<bytes>\t<path>" line per file or directory
+fn parse_du_output(content: &str, du_file: &Path) -> Result<HashMap<PathBuf, u64>, Error> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_number, line)| !line.is_empty())
+        .map(|(number, line)| {
+            let unparsable = || {
+                Error::UnparsableSizeSource(
+                    du_file.to_path_buf(),
+                    format!("line {} is not \"<bytes><tab><path>\"", number + 1),
+                )
+            };
+            let (bytes, path) = line.split_once('\t').ok_or_else(unparsable)?;
+            let size = bytes.trim().parse::<u64>().map_err(|_| unparsable())?;
+            Ok((PathBuf::from(path), size))
+        })
+        .collect()
+}
+
+impl SizeSource {
+    /// read the du output if there is one, with `dedup_hardlinks` files with several hard links are
+    /// only counted once, with `disk_usage` the space allocated on disk is the size of a file
+    pub(crate) fn new(
+        du_file: Option<&Path>,
+        dedup_hardlinks: bool,
+        disk_usage: bool,
+    ) -> Result<Self, Error> {
+        let du = match du_file {
+            Some(file) => {
+                let content = fs::read_to_string(file)
+                    .map_err(|e| Error::UnparsableSizeSource(file.to_path_buf(), e.to_string()))?;
+                Some(DuOutput::new(parse_du_output(&content, file)?))
+            }
+            None => None,
+        };
+        Ok(Self {
+            du,
+            counted_inodes: dedup_hardlinks.then(|| Mutex::new(HashMap::new())),
+            disk_usage,
+        })
+    }
+}
+
+/// the number of bytes allocated to the file, the blocks are always 512 bytes
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.blocks() * 512
+}
+
+/// no block count, fall back to the apparent size
+#[cfg(not(unix))]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// the apparent size or with "--disk" the allocated size of a file or directory entry
+pub(crate) fn size_of_metadata(settings: &Settings, metadata: &fs::Metadata) -> u64 {
+    if settings.sizes.disk_usage {
+        allocated_size(metadata)
+    } else {
+        metadata.len()
+    }
+}
+
+/// (device, inode) of a file with several hard links, None if it has only one
+#[cfg(unix)]
+fn shared_inode(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// no inode numbers, every link is counted
+#[cfg(not(unix))]
+fn shared_inode(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// whether the inode is already counted for another path, if not it is counted for `file` from now on
+/// sizing the same path again (after a cache was invalidated) counts it again
+fn is_counted_elsewhere(
+    counted: &mut HashMap<(u64, u64), PathBuf>,
+    inode: (u64, u64),
+    file: &Path,
+) -> bool {
+    counted
+        .entry(inode)
+        .or_insert_with(|| file.to_path_buf())
+        .as_path()
+        != file
+}
+
+/// the metadata of a file, None with a warning if it can not be read
+fn metadata_of(settings: &Settings, file: &Path) -> Option<fs::Metadata> {
+    match fs::metadata(file) {
+        Ok(metadata) => Some(metadata),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            let shown = relative_paths::shown(settings, file);
+            warnings::warn(
+                settings,
+                format!("'{}' vanished during the scan", shown.display()),
+            );
+            None
+        }
+        Err(error) => {
+            let shown = relative_paths::shown(settings, file);
+            warnings::warn(
+                settings,
+                format!("failed to size '{}': {}", shown.display(), error),
+            );
+            None
+        }
+    }
+}
+
+/// whether `path` is a directory, paths listed in the du output are not stat-ed
+/// (an empty directory looks like a file to the du output, which also lists it as 0 bytes)
+pub(crate) fn is_dir(settings: &Settings, path: &Path) -> bool {
+    match &settings.sizes.du {
+        Some(du) if du.sizes.contains_key(path) => du.dirs.contains(path),
+        _ => path.is_dir(),
+    }
+}
+
+/// the size of a file, from the du output if it lists the file, from its metadata otherwise
+/// only pass files, du lists the total size of a directory and not the size of its entry
+/// files that can not be sized count as empty
+pub(crate) fn file_size(settings: &Settings, file: &Path) -> u64 {
+    let counted_inodes = settings.sizes.counted_inodes.as_ref();
+    let metadata = if counted_inodes.is_some() {
+        match metadata_of(settings, file) {
+            Some(file_metadata) => Some(file_metadata),
+            None => return 0,
+        }
+    } else {
+        None
+    };
+
+    if let Some(inode) = metadata.as_ref().and_then(shared_inode) {
+        let counted_elsewhere = counted_inodes
+            .and_then(|counted| counted.lock().ok())
+            .is_some_and(|mut counted| is_counted_elsewhere(&mut counted, inode, file));
+        if counted_elsewhere {
+            return 0;
+        }
+    }
+
+    match settings.sizes.du.as_ref().and_then(|du| du.sizes.get(file)) {
+        Some(size) => *size,
+        None => metadata
+            .or_else(|| metadata_of(settings, file))
+            .map_or(0, |file_metadata| {
+                size_of_metadata(settings, &file_metadata)
+            }),
+    }
+}
+
+#[cfg(test)]
+mod size_source_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_du() {
+        let du = "5\t/home/user/.cargo/registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate
+4101\t/home/user/.cargo/registry/cache/github.com-1ecc6299db9ec823
+12\t/home/user/.cargo/dir with\ttab/file
+
+";
+        let sizes = parse_du_output(du, Path::new("du.txt")).unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(
+            sizes[Path::new(
+                "/home/user/.cargo/registry/cache/github.com-1ecc6299db9ec823/foo-0.1.0.crate"
+            )],
+            5
+        );
+        assert_eq!(sizes[Path::new("/home/user/.cargo/dir with\ttab/file")], 12);
+    }
+
+    #[test]
+    fn dirs_of_du_output() {
+        let du = DuOutput::new(
+            parse_du_output(
+                "5\t/cargo/git/checkouts/foo/abc/lib.rs\n4101\t/cargo/git/checkouts/foo/abc\n0\t/cargo/git/checkouts/foo/empty\n",
+                Path::new("du.txt"),
+            )
+            .unwrap(),
+        );
+        assert!(du.dirs.contains(Path::new("/cargo/git/checkouts/foo/abc")));
+        assert!(!du
+            .dirs
+            .contains(Path::new("/cargo/git/checkouts/foo/abc/lib.rs")));
+        // not listed itself
+        assert!(!du.dirs.contains(Path::new("/cargo/git/checkouts/foo")));
+        assert_eq!(du.dirs.len(), 1);
+    }
+
+    #[test]
+    fn parse_du_errors() {
+        let error_of = |du| match parse_du_output(du, Path::new("du.txt")) {
+            Err(error) => error.to_string(),
+            Ok(sizes) => panic!("expected an error, got {:?}", sizes),
+        };
+        assert_eq!(
+            error_of("5\tfoo\nbar\n"),
+            "Failed to read sizes from du output 'du.txt': line 2 is not \"<bytes><tab><path>\""
+        );
+        // "du -h" output
+        assert_eq!(
+            error_of("5K\tfoo\n"),
+            "Failed to read sizes from du output 'du.txt': line 1 is not \"<bytes><tab><path>\""
+        );
+    }
+
+    #[test]
+    fn hardlinks_are_counted_once() {
+        let mut counted = HashMap::new();
+        let first = Path::new("registry/cache/a/foo-0.1.0.crate");
+        let second = Path::new("registry/cache/b/foo-0.1.0.crate");
+        assert!(!is_counted_elsewhere(&mut counted, (1, 42), first));
+        assert!(is_counted_elsewhere(&mut counted, (1, 42), second));
+        // the first path keeps being counted
+        assert!(!is_counted_elsewhere(&mut counted, (1, 42), first));
+        // same inode number on another device
+        assert!(!is_counted_elsewhere(&mut counted, (2, 42), second));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shared_inodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a");
+        let link = dir.path().join("b");
+        fs::write(&file, b"12345").unwrap();
+        assert_eq!(shared_inode(&fs::metadata(&file).unwrap()), None);
+
+        fs::hard_link(&file, &link).unwrap();
+        let inode = shared_inode(&fs::metadata(&file).unwrap());
+        assert!(inode.is_some());
+        assert_eq!(shared_inode(&fs::metadata(&link).unwrap()), inode);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn allocated_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a");
+        fs::write(&file, b"12345").unwrap();
+        let metadata = fs::metadata(&file).unwrap();
+        // a tiny file still takes at least one block, unless the filesystem inlines it
+        let allocated = allocated_size(&metadata);
+        assert_eq!(allocated % 512, 0);
+        assert!(allocated == 0 || allocated >= 512);
+        assert_eq!(metadata.len(), 5);
+    }
+
+    #[test]
+    fn unlisted_files_are_stated() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a");
+        fs::write(&file, b"12345").unwrap();
+        assert_eq!(file_size(&Settings::default(), &file), 5);
+    }
+
+    #[test]
+    fn vanished_files_are_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            file_size(&Settings::default(), &dir.path().join("removed")),
+            0
+        );
+    }
+}
@@ -9,6 +9,13 @@
 
 /// This file provides the `TableLine` struct which is used by
 /// `format_2_row_table()` to create neat-looking 2-column tables.
+/// With "--reconcile", the shown sizes of sub-lines are adjusted to add up to the shown total.
+use std::convert::TryFrom;
+
+use crate::library::size_opts;
+use crate::settings::Settings;
+
+use humansize::FileSize;
 
 /// struct used to format 2-column tables
 #[derive(Clone, Debug)]
@@ -126,6 +133,110 @@ pub(crate) fn two_row_table(
     table
 }
 
+/// the units of human readable sizes, each one is 1000 times the previous one
+const UNITS: [&str; 9] = ["B", "KB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
+
+/// a human readable size as it is shown, "12.57 MB" is 1257 hundredths of unit 2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ShownSize {
+    /// the shown digits without the decimal point
+    digits: u128,
+    /// index into `UNITS`
+    unit: usize,
+}
+
+impl ShownSize {
+    /// parse a size shown with `precision` decimal places, None if it is not a human readable size
+    fn parse(size: &str, precision: usize) -> Option<Self> {
+        let (number, unit_name) = size.split_once(' ')?;
+        let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if fraction.len() > precision {
+            return None;
+        }
+        Some(Self {
+            digits: format!("{integer}{fraction:0<precision$}").parse().ok()?,
+            unit: UNITS.iter().position(|unit| *unit == unit_name)?,
+        })
+    }
+
+    /// the shown size in multiples of the last shown digit of a byte
+    fn scaled_bytes(self) -> Option<u128> {
+        1000_u128
+            .checked_pow(u32::try_from(self.unit).ok()?)?
+            .checked_mul(self.digits)
+    }
+
+    /// format the size like humansize does, bytes have no decimal places
+    fn format(self, precision: usize) -> Option<String> {
+        let one = 10_u128.checked_pow(u32::try_from(precision).ok()?)?;
+        Some(if self.unit == 0 || precision == 0 {
+            format!("{} {}", self.digits / one, UNITS[self.unit])
+        } else {
+            format!(
+                "{}.{:0>precision$} {}",
+                self.digits / one,
+                self.digits % one,
+                UNITS[self.unit]
+            )
+        })
+    }
+}
+
+/// the human readable sizes of the sub-lines of the line that shows `total`
+/// with "--reconcile" the last shown digit of the largest sub-line is adjusted so that
+/// the shown sizes add up to the shown total
+pub(crate) fn sub_line_sizes<const N: usize>(
+    settings: &Settings,
+    total: &str,
+    sizes: [u64; N],
+) -> [String; N] {
+    let opts = size_opts(settings);
+    let mut shown = sizes.map(|size| size.file_size(&opts).unwrap());
+    if settings.reconcile {
+        let _ = reconcile(total, &sizes, &mut shown, opts.decimal_places);
+    }
+    shown
+}
+
+/// adjust the largest of the `shown` sizes so that they add up to the shown `total`
+/// sub-lines in a smaller unit than the total are rounded to its last digit when they are added up,
+/// if the largest one is shown in a smaller unit, the total is less precise than its sub-lines
+/// and nothing is changed
+fn reconcile(total: &str, sizes: &[u64], shown: &mut [String], precision: usize) -> Option<()> {
+    let shown_total = ShownSize::parse(total, precision)?;
+    // the first one if several are the largest
+    let (largest, _) = sizes
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, size)| **size)?;
+    let parts = shown
+        .iter()
+        .map(|size| ShownSize::parse(size, precision))
+        .collect::<Option<Vec<_>>>()?;
+    if parts[largest].unit != shown_total.unit {
+        return None;
+    }
+
+    let mut sum: u128 = 0;
+    for part in &parts {
+        sum = sum.checked_add(part.scaled_bytes()?)?;
+    }
+    let step = ShownSize {
+        digits: 1,
+        unit: shown_total.unit,
+    }
+    .scaled_bytes()?;
+    let shown_sum = (sum + step / 2) / step;
+    let digits = (parts[largest].digits + shown_total.digits).checked_sub(shown_sum)?;
+    shown[largest] = ShownSize {
+        digits,
+        unit: shown_total.unit,
+    }
+    .format(precision)?;
+    Some(())
+}
+
 /*
 structures the table as follows:
  vec![
@@ -266,3 +377,109 @@ mod format_table_tests {
         assert_eq!(t, output);
     }
 }
+
+#[cfg(test)]
+mod reconcile_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// the sizes as humansize shows them with 2 decimal places, reconciled with the shown total
+    fn reconciled(sizes: &[u64]) -> (String, Vec<String>) {
+        let opts = size_opts(&Settings::default());
+        let total = sizes.iter().sum::<u64>().file_size(&opts).unwrap();
+        let mut shown = sizes
+            .iter()
+            .map(|size| size.file_size(&opts).unwrap())
+            .collect::<Vec<_>>();
+        let _ = reconcile(&total, sizes, &mut shown, 2);
+        (total, shown)
+    }
+
+    #[test]
+    fn largest_sub_line_is_adjusted() {
+        // 1.00 KB + 1.00 KB + 1.00 KB is shown as 3.01 KB, the first of the largest lines is adjusted
+        assert_eq!(
+            reconciled(&[1004, 1004, 1004]),
+            (
+                String::from("3.01 KB"),
+                vec![
+                    String::from("1.01 KB"),
+                    String::from("1.00 KB"),
+                    String::from("1.00 KB")
+                ]
+            )
+        );
+        // lines in a smaller unit are rounded to the last digit of the total
+        assert_eq!(
+            reconciled(&[4990, 1_004_000, 1_004_000]),
+            (
+                String::from("2.01 MB"),
+                vec![
+                    String::from("4.99 KB"),
+                    String::from("1.01 MB"),
+                    String::from("1.00 MB")
+                ]
+            )
+        );
+        // lines that already add up are not changed
+        assert_eq!(
+            reconciled(&[23, 1000, 2500]),
+            (
+                String::from("3.52 KB"),
+                vec![
+                    String::from("23 B"),
+                    String::from("1 KB"),
+                    String::from("2.50 KB")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn largest_sub_line_in_a_smaller_unit_is_kept() {
+        // the total is shown in GB, less precise than its sub-lines
+        assert_eq!(
+            reconciled(&[505_004_999, 505_004_999]),
+            (
+                String::from("1.01 GB"),
+                vec![String::from("505.00 MB"), String::from("505.00 MB")]
+            )
+        );
+    }
+
+    #[test]
+    fn sub_lines_are_only_reconciled_with_the_setting() {
+        let settings = Settings::default();
+        assert_eq!(
+            sub_line_sizes(&settings, "3.01 KB", [1004, 1004, 1004]),
+            ["1.00 KB", "1.00 KB", "1.00 KB"]
+        );
+        let reconciling = Settings {
+            reconcile: true,
+            ..Settings::default()
+        };
+        assert_eq!(
+            sub_line_sizes(&reconciling, "3.01 KB", [1004, 1004, 1004]),
+            ["1.01 KB", "1.00 KB", "1.00 KB"]
+        );
+    }
+
+    #[test]
+    fn shown_sizes() {
+        let size = ShownSize::parse("12.5 MB", 2).unwrap();
+        assert_eq!(
+            size,
+            ShownSize {
+                digits: 1250,
+                unit: 2
+            }
+        );
+        assert_eq!(size.format(2).unwrap(), "12.50 MB");
+        assert_eq!(
+            ShownSize::parse("23 B", 2).unwrap().format(2).unwrap(),
+            "23 B"
+        );
+        assert_eq!(ShownSize::parse("1.234 MB", 2), None);
+        assert_eq!(ShownSize::parse("12 XB", 2), None);
+    }
+}
@@ -12,10 +12,13 @@ use std::path::Path;
 
 use crate::cache::caches::Cache;
 use crate::cache::*;
+use crate::library::size_opts;
+use crate::relative_paths;
+use crate::settings::Settings;
 use crate::tables::format_table;
 use crate::top_items::common::*;
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use rayon::prelude::*;
 
 #[derive(Debug)]
@@ -33,8 +36,8 @@ impl BinInfo {
         Self { name, size }
     }
 
-    fn size_string(&self) -> String {
-        self.size.file_size(file_size_opts::DECIMAL).unwrap()
+    fn size_string(&self, settings: &Settings) -> String {
+        self.size.file_size(size_opts(settings)).unwrap()
     }
 }
 
@@ -49,20 +52,23 @@ fn bininfo_list_from_path(bin_cache: &mut bin::BinaryCache) -> Vec<BinInfo> {
 }
 
 #[inline] // only called in one place
-fn bininfo_list_to_string(limit: u32, mut collections_vec: Vec<BinInfo>) -> String {
+fn bininfo_list_to_string(
+    settings: &Settings,
+    limit: u32,
+    mut collections_vec: Vec<BinInfo>,
+) -> String {
     if collections_vec.is_empty() {
         return String::new();
     }
-    // sort the BinInfo Vec in reverse
-    collections_vec.par_sort_by_key(|b| b.size);
-    collections_vec.reverse();
+    // biggest first, equal sizes by name
+    collections_vec.par_sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
 
     let mut table_matrix: Vec<Vec<String>> = Vec::with_capacity(collections_vec.len() + 1);
 
     table_matrix.push(vec!["Name".into(), "Size".into()]); // table header
 
     for bininfo in collections_vec.into_iter().take(limit as usize) {
-        let size = bininfo.size_string();
+        let size = bininfo.size_string(settings);
         table_matrix.push(vec![bininfo.name, size]);
     }
 
@@ -71,28 +77,29 @@ fn bininfo_list_to_string(limit: u32, mut collections_vec: Vec<BinInfo>) -> Stri
 
 #[inline] // only called in one place
 pub(crate) fn binary_stats(
+    settings: &Settings,
     path: &Path,
     limit: u32,
     mut bin_cache: &mut bin::BinaryCache,
 ) -> String {
     let mut output = String::new();
     // don't crash if the directory does not exist (issue #9)
-    if !dir_exists(path) {
+    if !dir_exists(settings, path) {
         return output;
     }
 
     output.push_str(&format!(
         "\nSummary of: {} ({} total)\n",
-        path.display(),
+        relative_paths::shown(settings, path).display(),
         bin_cache
             .total_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
 
     let collections_vec = bininfo_list_from_path(&mut bin_cache); // this is already sorted
 
-    let bininfo_string = bininfo_list_to_string(limit, collections_vec);
+    let bininfo_string = bininfo_list_to_string(settings, limit, collections_vec);
     output.push_str(&bininfo_string);
 
     output
@@ -149,7 +156,7 @@ mod bininfo_struct {
             name: String::from("abc"),
             size: 123,
         };
-        let size = bi.size_string();
+        let size = bi.size_string(&Settings::default());
         assert_eq!(size, "123 B");
     }
 
@@ -159,7 +166,7 @@ mod bininfo_struct {
             name: String::from("abc"),
             size: 1_234_567_890,
         };
-        let size = bi.size_string();
+        let size = bi.size_string(&Settings::default());
         assert_eq!(size, "1.23 GB");
     }
 
@@ -241,7 +248,7 @@ mod top_crates_binaries {
     fn stats_from_file_desc_none() {
         // empty list
         let list: Vec<BinInfo> = Vec::new();
-        let stats: String = bininfo_list_to_string(1, list);
+        let stats: String = bininfo_list_to_string(&Settings::default(), 1, list);
 
         let empty = String::new();
         assert_eq!(stats, empty);
@@ -254,7 +261,7 @@ mod top_crates_binaries {
             size: 1,
         };
         let list: Vec<BinInfo> = vec![bi];
-        let stats: String = bininfo_list_to_string(1, list);
+        let stats: String = bininfo_list_to_string(&Settings::default(), 1, list);
         let wanted = String::from("Name        Size\ncargo-cache 1 B\n");
         assert_eq!(stats, wanted);
     }
@@ -270,7 +277,7 @@ mod top_crates_binaries {
             size: 2,
         };
         let list: Vec<BinInfo> = vec![bi1, bi2];
-        let stats: String = bininfo_list_to_string(2, list);
+        let stats: String = bininfo_list_to_string(&Settings::default(), 2, list);
         let wanted = String::from("Name    Size\ncrate-B 2 B\ncrate-A 1 B\n");
         assert_eq!(stats, wanted);
     }
@@ -298,7 +305,7 @@ mod top_crates_binaries {
             size: 4,
         };
         let list: Vec<BinInfo> = vec![bi1, bi2, bi3, bi4, bi5];
-        let stats: String = bininfo_list_to_string(10, list);
+        let stats: String = bininfo_list_to_string(&Settings::default(), 10, list);
         let mut wanted = String::new();
         for i in &[
             "Name    Size\n",
@@ -327,7 +334,7 @@ mod top_crates_binaries {
         };
 
         let list: Vec<BinInfo> = vec![bi1, bi2];
-        let stats: String = bininfo_list_to_string(2, list);
+        let stats: String = bininfo_list_to_string(&Settings::default(), 2, list);
         let mut wanted = String::new();
         for i in &["Name    Size\n", "crate-A 3 B\n", "crate-A 3 B\n"] {
             wanted.push_str(i);
@@ -351,7 +358,7 @@ mod top_crates_binaries {
         };
 
         let list: Vec<BinInfo> = vec![bi1, bi2, bi3];
-        let stats: String = bininfo_list_to_string(4, list);
+        let stats: String = bininfo_list_to_string(&Settings::default(), 4, list);
         let mut wanted = String::new();
         for i in &[
             "Name    Size\n",
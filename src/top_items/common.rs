@@ -9,6 +9,9 @@
 
 use std::path::{Path, PathBuf};
 
+use crate::relative_paths;
+use crate::settings::Settings;
+
 #[derive(Debug)]
 pub(crate) struct Pair<T> {
     pub(crate) current: Option<T>,
@@ -22,12 +25,15 @@ pub(crate) struct FileDesc {
     pub(crate) size: u64,
 }
 
-pub(crate) fn dir_exists(path: &Path) -> bool {
+pub(crate) fn dir_exists(settings: &Settings, path: &Path) -> bool {
     // check if a directory exists and print an warning message if not
     if path.exists() {
         true
     } else {
-        eprintln!("Skipping '{}' because it doesn't exist.", path.display());
+        eprintln!(
+            "Skipping '{}' because it doesn't exist.",
+            relative_paths::shown(settings, path).display()
+        );
         false
     }
 }
@@ -12,10 +12,13 @@ use std::path::{Path, PathBuf};
 
 use crate::cache::caches::Cache;
 use crate::cache::*;
+use crate::library::size_opts;
+use crate::relative_paths;
+use crate::settings::Settings;
 use crate::tables::format_table;
 use crate::top_items::common::{dir_exists, FileDesc, Pair};
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
@@ -194,13 +197,20 @@ fn stats_from_file_desc_list(file_descs: Vec<FileDesc>) -> Vec<RepoInfo> {
     out
 }
 
-pub(crate) fn chkout_list_to_string(limit: u32, mut collections_vec: Vec<RepoInfo>) -> String {
+pub(crate) fn chkout_list_to_string(
+    settings: &Settings,
+    limit: u32,
+    mut collections_vec: Vec<RepoInfo>,
+) -> String {
     if collections_vec.is_empty() {
         return String::new();
     }
-    // sort the RepoInfo Vec in reverse, biggest item first
-    collections_vec.par_sort_by_key(|grb| grb.total_size);
-    collections_vec.reverse();
+    // sort the RepoInfo Vec, biggest item first, equal sizes by name
+    collections_vec.par_sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.name.cmp(&b.name))
+    });
     let mut table_matrix: Vec<Vec<String>> = Vec::with_capacity(collections_vec.len() + 1);
 
     table_matrix.push(vec![
@@ -213,13 +223,10 @@ pub(crate) fn chkout_list_to_string(limit: u32, mut collections_vec: Vec<RepoInf
     for repoinfo in collections_vec.into_iter().take(limit as usize) {
         #[allow(clippy::integer_division)]
         let average_size = (repoinfo.total_size / u64::from(repoinfo.counter))
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap();
 
-        let total_size = repoinfo
-            .total_size
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap();
+        let total_size = repoinfo.total_size.file_size(size_opts(settings)).unwrap();
 
         table_matrix.push(vec![
             repoinfo.name,
@@ -233,28 +240,29 @@ pub(crate) fn chkout_list_to_string(limit: u32, mut collections_vec: Vec<RepoInf
 
 // bare git repos
 pub(crate) fn git_repos_bare_stats(
+    settings: &Settings,
     path: &Path,
     limit: u32,
     mut bare_repos_cache: &mut git_bare_repos::GitRepoCache,
 ) -> String {
     let mut output = String::new();
     // don't crash if the directory does not exist (issue #9)
-    if !dir_exists(path) {
+    if !dir_exists(settings, path) {
         return output;
     }
 
     output.push_str(&format!(
         "\nSummary of: {} ({} total)\n",
-        path.display(),
+        relative_paths::shown(settings, path).display(),
         bare_repos_cache
             .total_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
 
     let collections_vec = file_desc_from_path(&mut bare_repos_cache);
     let summary: Vec<RepoInfo> = stats_from_file_desc_list(collections_vec);
-    let tmp = chkout_list_to_string(limit, summary);
+    let tmp = chkout_list_to_string(settings, limit, summary);
 
     output.push_str(&tmp);
     output
@@ -285,7 +293,7 @@ mod top_crates_git_repos_bare {
         // empty list
         let list: Vec<FileDesc> = Vec::new();
         let stats = stats_from_file_desc_list(list);
-        let is = chkout_list_to_string(4, stats);
+        let is = chkout_list_to_string(&Settings::default(), 4, stats);
         let empty = String::new();
         assert_eq!(is, empty);
     }
@@ -299,7 +307,7 @@ mod top_crates_git_repos_bare {
         };
         let list_fd: Vec<FileDesc> = vec![fd];
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(1, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 1, list_cb);
         let wanted = String::from("Name   Count Average Total\ncrateA 1     1 B     1 B\n");
 
         assert_eq!(is, wanted);
@@ -319,7 +327,7 @@ mod top_crates_git_repos_bare {
         };
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(3, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 3, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -362,7 +370,7 @@ mod top_crates_git_repos_bare {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5];
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
 
-        let is: String = chkout_list_to_string(6, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 6, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -393,7 +401,7 @@ mod top_crates_git_repos_bare {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(2, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 2, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 2     3 B     6 B\n");
 
         assert_eq!(is, wanted);
@@ -420,7 +428,7 @@ mod top_crates_git_repos_bare {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
 
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(3, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     3 B     9 B\n");
 
         assert_eq!(is, wanted);
@@ -446,7 +454,7 @@ mod top_crates_git_repos_bare {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(3, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 3, list_cb);
 
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     6 B     18 B\n");
 
@@ -501,7 +509,7 @@ mod top_crates_git_repos_bare {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5, fd6, fd7, fd8];
         let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(5, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 5, list_cb);
 
         let mut wanted = String::new();
 
@@ -574,7 +582,7 @@ mod benchmarks {
         b.iter(|| {
             let list_fd = list_fd.clone(); // @FIXME  don't?
             let list_cb: Vec<RepoInfo> = stats_from_file_desc_list(list_fd);
-            let is: String = chkout_list_to_string(5, list_cb);
+            let is: String = chkout_list_to_string(&Settings::default(), 5, list_cb);
 
             let _ = black_box(is);
         });
@@ -12,10 +12,13 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::cache::caches::Cache;
+use crate::library::size_opts;
+use crate::relative_paths;
+use crate::settings::Settings;
 use crate::tables::format_table;
 use crate::top_items::common::{dir_exists, FileDesc, Pair};
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
@@ -207,14 +210,21 @@ fn stats_from_file_desc_list(file_descs: Vec<FileDesc>) -> Vec<ChkInfo> {
 }
 
 #[inline] // only used in one place
-fn chkout_list_to_string(limit: u32, mut collections_vec: Vec<ChkInfo>) -> String {
+fn chkout_list_to_string(
+    settings: &Settings,
+    limit: u32,
+    mut collections_vec: Vec<ChkInfo>,
+) -> String {
     if collections_vec.is_empty() {
         return String::new();
     }
 
-    // sort the ChkInfo Vec in reverse
-    collections_vec.par_sort_by_key(|gc| gc.total_size);
-    collections_vec.reverse();
+    // sort the ChkInfo Vec, biggest item first, equal sizes by name
+    collections_vec.par_sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.name.cmp(&b.name))
+    });
     let mut table_matrix: Vec<Vec<String>> = Vec::with_capacity(collections_vec.len() + 1);
 
     table_matrix.push(vec![
@@ -227,12 +237,9 @@ fn chkout_list_to_string(limit: u32, mut collections_vec: Vec<ChkInfo>) -> Strin
     for chkout in collections_vec.into_iter().take(limit as usize) {
         #[allow(clippy::integer_division)]
         let average_size = (chkout.total_size / u64::from(chkout.counter))
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap();
-        let total_size = chkout
-            .total_size
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap();
+        let total_size = chkout.total_size.file_size(size_opts(settings)).unwrap();
 
         table_matrix.push(vec![
             chkout.name,
@@ -247,29 +254,30 @@ fn chkout_list_to_string(limit: u32, mut collections_vec: Vec<ChkInfo>) -> Strin
 
 #[inline]
 pub(crate) fn git_checkouts_stats(
+    settings: &Settings,
     path: &Path,
     limit: u32,
     mut checkouts_cache: &mut git_checkouts::GitCheckoutCache,
 ) -> String {
     let mut output = String::new();
     // don't crash if the directory does not exist (issue #9)
-    if !dir_exists(path) {
+    if !dir_exists(settings, path) {
         return output;
     }
 
     output.push_str(&format!(
         "\nSummary of: {} ({} total)\n",
-        path.display(),
+        relative_paths::shown(settings, path).display(),
         checkouts_cache
             .total_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
 
     let collections_vec = file_desc_from_path(&mut checkouts_cache);
     let summary: Vec<ChkInfo> = stats_from_file_desc_list(collections_vec);
 
-    let tmp = chkout_list_to_string(limit, summary);
+    let tmp = chkout_list_to_string(settings, limit, summary);
     output.push_str(&tmp);
 
     output
@@ -302,7 +310,7 @@ mod top_crates_git_checkouts {
         // empty list
         let list: Vec<FileDesc> = Vec::new();
         let stats = stats_from_file_desc_list(list);
-        let is = chkout_list_to_string(4, stats);
+        let is = chkout_list_to_string(&Settings::default(), 4, stats);
         let empty = String::new();
         assert_eq!(is, empty);
     }
@@ -316,7 +324,7 @@ mod top_crates_git_checkouts {
         };
         let list_fd: Vec<FileDesc> = vec![fd];
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(1, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 1, list_cb);
         let wanted = String::from("Name   Count Average Total\ncrateA 1     1 B     1 B\n");
         assert_eq!(is, wanted);
     }
@@ -335,7 +343,7 @@ mod top_crates_git_checkouts {
         };
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(3, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 3, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -378,7 +386,7 @@ mod top_crates_git_checkouts {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5];
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
 
-        let is: String = chkout_list_to_string(6, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 6, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -409,7 +417,7 @@ mod top_crates_git_checkouts {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(2, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 2, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 2     3 B     6 B\n");
         assert_eq!(is, wanted);
     }
@@ -435,7 +443,7 @@ mod top_crates_git_checkouts {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
 
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(3, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     3 B     9 B\n");
         assert_eq!(is, wanted);
     }
@@ -460,7 +468,7 @@ mod top_crates_git_checkouts {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(3, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     6 B     18 B\n");
         assert_eq!(is, wanted);
     }
@@ -513,7 +521,7 @@ mod top_crates_git_checkouts {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5, fd6, fd7, fd8];
         let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = chkout_list_to_string(5, list_cb);
+        let is: String = chkout_list_to_string(&Settings::default(), 5, list_cb);
 
         let mut wanted = String::new();
 
@@ -587,7 +595,7 @@ mod benchmarks {
         b.iter(|| {
             let list_fd = list_fd.clone(); // @FIXME  don't?
             let list_cb: Vec<ChkInfo> = stats_from_file_desc_list(list_fd);
-            let is: String = chkout_list_to_string(5, list_cb);
+            let is: String = chkout_list_to_string(&Settings::default(), 5, list_cb);
 
             let _ = black_box(is);
         });
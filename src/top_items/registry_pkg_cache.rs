@@ -12,10 +12,13 @@ use std::path::{Path, PathBuf};
 
 use crate::cache::caches::RegistrySuperCache;
 use crate::cache::registry_pkg_cache;
+use crate::library::size_opts;
+use crate::relative_paths;
+use crate::settings::Settings;
 use crate::tables::format_table;
 use crate::top_items::common::{dir_exists, FileDesc, Pair};
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use rayon::prelude::*;
 
 #[inline]
@@ -181,14 +184,21 @@ fn stats_from_file_desc_list(file_descs: Vec<FileDesc>) -> Vec<RgchInfo> {
     out
 }
 
-pub(crate) fn regcache_list_to_string(limit: u32, mut collections_vec: Vec<RgchInfo>) -> String {
+pub(crate) fn regcache_list_to_string(
+    settings: &Settings,
+    limit: u32,
+    mut collections_vec: Vec<RgchInfo>,
+) -> String {
     if collections_vec.is_empty() {
         return String::new();
     }
 
-    // sort the RepoInfo Vec in reverse, biggest item first
-    collections_vec.par_sort_by_key(|rpc| rpc.total_size);
-    collections_vec.reverse();
+    // sort the RgchInfo Vec, biggest item first, equal sizes by name
+    collections_vec.par_sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.name.cmp(&b.name))
+    });
     let mut table_matrix: Vec<Vec<String>> = Vec::with_capacity(collections_vec.len() + 1);
 
     table_matrix.push(vec![
@@ -201,13 +211,10 @@ pub(crate) fn regcache_list_to_string(limit: u32, mut collections_vec: Vec<RgchI
     for regcache in collections_vec.into_iter().take(limit as usize) {
         #[allow(clippy::integer_division)]
         let average_size = (regcache.total_size / u64::from(regcache.counter))
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap();
 
-        let total_size = regcache
-            .total_size
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap();
+        let total_size = regcache.total_size.file_size(size_opts(settings)).unwrap();
 
         table_matrix.push(vec![
             regcache.name,
@@ -221,28 +228,29 @@ pub(crate) fn regcache_list_to_string(limit: u32, mut collections_vec: Vec<RgchI
 
 // registry cache
 pub(crate) fn registry_pkg_cache_stats(
+    settings: &Settings,
     path: &Path,
     limit: u32,
     mut registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
 ) -> String {
     let mut stdout = String::new();
     // don't crash if the directory does not exist (issue #9)
-    if !dir_exists(path) {
+    if !dir_exists(settings, path) {
         return stdout;
     }
 
     stdout.push_str(&format!(
         "\nSummary of: {} ({} total)\n",
-        path.display(),
+        relative_paths::shown(settings, path).display(),
         registry_pkg_caches
             .total_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
 
     let file_descs: Vec<FileDesc> = file_desc_list_from_path(&mut registry_pkg_caches);
     let summary: Vec<RgchInfo> = stats_from_file_desc_list(file_descs);
-    let string = regcache_list_to_string(limit, summary);
+    let string = regcache_list_to_string(settings, limit, summary);
     stdout.push_str(&string);
 
     stdout
@@ -275,7 +283,7 @@ mod top_crates_registry_pkg_cache {
         // empty list
         let list: Vec<FileDesc> = Vec::new();
         let stats = stats_from_file_desc_list(list);
-        let is = regcache_list_to_string(4, stats);
+        let is = regcache_list_to_string(&Settings::default(), 4, stats);
         let empty = String::new();
         assert_eq!(is, empty);
     }
@@ -289,7 +297,7 @@ mod top_crates_registry_pkg_cache {
         };
         let list_fd: Vec<FileDesc> = vec![fd];
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = regcache_list_to_string(1, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 1, list_cb);
         let wanted = String::from("Name   Count Average Total\ncrateA 1     1 B     1 B\n");
 
         assert_eq!(is, wanted);
@@ -309,7 +317,7 @@ mod top_crates_registry_pkg_cache {
         };
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = regcache_list_to_string(3, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 3, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -352,7 +360,7 @@ mod top_crates_registry_pkg_cache {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5];
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
 
-        let is: String = regcache_list_to_string(6, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 6, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -383,7 +391,7 @@ mod top_crates_registry_pkg_cache {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = regcache_list_to_string(2, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 2, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 2     3 B     6 B\n");
 
         assert_eq!(is, wanted);
@@ -410,7 +418,7 @@ mod top_crates_registry_pkg_cache {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
 
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = regcache_list_to_string(3, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     3 B     9 B\n");
 
         assert_eq!(is, wanted);
@@ -436,7 +444,7 @@ mod top_crates_registry_pkg_cache {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = regcache_list_to_string(3, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     6 B     18 B\n");
 
         assert_eq!(is, wanted);
@@ -490,7 +498,7 @@ mod top_crates_registry_pkg_cache {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5, fd6, fd7, fd8];
         let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = regcache_list_to_string(5, list_cb);
+        let is: String = regcache_list_to_string(&Settings::default(), 5, list_cb);
 
         let mut wanted = String::new();
 
@@ -563,7 +571,7 @@ mod benchmarks {
         b.iter(|| {
             let list_fd = list_fd.clone(); // @FIXME  don't?
             let list_cb: Vec<RgchInfo> = stats_from_file_desc_list(list_fd);
-            let is: String = regcache_list_to_string(5, list_cb);
+            let is: String = regcache_list_to_string(&Settings::default(), 5, list_cb);
 
             let _ = black_box(is);
         });
@@ -12,10 +12,13 @@ use std::path::{Path, PathBuf};
 
 use crate::cache::caches::RegistrySuperCache;
 use crate::cache::*;
+use crate::library::size_opts;
+use crate::relative_paths;
+use crate::settings::Settings;
 use crate::tables::format_table;
 use crate::top_items::common::{dir_exists, FileDesc, Pair};
 
-use humansize::{file_size_opts, FileSize};
+use humansize::FileSize;
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
@@ -193,14 +196,21 @@ fn stats_from_file_desc_list(file_descs: Vec<FileDesc>) -> Vec<RgSrcInfo> {
     }
     out
 }
-pub(crate) fn reg_src_list_to_string(limit: u32, mut collections_vec: Vec<RgSrcInfo>) -> String {
+pub(crate) fn reg_src_list_to_string(
+    settings: &Settings,
+    limit: u32,
+    mut collections_vec: Vec<RgSrcInfo>,
+) -> String {
     if collections_vec.is_empty() {
         return String::new();
     }
 
-    // sort the RepoImfo Vec in reverse, biggest item first
-    collections_vec.par_sort_by_key(|rs| rs.total_size);
-    collections_vec.reverse();
+    // sort the RgSrcInfo Vec, biggest item first, equal sizes by name
+    collections_vec.par_sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.name.cmp(&b.name))
+    });
 
     let mut table_matrix: Vec<Vec<String>> = Vec::with_capacity(collections_vec.len() + 1);
 
@@ -214,13 +224,10 @@ pub(crate) fn reg_src_list_to_string(limit: u32, mut collections_vec: Vec<RgSrcI
     for regsrc in collections_vec.into_iter().take(limit as usize) {
         #[allow(clippy::integer_division)]
         let average_size = (regsrc.total_size / u64::from(regsrc.counter))
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap();
 
-        let total_size = regsrc
-            .total_size
-            .file_size(file_size_opts::DECIMAL)
-            .unwrap();
+        let total_size = regsrc.total_size.file_size(size_opts(settings)).unwrap();
 
         table_matrix.push(vec![
             regsrc.name,
@@ -233,28 +240,29 @@ pub(crate) fn reg_src_list_to_string(limit: u32, mut collections_vec: Vec<RgSrcI
 }
 
 pub(crate) fn registry_source_stats(
+    settings: &Settings,
     path: &Path,
     limit: u32,
     mut registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
 ) -> String {
     let mut stdout = String::new();
     // don't crash if the directory does not exist (issue #9)
-    if !dir_exists(path) {
+    if !dir_exists(settings, path) {
         return stdout;
     }
 
     stdout.push_str(&format!(
         "\nSummary of: {} ({} total)\n",
-        path.display(),
+        relative_paths::shown(settings, path).display(),
         registry_sources_caches
             .total_size()
-            .file_size(file_size_opts::DECIMAL)
+            .file_size(size_opts(settings))
             .unwrap()
     ));
 
     let file_descs: Vec<FileDesc> = file_desc_list_from_path(&mut registry_sources_caches);
     let summary: Vec<RgSrcInfo> = stats_from_file_desc_list(file_descs);
-    let string = reg_src_list_to_string(limit, summary);
+    let string = reg_src_list_to_string(settings, limit, summary);
     stdout.push_str(&string);
 
     stdout
@@ -288,7 +296,7 @@ mod top_crates_registry_sources {
         // empty list
         let list: Vec<FileDesc> = Vec::new();
         let stats = stats_from_file_desc_list(list);
-        let is = reg_src_list_to_string(4, stats);
+        let is = reg_src_list_to_string(&Settings::default(), 4, stats);
         let empty = String::new();
         assert_eq!(is, empty);
     }
@@ -302,7 +310,7 @@ mod top_crates_registry_sources {
         };
         let list_fd: Vec<FileDesc> = vec![fd];
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = reg_src_list_to_string(1, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 1, list_cb);
         let wanted = String::from("Name   Count Average Total\ncrateA 1     1 B     1 B\n");
         assert_eq!(is, wanted);
     }
@@ -321,7 +329,7 @@ mod top_crates_registry_sources {
         };
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = reg_src_list_to_string(3, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 3, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -364,7 +372,7 @@ mod top_crates_registry_sources {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5];
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
 
-        let is: String = reg_src_list_to_string(6, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 6, list_cb);
 
         let mut wanted = String::new();
         for i in &[
@@ -395,7 +403,7 @@ mod top_crates_registry_sources {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2];
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = reg_src_list_to_string(2, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 2, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 2     3 B     6 B\n");
 
         assert_eq!(is, wanted);
@@ -422,7 +430,7 @@ mod top_crates_registry_sources {
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
 
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = reg_src_list_to_string(3, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     3 B     9 B\n");
 
         assert_eq!(is, wanted);
@@ -448,7 +456,7 @@ mod top_crates_registry_sources {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3];
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = reg_src_list_to_string(3, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 3, list_cb);
         let wanted = String::from("Name    Count Average Total\ncrate-A 3     6 B     18 B\n");
         assert_eq!(is, wanted);
     }
@@ -501,7 +509,7 @@ mod top_crates_registry_sources {
 
         let list_fd: Vec<FileDesc> = vec![fd1, fd2, fd3, fd4, fd5, fd6, fd7, fd8];
         let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-        let is: String = reg_src_list_to_string(5, list_cb);
+        let is: String = reg_src_list_to_string(&Settings::default(), 5, list_cb);
 
         let mut wanted = String::new();
 
@@ -574,7 +582,7 @@ mod benchmarks {
         b.iter(|| {
             let list_fd = list_fd.clone(); // @FIXME  don't?
             let list_cb: Vec<RgSrcInfo> = stats_from_file_desc_list(list_fd);
-            let is: String = reg_src_list_to_string(5, list_cb);
+            let is: String = reg_src_list_to_string(&Settings::default(), 5, list_cb);
 
             let _ = black_box(is);
         });
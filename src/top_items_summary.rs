@@ -7,16 +7,26 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::path::{Path, PathBuf};
+
+use crate::cache::caches::RegistrySuperCache;
 use crate::cache::*;
-use crate::library::CargoCachePaths;
+use crate::library::{cmp_versions, size_of_path, CargoCachePaths, Error};
+use crate::relative_paths;
+use crate::remove::parse_version;
+use crate::settings::Settings;
 use crate::top_items::binaries::*;
 use crate::top_items::git_bare_repos::*;
 use crate::top_items::git_checkouts::*;
 use crate::top_items::registry_pkg_cache::*;
 use crate::top_items::registry_sources::*;
 
+use rayon::prelude::*;
+use serde::Serialize;
+
 #[allow(clippy::complexity)]
 pub(crate) fn get_top_crates(
+    settings: &Settings,
     limit: u32,
     ccd: &CargoCachePaths,
     mut bin_cache: &mut bin::BinaryCache,
@@ -33,25 +43,35 @@ pub(crate) fn get_top_crates(
 
     rayon::scope(|s| {
         s.spawn(|_| {
-            reg_src =
-                registry_source_stats(&ccd.registry_sources, limit, &mut registry_sources_caches);
+            reg_src = registry_source_stats(
+                settings,
+                &ccd.registry_sources,
+                limit,
+                &mut registry_sources_caches,
+            );
         });
 
         s.spawn(|_| {
-            reg_cache =
-                registry_pkg_cache_stats(&ccd.registry_pkg_cache, limit, &mut registry_pkg_caches);
+            reg_cache = registry_pkg_cache_stats(
+                settings,
+                &ccd.registry_pkg_cache,
+                limit,
+                &mut registry_pkg_caches,
+            );
         });
 
         s.spawn(|_| {
-            bare_repos = git_repos_bare_stats(&ccd.git_repos_bare, limit, &mut bare_repos_cache);
+            bare_repos =
+                git_repos_bare_stats(settings, &ccd.git_repos_bare, limit, &mut bare_repos_cache);
         });
 
         s.spawn(|_| {
-            repo_checkouts = git_checkouts_stats(&ccd.git_checkouts, limit, &mut checkouts_cache);
+            repo_checkouts =
+                git_checkouts_stats(settings, &ccd.git_checkouts, limit, &mut checkouts_cache);
         });
 
         s.spawn(|_| {
-            binaries = binary_stats(&ccd.bin_dir, limit, &mut bin_cache);
+            binaries = binary_stats(settings, &ccd.bin_dir, limit, &mut bin_cache);
         });
     });
 
@@ -67,3 +87,196 @@ pub(crate) fn get_top_crates(
     // strip newlines at the end and the beginning
     output.trim().to_string()
 }
+
+/// a single crate archive or extracted crate source, for "--top-cache-items --format json"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct TopCrate {
+    name: String,
+    version: String,
+    size: u64,
+    /// the path as it is shown
+    path: PathBuf,
+}
+
+/// the biggest crates, wrapped into an object so it can carry the schema version
+#[derive(Debug, Serialize)]
+pub(crate) struct TopCrates {
+    /// the cargo home the crates are stored in
+    cargo_home: PathBuf,
+    top_items: Vec<TopCrate>,
+}
+
+/// JSON Schema (draft 7) of the biggest crates
+#[cfg(test)]
+pub(crate) const TOP_CRATES_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "cargo cache --top-cache-items --format json",
+  "type": "object",
+  "properties": {
+    "schema_version": { "const": 1 },
+    "generated_at": { "type": "string", "format": "date-time" },
+    "cargo_home": { "type": "string" },
+    "top_items": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "version": { "type": "string" },
+          "size": { "type": "integer", "minimum": 0 },
+          "path": { "type": "string" }
+        },
+        "required": ["name", "version", "size", "path"],
+        "additionalProperties": false
+      }
+    },
+    "warnings": {
+      "type": "array",
+      "items": { "type": "string" }
+    }
+  },
+  "required": ["schema_version", "generated_at", "cargo_home", "top_items"],
+  "additionalProperties": false
+}"#;
+
+impl TopCrate {
+    fn new(settings: &Settings, path: &Path) -> Result<Self, Error> {
+        // parse_version() strips the extension, so turn "foo-0.1.0" into "foo-0.1.0.crate"
+        let (name, version) = if path.is_dir() {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            parse_version(&path.with_file_name(format!("{file_name}.crate")))?
+        } else {
+            parse_version(path)?
+        };
+
+        Ok(Self {
+            name,
+            version,
+            size: size_of_path(settings, path),
+            path: relative_paths::shown(settings, path).to_path_buf(),
+        })
+    }
+}
+
+/// sort by size, biggest first, equal sizes by name and version, and only keep the first `limit` items
+fn biggest(mut crates: Vec<TopCrate>, limit: u32) -> Vec<TopCrate> {
+    crates.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| cmp_versions(&a.version, &b.version))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    crates.truncate(limit as usize);
+    crates
+}
+
+/// the `limit` biggest .crate archives and extracted crate sources
+pub(crate) fn get_top_crates_json(
+    settings: &Settings,
+    cargo_home: &Path,
+    limit: u32,
+    registry_pkg_caches: &mut registry_pkg_cache::RegistryPkgCaches,
+    registry_sources_caches: &mut registry_sources::RegistrySourceCaches,
+) -> Result<TopCrates, Error> {
+    let mut paths = registry_pkg_caches.files();
+    paths.extend_from_slice(registry_sources_caches.items());
+
+    let crates = paths
+        .par_iter()
+        .map(|path| TopCrate::new(settings, path))
+        .collect::<Result<Vec<TopCrate>, Error>>()?;
+
+    Ok(TopCrates {
+        cargo_home: cargo_home.to_path_buf(),
+        top_items: biggest(crates, limit),
+    })
+}
+
+#[cfg(test)]
+mod top_items_summary_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn top_crates() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("foo-bar-0.1.0.crate");
+        fs::write(&archive, b"1234").unwrap();
+        let source = dir.path().join("foo-bar-0.1.0");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("lib.rs"), b"12345678").unwrap();
+        let small = dir.path().join("baz-1.0.0-alpha.1.crate");
+        fs::write(&small, b"1").unwrap();
+
+        let crates = [&archive, &source, &small]
+            .iter()
+            .map(|path| TopCrate::new(&Settings::default(), path).unwrap())
+            .collect::<Vec<_>>();
+
+        let top = biggest(crates, 2);
+        assert_eq!(top.len(), 2);
+        // the size of a directory includes the size of the directory entries themselves
+        assert_eq!(top[0].path, source);
+        assert_eq!(
+            (top[0].name.as_str(), top[0].version.as_str()),
+            ("foo-bar", "0.1.0")
+        );
+        assert_eq!(
+            top[1],
+            TopCrate {
+                name: String::from("foo-bar"),
+                version: String::from("0.1.0"),
+                size: 4,
+                path: archive,
+            }
+        );
+        let output = crate::output::to_json_at(
+            &TopCrates {
+                cargo_home: dir.path().to_path_buf(),
+                top_items: top,
+            },
+            crate::output::test_time(),
+        );
+        assert_eq!(
+            crate::output::schema_violations(TOP_CRATES_SCHEMA, &output),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn equal_sizes_are_sorted_by_name_and_version() {
+        let top_crate = |name: &str, version: &str, registry: &str| TopCrate {
+            name: name.into(),
+            version: version.into(),
+            size: 10,
+            path: PathBuf::from(format!("registry/cache/{registry}/{name}-{version}.crate")),
+        };
+        let crates = vec![
+            top_crate("foo", "0.10.0", "a"),
+            top_crate("foo", "0.2.0", "a"),
+            top_crate("bar", "1.0.0", "a"),
+            top_crate("foo", "0.1.0", "b"),
+            top_crate("foo", "0.1.0", "a"),
+        ];
+
+        // versions are compared by semver, not as strings
+        let expected = vec![
+            top_crate("bar", "1.0.0", "a"),
+            top_crate("foo", "0.1.0", "a"),
+            top_crate("foo", "0.1.0", "b"),
+            top_crate("foo", "0.2.0", "a"),
+            top_crate("foo", "0.10.0", "a"),
+        ];
+        // the order of the input must not matter
+        let mut shuffled = crates;
+        for _ in 0..shuffled.len() {
+            shuffled.rotate_left(1);
+            assert_eq!(biggest(shuffled.clone(), 10), expected);
+            let mut reversed = shuffled.clone();
+            reversed.reverse();
+            assert_eq!(biggest(reversed, 10), expected);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file provides the trend log (cmd: "cargo cache --log-trend"): the json summary is
+/// appended to "$CARGO_HOME/cargo-cache-trend.jsonl" as a single line on every summary run,
+/// which builds a dataset of the cache size over time.
+/// A new file starts with a header line that names the fields of the entries.
+///
+/// It also implements "--append-json <FILE>": the summary is appended to a json array in any file,
+/// which keeps the history in a single json document. "--keep-runs N" drops all but the newest N runs.
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::dirsizes::SummaryJson;
+use crate::library::Error;
+use crate::output::{to_json_line_at, SCHEMA_VERSION};
+
+use chrono::{DateTime, Utc};
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Serialize;
+use serde_json::value::RawValue;
+
+/// name of the trend log inside the cargo home
+pub(crate) const TREND_FILE: &str = "cargo-cache-trend.jsonl";
+
+/// first line of a new trend log
+#[derive(Debug, Serialize)]
+struct TrendHeader {
+    schema_version: u32,
+    /// the fields of every following line
+    fields: Vec<String>,
+}
+
+/// the keys of a json object in the order they appear in
+/// (`serde_json::Value` would sort them)
+struct FieldNames(Vec<String>);
+
+impl<'de> Deserialize<'de> for FieldNames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = FieldNames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a json object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldNames, A::Error> {
+                let mut keys = Vec::new();
+                while let Some((key, _value)) = map.next_entry::<String, IgnoredAny>()? {
+                    keys.push(key);
+                }
+                Ok(FieldNames(keys))
+            }
+        }
+
+        deserializer.deserialize_map(KeyVisitor)
+    }
+}
+
+/// the lines to append to the trend log, including the header if the log is new
+fn trend_lines(summary: &SummaryJson, time: DateTime<Utc>, new_file: bool) -> String {
+    let entry = to_json_line_at(summary, time);
+    if !new_file {
+        return format!("{entry}\n");
+    }
+
+    let FieldNames(fields) =
+        serde_json::from_str(&entry).expect("the summary is always serialized as a json object");
+    let header = TrendHeader {
+        schema_version: SCHEMA_VERSION,
+        fields,
+    };
+    // the header only consists of strings and numbers, this can not fail
+    let header_line = serde_json::to_string(&header).expect("failed to serialize trend header");
+    format!("{header_line}\n{entry}\n")
+}
+
+fn append_trend(trend_file: &Path, summary: &SummaryJson, time: DateTime<Utc>) -> io::Result<()> {
+    let new_file = !trend_file.exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(trend_file)?;
+    file.write_all(trend_lines(summary, time, new_file).as_bytes())
+}
+
+/// append the summary to the trend log in the cargo home
+pub(crate) fn log_trend(cargo_home: &Path, summary: &SummaryJson) {
+    let trend_file = cargo_home.join(TREND_FILE);
+    if let Err(error) = append_trend(&trend_file, summary, Utc::now()) {
+        eprintln!(
+            "Warning: failed to append to the trend log \"{}\": {}",
+            trend_file.display(),
+            error
+        );
+    }
+}
+
+/// the runs of an "--append-json" file with the current run appended, without the oldest runs
+/// that exceed `keep_runs`, every run is printed on its own line
+fn appended_runs(
+    existing: Option<&str>,
+    run: Box<RawValue>,
+    keep_runs: Option<usize>,
+) -> Result<String, String> {
+    let mut runs: Vec<Box<RawValue>> = match existing {
+        Some(content) if !content.trim().is_empty() => serde_json::from_str(content)
+            .map_err(|error| format!("the file is not a json array: {error}"))?,
+        _ => Vec::new(),
+    };
+    runs.push(run);
+    if let Some(keep) = keep_runs {
+        let _ = runs.drain(..runs.len().saturating_sub(keep));
+    }
+
+    let lines = runs
+        .iter()
+        .map(|entry| entry.get())
+        .collect::<Vec<_>>()
+        .join(",\n");
+    Ok(format!("[\n{lines}\n]\n"))
+}
+
+/// replace the file by writing a temporary file next to it and renaming that,
+/// a concurrent run never sees a half written array (but one of two concurrent runs may be lost)
+fn replace_atomically(file: &Path, content: &str) -> io::Result<()> {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let temp_file: PathBuf = file.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&temp_file, content)?;
+    fs::rename(&temp_file, file).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_file);
+    })
+}
+
+fn append_json_at(
+    file: &Path,
+    summary: &SummaryJson,
+    time: DateTime<Utc>,
+    keep_runs: Option<usize>,
+) -> Result<(), Error> {
+    let failed = |error: String| Error::AppendJsonFailed(file.to_path_buf(), error);
+
+    let existing = match fs::read_to_string(file) {
+        Ok(content) => Some(content),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(failed(error.to_string())),
+    };
+    // a serialized summary is always valid json
+    let run = RawValue::from_string(to_json_line_at(summary, time))
+        .expect("the summary is always serialized as valid json");
+    let content = appended_runs(existing.as_deref(), run, keep_runs).map_err(failed)?;
+    replace_atomically(file, &content).map_err(|error| failed(error.to_string()))
+}
+
+/// append the summary to the json array in `file`, the file is created if it does not exist
+pub(crate) fn append_json(
+    file: &Path,
+    summary: &SummaryJson,
+    keep_runs: Option<usize>,
+) -> Result<(), Error> {
+    append_json_at(file, summary, Utc::now(), keep_runs)
+}
+
+#[cfg(test)]
+mod trend_tests {
+    use super::*;
+    use crate::dirsizes::DirSizes;
+    use crate::library::DirInfo;
+    use crate::output::test_time;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn summary() -> SummaryJson {
+        let dir_info = |dir_size, file_number| DirInfo {
+            dir_size,
+            file_number,
+        };
+        let cargo_home = PathBuf::from("/home/user/.cargo");
+        DirSizes::new_manually(
+            &dir_info(100, 2),
+            &dir_info(200, 3),
+            &dir_info(300, 4),
+            &dir_info(400, 5),
+            &dir_info(500, 6),
+            &dir_info(600, 7),
+            &cargo_home,
+        )
+        .summary_json()
+    }
+
+    #[test]
+    fn trend_log_is_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let trend_file = dir.path().join(TREND_FILE);
+
+        append_trend(&trend_file, &summary(), test_time()).unwrap();
+        append_trend(&trend_file, &summary(), test_time()).unwrap();
+
+        let trend = fs::read_to_string(&trend_file).unwrap();
+        let lines = trend.lines().collect::<Vec<_>>();
+        // one header, two entries
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(r#"{"schema_version":1,"fields":["schema_version","generated_at","cargo_home","total_size","#));
+        assert!(lines[1].starts_with(
+            r#"{"schema_version":1,"generated_at":"2021-05-01T12:30:00Z","cargo_home":"/home/user/.cargo","total_size":2100,"#
+        ));
+        assert_eq!(lines[1], lines[2]);
+    }
+
+    fn run(number: u32) -> Box<RawValue> {
+        RawValue::from_string(format!("{{\"run\":{number}}}")).unwrap()
+    }
+
+    #[test]
+    fn runs_are_appended() {
+        assert_eq!(
+            appended_runs(None, run(1), None).unwrap(),
+            "[\n{\"run\":1}\n]\n"
+        );
+        assert_eq!(
+            appended_runs(Some(""), run(1), None).unwrap(),
+            "[\n{\"run\":1}\n]\n"
+        );
+        assert_eq!(
+            appended_runs(Some("[\n{\"run\":1}\n]\n"), run(2), None).unwrap(),
+            "[\n{\"run\":1},\n{\"run\":2}\n]\n"
+        );
+        // existing runs are kept as they are
+        assert_eq!(
+            appended_runs(Some("[{ \"run\": 1 }]"), run(2), None).unwrap(),
+            "[\n{ \"run\": 1 },\n{\"run\":2}\n]\n"
+        );
+    }
+
+    #[test]
+    fn oldest_runs_are_dropped() {
+        let existing = "[\n{\"run\":1},\n{\"run\":2},\n{\"run\":3}\n]\n";
+        assert_eq!(
+            appended_runs(Some(existing), run(4), Some(2)).unwrap(),
+            "[\n{\"run\":3},\n{\"run\":4}\n]\n"
+        );
+        assert_eq!(
+            appended_runs(Some(existing), run(4), Some(10)).unwrap(),
+            "[\n{\"run\":1},\n{\"run\":2},\n{\"run\":3},\n{\"run\":4}\n]\n"
+        );
+    }
+
+    #[test]
+    fn invalid_file_is_not_replaced() {
+        assert!(appended_runs(Some("{\"run\":1}"), run(2), None).is_err());
+        assert!(appended_runs(Some("[{\"run\":1}"), run(2), None).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("history.json");
+        fs::write(&file, "not json").unwrap();
+        let error = append_json_at(&file, &summary(), test_time(), None).unwrap_err();
+        assert!(error.to_string().starts_with(&format!(
+            "Failed to append the summary to \"{}\": the file is not a json array",
+            file.display()
+        )));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "not json");
+    }
+
+    #[test]
+    fn json_history_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("history.json");
+
+        for _ in 0..3 {
+            append_json_at(&file, &summary(), test_time(), Some(2)).unwrap();
+        }
+        let runs: Vec<serde_json::Value> =
+            serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0]["total_size"], 2100);
+        // no temporary file is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
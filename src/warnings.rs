@@ -0,0 +1,113 @@
+// Copyright 2021 Matthias Krüger. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// This file collects the warnings of the scan, such as files that vanished while the cache was scanned.
+/// With "--format json" they are added to the json output as "warnings" instead of being printed
+/// to stderr between the lines of the output.
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::exclude;
+use crate::relative_paths;
+use crate::settings::Settings;
+
+use walkdir::{DirEntry, WalkDir};
+
+/// the warnings of a run, printed to stderr right away unless they are collected
+#[derive(Debug, Default)]
+pub(crate) struct Warnings {
+    /// the warnings so far, None if they are printed right away
+    collected: Option<Mutex<Vec<String>>>,
+}
+
+impl Warnings {
+    /// collect the warnings instead of printing them
+    pub(crate) fn collected() -> Self {
+        Self {
+            collected: Some(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+/// print a warning to stderr, or keep it for the json output if the warnings are collected
+pub(crate) fn warn(settings: &Settings, message: String) {
+    if let Some(Ok(mut warnings)) = settings.warnings.collected.as_ref().map(Mutex::lock) {
+        warnings.push(message);
+        return;
+    }
+    eprintln!("Warning: {message}");
+}
+
+/// the collected warnings, they are only returned once
+pub(crate) fn take(settings: &Settings) -> Vec<String> {
+    settings
+        .warnings
+        .collected
+        .as_ref()
+        .and_then(|collected| collected.lock().ok())
+        .map(|mut warnings| std::mem::take(&mut *warnings))
+        .unwrap_or_default()
+}
+
+/// all entries below `dir`, entries that can not be read are skipped with a warning
+/// entries excluded by "--exclude-glob" are skipped together with their contents
+pub(crate) fn readable_entries<'a>(
+    settings: &'a Settings,
+    dir: &Path,
+) -> impl Iterator<Item = DirEntry> + 'a {
+    let root = dir.to_path_buf();
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(move |entry| !exclude::is_excluded(settings, entry.path()))
+        .filter_map(move |entry| match entry {
+            Ok(readable) => Some(readable),
+            Err(error) => {
+                let path = relative_paths::shown(settings, error.path().unwrap_or(&root)).display();
+                warn(settings, format!("skipped '{path}': {error}"));
+                None
+            }
+        })
+}
+
+#[cfg(test)]
+mod warnings_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn entries_of_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("foo"), b"").unwrap();
+
+        let entries = readable_entries(&Settings::default(), dir.path())
+            .map(DirEntry::into_path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![dir.path().to_path_buf(), dir.path().join("foo")]
+        );
+    }
+
+    #[test]
+    fn collected_warnings_are_taken_once() {
+        let settings = Settings {
+            warnings: Warnings::collected(),
+            ..Settings::default()
+        };
+        warn(
+            &settings,
+            String::from("'bin/foo' vanished during the scan"),
+        );
+        assert_eq!(
+            take(&settings),
+            vec![String::from("'bin/foo' vanished during the scan")]
+        );
+        assert_eq!(take(&settings), Vec::<String>::new());
+    }
+}
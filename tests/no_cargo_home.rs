@@ -16,18 +16,41 @@ use std::process::Command;
 
 #[test]
 fn run_tests() {
-    // we need this fake harness to make sure the two tests don't modify CARGO_HOME at the same time
+    // we need this fake harness to make sure the tests don't modify CARGO_HOME at the same time
     // which would be a race condition
     CARGO_HOME_is_nonexisting_dir();
+    CARGO_HOME_is_file();
     CARGO_HOME_is_empty();
 }
 
 #[allow(non_snake_case)]
 fn CARGO_HOME_is_nonexisting_dir() {
-    // CARGO_HOME points to a directory that does not exist
+    // CARGO_HOME points to a directory that does not exist (yet), there is nothing to report
     let cargo_cache = Command::new(bin_path())
         .env("CARGO_HOME", "./xyxyxxxyyyxxyxyxqwertywasd")
         .output();
+    // make sure we succeeded
+    let cmd = cargo_cache.unwrap();
+    assert!(cmd.status.success(), "bad exit status!");
+
+    // no stderr
+    let stderr = String::from_utf8_lossy(&cmd.stderr).into_owned();
+    assert!(stderr.is_empty(), "unexpected stderr!");
+    // stdout
+    let stdout = String::from_utf8_lossy(&cmd.stdout).into_owned();
+    let re = Regex::new(r"^No cargo cache found at '.*./xyxyxxxyyyxxyxyxqwertywasd'\n$").unwrap();
+    eprintln!("REGEX:\n{}", &re);
+    eprintln!("OUTPUT:\n{}", &stdout);
+    assert!(re.is_match(&stdout));
+}
+
+#[allow(non_snake_case)]
+fn CARGO_HOME_is_file() {
+    // CARGO_HOME points to a file
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("cargo_home");
+    std::fs::write(&file, b"").unwrap();
+    let cargo_cache = Command::new(bin_path()).env("CARGO_HOME", &file).output();
     // make sure we failed
     let cmd = cargo_cache.unwrap();
     assert!(!cmd.status.success(), "no bad exit status!");
@@ -36,14 +59,12 @@ fn CARGO_HOME_is_nonexisting_dir() {
     assert!(cmd.stdout.is_empty(), "unexpected stdout!");
     // stderr
     let stderr = String::from_utf8_lossy(&cmd.stderr).into_owned();
-    assert!(!stderr.is_empty(), "found no stderr!");
-    let re =
-        Regex::new(r"CARGO_HOME .*./xyxyxxxyyyxxyxyxqwertywasd. is not an existing directory!\n")
-            .unwrap();
+    let re = Regex::new(r"CARGO_HOME .*cargo_home. is not a directory!\n").unwrap();
     eprintln!("REGEX:\n{}", &re);
     eprintln!("OUTPUT:\n{}", &stderr);
     assert!(re.is_match(&stderr));
 }
+
 #[allow(non_snake_case)]
 fn CARGO_HOME_is_empty() {
     // CARGO_HOME is empty